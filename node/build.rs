@@ -0,0 +1,5 @@
+// SPDX-License-Identifier: Apache-2.0
+
+fn main() {
+    napi_build::setup();
+}
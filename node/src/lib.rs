@@ -0,0 +1,114 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! The Node.js module of geoffrey for build tools like Docusaurus or remark plugins, which transform the
+//! markdown pages in memory with the same parser as the command line
+
+use geoffrey::documents::{Documents, Symlinks, SyncOptions};
+use geoffrey::error::{FileError, GeoffreyError};
+
+use napi_derive::napi;
+
+use std::path::{Path, PathBuf};
+
+/// The options of `syncMarkdownString`
+#[napi(object)]
+pub struct SyncMarkdownOptions {
+    /// The path of the markdown page, which resolves relative content paths and the config; the file must exist
+    pub path: String,
+    /// 'error', 'skip' or 'full-file' like '--fallback'; defaults to 'error'
+    pub fallback: Option<String>,
+    /// keep code blocks which differ from their content only in whitespace, like '--ignore-whitespace'
+    pub ignore_whitespace: Option<bool>,
+}
+
+fn message(failures: &[FileError]) -> String {
+    failures
+        .iter()
+        .map(FileError::to_string)
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// The markdown `content` of the page `path` with its code blocks synced; the error message lists all failures
+fn sync_markdown(content: String, path: &Path, options: &SyncOptions) -> Result<String, String> {
+    let failed = |e: GeoffreyError| format!("[{}] {}", e.code(), e);
+    let path = std::env::current_dir()
+        .map_err(|e| failed(e.into()))?
+        .join(path);
+    let mut documents = Documents::new(path.clone(), Symlinks::Follow).map_err(failed)?;
+    documents.set_md_text(&path, content).map_err(failed)?;
+    documents.parse().map_err(failed)?;
+
+    documents
+        .synced_md_text(&path, options)
+        .map_err(|failures| message(&failures))
+}
+
+/// Syncs the code blocks of the markdown `content` of a page with their content files and returns the synced
+/// markdown; nothing is written. Throws with the errors of all tags which could not be synced.
+#[napi]
+pub fn sync_markdown_string(content: String, options: SyncMarkdownOptions) -> napi::Result<String> {
+    let fallback = options.fallback.as_deref().unwrap_or("error");
+    let sync_options = SyncOptions {
+        fallback: fallback
+            .parse()
+            .map_err(|e: GeoffreyError| napi::Error::from_reason(e.to_string()))?,
+        ignore_whitespace: options.ignore_whitespace.unwrap_or(false),
+        ..Default::default()
+    };
+
+    sync_markdown(content, &PathBuf::from(options.path), &sync_options)
+        .map_err(napi::Error::from_reason)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use anyhow::Result;
+    use std::fs;
+    use tempfile::Builder;
+
+    #[test]
+    fn markdown_strings_are_synced_with_the_content_files_of_their_page() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        fs::write(
+            tmp_dir.path().join("content.cpp"),
+            "//! [answer]\n42\n//! [answer]\n",
+        )?;
+        let page = tmp_dir.path().join("page.md");
+        fs::write(&page, "")?;
+        let content = "<!-- [geoffrey] [content.cpp] [answer] -->\n```cpp\n```\n";
+        // the content paths are relative to the page
+        fs::write(
+            tmp_dir.path().join("geoffrey.toml"),
+            "paths = \"markdown-file\"\n",
+        )?;
+
+        let synced = sync_markdown(content.to_owned(), &page, &SyncOptions::default())
+            .map_err(anyhow::Error::msg)?;
+        assert_eq!(synced, content.replace("```cpp\n", "```cpp\n42\n"));
+        assert_eq!(fs::read_to_string(&page)?, "");
+
+        let error = sync_markdown(
+            content.replace("[answer]", "[answr]"),
+            &page,
+            &SyncOptions::default(),
+        )
+        .err()
+        .unwrap_or_default();
+        assert!(error.contains("[G009]"), "{}", error);
+
+        let error = sync_markdown(
+            content.to_owned(),
+            &tmp_dir.path().join("missing.md"),
+            &SyncOptions::default(),
+        )
+        .err()
+        .unwrap_or_default();
+        assert!(error.starts_with("[G001]"), "{}", error);
+
+        Ok(())
+    }
+}
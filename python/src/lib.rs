@@ -0,0 +1,189 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! The Python module 'geoffrey', which runs the sync engine in-process for documentation tools like Sphinx or
+//! MkDocs plugins; the results have the structure of the JSON report as dicts and lists
+
+use geoffrey::documents::{Documents, Symlinks, SyncOptions};
+use geoffrey::error;
+use geoffrey::lock::RunLock;
+use geoffrey::report::Diagnostic;
+
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use serde_json::{json, Value};
+
+use std::path::{Path, PathBuf};
+
+create_exception!(
+    geoffrey,
+    GeoffreyError,
+    PyException,
+    "An error of geoffrey; the message starts with its stable code like '[G001]'"
+);
+
+/// The parsed documents of the markdown file or folder `doc_path`, which is relative to the current directory
+fn parsed(doc_path: &Path) -> Result<Documents, error::GeoffreyError> {
+    let doc_path = std::env::current_dir()?.join(doc_path);
+    let mut documents = Documents::new(doc_path, Symlinks::Follow)?;
+    documents.parse()?;
+
+    Ok(documents)
+}
+
+/// The tags of the documentation and the failures from parsing it
+fn parse_json(doc_path: &Path) -> Result<Value, error::GeoffreyError> {
+    let documents = parsed(doc_path)?;
+    let diagnostics = documents
+        .failures()
+        .iter()
+        .map(Diagnostic::from)
+        .collect::<Vec<Diagnostic>>();
+
+    Ok(json!({ "tags": documents.tags(), "diagnostics": diagnostics }))
+}
+
+/// The problems of the tags, like missing content files or snippets, and the code blocks which are not in sync
+/// with their snippets, without writing anything
+fn check_json(doc_path: &Path) -> Result<Value, error::GeoffreyError> {
+    let documents = parsed(doc_path)?;
+    let mut diagnostics = documents
+        .failures()
+        .iter()
+        .map(Diagnostic::from)
+        .collect::<Vec<Diagnostic>>();
+    diagnostics.extend(
+        documents.tag_problems().into_iter().map(|problem| {
+            Diagnostic::from(&error::FileError::new(problem.md_file, problem.error))
+        }),
+    );
+    let stale = documents.diff(&SyncOptions::default()).diffs;
+
+    Ok(json!({ "diagnostics": diagnostics, "stale": stale }))
+}
+
+/// The report of syncing the documentation, which holds the lock against other runs like the command line does
+fn sync_json(doc_path: &Path, options: &SyncOptions) -> Result<Value, error::GeoffreyError> {
+    let _lock = RunLock::acquire(doc_path, true)?;
+    let report = parsed(doc_path)?.sync(options)?;
+
+    Ok(serde_json::to_value(&report).expect("the report is serializable"))
+}
+
+fn exception(e: error::GeoffreyError) -> PyErr {
+    GeoffreyError::new_err(format!("[{}] {}", e.code(), e))
+}
+
+fn to_python(py: Python<'_>, value: &Value) -> PyResult<PyObject> {
+    Ok(py
+        .import("json")?
+        .call_method1("loads", (value.to_string(),))?
+        .unbind())
+}
+
+/// Parses the documentation in `doc_path` and returns a dict with its 'tags' and the 'diagnostics' of the files
+/// which failed to parse
+#[pyfunction]
+fn parse(py: Python<'_>, doc_path: PathBuf) -> PyResult<PyObject> {
+    let result = py
+        .allow_threads(|| parse_json(&doc_path))
+        .map_err(exception)?;
+    to_python(py, &result)
+}
+
+/// Checks the documentation in `doc_path` like 'geoffrey check' and returns a dict with the 'diagnostics' of missing
+/// content files and snippets and the code blocks which are not in sync as 'stale', with their 'current' and
+/// 'synced' content; both are empty if the documentation is in sync
+#[pyfunction]
+fn check(py: Python<'_>, doc_path: PathBuf) -> PyResult<PyObject> {
+    let result = py
+        .allow_threads(|| check_json(&doc_path))
+        .map_err(exception)?;
+    to_python(py, &result)
+}
+
+/// Syncs the documentation in `doc_path` and returns the report as dict, with the failures of single files in
+/// its 'diagnostics'; `fallback` is 'error', 'skip' or 'full-file' like '--fallback'
+#[pyfunction]
+#[pyo3(signature = (doc_path, fallback = "error", ignore_whitespace = false, force = false))]
+fn sync(
+    py: Python<'_>,
+    doc_path: PathBuf,
+    fallback: &str,
+    ignore_whitespace: bool,
+    force: bool,
+) -> PyResult<PyObject> {
+    let options = SyncOptions {
+        fallback: fallback.parse().map_err(exception)?,
+        ignore_whitespace,
+        force,
+        ..Default::default()
+    };
+    let result = py
+        .allow_threads(|| sync_json(&doc_path, &options))
+        .map_err(exception)?;
+    to_python(py, &result)
+}
+
+#[pymodule]
+#[pyo3(name = "geoffrey")]
+fn geoffrey_python(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("GeoffreyError", m.py().get_type::<GeoffreyError>())?;
+    m.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    m.add_function(wrap_pyfunction!(parse, m)?)?;
+    m.add_function(wrap_pyfunction!(check, m)?)?;
+    m.add_function(wrap_pyfunction!(sync, m)?)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use anyhow::Result;
+    use std::fs;
+    use tempfile::Builder;
+
+    #[test]
+    fn results_have_the_structure_of_the_json_report() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let content_path = tmp_dir.path().join("content.cpp");
+        fs::write(&content_path, "//! [answer]\n42\n//! [answer]\n")?;
+        let md_path = tmp_dir.path().join("doc.md");
+        let md = format!(
+            "<!-- [geoffrey] [{0}] [answer] -->\n```cpp\nstale\n```\n\n<!-- [geoffrey] [{0}] [answr] -->\n```cpp\n```\n",
+            content_path.display()
+        );
+        fs::write(&md_path, &md)?;
+
+        let parsed = parse_json(tmp_dir.path())?;
+        assert_eq!(parsed["tags"][0]["snippet"], "answer");
+        assert_eq!(parsed["tags"][1]["line"], 6);
+        assert_eq!(parsed["diagnostics"], json!([]));
+
+        let checked = check_json(tmp_dir.path())?;
+        assert_eq!(checked["diagnostics"][0]["code"], "G009");
+        assert_eq!(checked["diagnostics"][0]["file"], json!(md_path));
+
+        fs::write(&md_path, md.replace("[answr]", "[answer]"))?;
+        let checked = check_json(tmp_dir.path())?;
+        assert_eq!(checked["diagnostics"], json!([]));
+        assert_eq!(checked["stale"][0]["line"], 1);
+        assert_eq!(checked["stale"][0]["current"], "stale\n");
+        assert_eq!(checked["stale"][0]["synced"], "42\n");
+        assert_eq!(checked["stale"][1]["line"], 6);
+
+        let report = sync_json(tmp_dir.path(), &SyncOptions::default())?;
+        assert_eq!(report["summary"]["snippets_updated"], 2);
+        assert!(fs::read_to_string(&md_path)?.starts_with(&format!(
+            "<!-- [geoffrey] [{}] [answer] -->\n```cpp\n42\n```\n",
+            content_path.display()
+        )));
+
+        assert!(parse_json(&tmp_dir.path().join("missing")).is_err_and(|e| e.code() == "G001"));
+
+        Ok(())
+    }
+}
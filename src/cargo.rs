@@ -0,0 +1,105 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use serde::Deserialize;
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// The part of the output of 'cargo metadata' needed to locate the sources of the crates
+#[derive(Debug, Deserialize)]
+struct Metadata {
+    packages: Vec<Package>,
+    workspace_members: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Package {
+    id: String,
+    name: String,
+    version: String,
+    manifest_path: PathBuf,
+}
+
+/// The numeric parts of a version like '1.0.200', without pre-release and build metadata
+fn version_key(version: &str) -> Vec<u64> {
+    version
+        .split(['-', '+'])
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
+
+/// The directories of the crates of the cargo workspace at `dir` and of their dependencies, including vendored
+/// ones, by crate name; a workspace member takes precedence over a dependency of the same name, and of several
+/// versions of a dependency, the newest one is taken
+pub fn crate_dirs(dir: &Path) -> Result<HashMap<String, PathBuf>, String> {
+    log::info!("running 'cargo metadata' in {:?}", dir);
+    let output = std::process::Command::new("cargo")
+        .args(["metadata", "--format-version", "1"])
+        .current_dir(dir)
+        .output()
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_owned());
+    }
+    let metadata = serde_json::from_slice::<Metadata>(&output.stdout).map_err(|e| e.to_string())?;
+
+    let members = metadata
+        .workspace_members
+        .iter()
+        .collect::<HashSet<&String>>();
+    let mut packages = metadata.packages.iter().collect::<Vec<&Package>>();
+    packages.sort_by_key(|package| (members.contains(&package.id), version_key(&package.version)));
+
+    // the preferred package of a name is the last one
+    Ok(packages
+        .into_iter()
+        .filter_map(|package| {
+            let dir = package.manifest_path.parent()?;
+            Some((package.name.clone(), dir.to_path_buf()))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use anyhow::Result;
+    use std::fs;
+    use tempfile::Builder;
+
+    #[test]
+    fn workspace_members_and_path_dependencies_are_located() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let member = tmp_dir.path().join("app");
+        let dependency = tmp_dir.path().join("vendor").join("answer");
+        for (dir, manifest) in [
+            (
+                &member,
+                "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[dependencies]\nanswer = { path = \"../vendor/answer\" }\n",
+            ),
+            (
+                &dependency,
+                "[package]\nname = \"answer\"\nversion = \"1.2.3\"\nedition = \"2021\"\n",
+            ),
+        ] {
+            fs::create_dir_all(dir.join("src"))?;
+            fs::write(dir.join("Cargo.toml"), manifest)?;
+            fs::write(dir.join("src").join("lib.rs"), "")?;
+        }
+
+        let dirs = crate_dirs(&member).map_err(anyhow::Error::msg)?;
+        assert_eq!(dirs.len(), 2);
+        assert_eq!(dirs["app"].canonicalize()?, member.canonicalize()?);
+        assert_eq!(dirs["answer"].canonicalize()?, dependency.canonicalize()?);
+
+        assert!(crate_dirs(tmp_dir.path()).is_err());
+        assert!(version_key("1.10.0") > version_key("1.9.5-beta"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::error::GeoffreyError;
+
+use std::str::FromStr;
+
+/// The major version whose behavior is kept, so that CI scripts parsing the output of geoffrey keep working
+/// after upgrading the binary: the codes of the diagnostics, the schema of the JSON report and the exit codes.
+///
+/// The diagnostic codes of a version are never changed or reused, see `GeoffreyError::code`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compat {
+    #[default]
+    V1,
+}
+
+impl Compat {
+    /// The version of the schema of the JSON report
+    pub fn schema_version(self) -> u32 {
+        match self {
+            Compat::V1 => 1,
+        }
+    }
+
+    /// The exit code if the run failed
+    pub fn exit_code_failure(self) -> i32 {
+        match self {
+            Compat::V1 => 1,
+        }
+    }
+
+    /// The exit code if the run was stopped by '--timeout', like the one of the `timeout` command
+    pub fn exit_code_timeout(self) -> i32 {
+        match self {
+            Compat::V1 => 124,
+        }
+    }
+}
+
+impl FromStr for Compat {
+    type Err = GeoffreyError;
+
+    fn from_str(version: &str) -> Result<Self, Self::Err> {
+        match version {
+            "1" => Ok(Compat::V1),
+            _ => Err(GeoffreyError::InvalidCompat(version.to_owned())),
+        }
+    }
+}
@@ -0,0 +1,508 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::error::GeoffreyError;
+use crate::marker::MarkerForm;
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The configuration from the 'geoffrey.toml' file at the git toplevel of the documentation
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Additional repositories with content files, referenced by '[name:path/to/file]' in geoffrey tags
+    pub repos: BTreeMap<String, RepoConfig>,
+    /// The line endings of the code blocks inserted into the markdown files
+    pub eol: Eol,
+    /// The directory the content file paths in geoffrey tags are relative to
+    pub paths: ContentPaths,
+    /// Directories in which the content files are searched in order instead of the git toplevel,
+    /// relative to the directory of the config file
+    pub content_roots: Vec<PathBuf>,
+    /// Directory with docs-only variants of content files, relative to the directory of the config file;
+    /// a file at the same path relative to the git toplevel or content root shadows the real one
+    pub overrides: Option<PathBuf>,
+    /// Content which is generated during the run, referenced by '[gen:name]' in geoffrey tags
+    pub generated: GeneratedConfig,
+    /// The keywords identifying a geoffrey tag like '<!-- [geoffrey] [path] -->'; the first one is used by
+    /// `geoffrey fmt`, the others are accepted e.g. during a migration. Defaults to 'geoffrey'.
+    pub keywords: Vec<String>,
+    /// Named sets of values like '[envs.cloud]' which are substituted for the '{{name}}' placeholders in the
+    /// snippets of the geoffrey tags with the option 'env=cloud'
+    pub envs: BTreeMap<String, BTreeMap<String, String>>,
+    /// The comment forms accepted for snippet markers in content files, e.g. '["doxygen", "line", "block"]'
+    /// for '//! [tag]', '// [tag]' and '/* [tag] */'. Defaults to 'doxygen'.
+    pub markers: Vec<MarkerForm>,
+    /// Whether a snippet name may only be defined in one of the content files referenced by the documentation,
+    /// e.g. when snippets are referred to by name only in the prose
+    pub unique_snippets: bool,
+    /// The size in bytes above which content files are skipped with a warning. Defaults to 10 MiB.
+    pub max_content_file_size: Option<u64>,
+    /// The size in bytes above which markdown files are skipped with a warning. Defaults to 10 MiB.
+    pub max_md_file_size: Option<u64>,
+    /// The number of snippets in a content file which may be nested into each other. Defaults to 32.
+    pub max_nesting_depth: Option<usize>,
+    /// The values of the '{{geoffrey:name}}' placeholders in the synced code blocks and markdown templates
+    pub variables: VariablesConfig,
+    /// A regular expression the snippet names in the content files and geoffrey tags are expected to match,
+    /// e.g. '^[a-z0-9]+(-[a-z0-9]+)*$' for kebab-case; names which do not match are reported as warnings
+    pub tag_pattern: Option<String>,
+    /// Whether the hash of each synced code block is recorded in its tag, so that code blocks edited by hand
+    /// since are not overwritten without '--force'
+    pub guard_edits: bool,
+    /// The number of columns the lines of the synced code blocks should not exceed, e.g. to fit the page width
+    /// of a PDF; wider lines are reported as warnings. The tag option 'max-width' overrides it.
+    pub max_width: Option<usize>,
+    /// The commands which compile the synced snippets with '--verify', by the language of the code block or the
+    /// extension of the content file, like 'cpp = "g++ -fsyntax-only {file}"'; '{file}' is replaced by the file
+    /// with the snippet in a temporary directory, which is the working directory of the command
+    pub verify: BTreeMap<String, String>,
+    /// The commands which run the snippets of output tags like '<!-- [geoffrey-output] [path] [snippet] -->', by
+    /// the extension of the content file or its language, like 'py = "python3 {file}"'; '{file}' is replaced like
+    /// for 'verify' and the standard output becomes the code block
+    pub run: BTreeMap<String, String>,
+    /// The commands whose standard output may be embedded by '[cmd:command]' in geoffrey tags, like
+    /// 'cargo run --example'; a command is allowed if its words start with the words of one of them
+    pub allowed_commands: Vec<String>,
+    /// Whether a comment with the content file, the last commit changing it and the hash of the code is emitted
+    /// in the first line of every synced code block, see `provenance::Provenance`
+    pub provenance: bool,
+    /// A file to which the log messages are also appended, relative to the directory of the config file; read
+    /// from the config for the working directory and overridden by '--log-file'
+    pub log_file: Option<PathBuf>,
+}
+
+/// The sources of the values of the '{{geoffrey:name}}' placeholders
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VariablesConfig {
+    /// A TOML or JSON file with the values, relative to the directory of the config file; the values of nested
+    /// tables are named with dots like 'server.port'
+    pub file: Option<PathBuf>,
+    /// Variables whose value is the single line of a snippet like 'port = "config.toml#port"' or of a whole file
+    /// like 'version = "VERSION"'; the paths are relative like in the geoffrey tags
+    pub snippets: BTreeMap<String, String>,
+}
+
+/// The directory the content file paths in geoffrey tags are relative to; paths prefixed with a repository
+/// are always relative to the git toplevel of that repository
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ContentPaths {
+    #[default]
+    GitToplevel,
+    MarkdownFile,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GeneratedConfig {
+    /// Command which is run once before the outputs are captured, e.g. to build the binaries
+    pub build: Option<String>,
+    pub outputs: BTreeMap<String, GeneratedOutput>,
+}
+
+/// The source of a generated content file; commands and paths are relative to the directory of the config file
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase", deny_unknown_fields)]
+pub enum GeneratedOutput {
+    /// the standard output of a shell command
+    Command(String),
+    /// a file created by the build command
+    File(PathBuf),
+}
+
+/// Line endings; 'preserve' uses the line endings of the markdown file
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Eol {
+    Lf,
+    Crlf,
+    #[default]
+    Preserve,
+}
+
+/// A 'geoffrey.toml' file in a subdirectory of the documentation, which overrides the settings of the parent
+/// directories for the markdown files in its subtree
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SubtreeConfig {
+    pub eol: Option<Eol>,
+    pub paths: Option<ContentPaths>,
+    /// relative to the directory of the config file
+    pub content_roots: Option<Vec<PathBuf>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RepoConfig {
+    /// Path to the repository, relative to the directory of the config file
+    pub path: PathBuf,
+    /// Remote to clone the repository from if it does not exist at `path`
+    pub remote: Option<String>,
+}
+
+impl Config {
+    pub const FILE_NAME: &'static str = "geoffrey.toml";
+    /// The prefix of generated content in geoffrey tags, which can therefore not be used as repository name
+    pub const GENERATED_PREFIX: &'static str = "gen";
+    /// The prefix of the commands in geoffrey tags like '[cmd:my-tool --help]' whose output is embedded, see
+    /// `allowed_commands`
+    pub const COMMAND_PREFIX: &'static str = "cmd";
+    /// The scheme of the paths in geoffrey tags like 'crate://serde/src/lib.rs', which refer to the sources of a
+    /// crate of the cargo workspace or one of its dependencies
+    pub const CRATE_SCHEME: &'static str = "crate://";
+    /// The directory next to the config file with the code blocks as last synced with 'guard_edits', named by
+    /// their hash; they are the base for merging manual edits with changed snippets
+    pub const SNAPSHOT_DIR: &'static str = ".geoffrey-snapshots";
+    pub const DEFAULT_KEYWORD: &'static str = "geoffrey";
+    pub const DEFAULT_MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
+    pub const DEFAULT_MAX_NESTING_DEPTH: usize = 32;
+
+    /// Loads the config file from `dir` or returns the default config if there is none
+    pub fn load(dir: &Path) -> Result<Self, GeoffreyError> {
+        let config = load_file::<Self>(dir)?.unwrap_or_default();
+
+        let valid_keyword = |keyword: &String| {
+            !keyword.is_empty()
+                && keyword
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        };
+        if let Some(keyword) = config
+            .keywords
+            .iter()
+            .find(|keyword| !valid_keyword(keyword))
+        {
+            return Err(GeoffreyError::ConfigError(
+                dir.join(Self::FILE_NAME),
+                format!(
+                    "invalid keyword '{}'; only letters, digits, '-' and '_' are allowed",
+                    keyword
+                ),
+            ));
+        }
+
+        if config.max_nesting_depth == Some(0) {
+            return Err(GeoffreyError::ConfigError(
+                dir.join(Self::FILE_NAME),
+                "'max_nesting_depth' must be at least 1".to_owned(),
+            ));
+        }
+
+        if let Some(pattern) = &config.tag_pattern {
+            regex::Regex::new(pattern).map_err(|e| {
+                GeoffreyError::ConfigError(
+                    dir.join(Self::FILE_NAME),
+                    format!("invalid 'tag_pattern': {}", e),
+                )
+            })?;
+        }
+
+        Ok(config)
+    }
+
+    /// The configured marker forms or the doxygen markers if there are none
+    pub fn markers(&self) -> Vec<MarkerForm> {
+        if self.markers.is_empty() {
+            vec![MarkerForm::Doxygen]
+        } else {
+            self.markers.clone()
+        }
+    }
+
+    /// The configured size limit for content files or the default one
+    pub fn max_content_file_size(&self) -> u64 {
+        self.max_content_file_size
+            .unwrap_or(Self::DEFAULT_MAX_FILE_SIZE)
+    }
+
+    /// The configured size limit for markdown files or the default one
+    pub fn max_md_file_size(&self) -> u64 {
+        self.max_md_file_size.unwrap_or(Self::DEFAULT_MAX_FILE_SIZE)
+    }
+
+    /// The configured nesting limit for snippets or the default one
+    pub fn max_nesting_depth(&self) -> usize {
+        self.max_nesting_depth
+            .unwrap_or(Self::DEFAULT_MAX_NESTING_DEPTH)
+    }
+
+    /// The configured keywords or the default keyword if there are none
+    pub fn keywords(&self) -> Vec<String> {
+        if self.keywords.is_empty() {
+            vec![Self::DEFAULT_KEYWORD.to_owned()]
+        } else {
+            self.keywords.clone()
+        }
+    }
+}
+
+impl VariablesConfig {
+    /// The values from the values file, relative to `dir`, by their names; empty if there is none
+    pub fn load_values(&self, dir: &Path) -> Result<BTreeMap<String, String>, GeoffreyError> {
+        let path = match &self.file {
+            Some(file) => dir.join(file),
+            None => return Ok(BTreeMap::new()),
+        };
+
+        let content = fs::read_to_string(&path)?;
+        let is_json = path
+            .extension()
+            .is_some_and(|extension| extension == "json");
+        let values = if is_json {
+            serde_json::from_str::<serde_json::Value>(&content).map_err(|e| e.to_string())
+        } else {
+            toml::from_str::<serde_json::Value>(&content).map_err(|e| e.to_string())
+        }
+        .map_err(|e| GeoffreyError::ConfigError(path.clone(), e))?;
+
+        let mut flattened = BTreeMap::new();
+        flatten_values("", &values, &mut flattened)
+            .map_err(|e| GeoffreyError::ConfigError(path, e))?;
+
+        Ok(flattened)
+    }
+}
+
+/// Collects the strings, numbers and booleans of `value` by their names, prefixed with the names of the tables
+/// they are nested in
+fn flatten_values(
+    name: &str,
+    value: &serde_json::Value,
+    values: &mut BTreeMap<String, String>,
+) -> Result<(), String> {
+    use serde_json::Value;
+
+    match value {
+        Value::Object(table) => {
+            for (key, value) in table {
+                let name = match name {
+                    "" => key.clone(),
+                    _ => format!("{}.{}", name, key),
+                };
+                flatten_values(&name, value, values)?;
+            }
+        }
+        Value::String(text) => {
+            values.insert(name.to_owned(), text.clone());
+        }
+        Value::Number(number) => {
+            values.insert(name.to_owned(), number.to_string());
+        }
+        Value::Bool(flag) => {
+            values.insert(name.to_owned(), flag.to_string());
+        }
+        Value::Array(_) | Value::Null => {
+            return Err(format!(
+                "the value of '{}' must be a string, number or boolean",
+                name
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+impl SubtreeConfig {
+    /// Loads the config file from `dir` if there is one
+    pub fn load(dir: &Path) -> Result<Option<Self>, GeoffreyError> {
+        load_file(dir)
+    }
+}
+
+fn load_file<T: DeserializeOwned>(dir: &Path) -> Result<Option<T>, GeoffreyError> {
+    let path = dir.join(Config::FILE_NAME);
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(&path)?;
+    toml::from_str(&content)
+        .map(Some)
+        .map_err(|e| GeoffreyError::ConfigError(path, e.to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use anyhow::Result;
+
+    #[test]
+    fn config_with_repos_can_be_parsed() -> Result<()> {
+        let config: Config = toml::from_str(
+            r#"
+            [repos.product-a]
+            path = "../product-a"
+            remote = "https://example.com/product-a.git"
+
+            [repos.product-b]
+            path = "/opt/product-b"
+            "#,
+        )?;
+
+        assert_eq!(config.repos.len(), 2);
+        assert_eq!(
+            config.repos["product-a"].path,
+            PathBuf::from("../product-a")
+        );
+        assert_eq!(
+            config.repos["product-a"].remote.as_deref(),
+            Some("https://example.com/product-a.git")
+        );
+        assert_eq!(config.repos["product-b"].remote, None);
+        assert_eq!(config.eol, Eol::Preserve);
+
+        Ok(())
+    }
+
+    #[test]
+    fn config_with_eol_can_be_parsed() -> Result<()> {
+        let config: Config = toml::from_str("eol = \"crlf\"\n")?;
+
+        assert_eq!(config.eol, Eol::Crlf);
+        assert_eq!(config.paths, ContentPaths::GitToplevel);
+        assert!(toml::from_str::<Config>("eol = \"cr\"\n").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn config_with_content_paths_can_be_parsed() -> Result<()> {
+        let config: Config = toml::from_str("paths = \"markdown-file\"\n")?;
+
+        assert_eq!(config.paths, ContentPaths::MarkdownFile);
+
+        let config: Config = toml::from_str("content_roots = [\"../src\", \"../include\"]\n")?;
+
+        assert_eq!(
+            config.content_roots,
+            [PathBuf::from("../src"), PathBuf::from("../include")]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn config_with_generated_content_can_be_parsed() -> Result<()> {
+        let config: Config = toml::from_str(
+            r#"
+            [generated]
+            build = "cargo build"
+
+            [generated.outputs]
+            cli-help = { command = "target/debug/geoffrey --help" }
+            schema = { file = "target/schema.json" }
+            "#,
+        )?;
+
+        assert_eq!(config.generated.build.as_deref(), Some("cargo build"));
+        assert!(matches!(
+            &config.generated.outputs["cli-help"],
+            GeneratedOutput::Command(command) if command == "target/debug/geoffrey --help"
+        ));
+        assert!(matches!(
+            &config.generated.outputs["schema"],
+            GeneratedOutput::File(file) if file == Path::new("target/schema.json")
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn subtree_config_must_not_contain_repos() -> Result<()> {
+        let config: SubtreeConfig = toml::from_str("eol = \"lf\"\n")?;
+
+        assert_eq!(config.eol, Some(Eol::Lf));
+        assert_eq!(config.paths, None);
+        assert!(toml::from_str::<SubtreeConfig>("[repos.foo]\npath = \"foo\"\n").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn config_with_envs() -> Result<()> {
+        let config: Config = toml::from_str(
+            "[envs.cloud]\nendpoint = \"https://api.example.com\"\n[envs.on-prem]\nendpoint = \"http://localhost:8080\"\n",
+        )?;
+
+        assert_eq!(config.envs.len(), 2);
+        assert_eq!(config.envs["on-prem"]["endpoint"], "http://localhost:8080");
+
+        Ok(())
+    }
+
+    #[test]
+    fn config_with_keywords() -> Result<()> {
+        let tmp_dir = tempfile::Builder::new().prefix("geoffrey").tempdir()?;
+
+        fs::write(
+            tmp_dir.path().join(Config::FILE_NAME),
+            "keywords = [\"include-code\", \"snippet\"]\n",
+        )?;
+        assert_eq!(
+            Config::load(tmp_dir.path())?.keywords(),
+            ["include-code", "snippet"]
+        );
+
+        fs::write(
+            tmp_dir.path().join(Config::FILE_NAME),
+            "keywords = [\"in clude\"]\n",
+        )?;
+        assert!(Config::load(tmp_dir.path()).is_err());
+
+        assert_eq!(Config::default().keywords(), ["geoffrey"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn config_with_unknown_field_fails() {
+        assert!(
+            toml::from_str::<Config>("[repos.foo]\npath = \"foo\"\nbranch = \"main\"\n").is_err()
+        );
+    }
+
+    #[test]
+    fn values_files_are_flattened() -> Result<()> {
+        let tmp_dir = tempfile::Builder::new().prefix("geoffrey").tempdir()?;
+        fs::write(
+            tmp_dir.path().join("values.toml"),
+            "version = \"1.2.3\"\n[server]\nport = 8080\ntls = true\n",
+        )?;
+        fs::write(
+            tmp_dir.path().join("values.json"),
+            r#"{ "server": { "hosts": ["a", "b"] } }"#,
+        )?;
+
+        let variables = VariablesConfig {
+            file: Some(PathBuf::from("values.toml")),
+            ..VariablesConfig::default()
+        };
+        let values = variables.load_values(tmp_dir.path())?;
+        assert_eq!(
+            values.into_iter().collect::<Vec<(String, String)>>(),
+            [
+                ("server.port".to_owned(), "8080".to_owned()),
+                ("server.tls".to_owned(), "true".to_owned()),
+                ("version".to_owned(), "1.2.3".to_owned()),
+            ]
+        );
+
+        let variables = VariablesConfig {
+            file: Some(PathBuf::from("values.json")),
+            ..VariablesConfig::default()
+        };
+        assert!(matches!(
+            variables.load_values(tmp_dir.path()),
+            Err(GeoffreyError::ConfigError(_, message)) if message.contains("'server.hosts'")
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,180 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::socket::Root;
+
+use geoffrey::report::{BlockReport, Diagnostic, Report};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum ResyncStatus {
+    Changed,
+    Unchanged,
+    Failed,
+}
+
+/// The result of a 'resync <path>' request
+#[derive(Debug, Serialize)]
+struct ResyncResponse<'a> {
+    path: &'a Path,
+    status: ResyncStatus,
+    blocks: &'a [BlockReport],
+    diagnostics: &'a [Diagnostic],
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+/// Serves one request per line on each connection to the unix socket at `path` and answers with one line of JSON;
+/// `resync` syncs the markdown file or folder of a 'resync <path>' request if it is inside of `root`, see
+/// `socket::serve`
+#[cfg(unix)]
+pub fn serve<F>(path: &Path, root: &Path, resync: F) -> Result<()>
+where
+    F: Fn(&Path) -> Report,
+{
+    let root = Root::new(root)?;
+    crate::socket::serve(path, |reader, writer| {
+        handle_connection(reader, writer, &root, &resync)
+    })
+}
+
+fn handle_connection<R, W, F>(reader: R, mut writer: W, root: &Root, resync: &F) -> Result<()>
+where
+    R: BufRead,
+    W: Write,
+    F: Fn(&Path) -> Report,
+{
+    for line in reader.lines() {
+        let response = handle_request(&line?, root, resync)?;
+        writer.write_all(response.as_bytes())?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+    }
+
+    Ok(())
+}
+
+/// The answer to `request`; paths which are not inside of `root` are refused
+fn handle_request<F>(request: &str, root: &Root, resync: &F) -> Result<String>
+where
+    F: Fn(&Path) -> Report,
+{
+    let error = |error: String| Ok(serde_json::to_string(&ErrorResponse { error })?);
+    let path = match request.trim().split_once(' ') {
+        Some(("resync", path)) if !path.trim().is_empty() => PathBuf::from(path.trim()),
+        _ => {
+            return error(format!(
+                "invalid request '{}'; expected 'resync <path>'",
+                request
+            ))
+        }
+    };
+    if let Err(reason) = root.check(&path) {
+        return error(reason);
+    }
+
+    log::info!("resync {:?}", path);
+    let report = resync(&path);
+    let status = if !report.diagnostics.is_empty() {
+        ResyncStatus::Failed
+    } else if report.summary.snippets_updated > 0 {
+        ResyncStatus::Changed
+    } else {
+        ResyncStatus::Unchanged
+    };
+
+    Ok(serde_json::to_string(&ResyncResponse {
+        path: &path,
+        status,
+        blocks: &report.blocks,
+        diagnostics: &report.diagnostics,
+    })?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use geoffrey::report::Summary;
+    use tempfile::Builder;
+
+    #[test]
+    fn resync_request_is_answered_with_status() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+        let root = tmp_dir.path().canonicalize()?;
+        std::fs::create_dir(root.join("doc"))?;
+        std::fs::write(root.join("doc/changed.md"), "")?;
+        std::fs::write(root.join("doc/unchanged.md"), "")?;
+        let resync = |path: &Path| Report {
+            summary: Summary {
+                snippets_updated: usize::from(path.ends_with("doc/changed.md")),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let root = Root::new(&root)?;
+
+        let changed = tmp_dir.path().canonicalize()?.join("doc/changed.md");
+        assert_eq!(
+            handle_request(&format!("resync {}", changed.display()), &root, &resync)?,
+            format!(
+                r#"{{"path":"{}","status":"changed","blocks":[],"diagnostics":[]}}"#,
+                changed.display()
+            )
+        );
+        let unchanged = tmp_dir.path().canonicalize()?.join("doc/unchanged.md");
+        assert_eq!(
+            handle_request(&format!("resync {}\r", unchanged.display()), &root, &resync)?,
+            format!(
+                r#"{{"path":"{}","status":"unchanged","blocks":[],"diagnostics":[]}}"#,
+                unchanged.display()
+            )
+        );
+        assert_eq!(
+            handle_request("sync doc", &root, &resync)?,
+            r#"{"error":"invalid request 'sync doc'; expected 'resync <path>'"}"#
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn paths_outside_of_the_root_are_refused() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+        let root = tmp_dir.path().canonicalize()?.join("doc");
+        std::fs::create_dir(&root)?;
+        std::fs::write(tmp_dir.path().join("outside.md"), "")?;
+        let resync = |_: &Path| -> Report { panic!("the path must not be synced") };
+
+        let requests = format!(
+            "resync {}\nresync {}\nresync {}\n",
+            tmp_dir.path().join("outside.md").display(),
+            root.join("../outside.md").display(),
+            root.join("missing.md").display()
+        );
+        let mut responses = Vec::new();
+        handle_connection(
+            requests.as_bytes(),
+            &mut responses,
+            &Root::new(&root)?,
+            &resync,
+        )?;
+
+        let responses = String::from_utf8(responses)?;
+        let responses = responses.lines().collect::<Vec<&str>>();
+        assert_eq!(responses.len(), 3);
+        assert!(responses[0].contains("is outside of"));
+        assert!(responses[1].contains("is outside of"));
+        assert!(responses[2].contains("is not readable"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,215 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::error::{FileError, GeoffreyError};
+use crate::rdjson;
+use crate::report::Diagnostic;
+
+use serde::Serialize;
+use similar::TextDiff;
+use yansi::Paint;
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// A code block which is not in sync with its content file
+#[derive(Debug, Serialize)]
+pub struct BlockDiff {
+    pub md_file: PathBuf,
+    pub line: usize,
+    /// the first line of `current`, which is the content of the code block or its opening fence if it changed
+    pub code_line: usize,
+    /// the character offset of `current` in `code_line` for the inline code spans of inline tags
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+    pub content_file: String,
+    /// the embedded snippet or `None` for the full file
+    pub snippet: Option<String>,
+    /// the current content of the code block
+    pub current: String,
+    /// the content of the code block after syncing
+    pub synced: String,
+}
+
+/// How `geoffrey diff` prints the stale code blocks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffFormat {
+    /// a unified diff per code block
+    Unified,
+    /// a JSON line per stale code block, failure and warning for reviewdog, see `DiffReport::rdjsonl`
+    Rdjsonl,
+}
+
+impl FromStr for DiffFormat {
+    type Err = GeoffreyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "unified" => Ok(DiffFormat::Unified),
+            "rdjsonl" => Ok(DiffFormat::Rdjsonl),
+            _ => Err(GeoffreyError::InvalidDiffFormat(s.to_owned())),
+        }
+    }
+}
+
+/// The outcome of diffing the markdown documentation with the content files
+#[derive(Debug)]
+pub struct DiffReport {
+    pub diffs: Vec<BlockDiff>,
+    pub failures: Vec<FileError>,
+    /// the problems which did not prevent rendering, see `GeoffreyWarning`
+    pub warnings: Vec<Diagnostic>,
+    /// the markdown files which were not diffed because the deadline was reached
+    pub skipped: Vec<PathBuf>,
+}
+
+impl DiffReport {
+    /// The stale code blocks, failures and warnings as JSON lines in the Reviewdog Diagnostic Format for
+    /// 'reviewdog -f=rdjsonl', with the paths relative to `base`; the stale code blocks have a suggestion
+    /// with their synced content
+    pub fn rdjsonl(&self, base: &Path) -> String {
+        rdjson::render(self, base)
+    }
+}
+
+impl BlockDiff {
+    /// The unified diff from the current to the synced content of the code block
+    pub fn unified(&self) -> String {
+        let source = match &self.snippet {
+            Some(snippet) => format!("{} [{}]", self.content_file, snippet),
+            None => self.content_file.clone(),
+        };
+
+        TextDiff::from_lines(&self.current, &self.synced)
+            .unified_diff()
+            .context_radius(3)
+            .header(
+                &format!("{}:{}", self.md_file.display(), self.line),
+                &source,
+            )
+            .to_string()
+    }
+}
+
+impl fmt::Display for BlockDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_colored(f, &self.unified())
+    }
+}
+
+/// The new content of a file, e.g. after renaming a snippet
+#[derive(Debug)]
+pub struct FileEdit {
+    pub path: PathBuf,
+    pub original: String,
+    pub edited: String,
+}
+
+impl FileEdit {
+    /// The unified diff from the original to the edited content
+    pub fn unified(&self) -> String {
+        let path = self.path.display().to_string();
+        TextDiff::from_lines(&self.original, &self.edited)
+            .unified_diff()
+            .context_radius(1)
+            .header(&path, &path)
+            .to_string()
+    }
+
+    /// The edit as patch which `git apply` applies in `base`, with the path of the file relative to it
+    pub fn patch(&self, base: &Path) -> String {
+        let path = self.path.strip_prefix(base).unwrap_or(&self.path).display();
+        format!(
+            "diff --git a/{0} b/{0}\n{1}",
+            path,
+            TextDiff::from_lines(&self.original, &self.edited)
+                .unified_diff()
+                .context_radius(3)
+                .header(&format!("a/{}", path), &format!("b/{}", path))
+        )
+    }
+}
+
+/// The pending updates of the markdown files which are not in sync with their content files
+#[derive(Debug)]
+pub struct PendingEdits {
+    /// the edits of the markdown files with stale code blocks, sorted by path
+    pub edits: Vec<FileEdit>,
+    /// the markdown files which were not rendered because the deadline was reached
+    pub skipped: Vec<PathBuf>,
+    /// the directory in which `git apply` applies the patch
+    pub base: PathBuf,
+}
+
+impl PendingEdits {
+    /// The edits as patch which `git apply` applies in `base`
+    pub fn patch(&self) -> String {
+        self.edits
+            .iter()
+            .map(|edit| edit.patch(&self.base))
+            .collect()
+    }
+}
+
+impl fmt::Display for FileEdit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_colored(f, &self.unified())
+    }
+}
+
+/// Writes a unified diff with the headers in bold, the hunk headers in cyan and the changed lines in red and green
+fn write_colored(f: &mut fmt::Formatter<'_>, unified: &str) -> fmt::Result {
+    for line in unified.lines() {
+        if line.starts_with("---") || line.starts_with("+++") {
+            writeln!(f, "{}", Paint::new(line).bold())?;
+        } else if line.starts_with("@@") {
+            writeln!(f, "{}", Paint::cyan(line))?;
+        } else if line.starts_with('-') {
+            writeln!(f, "{}", Paint::red(line))?;
+        } else if line.starts_with('+') {
+            writeln!(f, "{}", Paint::green(line))?;
+        } else {
+            writeln!(f, "{}", line)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn unified_diff_shows_stale_lines_of_the_block() {
+        let diff = BlockDiff {
+            md_file: PathBuf::from("doc.md"),
+            line: 3,
+            code_line: 5,
+            column: None,
+            content_file: "src/main.cpp".to_owned(),
+            snippet: Some("answer".to_owned()),
+            current: "int answer() {\n    return 41;\n}\n".to_owned(),
+            synced: "int answer() {\n    return 42;\n}\n".to_owned(),
+        };
+
+        assert_eq!(
+            diff.unified(),
+            "--- doc.md:3\n+++ src/main.cpp [answer]\n@@ -1,3 +1,3 @@\n int answer() {\n-    return 41;\n+    return 42;\n }\n"
+        );
+    }
+
+    #[test]
+    fn patch_has_git_headers_relative_to_the_base() {
+        let edit = FileEdit {
+            path: PathBuf::from("/repo/doc/api.md"),
+            original: "```cpp\nreturn 41;\n```\n".to_owned(),
+            edited: "```cpp\nreturn 42;\n```\n".to_owned(),
+        };
+
+        assert_eq!(
+            edit.patch(Path::new("/repo")),
+            "diff --git a/doc/api.md b/doc/api.md\n--- a/doc/api.md\n+++ b/doc/api.md\n@@ -1,3 +1,3 @@\n ```cpp\n-return 41;\n+return 42;\n ```\n"
+        );
+    }
+}
@@ -0,0 +1,248 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::config::Config;
+use crate::documents::{Documents, Symlinks};
+use crate::error::FileError;
+
+use std::fmt;
+use std::fs::OpenOptions;
+use std::path::Path;
+
+/// The outcome of one check of `geoffrey doctor`
+#[derive(Debug)]
+pub struct Check {
+    pub name: &'static str,
+    /// a note like the found git toplevel if the check passed, otherwise the problems
+    pub result: Result<String, Vec<String>>,
+}
+
+impl Check {
+    pub fn passed(&self) -> bool {
+        self.result.is_ok()
+    }
+}
+
+/// Like '[ok]   name: note' or '[fail] name' with the problems indented on the following lines
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.result {
+            Ok(note) if note.is_empty() => write!(f, "[ok]   {}", self.name),
+            Ok(note) => write!(f, "[ok]   {}: {}", self.name, note),
+            Err(problems) => {
+                write!(f, "[fail] {}", self.name)?;
+                for line in problems.iter().flat_map(|problem| problem.lines()) {
+                    write!(f, "\n       {}", line)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Checks the environment of the documentation at `doc_path`, i.e. the doc path, the git toplevel, the config,
+/// the permissions of the markdown files, the geoffrey tags and the referenced content files and snippets;
+/// the checks after a failed one which they depend on are left out
+pub fn diagnose(doc_path: &Path, symlinks: Symlinks) -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    if !doc_path.exists() {
+        checks.push(Check {
+            name: "doc path exists",
+            result: Err(vec![format!("'{}' does not exist", doc_path.display())]),
+        });
+        return checks;
+    }
+    checks.push(Check {
+        name: "doc path exists",
+        result: Ok(doc_path.display().to_string()),
+    });
+
+    let doc_dir = if doc_path.is_dir() {
+        doc_path
+    } else {
+        doc_path.parent().unwrap_or(Path::new("."))
+    };
+    let git_toplevel = match Documents::git_toplevel(doc_dir) {
+        Ok(git_toplevel) => git_toplevel,
+        Err(e) => {
+            checks.push(Check {
+                name: "git toplevel is resolved",
+                result: Err(vec![e.to_string()]),
+            });
+            return checks;
+        }
+    };
+    checks.push(Check {
+        name: "git toplevel is resolved",
+        result: Ok(if git_toplevel.as_os_str().is_empty() {
+            "not in a git repository; the content paths are relative to the current directory"
+                .to_owned()
+        } else {
+            git_toplevel.display().to_string()
+        }),
+    });
+
+    // without git, the config is expected next to the documentation
+    let config_dir = if git_toplevel.as_os_str().is_empty() {
+        doc_dir
+    } else {
+        git_toplevel.as_path()
+    };
+    let config_path = config_dir.join(Config::FILE_NAME);
+    let config = Config::load(config_dir).map(|_| {
+        if config_path.exists() {
+            config_path.display().to_string()
+        } else {
+            format!("no '{}'; using the defaults", config_path.display())
+        }
+    });
+    let config_failed = config.is_err();
+    checks.push(Check {
+        name: "config is valid",
+        result: config.map_err(|e| vec![e.to_string()]),
+    });
+    if config_failed {
+        return checks;
+    }
+
+    let mut documents = match Documents::new(doc_path.to_path_buf(), symlinks) {
+        Ok(documents) => documents,
+        Err(e) => {
+            checks.push(Check {
+                name: "markdown files are found",
+                result: Err(vec![e.to_string()]),
+            });
+            return checks;
+        }
+    };
+    let md_paths = documents.md_paths();
+    checks.push(Check {
+        name: "markdown files are found",
+        result: Ok(format!("{} files", md_paths.len())),
+    });
+
+    let inaccessible = md_paths
+        .iter()
+        .filter_map(|path| {
+            OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(path)
+                .err()
+                .map(|e| format!("{}: {}", path.display(), e))
+        })
+        .collect::<Vec<String>>();
+    checks.push(Check {
+        name: "markdown files are readable and writable",
+        result: outcome(inaccessible),
+    });
+
+    if let Err(e) = documents.parse_md_files() {
+        checks.push(Check {
+            name: "geoffrey tags are valid",
+            result: Err(vec![e.to_string()]),
+        });
+        return checks;
+    }
+    let md_failures = documents.failures().len();
+    checks.push(Check {
+        name: "geoffrey tags are valid",
+        result: outcome(
+            documents
+                .failures()
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+        ),
+    });
+
+    if let Err(e) = documents.parse_content_files() {
+        checks.push(Check {
+            name: "referenced content files and snippets exist",
+            result: Err(vec![e.to_string()]),
+        });
+        return checks;
+    }
+    let mut content_problems = documents.failures()[md_failures..]
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<String>>();
+    content_problems.extend(
+        documents
+            .tag_problems()
+            .into_iter()
+            .map(|problem| FileError::new(problem.md_file, problem.error).to_string()),
+    );
+    checks.push(Check {
+        name: "referenced content files and snippets exist",
+        result: outcome(content_problems),
+    });
+
+    checks
+}
+
+/// Passed if there are no problems
+fn outcome(problems: Vec<String>) -> Result<String, Vec<String>> {
+    if problems.is_empty() {
+        Ok(String::new())
+    } else {
+        Err(problems)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use anyhow::Result;
+    use tempfile::Builder;
+
+    use std::fs;
+
+    #[test]
+    fn diagnose_reports_each_check() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let checks = diagnose(&tmp_dir.path().join("missing"), Symlinks::Follow);
+        assert_eq!(checks.len(), 1);
+        assert!(!checks[0].passed());
+
+        let content_path = tmp_dir.path().join("content.cpp");
+        fs::write(&content_path, "//! [answer]\n42\n//! [answer]\n")?;
+        fs::write(
+            tmp_dir.path().join("doc.md"),
+            format!(
+                "<!-- [geoffrey] [{0}] [answer] -->\n```cpp\n```\n<!-- [geoffrey] [{0}] [question] -->\n```cpp\n```\n",
+                content_path.display()
+            ),
+        )?;
+
+        let checks = diagnose(tmp_dir.path(), Symlinks::Follow);
+        let outcomes = checks
+            .iter()
+            .map(|check| (check.name, check.passed()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            outcomes,
+            [
+                ("doc path exists", true),
+                ("git toplevel is resolved", true),
+                ("config is valid", true),
+                ("markdown files are found", true),
+                ("markdown files are readable and writable", true),
+                ("geoffrey tags are valid", true),
+                ("referenced content files and snippets exist", false),
+            ]
+        );
+        let report = checks[6].to_string();
+        assert!(report.starts_with("[fail] referenced content files and snippets exist\n       "));
+        assert!(report.contains("'question'"));
+
+        fs::write(tmp_dir.path().join(Config::FILE_NAME), "eol = \"cr\"\n")?;
+        let checks = diagnose(tmp_dir.path(), Symlinks::Follow);
+        assert_eq!(checks.len(), 3);
+        assert!(!checks[2].passed());
+
+        Ok(())
+    }
+}
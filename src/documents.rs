@@ -1,19 +1,73 @@
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::error::GeoffreyError;
+use crate::cargo;
+use crate::config::{Config, ContentPaths, Eol, SubtreeConfig};
+use crate::diff::{BlockDiff, DiffReport, FileEdit, PendingEdits};
+use crate::error::{FileError, GeoffreyError, GeoffreyWarning, SourceSpan, TagProblem};
+use crate::fence::{self, CodeFence, FenceTracker};
+use crate::marker::{CommentStyle, MarkerForm};
+use crate::merge;
+use crate::provenance::{self, Provenance};
+use crate::report::{
+    BlockReport, BlockStatus, CommitInfo, Coverage, Diagnostic, Freshness, OrphanedMarker, Report,
+    SnippetLocation, SnippetUsage, Summary, TagEntry,
+};
+use crate::selector::{Selectors, SnippetSelector};
+use crate::source::{self, AllowedCommandSource, SnippetSource, Sources};
+use crate::suggest;
+use crate::tangle::{self, TangledFile};
 
+use chrono::{DateTime, Utc};
 use rayon::prelude::*;
 use regex::Regex;
+use similar::{ChangeTag, TextDiff};
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::{self, OpenOptions};
-use std::io::{BufRead, BufReader, Write};
-use std::path::PathBuf;
-use std::sync::Mutex;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 use std::vec::Vec;
 
 type Tag = String;
 
+/// The UTF-8 byte order mark which might precede the first line of a markdown file
+const BOM: char = '\u{feff}';
+
+/// The extension of markdown templates, which are rendered to a markdown file without it
+const TEMPLATE_EXTENSION: &str = ".tpl";
+
+/// The extensions of markdown files, compared case-insensitively
+const MD_EXTENSIONS: &[&str] = &["md", "mdx", "markdown", "mdown"];
+
+/// The suffix of the keyword of output tags like '<!-- [geoffrey-output] [path] [snippet] -->'
+const OUTPUT_SUFFIX: &str = "-output";
+
+/// The extensions of source files whose documentation comments are synced like markdown files
+const DOC_COMMENT_EXTENSIONS: &[&str] = &["h", "hh", "hpp", "hxx", "java"];
+
+/// How the snippet markers of the content files are parsed, from the config
+#[derive(Debug, Clone)]
+struct ContentSyntax {
+    /// the accepted comment forms of the snippet markers
+    markers: Vec<MarkerForm>,
+    /// the number of snippets which may be nested into each other
+    max_nesting_depth: usize,
+}
+
+impl Default for ContentSyntax {
+    fn default() -> Self {
+        Self {
+            markers: vec![MarkerForm::Doxygen],
+            max_nesting_depth: Config::DEFAULT_MAX_NESTING_DEPTH,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 struct ContentSnippetDescription {
     tag: String,
@@ -26,15 +80,59 @@ struct ContentSnippetDescription {
 
 #[derive(Debug)]
 struct ContentFile {
+    path: PathBuf,
+    /// the content root in which the file was found
+    root: Option<PathBuf>,
+    style: &'static CommentStyle,
+    /// the accepted comment forms of the snippet markers
+    markers: Vec<MarkerForm>,
     data: Vec<String>,
     lookup: HashMap<Tag, ContentSnippetDescription>,
+    /// whether the file was skipped for its size or binary content; its code blocks are kept as they are
+    skipped: bool,
+    /// for the content of a file at a git revision, the key of the file in the working tree and the revision
+    revision: Option<(String, String)>,
 }
 
 impl ContentFile {
-    fn new() -> Self {
+    fn new(path: PathBuf, style: &'static CommentStyle) -> Self {
         ContentFile {
+            path,
+            root: None,
+            style,
+            markers: Vec::new(),
             data: Vec::new(),
             lookup: HashMap::new(),
+            skipped: false,
+            revision: None,
+        }
+    }
+}
+
+/// The comment syntax around a geoffrey tag
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TagEnvelope {
+    /// '<!-- [geoffrey] [path] -->' in markdown files
+    Html,
+    /// '{/* [geoffrey] [path] */}' in MDX files, which do not allow HTML comments
+    Jsx,
+}
+
+impl TagEnvelope {
+    /// The canonical envelope for the tags of a markdown file or template
+    fn for_path(path: &Path) -> Self {
+        let path = path.to_string_lossy();
+        let path = path.strip_suffix(TEMPLATE_EXTENSION).unwrap_or(&path);
+        match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("mdx") => TagEnvelope::Jsx,
+            _ => TagEnvelope::Html,
+        }
+    }
+
+    fn wrap(&self, tag: &str) -> String {
+        match self {
+            TagEnvelope::Html => format!("<!-- {} -->", tag),
+            TagEnvelope::Jsx => format!("{{/* {} */}}", tag),
         }
     }
 }
@@ -46,733 +144,9728 @@ enum MdSnippetTag {
     ElidedSnippet { main: String, sub: Vec<String> },
 }
 
-#[derive(Debug)]
-struct MdSnippetId {
-    path: String,
-    tag: MdSnippetTag,
-}
+impl MdSnippetTag {
+    /// The tag of the snippet to embed; empty for the full file
+    fn main(&self) -> &str {
+        match self {
+            MdSnippetTag::FullFile => "",
+            MdSnippetTag::FullSnippet { main } => main,
+            MdSnippetTag::ElidedSnippet { main, .. } => main,
+        }
+    }
 
-#[derive(Debug)]
-struct MdSegment {
-    text: String,
-    snippet_id: Option<MdSnippetId>,
-}
+    /// The geoffrey tag comment in canonical form
+    fn comment(
+        &self,
+        keyword: &str,
+        path: &str,
+        options: &TagOptions,
+        envelope: TagEnvelope,
+    ) -> String {
+        let tag = match self {
+            MdSnippetTag::FullFile => {
+                format!("[{}] [{}]{}", keyword, escape_tag_field(path), options)
+            }
+            MdSnippetTag::FullSnippet { main } => format!(
+                "[{}] [{}] [{}]{}",
+                keyword,
+                escape_tag_field(path),
+                escape_tag_field(main),
+                options
+            ),
+            MdSnippetTag::ElidedSnippet { main, sub } => {
+                let sub = sub
+                    .iter()
+                    .map(|sub| format!(" [{}]", escape_tag_field(sub)))
+                    .collect::<String>();
+                format!(
+                    "[{}] [{}] [[{}]{}]{}",
+                    keyword,
+                    escape_tag_field(path),
+                    escape_tag_field(main),
+                    sub,
+                    options
+                )
+            }
+        };
+        envelope.wrap(&tag)
+    }
 
-#[derive(Debug)]
-struct MdFile {
-    path: PathBuf,
-    segments: Vec<MdSegment>,
+    /// The tag of the snippet to embed or `None` for the full file
+    fn snippet(&self) -> Option<String> {
+        match self {
+            MdSnippetTag::FullFile => None,
+            tag => Some(tag.main().to_owned()),
+        }
+    }
 }
 
-impl MdFile {
-    fn new(path: PathBuf) -> Self {
-        Self {
-            path,
-            segments: Vec::new(),
+/// The text of a bracketed field of a geoffrey tag, i.e. the path or a snippet name, with the escaped characters
+/// '\[', '\]' and '\\' unescaped; other backslashes are kept
+pub(crate) fn unescape_tag_field(field: &str) -> String {
+    let mut text = String::with_capacity(field.len());
+    let mut chars = field.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('\\', Some(&next)) if matches!(next, '[' | ']' | '\\') => {
+                text.push(next);
+                chars.next();
+            }
+            _ => text.push(c),
         }
     }
+    text
 }
 
-type ContentMap = HashMap<String, ContentFile>;
-
-#[derive(Debug)]
-pub struct Documents {
-    git_toplevel: PathBuf,
-    md_files: Vec<MdFile>,
-    content: ContentMap,
+/// The text as a bracketed field of a geoffrey tag, with '[', ']' and '\' escaped by a backslash
+pub(crate) fn escape_tag_field(text: &str) -> String {
+    let mut field = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '[' | ']' | '\\') {
+            field.push('\\');
+        }
+        field.push(c);
+    }
+    field
 }
 
-impl Documents {
-    pub fn new(doc_path: PathBuf) -> Result<Self, GeoffreyError> {
-        if !doc_path.exists() {
-            return Err(GeoffreyError::DocPathDoesNotExist(doc_path));
-        }
+/// The options after the snippet of a geoffrey tag, like 'env=cloud' or the flag 'dedent'
+#[derive(Debug, Clone, Default, PartialEq)]
+struct TagOptions {
+    /// remove the leading whitespace common to all non-empty lines of the code block
+    dedent: bool,
+    /// keep the markers of the nested snippets in the code block instead of removing them
+    keep_markers: bool,
+    /// keep the given number of lines at the start and the end of each elided snippet, like 'grep -C'
+    context: Option<usize>,
+    /// the environment from the config whose values are substituted for the placeholders in the snippet
+    env: Option<String>,
+    /// instead of eliding, embed the full snippet and highlight the lines of the sub-snippets
+    highlight: Option<Highlight>,
+    /// render the code blocks of a glob tag as a tab group with one tab per language instead of with headings
+    tabs: Option<Tabs>,
+    /// render the diff of the snippet between two git revisions instead of the snippet
+    rev: Option<Revisions>,
+    /// the replacements which are applied in order to the lines of the snippet
+    replace: Vec<Replacement>,
+    /// the maximum number of lines of the code block; longer snippets are an error
+    max_lines: Option<usize>,
+    /// replace the tabs in the code block by spaces up to the next tab stop, which are the given number of
+    /// columns apart
+    expand_tabs: Option<usize>,
+    /// remove the whitespace at the end of the lines of the code block
+    strip_trailing_whitespace: bool,
+    /// the number of columns the lines of the code block should not exceed; overrides 'max_width' of the config
+    max_width: Option<usize>,
+    /// wrap the lines wider than the maximum width at whitespace
+    wrap: bool,
+    /// emit an HTML anchor in the line before the tag, with the given id or one derived from the snippet
+    anchor: Option<Anchor>,
+    /// the behavior if the snippet or content file is missing; overrides '--fallback'
+    fallback: Option<Fallback>,
+    /// the hash of the code block when it was last synced, see `block_hash`; set by the sync with 'guard_edits'
+    hash: Option<String>,
+}
 
-        let doc_dir = if doc_path.is_dir() {
-            doc_path.clone()
-        } else {
-            doc_path
-                .parent()
-                .ok_or(GeoffreyError::GitToplevelError)?
-                .to_path_buf()
-        };
-        let git_toplevel = std::process::Command::new("git")
-            .arg("rev-parse")
-            .arg("--show-toplevel")
-            .current_dir(doc_dir)
-            .output()
-            .map_err(|_| GeoffreyError::GitToplevelError)?;
+/// The id of the anchor emitted for a tag with the 'anchor' option
+#[derive(Debug, Clone, PartialEq)]
+enum Anchor {
+    /// 'snippet-' followed by the snippet name, or the file name for the full file, like 'snippet-main'
+    Derived,
+    /// the id given with 'anchor=id'
+    Id(String),
+}
 
-        let git_toplevel = PathBuf::from(
-            std::str::from_utf8(&git_toplevel.stdout)
-                .map_err(|_| GeoffreyError::GitToplevelError)?
-                .trim(),
-        );
+/// A 'replace="pattern -> replacement"' option; the pattern is a regular expression and the replacement
+/// may refer to its capture groups like '$1'
+#[derive(Debug, Clone, PartialEq)]
+struct Replacement {
+    pattern: String,
+    replacement: String,
+}
 
-        let mut md_files = Vec::new();
+impl FromStr for Replacement {
+    type Err = GeoffreyError;
 
-        if doc_path.is_file() {
-            Self::is_md_file(doc_path).map(|file| md_files.push(MdFile::new(file)))?;
-        } else {
-            Self::find_md_files(&doc_path, &mut |file| md_files.push(MdFile::new(file)))?;
-            if md_files.is_empty() {
-                return Err(GeoffreyError::NoMarkdownFilesInPath(doc_path));
-            }
-        }
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (pattern, replacement) = value.split_once(" -> ").ok_or_else(|| {
+            GeoffreyError::InvalidReplacement(value.to_owned(), "missing ' -> '".to_owned())
+        })?;
+        Regex::new(pattern)
+            .map_err(|e| GeoffreyError::InvalidReplacement(value.to_owned(), e.to_string()))?;
 
         Ok(Self {
-            git_toplevel,
-            md_files,
-            content: ContentMap::new(),
+            pattern: pattern.to_owned(),
+            replacement: replacement.to_owned(),
         })
     }
+}
 
-    pub fn parse(&mut self) -> Result<(), GeoffreyError> {
-        log::info!("#### parse md files for tags");
-        let content = Mutex::new(&mut self.content);
-        self.md_files
-            .par_iter_mut()
-            .map(|md_file| {
-                Self::parse_single_md_file(md_file, &content)?;
-                Ok(())
-            })
-            .collect::<Result<(), GeoffreyError>>()?;
+/// The syntax of the fence attribute with the highlighted lines, like 'hl_lines'
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Highlight {
+    /// '{hl_lines=[2,"4-6"]}' for Hugo
+    Hugo,
+    /// 'hl_lines="2 4-6"' for MkDocs
+    Mkdocs,
+}
 
-        log::info!("#### parse content files for tags");
-        let git_toplevel = &self.git_toplevel;
-        self.content
-            .par_iter_mut()
-            .map(|(path, content_file)| {
-                let absolute_path = git_toplevel.join(path);
-                if !absolute_path.exists() {
-                    return Err(GeoffreyError::ContentFileNotFound(path.to_owned()));
-                }
-                *content_file = Self::parse_content_file(&absolute_path)?;
+impl FromStr for Highlight {
+    type Err = GeoffreyError;
 
-                Ok(())
-            })
-            .collect::<Result<(), GeoffreyError>>()?;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hugo" => Ok(Highlight::Hugo),
+            "mkdocs" => Ok(Highlight::Mkdocs),
+            _ => Err(GeoffreyError::InvalidTagOption(format!("highlight={}", s))),
+        }
+    }
+}
 
-        Ok(())
+impl std::fmt::Display for Highlight {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Highlight::Hugo => write!(f, "hugo"),
+            Highlight::Mkdocs => write!(f, "mkdocs"),
+        }
     }
+}
 
-    fn has_elided_lines(
-        tags: &Vec<&str>,
-        elided_lines: &mut Vec<usize>,
-        ellipsis_lines: &mut Vec<(usize, usize, String)>,
-        snip_desc: &ContentSnippetDescription,
-    ) -> bool {
-        let current_snippet_tag = &snip_desc.tag as &str;
-        let mut keep_this = tags.contains(&current_snippet_tag);
-        let keep_nested = snip_desc
-            .nested
-            .iter()
-            .map(|snip_desc| {
-                let keep = Self::has_elided_lines(tags, elided_lines, ellipsis_lines, snip_desc);
-                keep_this |= keep;
-                keep
-            })
-            .collect::<Vec<bool>>();
+impl Highlight {
+    /// The opening fence with the attribute highlighting `lines`, replacing a previous one of the same syntax
+    fn fence(&self, fence: &str, lines: &[usize]) -> Result<String, GeoffreyError> {
+        let content = fence.trim_end_matches(['\r', '\n']);
+        let eol = &fence[content.len()..];
+        let re_attribute = match self {
+            Highlight::Hugo => Regex::new(r"\s*\{hl_lines=\[[^\]]*\]\}"),
+            Highlight::Mkdocs => Regex::new(r#"\s*hl_lines="[^"]*""#),
+        }
+        .map_err(|_| GeoffreyError::RegexError)?;
+        let mut fence = re_attribute.replace_all(content, "").into_owned();
 
-        if keep_this {
-            keep_nested
-                .iter()
-                .zip(snip_desc.nested.iter())
-                .for_each(|(keep, snip_desc)| {
-                    if !keep {
-                        ellipsis_lines.push((
-                            snip_desc.begin,
-                            snip_desc.end,
-                            snip_desc.ellipsis_line.clone(),
-                        ));
-                        elided_lines.extend_from_slice(
-                            &(snip_desc.begin..=snip_desc.end)
-                                .into_iter()
-                                .map(|x| x)
-                                .collect::<Vec<usize>>(),
-                        )
-                    }
-                });
+        let mut ranges = Vec::<(usize, usize)>::new();
+        for &line in lines {
+            match ranges.last_mut() {
+                Some(range) if range.1 + 1 == line => range.1 = line,
+                _ => ranges.push((line, line)),
+            }
+        }
+        let ranges = ranges
+            .iter()
+            .map(|(first, last)| match (self, first == last) {
+                (_, true) => first.to_string(),
+                (Highlight::Hugo, false) => format!("\"{}-{}\"", first, last),
+                (Highlight::Mkdocs, false) => format!("{}-{}", first, last),
+            });
+        if !lines.is_empty() {
+            match self {
+                Highlight::Hugo => fence.push_str(&format!(
+                    " {{hl_lines=[{}]}}",
+                    ranges.collect::<Vec<_>>().join(",")
+                )),
+                Highlight::Mkdocs => fence.push_str(&format!(
+                    " hl_lines=\"{}\"",
+                    ranges.collect::<Vec<_>>().join(" ")
+                )),
+            }
         }
+        fence.push_str(eol);
 
-        keep_this
+        Ok(fence)
     }
+}
 
-    pub fn sync(self) -> Result<(), GeoffreyError> {
-        log::info!("#### sync md files with content");
-        self.md_files
-            .par_iter()
-            .map(|md_file| {
-                // create synced data
-                let mut synced_file = String::new();
-                for segment in md_file.segments.iter() {
-                    synced_file.push_str(&segment.text);
-                    if let Some(snippet_id) = &segment.snippet_id {
-                        let content_cache = self.content.get(&snippet_id.path).ok_or(
-                            GeoffreyError::ContentFileNotFound(snippet_id.path.to_owned()),
-                        )?;
+/// The syntax of the tab group of a glob tag with the 'tabs' option
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Tabs {
+    /// '<Tabs>' and '<TabItem>' for Docusaurus; the page must import both components
+    Docusaurus,
+    /// content tabs like '=== "C++"' for MkDocs with the 'pymdownx.tabbed' extension
+    Mkdocs,
+}
 
-                        let tag = match &snippet_id.tag {
-                            MdSnippetTag::FullFile => "",
-                            MdSnippetTag::FullSnippet { main } => &main,
-                            MdSnippetTag::ElidedSnippet { main, .. } => &main,
-                        };
+impl FromStr for Tabs {
+    type Err = GeoffreyError;
 
-                        let mut ellipsis_lines = Vec::<(usize, usize, String)>::new();
-
-                        if let Some(snip_desc) = content_cache.lookup.get(tag) {
-                            let mut elided_lines = Vec::new();
-                            if let MdSnippetTag::ElidedSnippet { main, sub } = &snippet_id.tag {
-                                let mut all_tags = Vec::<&str>::new();
-                                all_tags.push(main);
-                                sub.into_iter().for_each(|tag| all_tags.push(tag));
-
-                                Self::has_elided_lines(
-                                    &all_tags,
-                                    &mut elided_lines,
-                                    &mut ellipsis_lines,
-                                    &snip_desc,
-                                );
-                                elided_lines.sort();
-
-                                let mut empty_lines = Vec::new();
-                                let mut potentially_remove = Vec::new();
-                                let mut extend_empty_on_next_non_empty = false;
-
-                                let mut current_line = snip_desc.end.min(snip_desc.begin + 1);
-                                for elided in &elided_lines {
-                                    while *elided > current_line {
-                                        let trimmed = content_cache.data[current_line].trim();
-                                        if trimmed.is_empty() {
-                                            potentially_remove.push(current_line);
-                                        } else {
-                                            if extend_empty_on_next_non_empty {
-                                                empty_lines.extend_from_slice(&potentially_remove);
-                                            }
-                                            extend_empty_on_next_non_empty = false;
-                                            potentially_remove.clear();
-                                        }
-                                        current_line += 1;
-                                    }
-                                    empty_lines.extend_from_slice(&potentially_remove);
-                                    potentially_remove.clear();
-                                    extend_empty_on_next_non_empty = true;
-                                    current_line += 1;
-                                }
-                                while snip_desc.end > current_line {
-                                    let trimmed = content_cache.data[current_line].trim();
-                                    if trimmed.is_empty() {
-                                        potentially_remove.push(current_line);
-                                    } else {
-                                        empty_lines.extend_from_slice(&potentially_remove);
-                                        potentially_remove.clear();
-                                        break;
-                                    }
-                                    current_line += 1;
-                                }
-                                empty_lines.extend_from_slice(&potentially_remove);
-                                potentially_remove.clear();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "docusaurus" => Ok(Tabs::Docusaurus),
+            "mkdocs" => Ok(Tabs::Mkdocs),
+            _ => Err(GeoffreyError::InvalidTagOption(format!("tabs={}", s))),
+        }
+    }
+}
 
-                                elided_lines.extend_from_slice(&empty_lines);
-                                elided_lines.sort();
-                            }
+impl std::fmt::Display for Tabs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Tabs::Docusaurus => write!(f, "docusaurus"),
+            Tabs::Mkdocs => write!(f, "mkdocs"),
+        }
+    }
+}
 
-                            let snippet = match &snippet_id.tag {
-                                MdSnippetTag::FullFile => content_cache.data[..]
-                                    .into_iter()
-                                    .map(|line| line as &str)
-                                    .collect::<Vec<&str>>(),
-                                MdSnippetTag::FullSnippet { .. } => content_cache.data
-                                    [snip_desc.end.min(snip_desc.begin + 1)..snip_desc.end]
-                                    .into_iter()
-                                    .map(|line| line as &str)
-                                    .collect::<Vec<&str>>(),
-                                MdSnippetTag::ElidedSnippet { .. } => {
-                                    let mut current_line = snip_desc.end.min(snip_desc.begin + 1);
-
-                                    let mut remaining_lines = Vec::<&str>::new();
-                                    let mut add_ellipsis_line = true;
-
-                                    for elided in &elided_lines {
-                                        while *elided > current_line {
-                                            remaining_lines.push(&content_cache.data[current_line]);
-                                            current_line += 1;
-                                            add_ellipsis_line = true;
-                                        }
-
-                                        if add_ellipsis_line {
-                                            for ellipsis in &ellipsis_lines {
-                                                if current_line >= ellipsis.0
-                                                    || current_line <= ellipsis.1
-                                                {
-                                                    remaining_lines.push(&ellipsis.2);
-                                                    break;
-                                                }
-                                            }
-
-                                            add_ellipsis_line = false;
-                                        }
-                                        current_line += 1;
-                                    }
-                                    while snip_desc.end > current_line {
-                                        remaining_lines.push(&content_cache.data[current_line]);
-                                        current_line += 1;
-                                    }
-                                    remaining_lines
-                                }
-                            };
-
-                            let re = Regex::new(r"( *)//! \[(.*)\]")
-                                .map_err(|_| GeoffreyError::RegexError)?;
-                            for line in snippet {
-                                // skip tag lines
-                                if !re.is_match(line) {
-                                    synced_file.push_str(
-                                        line.strip_prefix(&snip_desc.indentation).unwrap_or(&line),
-                                    );
-                                }
-                            }
-                            Ok(())
-                        } else {
-                            Err(GeoffreyError::ContentSnippetNotFound(
-                                snippet_id.path.to_owned(),
-                                tag.to_owned(),
-                            ))
-                        }?;
+impl Tabs {
+    /// The tab group with a tab for each of the `code_blocks`, given as extension and code
+    fn group(&self, code_blocks: &[(String, String)]) -> String {
+        let mut group = String::new();
+        match self {
+            Tabs::Docusaurus => {
+                group.push_str("<Tabs>\n");
+                for (extension, code_block) in code_blocks {
+                    group.push_str(&format!(
+                        "<TabItem value=\"{}\" label=\"{}\">\n\n```{}\n{}```\n\n</TabItem>\n",
+                        extension,
+                        fence::language_name(extension),
+                        extension,
+                        code_block
+                    ));
+                }
+                group.push_str("</Tabs>\n");
+            }
+            Tabs::Mkdocs => {
+                for (extension, code_block) in code_blocks {
+                    group.push_str(&format!("=== \"{}\"\n\n", fence::language_name(extension)));
+                    let fenced = format!("```{}\n{}```\n", extension, code_block);
+                    for line in fenced.split_inclusive('\n') {
+                        if !line.trim().is_empty() {
+                            group.push_str("    ");
+                        }
+                        group.push_str(line);
                     }
+                    group.push('\n');
                 }
+            }
+        }
 
-                // sync to file
-                let mut file = OpenOptions::new()
-                    .write(true)
-                    .create(false)
-                    .truncate(true)
-                    .open(md_file.path.clone())?;
+        group
+    }
+}
 
-                file.write_all(synced_file.as_bytes())?;
-                file.sync_all()?;
+/// The old and the new git revision of a 'rev=v1.0..v2.0' option
+#[derive(Debug, Clone, PartialEq)]
+struct Revisions {
+    old: String,
+    new: String,
+}
 
-                Ok(())
-            })
-            .collect::<Result<(), GeoffreyError>>()
-    }
+impl FromStr for Revisions {
+    type Err = GeoffreyError;
 
-    fn find_md_files(
-        doc_path: &PathBuf,
-        md_found_cb: &mut dyn FnMut(PathBuf),
-    ) -> Result<(), GeoffreyError> {
-        for dir_entry in fs::read_dir(doc_path)? {
-            let dir_entry = dir_entry?;
-            let path = dir_entry.path();
-            if path.is_dir() {
-                Self::find_md_files(&path, md_found_cb)?;
-            } else {
-                Self::is_md_file(path)
-                    .map(|md_file| md_found_cb(md_file))
-                    .ok();
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.split_once("..") {
+            // the revisions are written unquoted in canonical tags and must not be taken as options of git
+            Some((old, new))
+                if [old, new].iter().all(|rev| {
+                    !rev.is_empty()
+                        && !rev.starts_with('-')
+                        && rev
+                            .chars()
+                            .all(|c| c.is_alphanumeric() || "_-./~^".contains(c))
+                }) =>
+            {
+                Ok(Self {
+                    old: old.to_owned(),
+                    new: new.to_owned(),
+                })
             }
+            _ => Err(GeoffreyError::InvalidTagOption(format!("rev={}", value))),
         }
+    }
+}
 
-        Ok(())
+impl std::fmt::Display for Revisions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}..{}", self.old, self.new)
     }
+}
 
-    fn is_md_file(path: PathBuf) -> Result<PathBuf, GeoffreyError> {
-        path.extension()
-            .as_ref()
-            .and_then(|ext_osstr| ext_osstr.to_str())
-            .and_then(|ext| {
-                if ext.eq_ignore_ascii_case("md") {
-                    Some(())
-                } else {
-                    None
+impl Revisions {
+    /// The keys of the content file `path` at the old and the new revision, each with its revision
+    fn keys(&self, path: &str) -> [(String, &str); 2] {
+        [&self.old, &self.new].map(|rev| (format!("{}@{}", path, rev), rev.as_str()))
+    }
+
+    /// The lines of the `old` and the `new` snippet for a diff code block, prefixed by ' ' if unchanged, by '-'
+    /// if removed and by '+' if added
+    fn diff(old: &str, new: &str) -> String {
+        TextDiff::from_lines(old, new)
+            .iter_all_changes()
+            .map(|change| {
+                let sign = match change.tag() {
+                    ChangeTag::Equal => ' ',
+                    ChangeTag::Delete => '-',
+                    ChangeTag::Insert => '+',
+                };
+                let mut line = format!("{}{}", sign, change.value());
+                if change.missing_newline() {
+                    line.push('\n');
                 }
+                line
             })
-            .ok_or(GeoffreyError::NotAMarkdownFile(path.clone()))?;
-
-        Ok(path)
+            .collect()
     }
+}
 
-    fn parse_single_md_file(
-        md_file: &mut MdFile,
-        content: &Mutex<&mut ContentMap>,
-    ) -> Result<(), GeoffreyError> {
-        let f = fs::File::open(md_file.path.clone())?;
-        let mut reader = BufReader::new(f);
+impl TagOptions {
+    /// Splits the options into names and values, flags like 'dedent' have no value; values with whitespace are
+    /// quoted like 'replace="a -> b"', where '\"' and '\\' are escapes for a quote and a backslash
+    fn split(options: &str) -> Result<Vec<(&str, Option<String>)>, GeoffreyError> {
+        let mut split = Vec::new();
+        let mut rest = options.trim_start();
+        while !rest.is_empty() {
+            let invalid = || {
+                let option = rest.split_whitespace().next().unwrap_or_default();
+                GeoffreyError::InvalidTagOption(option.to_owned())
+            };
+            let name_end = rest
+                .find(|c: char| c == '=' || c.is_whitespace())
+                .unwrap_or(rest.len());
+            let (name, value) = match rest[name_end..].strip_prefix('=') {
+                Some(value) => (&rest[..name_end], value),
+                None => {
+                    split.push((&rest[..name_end], None));
+                    rest = rest[name_end..].trim_start();
+                    continue;
+                }
+            };
+            if name.is_empty() {
+                return Err(invalid());
+            }
 
-        let re_tag = Regex::new(r"^<!-- *\[geoffrey\] *\[([\w\s\.\-/]*)\] *(\[(.*)\])? *-->")
-            .map_err(|_| GeoffreyError::RegexError)?;
+            let (value, remainder) = match value.strip_prefix('"') {
+                Some(quoted) => {
+                    let mut unquoted = String::new();
+                    let mut chars = quoted.char_indices();
+                    let end = loop {
+                        match chars.next() {
+                            Some((i, '"')) => break i,
+                            Some((_, '\\')) => match chars.clone().next() {
+                                Some((_, escaped @ ('"' | '\\'))) => {
+                                    unquoted.push(escaped);
+                                    chars.next();
+                                }
+                                _ => unquoted.push('\\'),
+                            },
+                            Some((_, c)) => unquoted.push(c),
+                            None => return Err(invalid()),
+                        }
+                    };
+                    (unquoted, &quoted[end + 1..])
+                }
+                None => {
+                    let end = value.find(char::is_whitespace).unwrap_or(value.len());
+                    (value[..end].to_owned(), &value[end..])
+                }
+            };
+            split.push((name, Some(value)));
+            rest = remainder.trim_start();
+        }
 
-        let re_sub_tag = Regex::new(r"\[([\w\s\.\-]*)\]").map_err(|_| GeoffreyError::RegexError)?;
+        Ok(split)
+    }
 
-        let re_code_block = Regex::new(r"```").map_err(|_| GeoffreyError::RegexError)?;
+    /// Quotes a value with the escapes of `split`
+    fn quoted(value: &str) -> String {
+        let mut quoted = String::from('"');
+        let mut chars = value.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => quoted.push_str("\\\""),
+                '\\' if matches!(chars.peek(), None | Some('"') | Some('\\')) => {
+                    quoted.push_str("\\\\")
+                }
+                c => quoted.push(c),
+            }
+        }
+        quoted.push('"');
 
-        md_file.segments.push(MdSegment {
-            text: String::new(),
-            snippet_id: None,
-        });
-        let mut segment = md_file.segments.last_mut().expect("just added");
+        quoted
+    }
+}
 
-        let mut line = String::new();
-        while reader.read_line(&mut line)? > 0 {
-            segment.text.push_str(&line);
-            if let Some(caps) = re_tag.captures(&line) {
-                let path = caps.get(1).ok_or(GeoffreyError::RegexError)?.as_str();
-                let str_tag = caps.get(3).map_or("", |matcher| matcher.as_str().trim());
+impl FromStr for TagOptions {
+    type Err = GeoffreyError;
 
-                log::info!("{:?} '{}' - '{}'", md_file.path, path, str_tag);
-
-                let tag = match str_tag {
-                    "" => MdSnippetTag::FullFile,
-                    _ => {
-                        let mut caps_iter = re_sub_tag.captures_iter(str_tag);
-
-                        if let Some(caps) = caps_iter.next() {
-                            let main = caps
-                                .get(1)
-                                .ok_or(GeoffreyError::RegexError)?
-                                .as_str()
-                                .to_owned();
-                            let sub = caps_iter
-                                .map(|caps| {
-                                    Ok(caps
-                                        .get(1)
-                                        .ok_or(GeoffreyError::RegexError)?
-                                        .as_str()
-                                        .to_owned())
-                                })
-                                .collect::<Result<Vec<String>, GeoffreyError>>()?;
-                            MdSnippetTag::ElidedSnippet { main, sub }
-                        } else {
-                            MdSnippetTag::FullSnippet {
-                                main: str_tag.to_owned(),
-                            }
-                        }
+    fn from_str(options: &str) -> Result<Self, Self::Err> {
+        let mut tag_options = Self::default();
+        for (name, value) in Self::split(options)? {
+            let duplicate = || GeoffreyError::DuplicateTagOption(name.to_owned());
+            match (name, value) {
+                ("dedent", None) => {
+                    if std::mem::replace(&mut tag_options.dedent, true) {
+                        return Err(duplicate());
                     }
-                };
-
-                content
-                    .lock()
-                    .expect("could not lock mutex")
-                    .insert(path.to_owned(), ContentFile::new());
-                segment.snippet_id = Some(MdSnippetId {
-                    path: path.to_owned(),
-                    tag,
-                });
-
-                // next line must be the begin of a code block
-                let mut line = String::new();
-                if reader.read_line(&mut line)? > 0 && re_code_block.is_match(&line) {
-                    segment.text.push_str(&line);
-                    Ok(())
-                } else {
-                    Err(GeoffreyError::CodeBlockMustFollowTag(
-                        md_file.path.clone(),
-                        str_tag.to_owned(),
-                    ))
-                }?;
-
-                // skip everything until the end of the code block which is part of the next segment
-                md_file.segments.push(MdSegment {
-                    text: String::new(),
-                    snippet_id: None,
-                });
-                segment = md_file.segments.last_mut().expect("just added");
+                }
+                ("keep-markers", None) => {
+                    if std::mem::replace(&mut tag_options.keep_markers, true) {
+                        return Err(duplicate());
+                    }
+                }
+                ("context", Some(value)) => {
+                    let context = value
+                        .parse()
+                        .ok()
+                        .filter(|context| *context > 0)
+                        .ok_or_else(|| {
+                            GeoffreyError::InvalidTagOption(format!("context={}", value))
+                        })?;
+                    if tag_options.context.replace(context).is_some() {
+                        return Err(duplicate());
+                    }
+                }
+                ("env", Some(value)) if !value.is_empty() => {
+                    if tag_options.env.replace(value).is_some() {
+                        return Err(duplicate());
+                    }
+                }
+                ("highlight", Some(value)) => {
+                    if tag_options.highlight.replace(value.parse()?).is_some() {
+                        return Err(duplicate());
+                    }
+                }
+                ("tabs", Some(value)) => {
+                    if tag_options.tabs.replace(value.parse()?).is_some() {
+                        return Err(duplicate());
+                    }
+                }
+                ("rev", Some(value)) => {
+                    if tag_options.rev.replace(value.parse()?).is_some() {
+                        return Err(duplicate());
+                    }
+                }
+                ("replace", Some(value)) => tag_options.replace.push(value.parse()?),
+                ("max-lines", Some(value)) => {
+                    let max_lines = value
+                        .parse()
+                        .ok()
+                        .filter(|max_lines| *max_lines > 0)
+                        .ok_or_else(|| {
+                            GeoffreyError::InvalidTagOption(format!("max-lines={}", value))
+                        })?;
+                    if tag_options.max_lines.replace(max_lines).is_some() {
+                        return Err(duplicate());
+                    }
+                }
+                ("expand-tabs", Some(value)) => {
+                    let tab_width = value
+                        .parse()
+                        .ok()
+                        .filter(|tab_width| *tab_width > 0)
+                        .ok_or_else(|| {
+                            GeoffreyError::InvalidTagOption(format!("expand-tabs={}", value))
+                        })?;
+                    if tag_options.expand_tabs.replace(tab_width).is_some() {
+                        return Err(duplicate());
+                    }
+                }
+                ("strip-trailing-whitespace", None) => {
+                    if std::mem::replace(&mut tag_options.strip_trailing_whitespace, true) {
+                        return Err(duplicate());
+                    }
+                }
+                ("max-width", Some(value)) => {
+                    let max_width = value
+                        .parse()
+                        .ok()
+                        .filter(|max_width| *max_width > 0)
+                        .ok_or_else(|| {
+                            GeoffreyError::InvalidTagOption(format!("max-width={}", value))
+                        })?;
+                    if tag_options.max_width.replace(max_width).is_some() {
+                        return Err(duplicate());
+                    }
+                }
+                ("wrap", None) => {
+                    if std::mem::replace(&mut tag_options.wrap, true) {
+                        return Err(duplicate());
+                    }
+                }
+                ("anchor", value) => {
+                    let anchor = match value {
+                        None => Anchor::Derived,
+                        Some(id)
+                            if !id.is_empty()
+                                && id.chars().all(|c| {
+                                    c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':')
+                                }) =>
+                        {
+                            Anchor::Id(id)
+                        }
+                        Some(id) => {
+                            return Err(GeoffreyError::InvalidTagOption(format!("anchor={}", id)))
+                        }
+                    };
+                    if tag_options.anchor.replace(anchor).is_some() {
+                        return Err(duplicate());
+                    }
+                }
+                ("fallback", Some(value)) => {
+                    let fallback = value.parse().map_err(|_| {
+                        GeoffreyError::InvalidTagOption(format!("fallback={}", value))
+                    })?;
+                    if tag_options.fallback.replace(fallback).is_some() {
+                        return Err(duplicate());
+                    }
+                }
+                ("hash", Some(value)) => {
+                    if value.len() != 16 || !value.chars().all(|c| c.is_ascii_hexdigit()) {
+                        return Err(GeoffreyError::InvalidTagOption(format!("hash={}", value)));
+                    }
+                    if tag_options.hash.replace(value).is_some() {
+                        return Err(duplicate());
+                    }
+                }
+                (name, None) => return Err(GeoffreyError::InvalidTagOption(name.to_owned())),
+                (name, Some(value)) => {
+                    return Err(GeoffreyError::InvalidTagOption(format!(
+                        "{}={}",
+                        name, value
+                    )))
+                }
+            }
+        }
 
-                let mut line = String::new();
-                let mut end_of_block_found = false;
-                while reader.read_line(&mut line)? > 0 {
-                    if re_code_block.is_match(&line) {
-                        segment.text.push_str(&line);
-                        end_of_block_found = true;
-                        break;
+        Ok(tag_options)
+    }
+}
+
+/// The options in canonical form, in the order of the fields and each preceded by a space
+impl std::fmt::Display for TagOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.dedent {
+            write!(f, " dedent")?;
+        }
+        if self.keep_markers {
+            write!(f, " keep-markers")?;
+        }
+        if let Some(context) = &self.context {
+            write!(f, " context={}", context)?;
+        }
+        if let Some(env) = &self.env {
+            write!(f, " env={}", env)?;
+        }
+        if let Some(highlight) = &self.highlight {
+            write!(f, " highlight={}", highlight)?;
+        }
+        if let Some(tabs) = &self.tabs {
+            write!(f, " tabs={}", tabs)?;
+        }
+        if let Some(rev) = &self.rev {
+            write!(f, " rev={}", rev)?;
+        }
+        for replace in &self.replace {
+            write!(
+                f,
+                " replace={}",
+                Self::quoted(&format!("{} -> {}", replace.pattern, replace.replacement))
+            )?;
+        }
+        if let Some(max_lines) = &self.max_lines {
+            write!(f, " max-lines={}", max_lines)?;
+        }
+        if let Some(tab_width) = &self.expand_tabs {
+            write!(f, " expand-tabs={}", tab_width)?;
+        }
+        if self.strip_trailing_whitespace {
+            write!(f, " strip-trailing-whitespace")?;
+        }
+        if let Some(max_width) = &self.max_width {
+            write!(f, " max-width={}", max_width)?;
+        }
+        if self.wrap {
+            write!(f, " wrap")?;
+        }
+        match &self.anchor {
+            Some(Anchor::Derived) => write!(f, " anchor")?,
+            Some(Anchor::Id(id)) => write!(f, " anchor={}", id)?,
+            None => (),
+        }
+        if let Some(fallback) = &self.fallback {
+            write!(f, " fallback={}", fallback)?;
+        }
+        if let Some(hash) = &self.hash {
+            write!(f, " hash={}", hash)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct MdSnippetId {
+    path: String,
+    tag: MdSnippetTag,
+    options: TagOptions,
+    line: usize,
+    /// the files matched by a glob like 'examples/*.cpp' in the path; each of them gets a code block
+    glob: Option<Vec<GlobMatch>>,
+    /// the prefix of the lines of the documentation comment with the tag, like ' * '; empty in markdown files
+    comment_prefix: String,
+    /// for inline tags like '<!--[geoffrey-inline][path][snippet]-->', whose code block is the value of the
+    /// inline code span following them, the character offset of the value in the line of the tag
+    inline: Option<usize>,
+    /// for output tags like '<!-- [geoffrey-output] [path] [snippet] -->', whose code block is the standard output
+    /// of the command configured in `run` of the config for the snippet
+    output: bool,
+}
+
+impl MdSnippetId {
+    /// The id of the anchor emitted before the tag with the 'anchor' option; inline tags have none
+    fn anchor_id(&self) -> Option<String> {
+        if self.inline.is_some() {
+            return None;
+        }
+        match self.options.anchor.as_ref()? {
+            Anchor::Id(id) => Some(id.clone()),
+            Anchor::Derived => {
+                let name = self.tag.snippet().unwrap_or_else(|| {
+                    Path::new(&self.path).file_name().map_or_else(
+                        || self.path.clone(),
+                        |name| name.to_string_lossy().into_owned(),
+                    )
+                });
+                // like the ids of headings, with runs of other characters replaced by a single '-'
+                let mut slug = String::from("snippet-");
+                for c in name.chars() {
+                    if c.is_alphanumeric() {
+                        slug.extend(c.to_lowercase());
+                    } else if !slug.ends_with('-') {
+                        slug.push('-');
                     }
-                    line.clear();
                 }
+                Some(slug.trim_end_matches('-').to_owned())
+            }
+        }
+    }
 
-                if !end_of_block_found {
-                    return Err(GeoffreyError::CodeBlockEndMissing(
-                        md_file.path.clone(),
-                        str_tag.to_owned(),
-                    ));
+    /// The keys of the content files referenced by the tag
+    fn content_paths(&self) -> Vec<&str> {
+        match &self.glob {
+            Some(matches) => matches
+                .iter()
+                .map(|glob_match| glob_match.path.as_str())
+                .collect(),
+            None => vec![self.path.as_str()],
+        }
+    }
+}
+
+#[derive(Debug)]
+struct GlobMatch {
+    /// the path relative to the directory the glob was resolved in, used for the heading of the code block
+    heading: String,
+    /// the key of the content file
+    path: String,
+    root: Option<PathBuf>,
+}
+
+/// What ends the code block after a geoffrey tag
+#[derive(Debug, Clone, Copy)]
+enum BlockEnd {
+    /// the closing fence of a markdown code block
+    Fence(CodeFence),
+    /// '@endcode' or '\endcode' in a documentation comment
+    EndCode,
+    /// the end tag of a glob tag, since its code blocks are generated
+    GlobEndTag,
+}
+
+#[derive(Debug)]
+struct MdSegment {
+    text: String,
+    snippet_id: Option<MdSnippetId>,
+    /// the line opening the code block after the tag; empty for glob tags, whose code blocks are generated
+    fence: String,
+    code_block: String,
+}
+
+/// A code block synced with 'guard_edits', see `Documents::guard_block`
+struct GuardedBlock {
+    /// the text of the segment with the hash of the written code block in its tag
+    text: String,
+    /// the written opening fence and code block
+    block: String,
+    /// the hash recorded in the tag and the synced code block which is saved as snapshot under it, see
+    /// `Documents::save_snapshot`
+    snapshot: Option<(String, String)>,
+    /// why the code block was not overwritten if it was edited by hand
+    conflict: Option<GeoffreyError>,
+}
+
+/// The opening fence and the content of a code block synced with its content file
+#[derive(Debug)]
+struct SyncedBlock {
+    fence: String,
+    code_block: String,
+}
+
+impl SyncedBlock {
+    /// Splits a code block with its opening fence, like a guarded one, after the line of the fence
+    fn from_block(mut block: String) -> Self {
+        let code_block = match block.find('\n') {
+            Some(end) => block.split_off(end + 1),
+            None => String::new(),
+        };
+
+        Self {
+            fence: block,
+            code_block,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct MdFile {
+    path: PathBuf,
+    segments: Vec<MdSegment>,
+    failed: bool,
+    /// whether the file or one of its content files was not parsed before the deadline, see `Documents::deadline`
+    skipped: bool,
+    /// whether the file uses '\r\n' line endings, detected from its first line
+    crlf: bool,
+    settings: Arc<Settings>,
+    /// the file to which a markdown template is rendered; markdown files are synced in place
+    output: Option<PathBuf>,
+    /// whether the file is a source file whose tags are in documentation comments
+    doc_comments: bool,
+    /// the text given in memory, which is parsed instead of the file, see `Documents::set_md_text`
+    text: Option<String>,
+}
+
+impl MdFile {
+    fn new(path: PathBuf, settings: Arc<Settings>) -> Self {
+        Self {
+            doc_comments: Documents::is_doc_comment_file(&path),
+            path,
+            segments: Vec::new(),
+            failed: false,
+            skipped: false,
+            crlf: false,
+            settings,
+            output: None,
+            text: None,
+        }
+    }
+}
+
+/// The settings for the markdown files of a documentation subtree, from the top-level config merged with
+/// the nested configs of the subtree
+#[derive(Debug, Clone, PartialEq)]
+struct Settings {
+    eol: Eol,
+    paths: ContentPaths,
+    content_roots: Vec<PathBuf>,
+}
+
+type ContentMap = HashMap<String, ContentFile>;
+
+/// The behavior when the snippet of a geoffrey tag is not present in the content file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fallback {
+    /// embed the whole content file instead of the snippet
+    FullFile,
+    /// keep the current content of the code block
+    Skip,
+    /// fail the sync
+    Error,
+}
+
+impl FromStr for Fallback {
+    type Err = GeoffreyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "full-file" => Ok(Fallback::FullFile),
+            "skip" => Ok(Fallback::Skip),
+            "error" => Ok(Fallback::Error),
+            _ => Err(GeoffreyError::InvalidFallback(s.to_owned())),
+        }
+    }
+}
+
+impl std::fmt::Display for Fallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Fallback::FullFile => write!(f, "full-file"),
+            Fallback::Skip => write!(f, "skip"),
+            Fallback::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// How symlinks are handled when searching for markdown files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Symlinks {
+    /// follow symlinked files and directories; each file and directory is visited only once
+    Follow,
+    /// ignore symlinked files and directories
+    Skip,
+}
+
+impl FromStr for Symlinks {
+    type Err = GeoffreyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "follow" => Ok(Symlinks::Follow),
+            "skip" => Ok(Symlinks::Skip),
+            _ => Err(GeoffreyError::InvalidSymlinks(s.to_owned())),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct SyncOptions {
+    pub fallback: Fallback,
+    /// determine when the content of each snippet was last modified
+    pub freshness: bool,
+    /// markdown files which are not started before this point in time are skipped
+    pub deadline: Option<Instant>,
+    /// keep code blocks which differ from the content only in indentation or trailing whitespace
+    pub ignore_whitespace: bool,
+    /// determine how many snippet markers are used by the documentation
+    pub coverage: bool,
+    /// overwrite code blocks which were edited by hand since they were synced, see `guard_edits` of the config
+    pub force: bool,
+    /// compile the synced snippets with the commands of `verify` of the config, see `verify_snippet`
+    pub verify: bool,
+    /// run the snippets of output tags with the commands of `run` of the config, see `snippet_output`; without,
+    /// the code blocks of output tags are kept as they are
+    pub run_snippets: bool,
+}
+
+impl SyncOptions {
+    fn deadline_reached(&self) -> bool {
+        deadline_reached(self.deadline)
+    }
+}
+
+fn deadline_reached(deadline: Option<Instant>) -> bool {
+    deadline.is_some_and(|deadline| Instant::now() >= deadline)
+}
+
+impl Default for SyncOptions {
+    fn default() -> Self {
+        Self {
+            fallback: Fallback::Error,
+            freshness: false,
+            deadline: None,
+            ignore_whitespace: false,
+            coverage: false,
+            force: false,
+            verify: false,
+            run_snippets: false,
+        }
+    }
+}
+
+/// Aborts long running operations of the documents when cancelled, e.g. by an editor when the user changes
+/// a file during a run; clones share the state
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Collects the warnings of a run, which are logged and reported sorted by file and location at the end, since
+/// they are emitted by parallel threads in any order
+#[derive(Debug, Default)]
+struct Warnings(Mutex<Vec<Diagnostic>>);
+
+impl Warnings {
+    fn warn(&self, file: &Path, span: Option<SourceSpan>, warning: GeoffreyWarning) {
+        self.0
+            .lock()
+            .expect("could not lock mutex")
+            .push(Diagnostic::warning(file, span, &warning));
+    }
+
+    /// Takes the collected warnings sorted by file and location and logs them
+    fn take(&self) -> Vec<Diagnostic> {
+        let mut warnings = std::mem::take(&mut *self.0.lock().expect("could not lock mutex"));
+        warnings.sort_by(|a, b| {
+            let location = |warning: &Diagnostic| {
+                let span = warning.span.as_ref();
+                (
+                    warning.file.clone(),
+                    span.map(|span| span.line),
+                    span.map(|span| span.column),
+                )
+            };
+            location(a).cmp(&location(b))
+        });
+        for warning in &warnings {
+            match (&warning.file, &warning.span) {
+                (Some(file), Some(span)) => log::warn!(
+                    "{}:{}: [{}] {}",
+                    file.display(),
+                    span.line,
+                    warning.code,
+                    warning.message
+                ),
+                (Some(file), None) => {
+                    log::warn!("{}: [{}] {}", file.display(), warning.code, warning.message)
                 }
+                (None, _) => log::warn!("[{}] {}", warning.code, warning.message),
             }
-            line.clear();
         }
-        Ok(())
+
+        warnings
+    }
+}
+
+impl Drop for Warnings {
+    /// Logs the warnings of runs which do not report them
+    fn drop(&mut self) {
+        self.take();
+    }
+}
+
+#[derive(Debug)]
+pub struct Documents {
+    git_toplevel: PathBuf,
+    repos: HashMap<String, PathBuf>,
+    /// the configured repositories which do not exist yet, with their remote and path; they are cloned by
+    /// `parse_content_files` if a tag references them
+    remotes: HashMap<String, (String, PathBuf)>,
+    md_files: Vec<MdFile>,
+    content: ContentMap,
+    failed_content: HashSet<String>,
+    failures: Vec<FileError>,
+    config_dir: PathBuf,
+    selectors: Selectors,
+    sources: Sources,
+    /// the settings from the top-level config
+    settings: Arc<Settings>,
+    /// the keywords of the geoffrey tags, the first one is the canonical one
+    keywords: Vec<String>,
+    re_tag: Regex,
+    cancellation: CancellationToken,
+    envs: BTreeMap<String, BTreeMap<String, String>>,
+    /// the directory with the files shadowing content files
+    overrides: Option<PathBuf>,
+    /// how the snippet markers in content files are parsed
+    syntax: ContentSyntax,
+    /// whether snippet names must be unique across the content files
+    unique_snippets: bool,
+    /// the pattern the snippet names are expected to match, see `lint_snippet_names`
+    tag_pattern: Option<Regex>,
+    /// whether the hash of each synced code block is recorded in its tag to detect manual edits
+    guard_edits: bool,
+    /// whether the provenance of each synced code block is emitted in its first line, see `with_provenance`
+    provenance: bool,
+    /// the number of columns the lines of the code blocks should not exceed, see `with_max_width`
+    max_width: Option<usize>,
+    /// the commands compiling the synced snippets by language or extension, see `verify_snippet`
+    verify: BTreeMap<String, String>,
+    /// the commands running the snippets of output tags by extension or language, see `snippet_output`
+    run: BTreeMap<String, String>,
+    /// the size limits in bytes above which content and markdown files are skipped
+    max_content_file_size: u64,
+    max_md_file_size: u64,
+    warnings: Warnings,
+    /// the last commits of the content files for the provenance comments, see `last_commit`
+    commits: Mutex<HashMap<PathBuf, Option<String>>>,
+    /// the tags to process, see `only_tags`; all tags if empty
+    tag_filters: Vec<String>,
+    /// the revision since which the markdown files or their content files must have changed, see `since`
+    since: Option<String>,
+    /// the files which are not parsed before this point in time are skipped, see `set_deadline`
+    deadline: Option<Instant>,
+    /// the values of the '{{geoffrey:name}}' placeholders; the ones from snippets are added when the content
+    /// files are parsed
+    variables: BTreeMap<String, String>,
+    /// the variables whose value is a snippet, with references like 'config.toml#port'
+    variable_snippets: BTreeMap<String, String>,
+}
+
+impl Documents {
+    pub fn new(doc_path: PathBuf, symlinks: Symlinks) -> Result<Self, GeoffreyError> {
+        Self::open(doc_path, symlinks, None)
+    }
+
+    /// Creates the documents for the markdown templates ending with '.md.tpl' in `doc_path` instead of the
+    /// markdown files; `sync` renders them to markdown files at the same relative path in `output_dir`
+    /// and does not touch the templates
+    pub fn with_templates(
+        doc_path: PathBuf,
+        symlinks: Symlinks,
+        output_dir: PathBuf,
+    ) -> Result<Self, GeoffreyError> {
+        Self::open(doc_path, symlinks, Some(output_dir))
+    }
+
+    fn open(
+        doc_path: PathBuf,
+        symlinks: Symlinks,
+        output_dir: Option<PathBuf>,
+    ) -> Result<Self, GeoffreyError> {
+        if !doc_path.exists() {
+            return Err(GeoffreyError::DocPathDoesNotExist(doc_path));
+        }
+
+        let doc_dir = if doc_path.is_dir() {
+            doc_path.clone()
+        } else {
+            doc_path
+                .parent()
+                .ok_or(GeoffreyError::GitToplevelError)?
+                .to_path_buf()
+        };
+        let git_toplevel = Self::git_toplevel(&doc_dir)?;
+
+        // without git, the config is expected next to the documentation
+        let config_dir = if git_toplevel.as_os_str().is_empty() {
+            doc_dir
+        } else {
+            git_toplevel.clone()
+        };
+        let config = Config::load(&config_dir)?;
+        if config.repos.contains_key(Config::GENERATED_PREFIX) {
+            return Err(GeoffreyError::ConfigError(
+                config_dir.join(Config::FILE_NAME),
+                format!(
+                    "the repository name '{}' is reserved for generated content",
+                    Config::GENERATED_PREFIX
+                ),
+            ));
+        }
+
+        let settings = Arc::new(Settings {
+            eol: config.eol,
+            paths: config.paths,
+            content_roots: config
+                .content_roots
+                .iter()
+                .map(|root| config_dir.join(root))
+                .collect(),
+        });
+
+        let mut repos = HashMap::new();
+        let mut remotes = HashMap::new();
+        for (name, repo) in &config.repos {
+            let path = config_dir.join(&repo.path);
+            match &repo.remote {
+                Some(remote) if !path.exists() => {
+                    remotes.insert(name.clone(), (remote.clone(), path));
+                }
+                _ => {
+                    repos.insert(name.clone(), Self::resolve_repo(name, path)?);
+                }
+            }
+        }
+
+        let mut md_paths = Vec::new();
+
+        let is_doc_file = match output_dir {
+            Some(_) => Self::is_template_file,
+            None => Self::is_md_file,
+        };
+        if doc_path.is_file() {
+            is_doc_file(doc_path.clone()).map(|file| md_paths.push(file))?;
+        } else {
+            Self::find_md_files(
+                &doc_path,
+                symlinks,
+                is_doc_file,
+                &mut HashSet::new(),
+                &mut |file| md_paths.push(file),
+            )?;
+            if md_paths.is_empty() {
+                return Err(GeoffreyError::NoMarkdownFilesInPath(doc_path));
+            }
+        }
+        // the files are processed in parallel, but their results are collected and reported in this order
+        md_paths.sort();
+        // the templates are rendered to the same path relative to the doc path in the output directory
+        let template_dir = if doc_path.is_dir() {
+            doc_path.as_path()
+        } else {
+            doc_path.parent().unwrap_or_else(|| Path::new(""))
+        };
+
+        let mut subtree_settings = HashMap::new();
+        let md_files = md_paths
+            .into_iter()
+            .map(|path| {
+                let dir = path.parent().unwrap_or_else(|| Path::new(""));
+                let settings =
+                    Self::subtree_settings(&config_dir, &settings, dir, &mut subtree_settings)?;
+                let output = output_dir.as_ref().map(|output_dir| {
+                    let relative = path.strip_prefix(template_dir).unwrap_or(&path);
+                    let relative = relative.display().to_string();
+                    output_dir.join(
+                        relative
+                            .strip_suffix(TEMPLATE_EXTENSION)
+                            .unwrap_or(&relative),
+                    )
+                });
+                Ok(MdFile {
+                    output,
+                    ..MdFile::new(path, settings)
+                })
+            })
+            .collect::<Result<Vec<MdFile>, GeoffreyError>>()?;
+
+        let keywords = config.keywords();
+        let syntax = ContentSyntax {
+            markers: config.markers(),
+            max_nesting_depth: config.max_nesting_depth(),
+        };
+        let max_content_file_size = config.max_content_file_size();
+        let max_md_file_size = config.max_md_file_size();
+        let re_tag = Self::tag_regex(&keywords)?;
+        let overrides = config.overrides.map(|overrides| config_dir.join(overrides));
+        let variables = config.variables.load_values(&config_dir)?;
+        let mut sources = Sources::with_builtins(config.generated);
+        sources.register(
+            Config::COMMAND_PREFIX,
+            Box::new(AllowedCommandSource::new(&config.allowed_commands)),
+        );
+        let tag_pattern = config
+            .tag_pattern
+            .as_deref()
+            .map(Regex::new)
+            .transpose()
+            .map_err(|_| GeoffreyError::RegexError)?;
+
+        Ok(Self {
+            git_toplevel,
+            repos,
+            remotes,
+            md_files,
+            content: ContentMap::new(),
+            failed_content: HashSet::new(),
+            failures: Vec::new(),
+            config_dir,
+            selectors: Selectors::with_builtins(),
+            sources,
+            settings,
+            keywords,
+            re_tag,
+            cancellation: CancellationToken::new(),
+            syntax,
+            unique_snippets: config.unique_snippets,
+            tag_pattern,
+            guard_edits: config.guard_edits,
+            provenance: config.provenance,
+            max_width: config.max_width,
+            verify: config.verify,
+            run: config.run,
+            max_content_file_size,
+            max_md_file_size,
+            envs: config.envs,
+            overrides,
+            warnings: Warnings::default(),
+            commits: Mutex::default(),
+            tag_filters: Vec::new(),
+            since: None,
+            deadline: None,
+            variables,
+            variable_snippets: config.variables.snippets,
+        })
+    }
+
+    /// The settings for the markdown files in `dir`, i.e. the settings of the parent directory merged with
+    /// the config file in `dir`, if there is one, up to the directory of the top-level config
+    fn subtree_settings(
+        config_dir: &Path,
+        top_level: &Arc<Settings>,
+        dir: &Path,
+        cache: &mut HashMap<PathBuf, Arc<Settings>>,
+    ) -> Result<Arc<Settings>, GeoffreyError> {
+        if dir == config_dir || !dir.starts_with(config_dir) {
+            return Ok(top_level.clone());
+        }
+        if let Some(settings) = cache.get(dir) {
+            return Ok(settings.clone());
+        }
+
+        let parent = match dir.parent() {
+            Some(parent) => Self::subtree_settings(config_dir, top_level, parent, cache)?,
+            None => top_level.clone(),
+        };
+        let settings = match SubtreeConfig::load(dir)? {
+            Some(config) => Arc::new(Settings {
+                eol: config.eol.unwrap_or(parent.eol),
+                paths: config.paths.unwrap_or(parent.paths),
+                content_roots: config
+                    .content_roots
+                    .map(|roots| roots.iter().map(|root| dir.join(root)).collect())
+                    .unwrap_or_else(|| parent.content_roots.clone()),
+            }),
+            None => parent,
+        };
+        cache.insert(dir.to_path_buf(), settings.clone());
+
+        Ok(settings)
+    }
+
+    /// The directory of the config file for the documentation in the folder `doc_dir`: its git toplevel or,
+    /// without git, the folder itself
+    pub fn config_dir(doc_dir: &Path) -> Result<PathBuf, GeoffreyError> {
+        let git_toplevel = Self::git_toplevel(doc_dir)?;
+        if git_toplevel.as_os_str().is_empty() {
+            Ok(doc_dir.to_path_buf())
+        } else {
+            Ok(git_toplevel)
+        }
     }
 
-    fn parse_content_file(path: &PathBuf) -> Result<ContentFile, GeoffreyError> {
-        let file = fs::File::open(path)?;
-        let mut reader = BufReader::new(file);
+    /// The git toplevel of `dir`; empty if it is not within a git repository
+    pub(crate) fn git_toplevel(dir: &Path) -> Result<PathBuf, GeoffreyError> {
+        let git_toplevel = std::process::Command::new("git")
+            .arg("rev-parse")
+            .arg("--show-toplevel")
+            .current_dir(dir)
+            .output()
+            .map_err(|_| GeoffreyError::GitToplevelError)?;
+
+        Ok(PathBuf::from(
+            std::str::from_utf8(&git_toplevel.stdout)
+                .map_err(|_| GeoffreyError::GitToplevelError)?
+                .trim(),
+        ))
+    }
+
+    /// The git submodules within `dir`, including nested ones, sorted by path
+    pub fn git_submodules(dir: &Path) -> Result<Vec<PathBuf>, GeoffreyError> {
+        let output = std::process::Command::new("git")
+            .arg("submodule")
+            .arg("foreach")
+            .arg("--quiet")
+            .arg("--recursive")
+            .arg("pwd")
+            .current_dir(dir)
+            .output()
+            .map_err(|e| GeoffreyError::GitSubmodulesError(dir.to_path_buf(), e.to_string()))?;
+        if !output.status.success() {
+            return Err(GeoffreyError::GitSubmodulesError(
+                dir.to_path_buf(),
+                String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+            ));
+        }
+
+        let mut submodules = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(PathBuf::from)
+            .filter(|submodule| submodule.starts_with(dir) && submodule != dir)
+            .collect::<Vec<PathBuf>>();
+        submodules.sort();
+
+        Ok(submodules)
+    }
+
+    /// Stages the `files` with 'git add', e.g. the changed files of a sync in a pre-commit hook; each file is added
+    /// in the git repository it is in, which may be a submodule
+    pub fn stage(files: &[PathBuf]) -> Result<(), GeoffreyError> {
+        for file in files {
+            let (dir, file_name) = match (file.parent(), file.file_name()) {
+                (Some(dir), Some(file_name)) => (dir, file_name),
+                _ => {
+                    return Err(GeoffreyError::GitAddError(
+                        file.clone(),
+                        "not a file".to_owned(),
+                    ))
+                }
+            };
+            let output = std::process::Command::new("git")
+                .arg("add")
+                .arg("--")
+                .arg(file_name)
+                .current_dir(dir)
+                .output()
+                .map_err(|e| GeoffreyError::GitAddError(file.clone(), e.to_string()))?;
+            if !output.status.success() {
+                return Err(GeoffreyError::GitAddError(
+                    file.clone(),
+                    String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+                ));
+            }
+            log::debug!("staged {:?}", file);
+        }
+
+        Ok(())
+    }
+
+    /// Removes the markdown files within `dirs`, e.g. the ones of git submodules which are synced separately
+    pub fn exclude_dirs(&mut self, dirs: &[PathBuf]) {
+        self.md_files
+            .retain(|md_file| !dirs.iter().any(|dir| md_file.path.starts_with(dir)));
+    }
+
+    /// Keeps only the markdown files matching one of the glob `patterns`, e.g. to check a single page instead of
+    /// the whole documentation; the patterns are matched against the full paths of the files
+    pub fn include_md_files(&mut self, patterns: &[String]) -> Result<(), GeoffreyError> {
+        let patterns = patterns
+            .iter()
+            .map(|pattern| {
+                glob::Pattern::new(pattern)
+                    .map_err(|e| GeoffreyError::InvalidGlob(pattern.clone(), e.msg.to_owned()))
+            })
+            .collect::<Result<Vec<glob::Pattern>, _>>()?;
+        self.md_files.retain(|md_file| {
+            patterns
+                .iter()
+                .any(|pattern| pattern.matches_path(&md_file.path))
+        });
+        if self.md_files.is_empty() {
+            log::warn!("no markdown files match the included patterns");
+        }
+
+        Ok(())
+    }
+
+    /// Restricts `parse` and `sync` to the tags matching one of the `filters`, which are either the content path
+    /// as written in the tag, like 'source/main.cpp', or the content path and the snippet separated by '#', like
+    /// 'source/main.cpp#main'; the other tags and their code blocks are kept as they are and not checked
+    pub fn only_tags(&mut self, filters: Vec<String>) {
+        self.tag_filters = filters;
+    }
+
+    /// Restricts `parse` and `sync` to the markdown files which changed since the git revision `rev`, or whose
+    /// referenced content files changed, as listed by 'git diff --name-only'; all files if the config changed
+    pub fn since(&mut self, rev: String) {
+        self.since = Some(rev);
+    }
+
+    /// Skips the markdown files which are not parsed before `deadline`, like `SyncOptions::deadline` when syncing,
+    /// and the ones referencing content files which are not parsed before it; they are returned as skipped by
+    /// `sync`, `diff` and `pending_edits`
+    pub fn set_deadline(&mut self, deadline: Instant) {
+        self.deadline = Some(deadline);
+    }
+
+    /// The absolute paths of the files changed in the working tree since the git revision `rev`
+    fn changed_files(git_toplevel: &Path, rev: &str) -> Result<HashSet<PathBuf>, GeoffreyError> {
+        if git_toplevel.as_os_str().is_empty() {
+            return Err(GeoffreyError::GitDiffError(
+                rev.to_owned(),
+                "not in a git repository".to_owned(),
+            ));
+        }
+        let output = std::process::Command::new("git")
+            .arg("diff")
+            .arg("--name-only")
+            .arg(rev)
+            .arg("--")
+            .current_dir(git_toplevel)
+            .output()
+            .map_err(|e| GeoffreyError::GitDiffError(rev.to_owned(), e.to_string()))?;
+        if !output.status.success() {
+            return Err(GeoffreyError::GitDiffError(
+                rev.to_owned(),
+                String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+            ));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|path| git_toplevel.join(path))
+            .collect())
+    }
+
+    /// Keeps only the parsed markdown files affected by the changes since the revision set with `since`, with
+    /// the content files referenced by them
+    fn retain_changed_md_files(&mut self, rev: &str) -> Result<(), GeoffreyError> {
+        let changed = Self::changed_files(&self.git_toplevel, rev)?;
+        if changed.contains(&self.config_dir.join(Config::FILE_NAME)) {
+            log::info!(
+                "the config changed since '{}'; keeping all markdown files",
+                rev
+            );
+            return Ok(());
+        }
+
+        // deleted files cannot be canonicalized but are listed with the path of the git toplevel
+        let is_changed = |path: &Path| {
+            changed.contains(path)
+                || path
+                    .canonicalize()
+                    .is_ok_and(|path| changed.contains(&path))
+        };
+        let git_toplevel = &self.git_toplevel;
+        let content_roots = &self.settings.content_roots;
+        let repos = &self.repos;
+        self.md_files.retain(|md_file| {
+            is_changed(&md_file.path)
+                || md_file
+                    .segments
+                    .iter()
+                    .filter_map(|segment| segment.snippet_id.as_ref())
+                    .flat_map(|snippet_id| snippet_id.content_paths())
+                    .any(|path| {
+                        Self::resolve_content_path(git_toplevel, content_roots, repos, path)
+                            .is_ok_and(|(absolute_path, _)| is_changed(&absolute_path))
+                    })
+        });
+        log::info!(
+            "{} markdown files are affected by the changes since '{}'",
+            self.md_files.len(),
+            rev
+        );
+
+        let md_paths = self
+            .md_files
+            .iter()
+            .map(|md_file| md_file.path.clone())
+            .collect::<HashSet<PathBuf>>();
+        self.failures
+            .retain(|failure| md_paths.contains(&failure.path));
+        let referenced = self
+            .md_files
+            .iter()
+            .flat_map(|md_file| &md_file.segments)
+            .filter_map(|segment| segment.snippet_id.as_ref())
+            .flat_map(|snippet_id| snippet_id.content_paths())
+            .map(PathBuf::from)
+            .collect::<HashSet<PathBuf>>();
+        // the content files at other revisions are keyed differently but have the path of the tag
+        self.content
+            .retain(|_, content_file| referenced.contains(&content_file.path));
+
+        Ok(())
+    }
+
+    /// Whether a tag is processed with the filters of `only_tags`
+    fn tag_selected(filters: &[String], tag_path: &str, tag: &MdSnippetTag) -> bool {
+        filters.is_empty()
+            || filters.iter().any(|filter| match filter.rsplit_once('#') {
+                Some((path, snippet)) => {
+                    path == tag_path && tag.snippet().as_deref() == Some(snippet)
+                }
+                None => filter == tag_path,
+            })
+    }
+
+    /// Sets the token to cancel `parse`, `sync` and `diff`; parsing fails with `GeoffreyError::Cancelled` and the
+    /// markdown files which are not synced yet are skipped like with a deadline
+    pub fn set_cancellation(&mut self, token: CancellationToken) {
+        self.cancellation = token;
+    }
+
+    /// Registers a custom selector which is referenced by '[name:argument]' instead of a snippet name
+    /// in geoffrey tags; a previously registered selector with the same name is replaced
+    pub fn register_selector(&mut self, name: &str, selector: Box<dyn SnippetSelector>) {
+        self.selectors.register(name, selector);
+    }
+
+    /// Registers a custom source of the content referenced by paths like '[name:argument]' in geoffrey tags;
+    /// a previously registered source with the same name is replaced
+    pub fn register_source(&mut self, name: &str, source: Box<dyn SnippetSource>) {
+        self.sources.register(name, source);
+    }
+
+    /// Returns the git toplevel of a configured repository at `path`
+    fn resolve_repo(name: &str, path: PathBuf) -> Result<PathBuf, GeoffreyError> {
+        if !path.exists() {
+            return Err(GeoffreyError::RepositoryNotFound(name.to_owned(), path));
+        }
+
+        let toplevel = Self::git_toplevel(&path)?;
+        if toplevel.as_os_str().is_empty() {
+            // not a git repository; the configured path is the root for the content files
+            Ok(path)
+        } else {
+            Ok(toplevel)
+        }
+    }
+
+    /// Resolves the path of a geoffrey tag, which is either relative to the git toplevel of the documentation
+    /// or prefixed with the name of a configured repository like 'repo:path/to/file' or a crate like
+    /// 'crate://name/path/to/file', whose directories are in `repos`. A prefix which is not a configured
+    /// repository is part of the path; only if such a file does not exist, the repository is reported as unknown.
+    /// With content roots, the path is resolved against the first root which contains it instead of the git toplevel
+    /// and this root is returned as well
+    fn resolve_content_path<'a>(
+        git_toplevel: &Path,
+        content_roots: &'a [PathBuf],
+        repos: &HashMap<String, PathBuf>,
+        path: &str,
+    ) -> Result<(PathBuf, Option<&'a PathBuf>), GeoffreyError> {
+        match Self::crate_prefix(path).or_else(|| Self::repo_prefix(path)) {
+            Some((repo, repo_path)) if repos.contains_key(repo) => {
+                Ok((repos[repo].join(repo_path), None))
+            }
+            Some((repo, _)) => match repo.strip_prefix(Config::CRATE_SCHEME) {
+                Some(name) => Err(GeoffreyError::UnknownCrate(
+                    name.to_owned(),
+                    path.to_owned(),
+                )),
+                None => Some(Self::resolve_plain_path(git_toplevel, content_roots, path))
+                    .filter(|(absolute_path, _)| absolute_path.exists())
+                    .ok_or_else(|| {
+                        GeoffreyError::UnknownRepository(repo.to_owned(), path.to_owned())
+                    }),
+            },
+            None => Ok(Self::resolve_plain_path(git_toplevel, content_roots, path)),
+        }
+    }
+
+    /// Resolves a path of a geoffrey tag without a repository, see `resolve_content_path`
+    fn resolve_plain_path<'a>(
+        git_toplevel: &Path,
+        content_roots: &'a [PathBuf],
+        path: &str,
+    ) -> (PathBuf, Option<&'a PathBuf>) {
+        if Path::new(path).is_absolute() {
+            // already resolved when parsing the markdown file
+            (PathBuf::from(path), None)
+        } else if content_roots.is_empty() {
+            (git_toplevel.join(path), None)
+        } else {
+            content_roots
+                .iter()
+                .map(|root| (root.join(path), Some(root)))
+                .find(|(absolute_path, _)| absolute_path.exists())
+                .unwrap_or_else(|| (content_roots[0].join(path), None))
+        }
+    }
+
+    /// Sets the directories in which the content files are searched, in order, instead of the git toplevel;
+    /// this overrides the content roots from the config
+    pub fn set_content_roots(&mut self, content_roots: Vec<PathBuf>) {
+        for md_file in &mut self.md_files {
+            Arc::make_mut(&mut md_file.settings).content_roots = content_roots.clone();
+        }
+        Arc::make_mut(&mut self.settings).content_roots = content_roots;
+    }
+
+    /// Splits a path like 'repo:path/to/file' into the repository name and the path in the repository; absolute
+    /// paths like 'C:\path\to\file' on Windows have no repository
+    fn repo_prefix(path: &str) -> Option<(&str, &str)> {
+        path.split_once(':')
+            .filter(|(repo, _)| !repo.contains(['/', '\\']) && !Path::new(path).is_absolute())
+    }
+
+    /// Splits a path like 'crate://serde/src/lib.rs' into the crate 'crate://serde', which is resolved like a
+    /// repository, and the path in the crate
+    fn crate_prefix(path: &str) -> Option<(&str, &str)> {
+        let (name, _) = path.strip_prefix(Config::CRATE_SCHEME)?.split_once('/')?;
+        let end = Config::CRATE_SCHEME.len() + name.len();
+        Some((&path[..end], &path[end + 1..]))
+    }
+
+    fn is_glob(path: &str) -> bool {
+        path.contains(['*', '?'])
+    }
+
+    /// Whether `line` is the '<!-- [geoffrey] [end] -->' tag closing the code blocks of a glob tag
+    fn is_glob_end_tag(re_tag: &Regex, line: &str) -> bool {
+        re_tag.captures(line).is_some_and(|caps| {
+            caps.get(1).map(|matcher| matcher.as_str().trim()) == Some("end")
+                && caps.get(2).is_none()
+                && caps
+                    .get(4)
+                    .is_none_or(|matcher| matcher.as_str().is_empty())
+        })
+    }
+
+    /// The files matching the glob `pattern` of a tag, sorted by path; relative patterns are resolved like the
+    /// paths of other tags, and for multiple content roots, a file found in an earlier root shadows the later ones
+    fn expand_glob(
+        settings: &Settings,
+        git_toplevel: &Path,
+        md_path: &Path,
+        pattern: &str,
+    ) -> Result<Vec<GlobMatch>, GeoffreyError> {
+        if Self::repo_prefix(pattern).is_some() {
+            return Err(GeoffreyError::InvalidGlob(
+                pattern.to_owned(),
+                "globs are not supported for repositories and generated content".to_owned(),
+            ));
+        }
+
+        let bases = match settings.paths {
+            _ if Path::new(pattern).is_absolute() => vec![(PathBuf::new(), None)],
+            ContentPaths::MarkdownFile => vec![(
+                md_path
+                    .parent()
+                    .unwrap_or_else(|| Path::new(""))
+                    .to_path_buf(),
+                None,
+            )],
+            ContentPaths::GitToplevel if settings.content_roots.is_empty() => {
+                vec![(git_toplevel.to_path_buf(), None)]
+            }
+            ContentPaths::GitToplevel => settings
+                .content_roots
+                .iter()
+                .map(|root| (root.clone(), Some(root.clone())))
+                .collect(),
+        };
+
+        let mut matches = Vec::<GlobMatch>::new();
+        for (base, root) in bases {
+            let base_pattern = base.join(pattern).display().to_string();
+            let paths = glob::glob(&base_pattern)
+                .map_err(|e| GeoffreyError::InvalidGlob(pattern.to_owned(), e.to_string()))?;
+            for path in paths
+                .filter_map(|path| path.ok())
+                .filter(|path| path.is_file())
+            {
+                let heading = path
+                    .strip_prefix(&base)
+                    .unwrap_or(&path)
+                    .display()
+                    .to_string();
+                if matches
+                    .iter()
+                    .any(|glob_match| glob_match.heading == heading)
+                {
+                    continue;
+                }
+                matches.push(GlobMatch {
+                    heading,
+                    path: Self::normalize(&path).display().to_string(),
+                    root: root.clone(),
+                });
+            }
+        }
+        matches.sort_by(|a, b| a.heading.cmp(&b.heading));
+
+        Ok(matches)
+    }
+
+    /// The key of the content file of a geoffrey tag in `md_path` and the content root it was found in.
+    /// With paths relative to the markdown file, the path is converted to one relative to the git toplevel
+    /// or to an absolute path outside of git. With content roots of a subtree, the same path might refer to
+    /// different files in other subtrees, therefore it is resolved to an absolute path.
+    fn content_key(
+        settings: &Settings,
+        top_level_roots: &[PathBuf],
+        git_toplevel: &Path,
+        md_path: &Path,
+        path: &str,
+    ) -> (String, Option<PathBuf>) {
+        if Self::repo_prefix(path).is_some() {
+            return (path.to_owned(), None);
+        }
+
+        match settings.paths {
+            ContentPaths::MarkdownFile => {
+                let md_dir = md_path.parent().unwrap_or_else(|| Path::new(""));
+                let absolute = Self::normalize(&md_dir.join(path));
+                let key = match absolute.strip_prefix(git_toplevel) {
+                    Ok(relative) if !git_toplevel.as_os_str().is_empty() => {
+                        relative.display().to_string()
+                    }
+                    _ => absolute.display().to_string(),
+                };
+                (key, None)
+            }
+            ContentPaths::GitToplevel if settings.content_roots == top_level_roots => {
+                (path.to_owned(), None)
+            }
+            ContentPaths::GitToplevel => {
+                let (absolute, root) = settings
+                    .content_roots
+                    .iter()
+                    .map(|root| (Self::normalize(&root.join(path)), Some(root.clone())))
+                    .find(|(absolute, _)| absolute.exists())
+                    .unwrap_or_else(|| {
+                        let base = settings
+                            .content_roots
+                            .first()
+                            .map_or(git_toplevel, |root| root);
+                        (Self::normalize(&base.join(path)), None)
+                    });
+                (absolute.display().to_string(), root)
+            }
+        }
+    }
+
+    /// Removes the '.' and '..' components of a path without accessing the file system
+    fn normalize(path: &Path) -> PathBuf {
+        let mut normalized = PathBuf::new();
+        for component in path.components() {
+            match component {
+                std::path::Component::CurDir => (),
+                std::path::Component::ParentDir => {
+                    normalized.pop();
+                }
+                component => normalized.push(component),
+            }
+        }
+        normalized
+    }
+
+    pub fn parse(&mut self) -> Result<(), GeoffreyError> {
+        self.parse_md_files()?;
+        self.parse_content_files()
+    }
+
+    /// Parses the markdown files; files which fail to parse are recorded in `failures` and skipped by `sync`
+    pub fn parse_md_files(&mut self) -> Result<(), GeoffreyError> {
+        log::info!("#### parse md files for tags");
+        let max_md_file_size = self.max_md_file_size;
+        let warnings = &self.warnings;
+        self.md_files.retain(|md_file| {
+            // files which cannot be read are kept so that parsing reports the error
+            match Self::skip_reason(&md_file.path, max_md_file_size) {
+                Ok(Some(reason)) => {
+                    warnings.warn(
+                        &md_file.path,
+                        None,
+                        GeoffreyWarning::FileSkipped(md_file.path.clone(), reason),
+                    );
+                    false
+                }
+                _ => true,
+            }
+        });
+
+        let content = Mutex::new(&mut self.content);
+        let top_level_roots = &self.settings.content_roots;
+        let git_toplevel = &self.git_toplevel;
+        let re_tag = &self.re_tag;
+        let re_tag_start = Self::tag_start_regex(&self.keywords)?;
+        let re_inline_tag = Self::inline_tag_regex(&self.keywords)?;
+        let re_opt_out = Self::opt_out_regex(&self.keywords)?;
+        let cancellation = &self.cancellation;
+        let deadline = self.deadline;
+        let tag_filters = &self.tag_filters;
+        let variables = &self.variables;
+        let failures = self
+            .md_files
+            .par_iter_mut()
+            .filter(|_| !cancellation.is_cancelled())
+            .flat_map_iter(|md_file| {
+                if deadline_reached(deadline) {
+                    md_file.skipped = true;
+                    return Vec::new();
+                }
+                // the errors of single tags are collected while the parsing continues with the next line
+                let mut errors = Vec::new();
+                if let Err(error) = Self::parse_single_md_file(
+                    md_file,
+                    &content,
+                    top_level_roots,
+                    git_toplevel,
+                    (re_tag, &re_tag_start, &re_inline_tag, &re_opt_out),
+                    (tag_filters, variables),
+                    &mut errors,
+                ) {
+                    errors.push(error);
+                }
+                md_file.failed = !errors.is_empty();
+                errors
+                    .into_iter()
+                    .map(|error| FileError::new(md_file.path.clone(), error))
+                    .collect::<Vec<FileError>>()
+            })
+            .collect::<Vec<FileError>>();
+        self.failures.extend(failures);
+
+        if self.cancellation.is_cancelled() {
+            return Err(GeoffreyError::Cancelled);
+        }
+
+        self.check_unique_anchors();
+        if let Some(rev) = self.since.clone() {
+            self.retain_changed_md_files(&rev)?;
+        }
+        FileError::sort(&mut self.failures);
+
+        Ok(())
+    }
+
+    /// Parses the content files referenced by the markdown files; files which fail to parse are recorded
+    /// in `failures` and the markdown files referencing them fail in `sync`
+    pub fn parse_content_files(&mut self) -> Result<(), GeoffreyError> {
+        log::info!("#### parse content files for tags");
+        if self.cancellation.is_cancelled() {
+            return Err(GeoffreyError::Cancelled);
+        }
+        let variable_sources = self.variable_sources();
+        for (_, (path, root), _) in &variable_sources {
+            self.content.entry(path.clone()).or_insert_with(|| {
+                let mut content_file =
+                    ContentFile::new(PathBuf::from(path), CommentStyle::for_path(Path::new(path)));
+                content_file.root = root.clone();
+                content_file
+            });
+        }
+        // the crates are located once and then resolved like repositories
+        let crates = self.locate_crates().map(|crates| self.repos.extend(crates));
+        let failed_clones = self.clone_repos();
+        let git_toplevel = &self.git_toplevel;
+        let content_roots = &self.settings.content_roots;
+        let repos = &self.repos;
+        let sources = &self.sources;
+        let config_dir = &self.config_dir;
+        let overrides = self.overrides.as_deref();
+        let cancellation = &self.cancellation;
+        let deadline = self.deadline;
+        let skipped = Mutex::new(HashSet::new());
+        let warnings = &self.warnings;
+        let syntax = &self.syntax;
+        let max_content_file_size = self.max_content_file_size;
+        let failures = self
+            .content
+            .par_iter_mut()
+            .filter(|_| !cancellation.is_cancelled())
+            .filter_map(|(path, content_file)| {
+                if deadline_reached(deadline) {
+                    skipped
+                        .lock()
+                        .expect("could not lock mutex")
+                        .insert(path.clone());
+                    return None;
+                }
+                if let (Err(reason), Some(_)) = (&crates, Self::crate_prefix(path)) {
+                    let error = GeoffreyError::CargoMetadataFailed(path.clone(), reason.clone());
+                    return Some((
+                        path.clone(),
+                        FileError::new(content_file.path.clone(), error),
+                    ));
+                }
+                if let Some((repo, remote)) =
+                    Self::repo_prefix(path).and_then(|(repo, _)| failed_clones.get_key_value(repo))
+                {
+                    let error = GeoffreyError::RepositoryCloneFailed(repo.clone(), remote.clone());
+                    return Some((
+                        path.clone(),
+                        FileError::new(content_file.path.clone(), error),
+                    ));
+                }
+                // configured repositories and crates take precedence over sources of the same name
+                let source = Self::repo_prefix(path)
+                    .filter(|(repo, _)| !repos.contains_key(*repo))
+                    .and_then(|_| sources.get(path));
+                let result = match (content_file.revision.take(), source) {
+                    (None, Some((source, argument))) => source
+                        .read(config_dir, argument)
+                        .map_err(|reason| match Self::repo_prefix(path) {
+                            Some((Config::GENERATED_PREFIX, _)) => {
+                                GeoffreyError::GeneratedContentFailed(path.to_owned(), reason)
+                            }
+                            _ => GeoffreyError::SourceNotReadable(path.to_owned(), reason),
+                        })
+                        .and_then(|data| {
+                            *content_file = Self::parse_content(
+                                content_file.path.clone(),
+                                data.as_bytes(),
+                                syntax,
+                            )?;
+                            Ok(())
+                        }),
+                    (Some((file_key, rev)), _) => {
+                        Self::resolve_content_path(git_toplevel, content_roots, repos, &file_key)
+                            .and_then(|(absolute_path, _)| {
+                                let data = Self::show_revision(&absolute_path, &rev).map_err(
+                                    |reason| {
+                                        GeoffreyError::RevisionNotReadable(
+                                            file_key.clone(),
+                                            rev.clone(),
+                                            reason,
+                                        )
+                                    },
+                                )?;
+                                *content_file =
+                                    Self::parse_content(absolute_path, data.as_bytes(), syntax)?;
+                                content_file.revision = Some((file_key, rev));
+                                Ok(())
+                            })
+                    }
+                    (None, None) => {
+                        Self::resolve_content_path(git_toplevel, content_roots, repos, path)
+                            .and_then(|(absolute_path, root)| {
+                                if !absolute_path.exists() {
+                                    let suggestions = Self::renamed_path(&absolute_path)
+                                        .and_then(|renamed| {
+                                            Self::renamed_tag_path(path, &absolute_path, &renamed)
+                                        })
+                                        .into_iter()
+                                        .collect();
+                                    return Err(GeoffreyError::ContentFileNotFound(
+                                        path.to_owned(),
+                                        suggestions,
+                                    ));
+                                }
+                                if let Some(root) = root {
+                                    log::debug!("'{}' found in content root {:?}", path, root);
+                                }
+                                let md_root = content_file.root.take();
+                                let base =
+                                    root.map_or(git_toplevel.as_path(), |root| root.as_path());
+                                let shadow = Self::shadowing_file(overrides, base, &absolute_path);
+                                let read_path = shadow.as_deref().unwrap_or(&absolute_path);
+                                if let Some(reason) =
+                                    Self::skip_reason(read_path, max_content_file_size)?
+                                {
+                                    warnings.warn(
+                                        read_path,
+                                        None,
+                                        GeoffreyWarning::FileSkipped(read_path.to_owned(), reason),
+                                    );
+                                    content_file.skipped = true;
+                                    content_file.root = root.cloned().or(md_root);
+                                    return Ok(());
+                                }
+                                *content_file = match shadow {
+                                    Some(shadow) => Self::parse_shadowed_content_file(
+                                        sources.files(),
+                                        &absolute_path,
+                                        &shadow,
+                                        syntax,
+                                        warnings,
+                                    )?,
+                                    None => Self::parse_content_file(
+                                        sources.files(),
+                                        &absolute_path,
+                                        syntax,
+                                    )?,
+                                };
+                                content_file.root = root.cloned().or(md_root);
+                                Ok(())
+                            })
+                    }
+                };
+
+                result.err().map(|error| {
+                    (
+                        path.clone(),
+                        FileError::new(content_file.path.clone(), error),
+                    )
+                })
+            })
+            .collect::<Vec<(String, FileError)>>();
+
+        for (path, failure) in failures {
+            self.failed_content.insert(path);
+            self.failures.push(failure);
+        }
+        let skipped = skipped.into_inner().expect("could not lock mutex");
+        for md_file in &mut self.md_files {
+            md_file.skipped |= md_file
+                .segments
+                .iter()
+                .filter_map(|segment| segment.snippet_id.as_ref())
+                .flat_map(MdSnippetId::content_paths)
+                .any(|path| skipped.contains(path));
+        }
+
+        if self.cancellation.is_cancelled() {
+            return Err(GeoffreyError::Cancelled);
+        }
+
+        if self.unique_snippets {
+            self.check_unique_snippets();
+        }
+        self.lint_snippet_names();
+
+        self.resolve_variables(variable_sources);
+        FileError::sort(&mut self.failures);
+
+        Ok(())
+    }
+
+    /// The names of the variables whose value is a snippet with the key and root of the content file and the
+    /// snippet; the paths are resolved like the ones of a markdown file next to the config file
+    fn variable_sources(&self) -> Vec<(String, (String, Option<PathBuf>), MdSnippetTag)> {
+        let config_path = self.config_dir.join(Config::FILE_NAME);
+        self.variable_snippets
+            .iter()
+            .map(|(name, reference)| {
+                let (path, tag) = match reference.rsplit_once('#') {
+                    Some((path, snippet)) => (
+                        path,
+                        MdSnippetTag::FullSnippet {
+                            main: snippet.to_owned(),
+                        },
+                    ),
+                    None => (reference.as_str(), MdSnippetTag::FullFile),
+                };
+                let key = Self::content_key(
+                    &self.settings,
+                    &self.settings.content_roots,
+                    &self.git_toplevel,
+                    &config_path,
+                    path,
+                );
+                (name.clone(), key, tag)
+            })
+            .collect()
+    }
+
+    /// Adds the values of the variables from snippets, which must be single lines; the problems are recorded
+    /// as failures of the config file
+    fn resolve_variables(
+        &mut self,
+        sources: Vec<(String, (String, Option<PathBuf>), MdSnippetTag)>,
+    ) {
+        let config_path = self.config_dir.join(Config::FILE_NAME);
+        for (name, (path, _), tag) in sources {
+            if self.failed_content.contains(&path) {
+                // already reported as failure from parsing
+                continue;
+            }
+            match self
+                .render_snippet(&path, &tag, false, 0)
+                .and_then(|value| Self::single_line(&path, tag.main(), &value))
+            {
+                Ok(value) => {
+                    self.variables.insert(name, value);
+                }
+                Err(error) => self
+                    .failures
+                    .push(FileError::new(config_path.clone(), error)),
+            }
+        }
+    }
+
+    /// Substitutes the values of the variables for the '{{geoffrey:name}}' placeholders in `text`
+    fn with_variables(&self, text: String) -> Result<String, GeoffreyError> {
+        if !text.contains("{{geoffrey:") {
+            return Ok(text);
+        }
+
+        let re =
+            Regex::new(r"\{\{geoffrey:([\w\.\-]+)\}\}").map_err(|_| GeoffreyError::RegexError)?;
+        let mut unknown = None;
+        let substituted = re.replace_all(&text, |caps: &regex::Captures| {
+            match self.variables.get(&caps[1]) {
+                Some(value) => value.clone(),
+                None => {
+                    unknown.get_or_insert_with(|| caps[1].to_owned());
+                    caps[0].to_owned()
+                }
+            }
+        });
+
+        match unknown {
+            Some(name) => Err(GeoffreyError::UnknownVariable(name)),
+            None => Ok(substituted.into_owned()),
+        }
+    }
+
+    /// The content file path of a tag with the '${name}' references replaced by the environment variable or,
+    /// if it is not set, by the variable from the values file, e.g. for directories differing between checkouts
+    fn expanded_path(
+        path: String,
+        variables: &BTreeMap<String, String>,
+    ) -> Result<String, GeoffreyError> {
+        if !path.contains("${") {
+            return Ok(path);
+        }
+
+        let re = Regex::new(r"\$\{([\w\.\-]+)\}").map_err(|_| GeoffreyError::RegexError)?;
+        let mut unknown = None;
+        let expanded = re.replace_all(&path, |caps: &regex::Captures| {
+            match std::env::var(&caps[1])
+                .ok()
+                .or_else(|| variables.get(&caps[1]).cloned())
+            {
+                Some(value) => value,
+                None => {
+                    unknown.get_or_insert_with(|| caps[1].to_owned());
+                    caps[0].to_owned()
+                }
+            }
+        });
+
+        match unknown {
+            Some(name) => Err(GeoffreyError::UnknownPathVariable(name, path.clone())),
+            None => Ok(expanded.into_owned()),
+        }
+    }
+
+    /// Why the file at `path` shall not be read, if it is larger than `max_size` bytes or contains binary
+    /// data, i.e. a NUL byte within the first 8000 bytes like git checks it
+    fn skip_reason(path: &Path, max_size: u64) -> Result<Option<String>, GeoffreyError> {
+        let size = fs::metadata(path)?.len();
+        if size > max_size {
+            return Ok(Some(format!(
+                "it has {} bytes which exceeds the limit of {} bytes",
+                size, max_size
+            )));
+        }
+
+        let mut head = Vec::with_capacity(8000);
+        fs::File::open(path)?.take(8000).read_to_end(&mut head)?;
+        if head.contains(&0) {
+            return Ok(Some("it contains binary data".to_owned()));
+        }
+
+        Ok(None)
+    }
+
+    /// Warns about the snippet markers in the content files whose names do not match the configured pattern
+    fn lint_snippet_names(&self) {
+        let tag_pattern = match &self.tag_pattern {
+            Some(tag_pattern) => tag_pattern,
+            None => return,
+        };
+
+        let mut keys = self
+            .content
+            .keys()
+            .filter(|key| self.content[*key].revision.is_none())
+            .collect::<Vec<&String>>();
+        keys.sort();
+        // a file referenced by several keys is checked once
+        let mut checked_files = HashSet::new();
+        for key in keys {
+            let content_file = &self.content[key];
+            if !checked_files.insert(&content_file.path) {
+                continue;
+            }
+            let mut snippets = content_file
+                .lookup
+                .values()
+                .filter(|snip_desc| {
+                    !snip_desc.tag.is_empty() && !tag_pattern.is_match(&snip_desc.tag)
+                })
+                .collect::<Vec<&ContentSnippetDescription>>();
+            snippets.sort_by_key(|snip_desc| snip_desc.begin);
+            for snip_desc in snippets {
+                self.warnings.warn(
+                    &content_file.path,
+                    Some(Self::marker_span(content_file, snip_desc)),
+                    GeoffreyWarning::TagNameMismatch(
+                        snip_desc.tag.clone(),
+                        tag_pattern.as_str().to_owned(),
+                    ),
+                );
+            }
+        }
+    }
+
+    /// Reports the snippets which are defined in more than one content file, at the marker in the later file
+    /// with the location of the first one; both content files are treated as invalid like the ones which failed
+    /// to parse
+    fn check_unique_snippets(&mut self) {
+        let mut keys = self
+            .content
+            .keys()
+            .filter(|key| {
+                !self.failed_content.contains(*key) && self.content[*key].revision.is_none()
+            })
+            .collect::<Vec<&String>>();
+        keys.sort();
+
+        // the first definition of each snippet name; a file referenced by several keys is indexed once
+        let mut index = HashMap::<&str, (&String, &ContentFile, &ContentSnippetDescription)>::new();
+        let mut indexed_files = HashSet::new();
+        let mut duplicates = Vec::new();
+        for key in keys {
+            let content_file = &self.content[key];
+            if !indexed_files.insert(&content_file.path) {
+                continue;
+            }
+            let mut snippets = content_file
+                .lookup
+                .values()
+                .filter(|snip_desc| !snip_desc.tag.is_empty())
+                .collect::<Vec<&ContentSnippetDescription>>();
+            snippets.sort_by_key(|snip_desc| snip_desc.begin);
+            for snip_desc in snippets {
+                match index.get(snip_desc.tag.as_str()) {
+                    Some((first_key, first_file, first_snip_desc)) => duplicates.push((
+                        [(*first_key).clone(), key.clone()],
+                        FileError::new(
+                            content_file.path.clone(),
+                            GeoffreyError::DuplicateSnippetAcrossFiles(
+                                snip_desc.tag.clone(),
+                                first_file.path.clone(),
+                                first_snip_desc.begin + 1,
+                                content_file.path.clone(),
+                            )
+                            .at(Self::marker_span(content_file, snip_desc)),
+                        ),
+                    )),
+                    None => {
+                        index.insert(&snip_desc.tag, (key, content_file, snip_desc));
+                    }
+                }
+            }
+        }
+
+        for (keys, failure) in duplicates {
+            self.failed_content.extend(keys);
+            self.failures.push(failure);
+        }
+    }
+
+    /// Locates the crates with 'cargo metadata' in the directory of the config file, keyed like repositories by
+    /// 'crate://name'; nothing is run if no 'crate://' path is referenced
+    fn locate_crates(&self) -> Result<HashMap<String, PathBuf>, String> {
+        let referenced = self.content.iter().any(|(path, content_file)| {
+            path.starts_with(Config::CRATE_SCHEME)
+                || content_file
+                    .revision
+                    .as_ref()
+                    .is_some_and(|(path, _)| path.starts_with(Config::CRATE_SCHEME))
+        });
+        if !referenced {
+            return Ok(HashMap::new());
+        }
+
+        Ok(cargo::crate_dirs(&self.config_dir)?
+            .into_iter()
+            .map(|(name, dir)| (format!("{}{}", Config::CRATE_SCHEME, name), dir))
+            .collect())
+    }
+
+    /// Clones the configured repositories which do not exist yet from their remotes and resolves them; nothing is
+    /// cloned for repositories which are not referenced. Returns the remotes of the repositories which failed.
+    fn clone_repos(&mut self) -> HashMap<String, String> {
+        let referenced = self
+            .content
+            .iter()
+            .flat_map(|(path, content_file)| {
+                [
+                    Some(path),
+                    content_file.revision.as_ref().map(|(path, _)| path),
+                ]
+            })
+            .flatten()
+            .filter_map(|path| Self::repo_prefix(path))
+            .filter(|(repo, _)| self.remotes.contains_key(*repo))
+            .map(|(repo, _)| repo.to_owned())
+            .collect::<HashSet<String>>();
+
+        let mut failed = HashMap::new();
+        for name in referenced {
+            let Some((remote, path)) = self.remotes.remove(&name) else {
+                continue;
+            };
+            log::info!("cloning repository '{}' from '{}'", name, remote);
+            // a remote starting with '-' is no option of git and a remote asking for credentials fails
+            let cloned = std::process::Command::new("git")
+                .args(["clone", "--"])
+                .arg(&remote)
+                .arg(&path)
+                .env("GIT_TERMINAL_PROMPT", "0")
+                .status()
+                .is_ok_and(|status| status.success());
+            match cloned.then(|| Self::resolve_repo(&name, path)) {
+                Some(Ok(toplevel)) => {
+                    self.repos.insert(name, toplevel);
+                }
+                Some(Err(e)) => {
+                    log::warn!("{}", e);
+                    failed.insert(name, remote);
+                }
+                None => {
+                    failed.insert(name, remote);
+                }
+            }
+        }
+
+        failed
+    }
+
+    /// The content of the file at `path` at the git revision `rev`
+    fn show_revision(path: &Path, rev: &str) -> Result<String, String> {
+        if rev.starts_with('-') {
+            return Err(format!("the revision '{}' starts with '-'", rev));
+        }
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| "not a file".to_owned())?
+            .to_string_lossy();
+        let mut git_show = std::process::Command::new("git");
+        git_show
+            .args(["show", "--end-of-options"])
+            .arg(format!("{}:./{}", rev, file_name));
+        if let Some(dir) = path.parent() {
+            git_show.current_dir(dir);
+        }
+        let output = git_show.output().map_err(|e| e.to_string())?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).trim().to_owned());
+        }
+
+        String::from_utf8(output.stdout).map_err(|e| e.to_string())
+    }
+
+    /// The path a missing content file at `path` was renamed to in git, following the staged renames and the
+    /// ones in the history; only an existing path is returned
+    fn renamed_path(path: &Path) -> Option<PathBuf> {
+        let path = Self::normalize(path);
+        let dir = path.ancestors().skip(1).find(|dir| dir.is_dir())?;
+        let git_toplevel = Self::git_toplevel(dir).ok()?;
+        // git reports the paths relative to the canonical toplevel
+        let relative = dir
+            .canonicalize()
+            .ok()?
+            .join(path.strip_prefix(dir).ok()?)
+            .strip_prefix(&git_toplevel)
+            .ok()?
+            .to_path_buf();
+
+        let renames = |args: &[&str]| {
+            let output = std::process::Command::new("git")
+                .args(args)
+                .args(["-M", "--diff-filter=R", "--name-status"])
+                .current_dir(&git_toplevel)
+                .output()
+                .ok()
+                .filter(|output| output.status.success())?;
+            Some(
+                String::from_utf8_lossy(&output.stdout)
+                    .lines()
+                    .filter_map(|line| {
+                        let mut fields = line.split('\t').skip(1);
+                        Some((PathBuf::from(fields.next()?), PathBuf::from(fields.next()?)))
+                    })
+                    .collect::<Vec<(PathBuf, PathBuf)>>(),
+            )
+        };
+        // both are listed from the newest to the oldest rename
+        let mut renames_newest_first = renames(&["diff", "--cached"])?;
+        renames_newest_first.extend(renames(&["log", "--format="])?);
+
+        let renamed =
+            renames_newest_first
+                .iter()
+                .rev()
+                .fold(relative.clone(), |current, (old, new)| {
+                    match *old == current {
+                        true => new.clone(),
+                        false => current,
+                    }
+                });
+        let base = path.ancestors().nth(relative.components().count())?;
+        Some(base.join(renamed)).filter(|renamed| *renamed != path && renamed.exists())
+    }
+
+    /// The content path `written` of a tag, which resolved to the missing file `old`, rewritten to refer to
+    /// `new` instead; the unchanged leading part of the path is kept, e.g. a repository prefix or '../'
+    fn renamed_tag_path(written: &str, old: &Path, new: &Path) -> Option<String> {
+        let (prefix, written) = match Self::repo_prefix(written) {
+            Some((repo, path)) => (format!("{}:", repo), path),
+            None => (String::new(), written),
+        };
+        let written = Path::new(written.trim());
+        let old = Self::normalize(old);
+        let common = written
+            .components()
+            .rev()
+            .zip(old.components().rev())
+            .take_while(|(a, b)| matches!(a, std::path::Component::Normal(_)) && a == b)
+            .count();
+        if common == 0 {
+            return None;
+        }
+        let new = new.strip_prefix(old.ancestors().nth(common)?).ok()?;
+        let renamed = written.ancestors().nth(common)?.join(new);
+
+        Some(format!("{}{}", prefix, renamed.display()))
+    }
+
+    /// The files which failed to parse
+    pub fn failures(&self) -> &[FileError] {
+        &self.failures
+    }
+
+    /// Checks that every referenced content file and snippet exists, without rendering or writing anything;
+    /// returns all problems including the failures from parsing
+    pub fn validate(mut self) -> Vec<FileError> {
+        let mut problems = std::mem::take(&mut self.failures);
+        problems.extend(
+            self.tag_problems()
+                .into_iter()
+                .map(|problem| FileError::new(problem.md_file, problem.error)),
+        );
+        FileError::sort(&mut problems);
+
+        problems
+    }
+
+    /// The problems of the geoffrey tags in the parsed markdown files, like missing content files or snippets,
+    /// sorted by markdown file and line; failures from parsing are not included
+    pub fn tag_problems(&self) -> Vec<TagProblem> {
+        let mut problems = Vec::new();
+
+        for md_file in self
+            .md_files
+            .iter()
+            .filter(|md_file| !md_file.failed && !md_file.skipped)
+        {
+            for snippet_id in md_file
+                .segments
+                .iter()
+                .filter_map(|segment| segment.snippet_id.as_ref())
+            {
+                for path in snippet_id.content_paths() {
+                    // the snippet of a diff needs to exist only at the revisions
+                    let paths = match &snippet_id.options.rev {
+                        Some(revisions) => revisions.keys(path).map(|(key, _)| key).to_vec(),
+                        None => vec![path.to_owned()],
+                    };
+                    for path in &paths {
+                        if let Err(error) = self.validate_snippet(path, &snippet_id.tag) {
+                            problems.push(TagProblem {
+                                md_file: md_file.path.clone(),
+                                line: snippet_id.line,
+                                error,
+                            });
+                        }
+                    }
+                }
+                if let Some(env) = snippet_id
+                    .options
+                    .env
+                    .as_ref()
+                    .filter(|env| !self.envs.contains_key(*env))
+                {
+                    problems.push(TagProblem {
+                        md_file: md_file.path.clone(),
+                        line: snippet_id.line,
+                        error: GeoffreyError::UnknownEnvironment(
+                            env.clone(),
+                            snippet_id.path.clone(),
+                        ),
+                    });
+                }
+            }
+        }
+        problems.sort_by(|a, b| (&a.md_file, a.line).cmp(&(&b.md_file, b.line)));
+
+        problems
+    }
+
+    /// The begin markers of the snippet `snippet` in the parsed content files and the tags embedding it, either
+    /// as main snippet or as kept sub-snippet, e.g. to find the pages affected by renaming the snippet
+    pub fn where_used(&self, snippet: &str) -> SnippetUsage {
+        let mut definitions = self
+            .content
+            .values()
+            .filter(|content_file| content_file.revision.is_none())
+            .filter_map(|content_file| {
+                content_file
+                    .lookup
+                    .get(snippet)
+                    .filter(|_| !snippet.is_empty())
+                    .map(|snip_desc| SnippetLocation {
+                        path: content_file.path.clone(),
+                        line: snip_desc.begin + 1,
+                    })
+            })
+            .collect::<Vec<SnippetLocation>>();
+        definitions.sort_by(|a, b| (&a.path, a.line).cmp(&(&b.path, b.line)));
+        // a file referenced by several keys defines the snippet once
+        definitions.dedup();
+
+        let references = self
+            .tags()
+            .into_iter()
+            .filter(|tag| {
+                tag.snippet.as_deref() == Some(snippet)
+                    || tag.sub_snippets.iter().any(|sub| sub == snippet)
+            })
+            .collect();
+
+        SnippetUsage {
+            snippet: snippet.to_owned(),
+            definitions,
+            references,
+        }
+    }
+
+    /// The location of the content referenced by the geoffrey tag in `line` of the markdown file `md_path`,
+    /// i.e. the begin marker of the snippet, the first selected line or the first line of the content file;
+    /// for glob tags the first matched file
+    pub fn definition(&self, md_path: &Path, line: usize) -> Option<SnippetLocation> {
+        let snippet_id = self
+            .md_files
+            .iter()
+            .filter(|md_file| md_file.path == md_path)
+            .flat_map(|md_file| md_file.segments.iter())
+            .filter_map(|segment| segment.snippet_id.as_ref())
+            .find(|snippet_id| snippet_id.line == line)?;
+        let content_file = self.content.get(*snippet_id.content_paths().first()?)?;
+
+        let line = match snippet_id.tag.main() {
+            "" => 1,
+            main => match self.selected_snippet(content_file, main) {
+                Some(selected) => selected.ok()?.1.start + 1,
+                None => content_file.lookup.get(main)?.begin + 1,
+            },
+        };
+
+        Some(SnippetLocation {
+            path: content_file.path.clone(),
+            line,
+        })
+    }
+
+    fn validate_snippet(&self, path: &str, tag: &MdSnippetTag) -> Result<(), GeoffreyError> {
+        if self.failed_content.contains(path) {
+            // already reported as failure from parsing
+            return Ok(());
+        }
+        let content_file = self
+            .content
+            .get(path)
+            .ok_or_else(|| GeoffreyError::ContentFileNotFound(path.to_owned(), Vec::new()))?;
+        if content_file.skipped {
+            // already reported as warning from parsing
+            return Ok(());
+        }
+
+        let not_found = |tag: &str| {
+            GeoffreyError::ContentSnippetNotFound(
+                path.to_owned(),
+                tag.to_owned(),
+                suggest::closest(tag, content_file.lookup.keys().map(|tag| tag.as_str())),
+            )
+        };
+
+        let main = tag.main();
+        let selected;
+        let snip_desc = match self.selected_snippet(content_file, main) {
+            Some(result) => {
+                selected = result?.0;
+                &selected
+            }
+            None => content_file
+                .lookup
+                .get(main)
+                .ok_or_else(|| not_found(main))?,
+        };
+
+        if let MdSnippetTag::ElidedSnippet { sub, .. } = tag {
+            for sub_tag in sub {
+                if !content_file.lookup.contains_key(sub_tag) {
+                    return Err(not_found(sub_tag));
+                }
+                if !Self::is_nested(snip_desc, sub_tag) {
+                    return Err(GeoffreyError::SubSnippetNotNested(
+                        path.to_owned(),
+                        main.to_owned(),
+                        sub_tag.to_owned(),
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The snippet selected by a registered selector for tags like '[name:argument]' together with the selected
+    /// lines, or `None` if the tag does not reference a selector; snippets within the selected lines are nested
+    fn selected_snippet(
+        &self,
+        content_file: &ContentFile,
+        tag: &str,
+    ) -> Option<Result<(ContentSnippetDescription, Range<usize>), GeoffreyError>> {
+        let (selector, argument) = self.selectors.get(tag)?;
+        let path = content_file.path.display().to_string();
+
+        let range = match selector.select(&content_file.path, &content_file.data, argument) {
+            Some(range) if range.start <= range.end && range.end <= content_file.data.len() => {
+                range
+            }
+            Some(_) => {
+                return Some(Err(GeoffreyError::SelectionOutOfBounds(
+                    path,
+                    tag.to_owned(),
+                )))
+            }
+            None => {
+                return Some(Err(GeoffreyError::ContentSnippetNotFound(
+                    path,
+                    tag.to_owned(),
+                    Vec::new(),
+                )))
+            }
+        };
+
+        let mut nested = Vec::new();
+        if let Some(root) = content_file.lookup.get("") {
+            Self::snippets_within(root, &range, &mut nested);
+        }
+        let indentation = content_file.data[range.clone()]
+            .iter()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| &line[..line.len() - line.trim_start().len()])
+            .min_by_key(|indentation| indentation.len())
+            .unwrap_or_default()
+            .to_owned();
+
+        Some(Ok((
+            ContentSnippetDescription {
+                tag: tag.to_owned(),
+                indentation,
+                ellipsis_line: String::new(),
+                begin: range.start,
+                end: range.end,
+                nested,
+            },
+            range,
+        )))
+    }
+
+    /// Collects the outermost snippets which are completely within `range`, including their markers
+    fn snippets_within(
+        snip_desc: &ContentSnippetDescription,
+        range: &Range<usize>,
+        found: &mut Vec<ContentSnippetDescription>,
+    ) {
+        for nested in &snip_desc.nested {
+            if nested.begin >= range.start && nested.end < range.end {
+                found.push(nested.clone());
+            } else {
+                Self::snippets_within(nested, range, found);
+            }
+        }
+    }
+
+    fn is_nested(snip_desc: &ContentSnippetDescription, tag: &str) -> bool {
+        snip_desc
+            .nested
+            .iter()
+            .any(|nested| nested.tag == tag || Self::is_nested(nested, tag))
+    }
+
+    /// All geoffrey tags of the parsed markdown files, sorted by markdown file and line
+    pub fn tags(&self) -> Vec<TagEntry> {
+        let mut tags = self
+            .md_files
+            .iter()
+            .flat_map(|md_file| {
+                md_file
+                    .segments
+                    .iter()
+                    .filter_map(|segment| segment.snippet_id.as_ref())
+                    .map(move |snippet_id| TagEntry {
+                        md_file: md_file.path.clone(),
+                        line: snippet_id.line,
+                        content_file: snippet_id.path.clone(),
+                        snippet: snippet_id.tag.snippet(),
+                        elided: matches!(snippet_id.tag, MdSnippetTag::ElidedSnippet { .. }),
+                        sub_snippets: match &snippet_id.tag {
+                            MdSnippetTag::ElidedSnippet { sub, .. } => sub.clone(),
+                            _ => Vec::new(),
+                        },
+                    })
+            })
+            .collect::<Vec<TagEntry>>();
+        tags.sort_by(|a, b| (&a.md_file, a.line).cmp(&(&b.md_file, b.line)));
+
+        tags
+    }
+
+    /// The files the parsed documents depend on: the config file, the markdown files and their folders, whose
+    /// modification time changes when files are added or removed, and the content files; a long running process
+    /// parses again when one of them changed
+    pub fn source_files(&self) -> Vec<PathBuf> {
+        let mut files = vec![self.config_dir.join(Config::FILE_NAME)];
+        for md_file in &self.md_files {
+            files.push(md_file.path.clone());
+            files.extend(md_file.path.parent().map(Path::to_path_buf));
+        }
+        files.extend(
+            self.content
+                .values()
+                .map(|content_file| content_file.path.clone()),
+        );
+        files.sort();
+        files.dedup();
+
+        files
+    }
+
+    /// The source files assembled from the code blocks following '<!-- [tangle] [path] -->' tags in the
+    /// markdown files, see `tangle::tangle`
+    pub fn tangle(&self) -> Result<Vec<TangledFile>, GeoffreyError> {
+        tangle::tangle(&self.md_paths())
+    }
+
+    /// The paths of the markdown files
+    pub fn md_paths(&self) -> Vec<PathBuf> {
+        self.md_files
+            .iter()
+            .map(|md_file| md_file.path.clone())
+            .collect()
+    }
+
+    /// Snippet markers in the parsed content files and the files in `scan_dirs` which are not referenced
+    /// by any geoffrey tag, sorted by content file and line
+    pub fn orphaned_markers(
+        &self,
+        scan_dirs: &[PathBuf],
+    ) -> Result<Vec<OrphanedMarker>, GeoffreyError> {
+        let mut referenced = HashMap::<&Path, HashSet<&str>>::new();
+        for snippet_id in self
+            .md_files
+            .iter()
+            .flat_map(|md_file| md_file.segments.iter())
+            .filter_map(|segment| segment.snippet_id.as_ref())
+        {
+            for path in snippet_id.content_paths() {
+                let content_file = match self.content.get(path) {
+                    Some(content_file) => content_file,
+                    None => continue,
+                };
+                let tags = referenced.entry(&content_file.path).or_default();
+                tags.insert(snippet_id.tag.main());
+                if let MdSnippetTag::ElidedSnippet { sub, .. } = &snippet_id.tag {
+                    tags.extend(sub.iter().map(|tag| tag.as_str()));
+                }
+            }
+        }
+
+        let mut scanned_files = Vec::new();
+        for scan_dir in scan_dirs {
+            Self::find_content_files(scan_dir, &mut |path| {
+                if self
+                    .content
+                    .values()
+                    .any(|content_file| content_file.path == path)
+                {
+                    return;
+                }
+                match Self::parse_content_file(self.sources.files(), &path, &self.syntax) {
+                    Ok(content_file) => scanned_files.push(content_file),
+                    Err(e) => log::debug!("skipping {:?}: {}", path, e),
+                }
+            })?;
+        }
+
+        let mut orphans = self
+            .content
+            .values()
+            .filter(|content_file| content_file.revision.is_none())
+            .chain(scanned_files.iter())
+            .flat_map(|content_file| {
+                let tags = referenced.get(content_file.path.as_path());
+                content_file
+                    .lookup
+                    .values()
+                    .filter(move |snip_desc| {
+                        !snip_desc.tag.is_empty()
+                            && !tags.is_some_and(|tags| tags.contains(snip_desc.tag.as_str()))
+                    })
+                    .map(move |snip_desc| OrphanedMarker {
+                        content_file: content_file.path.clone(),
+                        line: snip_desc.begin + 1,
+                        tag: snip_desc.tag.clone(),
+                    })
+            })
+            .collect::<Vec<OrphanedMarker>>();
+        orphans.sort_by(|a, b| (&a.content_file, a.line).cmp(&(&b.content_file, b.line)));
+
+        Ok(orphans)
+    }
+
+    /// Rewrites the geoffrey tags of the markdown files into their canonical form without touching anything else;
+    /// with `check`, nothing is written. Returns the files which are or would be changed and the files which failed
+    pub fn format_tags(&self, check: bool) -> (Vec<PathBuf>, Vec<FileError>) {
+        let results = self
+            .md_files
+            .par_iter()
+            .map(|md_file| {
+                Self::format_md_file(&md_file.path, check, &self.re_tag, &self.keywords[0])
+                    .map(|changed| changed.then(|| md_file.path.clone()))
+                    .map_err(|error| FileError::new(md_file.path.clone(), error))
+            })
+            .collect::<Vec<Result<Option<PathBuf>, FileError>>>();
+
+        let mut changed = Vec::new();
+        let mut failures = Vec::new();
+        for result in results {
+            match result {
+                Ok(path) => changed.extend(path),
+                Err(failure) => failures.push(failure),
+            }
+        }
+        changed.sort();
+        FileError::sort(&mut failures);
+
+        (changed, failures)
+    }
+
+    fn format_md_file(
+        path: &Path,
+        check: bool,
+        re_tag: &Regex,
+        keyword: &str,
+    ) -> Result<bool, GeoffreyError> {
+        let re_sub_tag = Self::sub_tag_regex()?;
+        let re_tangle = tangle::tangle_regex()?;
+
+        let original = fs::read_to_string(path)?;
+        let mut formatted = String::with_capacity(original.len());
+        let content = match original.strip_prefix(BOM) {
+            Some(content) => {
+                formatted.push(BOM);
+                content
+            }
+            None => &original,
+        };
+        let envelope = TagEnvelope::for_path(path);
+        let doc_comments = Self::is_doc_comment_file(path);
+        let mut unmanaged = FenceTracker::default();
+        for line in content.split_inclusive('\n') {
+            // in source files, only the tags in documentation comments are formatted
+            let (comment_prefix, line) = if doc_comments {
+                match Self::split_comment_prefix(line) {
+                    Some(split) => split,
+                    None => {
+                        formatted.push_str(line);
+                        continue;
+                    }
+                }
+            } else {
+                ("", line)
+            };
+            formatted.push_str(comment_prefix);
+            if !doc_comments && unmanaged.in_code_block(line) {
+                formatted.push_str(line);
+                continue;
+            }
+            match re_tag.captures(line) {
+                Some(caps) => {
+                    let (tag_path, tag, options) = Self::parse_tag(&caps, &re_sub_tag)?;
+                    let end = caps.get(0).ok_or(GeoffreyError::RegexError)?.end();
+                    let keyword = match Self::is_output_tag(line) {
+                        true => format!("{}{}", keyword, OUTPUT_SUFFIX),
+                        false => keyword.to_owned(),
+                    };
+                    formatted.push_str(&tag.comment(&keyword, &tag_path, &options, envelope));
+                    formatted.push_str(&line[end..]);
+                }
+                None => match tangle::format_tag(&re_tangle, line)? {
+                    Some(tangle_tag) => formatted.push_str(&tangle_tag),
+                    None => formatted.push_str(line),
+                },
+            }
+        }
+
+        if formatted == original {
+            return Ok(false);
+        }
+        if !check {
+            fs::write(path, formatted)?;
+        }
+
+        Ok(true)
+    }
+
+    /// The edits renaming the snippet `old` to `new` in the markers of the parsed content files defining it and in
+    /// the geoffrey tags embedding it from these files, sorted by path; nothing is written, see `apply_edits`
+    pub fn rename_snippet(&self, old: &str, new: &str) -> Result<Vec<FileEdit>, GeoffreyError> {
+        let fail = |reason: String| {
+            GeoffreyError::SnippetRenameFailed(old.to_owned(), new.to_owned(), reason)
+        };
+        let re_sub_tag = Self::sub_tag_regex()?;
+        if new.trim() != new || new.is_empty() || new.contains(['\n', '\r']) {
+            return Err(fail(
+                "the new name must not be empty, span several lines or start or end with whitespace"
+                    .to_owned(),
+            ));
+        }
+
+        // generated content and the content at git revisions cannot be edited
+        let mut defining = self
+            .content
+            .iter()
+            .filter(|(key, content_file)| {
+                !old.is_empty()
+                    && !content_file.skipped
+                    && content_file.revision.is_none()
+                    && key.split_once(':').map(|(prefix, _)| prefix)
+                        != Some(Config::GENERATED_PREFIX)
+                    && content_file.lookup.contains_key(old)
+            })
+            .collect::<Vec<(&String, &ContentFile)>>();
+        if defining.is_empty() {
+            return Err(fail(
+                "it is not defined in the content files referenced by the documentation".to_owned(),
+            ));
+        }
+        if let Some((_, content_file)) = defining
+            .iter()
+            .find(|(_, content_file)| content_file.lookup.contains_key(new))
+        {
+            return Err(fail(format!(
+                "'{}' already defines it",
+                content_file.path.display()
+            )));
+        }
+        defining.sort_by(|a, b| a.1.path.cmp(&b.1.path));
+
+        let mut edits = Vec::new();
+        let mut edited_files = HashSet::new();
+        for (_, content_file) in &defining {
+            // a file referenced by several keys is edited once
+            if !edited_files.insert(&content_file.path) {
+                continue;
+            }
+            let re_marker = content_file.style.marker_regex(&content_file.markers)?;
+            let original = fs::read_to_string(&content_file.path)?;
+            let edited = original
+                .split_inclusive('\n')
+                .map(|line| {
+                    match re_marker
+                        .captures(line)
+                        .and_then(|caps| caps.get(2))
+                        .filter(|matcher| matcher.as_str() == old)
+                    {
+                        Some(matcher) => {
+                            format!(
+                                "{}{}{}",
+                                &line[..matcher.start()],
+                                new,
+                                &line[matcher.end()..]
+                            )
+                        }
+                        None => line.to_owned(),
+                    }
+                })
+                .collect::<String>();
+            edits.push(FileEdit {
+                path: content_file.path.clone(),
+                original,
+                edited,
+            });
+        }
+
+        let keys = defining
+            .iter()
+            .map(|(key, _)| key.as_str())
+            .collect::<HashSet<&str>>();
+        let re_inline_tag = Self::inline_tag_regex(&self.keywords)?;
+        for md_file in &self.md_files {
+            let lines = md_file
+                .segments
+                .iter()
+                .filter_map(|segment| segment.snippet_id.as_ref())
+                .filter(|snippet_id| {
+                    let embeds = match &snippet_id.tag {
+                        MdSnippetTag::FullFile => false,
+                        MdSnippetTag::FullSnippet { main } => main == old,
+                        MdSnippetTag::ElidedSnippet { main, sub } => {
+                            main == old || sub.iter().any(|sub| sub == old)
+                        }
+                    };
+                    embeds
+                        && snippet_id
+                            .content_paths()
+                            .iter()
+                            .any(|path| keys.contains(path))
+                })
+                .map(|snippet_id| snippet_id.line)
+                .collect::<HashSet<usize>>();
+            if lines.is_empty() {
+                continue;
+            }
+
+            let original = fs::read_to_string(&md_file.path)?;
+            let mut edited = String::with_capacity(original.len());
+            for (index, line) in original.split_inclusive('\n').enumerate() {
+                if !lines.contains(&(index + 1)) {
+                    edited.push_str(line);
+                    continue;
+                }
+                let (offset, tag_line) = Self::split_tag_line(md_file.doc_comments, index, line);
+                // the names are only replaced within the brackets of the snippets of the tags
+                let ranges = match self.re_tag.captures(tag_line) {
+                    Some(caps) => caps.get(3).into_iter().collect::<Vec<_>>(),
+                    None => re_inline_tag
+                        .captures_iter(tag_line)
+                        .filter_map(|caps| caps.get(3))
+                        .collect(),
+                };
+                let mut end = 0;
+                for range in ranges.iter().map(|matcher| matcher.range()) {
+                    edited.push_str(&line[end..offset + range.start]);
+                    edited.push_str(&Self::renamed_snippet_names(
+                        &re_sub_tag,
+                        &tag_line[range.clone()],
+                        old,
+                        new,
+                    ));
+                    end = offset + range.end;
+                }
+                edited.push_str(&line[end..]);
+            }
+            edits.push(FileEdit {
+                path: md_file.path.clone(),
+                original,
+                edited,
+            });
+        }
+
+        Ok(edits)
+    }
+
+    /// The snippet of a tag like 'old' or '[main] [old]' with the name `old` replaced by `new`
+    fn renamed_snippet_names(re_sub_tag: &Regex, names: &str, old: &str, new: &str) -> String {
+        if !names.trim_start().starts_with('[') {
+            return match unescape_tag_field(names.trim()) == old {
+                true => names.replacen(names.trim(), &escape_tag_field(new), 1),
+                false => names.to_owned(),
+            };
+        }
+
+        re_sub_tag
+            .replace_all(names, |caps: &regex::Captures| {
+                match unescape_tag_field(&caps[1]) == old {
+                    true => format!("[{}]", escape_tag_field(new)),
+                    false => caps[0].to_owned(),
+                }
+            })
+            .into_owned()
+    }
+
+    /// Edits of the markdown files which rewrite the paths of the tags referencing content files that are
+    /// missing because they were renamed in git, see `renamed_path`
+    pub fn renamed_path_edits(&self) -> Result<Vec<FileEdit>, GeoffreyError> {
+        let mut renamed = HashMap::new();
+        for key in &self.failed_content {
+            let Ok((absolute_path, _)) = Self::resolve_content_path(
+                &self.git_toplevel,
+                &self.settings.content_roots,
+                &self.repos,
+                key,
+            ) else {
+                continue;
+            };
+            if absolute_path.exists() {
+                continue;
+            }
+            if let Some(new_path) = Self::renamed_path(&absolute_path) {
+                renamed.insert(key.as_str(), (absolute_path, new_path));
+            }
+        }
+        if renamed.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let re_inline_tag = Self::inline_tag_regex(&self.keywords)?;
+        let mut edits = Vec::new();
+        for md_file in &self.md_files {
+            let lines = md_file
+                .segments
+                .iter()
+                .filter_map(|segment| segment.snippet_id.as_ref())
+                .filter(|snippet_id| {
+                    snippet_id.glob.is_none() && renamed.contains_key(snippet_id.path.as_str())
+                })
+                .map(|snippet_id| snippet_id.line)
+                .collect::<HashSet<usize>>();
+            if lines.is_empty() {
+                continue;
+            }
+
+            let original = fs::read_to_string(&md_file.path)?;
+            let mut edited = String::with_capacity(original.len());
+            for (index, line) in original.split_inclusive('\n').enumerate() {
+                if !lines.contains(&(index + 1)) {
+                    edited.push_str(line);
+                    continue;
+                }
+                let (offset, tag_line) = Self::split_tag_line(md_file.doc_comments, index, line);
+                let paths = match self.re_tag.captures(tag_line) {
+                    Some(caps) => caps.get(1).into_iter().collect::<Vec<_>>(),
+                    None => re_inline_tag
+                        .captures_iter(tag_line)
+                        .filter_map(|caps| caps.get(1))
+                        .collect(),
+                };
+                let mut end = 0;
+                for path in paths {
+                    let written = unescape_tag_field(path.as_str());
+                    let (key, _) = Self::content_key(
+                        &md_file.settings,
+                        &self.settings.content_roots,
+                        &self.git_toplevel,
+                        &md_file.path,
+                        &written,
+                    );
+                    let Some(renamed_path) = renamed
+                        .get(key.as_str())
+                        .and_then(|(old, new)| Self::renamed_tag_path(&written, old, new))
+                    else {
+                        continue;
+                    };
+                    log::info!(
+                        "{:?}: '{}' was renamed to '{}'",
+                        md_file.path,
+                        written,
+                        renamed_path
+                    );
+                    edited.push_str(&line[end..offset + path.start()]);
+                    edited.push_str(&escape_tag_field(&renamed_path));
+                    end = offset + path.end();
+                }
+                edited.push_str(&line[end..]);
+            }
+            edits.push(FileEdit {
+                path: md_file.path.clone(),
+                original,
+                edited,
+            });
+        }
+
+        Ok(edits)
+    }
+
+    /// The offset of the tag in the line with the `index` of a markdown file and the line from there on,
+    /// i.e. without the byte order mark of the first line and the comment prefix of doc comments
+    fn split_tag_line(doc_comments: bool, index: usize, line: &str) -> (usize, &str) {
+        let bom_len = match index {
+            0 => line.len() - line.trim_start_matches(BOM).len(),
+            _ => 0,
+        };
+        let (comment_prefix, tag_line) = match doc_comments {
+            true => Self::split_comment_prefix(&line[bom_len..]).unwrap_or(("", "")),
+            false => ("", &line[bom_len..]),
+        };
+
+        (bom_len + comment_prefix.len(), tag_line)
+    }
+
+    /// Writes the edits of `rename_snippet` or `renamed_path_edits`; the edited contents are written to temporary files next to their
+    /// targets first and only renamed to them if all of them could be written, so that a failure leaves all
+    /// files untouched
+    pub fn apply_edits(edits: &[FileEdit]) -> Result<(), GeoffreyError> {
+        let temporary = |path: &Path| {
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            path.with_file_name(format!(".{}.geoffrey", file_name))
+        };
+
+        for (index, edit) in edits.iter().enumerate() {
+            // the permissions of the file are kept, e.g. of executable scripts
+            let written = fs::write(temporary(&edit.path), &edit.edited).and_then(|_| {
+                fs::set_permissions(
+                    temporary(&edit.path),
+                    fs::metadata(&edit.path)?.permissions(),
+                )
+            });
+            if let Err(error) = written {
+                for written in &edits[..=index] {
+                    let _ = fs::remove_file(temporary(&written.path));
+                }
+                return Err(error.into());
+            }
+        }
+        for edit in edits {
+            fs::rename(temporary(&edit.path), &edit.path)?;
+        }
+
+        Ok(())
+    }
+
+    /// The number of snippet markers in the referenced content files and the ones which are not used
+    pub fn coverage(&self) -> Result<Coverage, GeoffreyError> {
+        Ok(Coverage {
+            snippet_markers: self
+                .content
+                .values()
+                .flat_map(|content_file| content_file.lookup.keys())
+                .filter(|tag| !tag.is_empty())
+                .count(),
+            unused_markers: self.orphaned_markers(&[])?,
+        })
+    }
+
+    fn find_content_files(
+        dir: &Path,
+        content_found_cb: &mut dyn FnMut(PathBuf),
+    ) -> Result<(), GeoffreyError> {
+        for dir_entry in fs::read_dir(dir)? {
+            let path = dir_entry?.path();
+            let hidden = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with('.'));
+            if hidden {
+                continue;
+            }
+            if path.is_dir() {
+                Self::find_content_files(&path, content_found_cb)?;
+            } else if path.is_file() {
+                content_found_cb(path);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn has_elided_lines(
+        tags: &Vec<&str>,
+        elided_lines: &mut Vec<usize>,
+        ellipsis_lines: &mut Vec<(usize, usize, String)>,
+        snip_desc: &ContentSnippetDescription,
+    ) -> bool {
+        let current_snippet_tag = &snip_desc.tag as &str;
+        let mut keep_this = tags.contains(&current_snippet_tag);
+        let keep_nested = snip_desc
+            .nested
+            .iter()
+            .map(|snip_desc| {
+                let keep = Self::has_elided_lines(tags, elided_lines, ellipsis_lines, snip_desc);
+                keep_this |= keep;
+                keep
+            })
+            .collect::<Vec<bool>>();
+
+        if keep_this {
+            keep_nested
+                .iter()
+                .zip(snip_desc.nested.iter())
+                .for_each(|(keep, snip_desc)| {
+                    if !keep {
+                        ellipsis_lines.push((
+                            snip_desc.begin,
+                            snip_desc.end,
+                            snip_desc.ellipsis_line.clone(),
+                        ));
+                        elided_lines.extend(snip_desc.begin..=snip_desc.end)
+                    }
+                });
+        }
+
+        keep_this
+    }
+
+    /// Keeps `context` lines at the start and the end of each elided snippet with their marker lines, so that only
+    /// the lines in between are elided; snippets with at most twice as many lines are kept completely. The marker
+    /// lines of nested snippets are not counted since they are not rendered.
+    fn keep_context(
+        elided_lines: &mut Vec<usize>,
+        ellipsis_lines: &[(usize, usize, String)],
+        data: &[String],
+        re: &Regex,
+        context: usize,
+    ) {
+        let mut kept = HashSet::new();
+        for (begin, end, _) in ellipsis_lines {
+            let lines = (begin + 1..*end)
+                .filter(|index| !re.is_match(&data[*index]))
+                .collect::<Vec<usize>>();
+            if lines.len() <= 2 * context {
+                kept.extend(*begin..=*end);
+            } else {
+                kept.extend(*begin..=lines[context - 1]);
+                kept.extend(lines[lines.len() - context]..=*end);
+            }
+        }
+        elided_lines.retain(|index| !kept.contains(index));
+    }
+
+    /// Syncs all markdown files which were parsed successfully; a failing file does not affect the other files
+    /// and its error is reported in the diagnostics together with the failures from parsing
+    pub fn sync(self, options: &SyncOptions) -> Result<Report, GeoffreyError> {
+        log::info!("#### sync md files with content");
+        let coverage = if options.coverage {
+            Some(self.coverage()?)
+        } else {
+            None
+        };
+        let (results, skipped) =
+            self.for_each_md_file(options, |md_file| self.sync_md_file(md_file, options));
+        let kept_content = self.kept_content(options);
+        let documents = self.md_paths();
+
+        let mut blocks = Vec::new();
+        let mut changed_files = Vec::new();
+        let mut failures = self.failures;
+        failures.retain(|failure| !kept_content.contains(&failure.path));
+        let warnings = self.warnings.take();
+        for result in results {
+            match result {
+                Ok((md_blocks, written)) => {
+                    blocks.extend(md_blocks);
+                    changed_files.extend(written);
+                }
+                Err(md_failures) => failures.extend(md_failures),
+            }
+        }
+        changed_files.sort();
+        FileError::sort(&mut failures);
+
+        Ok(Report {
+            summary: Summary {
+                md_files: self.md_files.len(),
+                code_blocks: blocks.len(),
+                snippets_updated: blocks
+                    .iter()
+                    .filter(|block| block.status == BlockStatus::Updated)
+                    .count(),
+                snippets_unchanged: blocks
+                    .iter()
+                    .filter(|block| block.status == BlockStatus::Unchanged)
+                    .count(),
+                content_files: self.content.len(),
+                failed_files: FileError::distinct_files(&failures),
+                skipped_files: skipped.len(),
+                warnings: warnings.len(),
+                ..Default::default()
+            },
+            blocks,
+            diagnostics: failures.iter().map(Diagnostic::from).collect(),
+            warnings,
+            skipped,
+            changed_files,
+            documents,
+            coverage,
+            ..Default::default()
+        })
+    }
+
+    /// Calls `f` for every markdown file which was parsed successfully; once the deadline of the options
+    /// is reached or the documents are cancelled, the remaining files are not started anymore and returned
+    /// as skipped, like the ones skipped while parsing
+    fn for_each_md_file<T, F>(
+        &self,
+        options: &SyncOptions,
+        f: F,
+    ) -> (Vec<Result<T, Vec<FileError>>>, Vec<PathBuf>)
+    where
+        T: Send,
+        F: Fn(&MdFile) -> Result<T, Vec<GeoffreyError>> + Sync,
+    {
+        let results = self
+            .md_files
+            .par_iter()
+            .filter(|md_file| !md_file.failed)
+            .map(|md_file| {
+                if md_file.skipped || options.deadline_reached() || self.cancellation.is_cancelled()
+                {
+                    return Err(md_file.path.clone());
+                }
+                Ok(f(md_file).map_err(|errors| {
+                    errors
+                        .into_iter()
+                        .map(|error| FileError::new(md_file.path.clone(), error))
+                        .collect()
+                }))
+            })
+            .collect::<Vec<Result<Result<T, Vec<FileError>>, PathBuf>>>();
+
+        let mut completed = Vec::new();
+        let mut skipped = Vec::new();
+        for result in results {
+            match result {
+                Ok(result) => completed.push(result),
+                Err(path) => skipped.push(path),
+            }
+        }
+
+        (completed, skipped)
+    }
+
+    /// Renders the code blocks of a markdown file from the content files, aligned with its segments
+    fn render_md_file(
+        &self,
+        md_file: &MdFile,
+        options: &SyncOptions,
+    ) -> Result<Vec<Option<SyncedBlock>>, Vec<GeoffreyError>> {
+        let mut errors = Vec::new();
+        // the line of each tag in canonical form, to detect snippets embedded more than once the same way
+        let mut embedded = HashMap::<String, usize>::new();
+        let code_blocks = md_file
+            .segments
+            .iter()
+            .map(|segment| {
+                let snippet_id = segment.snippet_id.as_ref()?;
+                if snippet_id.glob.is_none() && snippet_id.inline.is_none() {
+                    self.lint_tag(md_file, segment, snippet_id);
+                    // the output of a snippet is no second reference to it
+                    let canonical = snippet_id.tag.comment(
+                        if snippet_id.output { OUTPUT_SUFFIX } else { "" },
+                        &snippet_id.path,
+                        &snippet_id.options,
+                        TagEnvelope::Html,
+                    );
+                    if let Some(line) = embedded.insert(canonical, snippet_id.line) {
+                        self.warnings.warn(
+                            &md_file.path,
+                            Some(Self::tag_span(segment, snippet_id)),
+                            GeoffreyWarning::DoubleReferencedSnippet(
+                                snippet_id.path.clone(),
+                                snippet_id.tag.main().to_owned(),
+                                line,
+                            ),
+                        );
+                    }
+                }
+                let synced = self
+                    .synced_fence(md_file, segment, snippet_id)
+                    .and_then(|fence| {
+                        Ok(SyncedBlock {
+                            fence,
+                            code_block: self
+                                .synced_code_block(md_file, segment, snippet_id, options)?,
+                        })
+                    });
+                // all code blocks are rendered to report every error of the file at its tag
+                match synced {
+                    Ok(synced) => Some(synced),
+                    Err(error) => {
+                        errors.push(error.at(Self::tag_span(segment, snippet_id)));
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        if errors.is_empty() {
+            Ok(code_blocks)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// The span of the tag of a segment, which is the last line of its text
+    fn tag_span(segment: &MdSegment, snippet_id: &MdSnippetId) -> SourceSpan {
+        let tag_line = segment.text.lines().last().unwrap_or_default();
+        // the text of an inline tag ends within its line, after the tag
+        let offset = match snippet_id.inline {
+            Some(_) => tag_line.rfind("<!--").or_else(|| tag_line.rfind("{/*")),
+            None => None,
+        };
+        SourceSpan::new(
+            snippet_id.line,
+            tag_line,
+            offset.unwrap_or(snippet_id.comment_prefix.len()),
+        )
+    }
+
+    /// Warns about tags which are likely not intended, like a code block language which does not fit the
+    /// content file or sub-snippets which have no effect
+    fn lint_tag(&self, md_file: &MdFile, segment: &MdSegment, snippet_id: &MdSnippetId) {
+        let warn = |warning| {
+            self.warnings.warn(
+                &md_file.path,
+                Some(Self::tag_span(segment, snippet_id)),
+                warning,
+            )
+        };
+
+        let extension = Path::new(&snippet_id.path)
+            .extension()
+            .and_then(|extension| extension.to_str());
+        let fence_text = Self::line_text(md_file.doc_comments, &segment.fence).unwrap_or_default();
+        // the code block of an output tag has the language of the output, not of the snippet
+        if let (Some(language), Some(extension), false) =
+            (fence::language(fence_text), extension, snippet_id.output)
+        {
+            if !fence::language_matches(language, extension) {
+                warn(GeoffreyWarning::FenceLanguageMismatch(
+                    language.to_owned(),
+                    snippet_id.path.clone(),
+                ));
+            }
+        }
+
+        if let Some(tag_pattern) = &self.tag_pattern {
+            let names = match &snippet_id.tag {
+                MdSnippetTag::FullFile => Vec::new(),
+                MdSnippetTag::FullSnippet { main } => vec![main],
+                MdSnippetTag::ElidedSnippet { main, sub } => {
+                    [main].into_iter().chain(sub).collect()
+                }
+            };
+            // selectors like 'lines:4-8' are no snippet names
+            for name in names
+                .into_iter()
+                .filter(|name| self.selectors.get(name).is_none() && !tag_pattern.is_match(name))
+            {
+                warn(GeoffreyWarning::TagNameMismatch(
+                    name.clone(),
+                    tag_pattern.as_str().to_owned(),
+                ));
+            }
+        }
+
+        let (main, sub) = match &snippet_id.tag {
+            MdSnippetTag::ElidedSnippet { main, sub } => (main, sub),
+            _ => return,
+        };
+        let lookup = match self.content.get(&snippet_id.path) {
+            Some(content_file) => &content_file.lookup,
+            None => return,
+        };
+        for (index, tag) in sub.iter().enumerate() {
+            let reason = if tag == main || sub[..index].contains(tag) {
+                Some("it is listed more than once".to_owned())
+            } else {
+                let within = |other: &String| match (lookup.get(tag), lookup.get(other)) {
+                    (Some(snip_desc), Some(outer)) => {
+                        other != tag && outer.begin < snip_desc.begin && snip_desc.end < outer.end
+                    }
+                    _ => false,
+                };
+                sub.iter()
+                    .find(|other| within(other))
+                    .map(|other| format!("it is within the kept sub-snippet '{}'", other))
+            };
+            if let Some(reason) = reason {
+                warn(GeoffreyWarning::UnusedSubSnippet(tag.clone(), reason));
+            }
+        }
+    }
+
+    /// Syncs the code blocks of a markdown file and returns their reports and the written file, if any
+    fn sync_md_file(
+        &self,
+        md_file: &MdFile,
+        options: &SyncOptions,
+    ) -> Result<(Vec<BlockReport>, Option<PathBuf>), Vec<GeoffreyError>> {
+        let code_blocks = self.render_md_file(md_file, options)?;
+
+        // create synced data
+        let mut original_file = String::new();
+        let mut synced_file = String::new();
+        let mut blocks = Vec::new();
+        let mut conflicts = Vec::new();
+        for (segment, synced) in md_file.segments.iter().zip(code_blocks.iter()) {
+            original_file.push_str(&segment.text);
+            original_file.push_str(&segment.fence);
+            original_file.push_str(&segment.code_block);
+            let mut guarded = self.guarded_block(md_file, segment, synced.as_ref(), options);
+            if let Some((hash, block)) = guarded
+                .as_ref()
+                .and_then(|guarded| guarded.snapshot.as_ref())
+            {
+                self.save_snapshot(hash, block)
+                    .map_err(|error| vec![error])?;
+            }
+            if let (Some(snippet_id), Some(conflict)) = (
+                &segment.snippet_id,
+                guarded.as_mut().and_then(|guarded| guarded.conflict.take()),
+            ) {
+                conflicts.push(conflict.at(Self::tag_span(segment, snippet_id)));
+            }
+            synced_file.push_str(
+                &self
+                    .synced_text(md_file, segment, guarded.as_ref())
+                    .map_err(|error| vec![error])?,
+            );
+            if let (Some(snippet_id), Some(synced)) = (&segment.snippet_id, synced) {
+                let status =
+                    if synced.fence == segment.fence && synced.code_block == segment.code_block {
+                        BlockStatus::Unchanged
+                    } else {
+                        BlockStatus::Updated
+                    };
+                match &guarded {
+                    Some(guarded) => synced_file.push_str(&guarded.block),
+                    None => {
+                        synced_file.push_str(&synced.fence);
+                        synced_file.push_str(&synced.code_block);
+                    }
+                }
+
+                blocks.push(BlockReport {
+                    md_file: md_file.path.clone(),
+                    line: snippet_id.line,
+                    content_file: snippet_id.path.clone(),
+                    snippet: snippet_id.tag.snippet(),
+                    status,
+                    content_root: self
+                        .content
+                        .get(&snippet_id.path)
+                        .and_then(|content_file| content_file.root.clone()),
+                    freshness: if options.freshness {
+                        Some(self.freshness(snippet_id))
+                    } else {
+                        None
+                    },
+                    diff: match status {
+                        BlockStatus::Updated => {
+                            Some(Self::block_diff(md_file, snippet_id, segment, synced))
+                        }
+                        BlockStatus::Unchanged => None,
+                    },
+                });
+            }
+        }
+
+        // the code blocks edited by hand are kept or merged, therefore the file is written despite the conflicts
+        let written = Self::write_md_file(md_file, &original_file, &synced_file)
+            .map_err(|error| vec![error])?;
+        if !conflicts.is_empty() {
+            return Err(conflicts);
+        }
+
+        Ok((blocks, written))
+    }
+
+    /// The text of a segment as written by the sync: with the hash of a guarded code block in its tag, the anchor
+    /// of its tag and, when rendering templates, the variables substituted
+    fn synced_text(
+        &self,
+        md_file: &MdFile,
+        segment: &MdSegment,
+        guarded: Option<&GuardedBlock>,
+    ) -> Result<String, GeoffreyError> {
+        // the placeholders outside of the synced code blocks are only substituted when rendering templates,
+        // since they would be lost in markdown files synced in place
+        let text = match &md_file.output {
+            Some(_) => self.with_variables(segment.text.clone())?,
+            None => guarded
+                .map_or(&segment.text, |guarded| &guarded.text)
+                .clone(),
+        };
+
+        Ok(
+            match segment
+                .snippet_id
+                .as_ref()
+                .and_then(MdSnippetId::anchor_id)
+                .filter(|_| !md_file.doc_comments)
+            {
+                Some(id) => Self::with_anchor(&text, &id, md_file.crlf),
+                None => text,
+            },
+        )
+    }
+
+    /// The code block of a segment as guarded by the sync with 'guard_edits', see `guard_block`, or `None` if it is
+    /// not guarded; the diff and the patch show the guarded code blocks like the sync writes them
+    fn guarded_block(
+        &self,
+        md_file: &MdFile,
+        segment: &MdSegment,
+        synced: Option<&SyncedBlock>,
+        options: &SyncOptions,
+    ) -> Option<GuardedBlock> {
+        match (&segment.snippet_id, synced) {
+            (Some(snippet_id), Some(synced))
+                if self.guard_edits && md_file.output.is_none() && snippet_id.inline.is_none() =>
+            {
+                Some(self.guard_block(md_file, segment, snippet_id, synced, options))
+            }
+            _ => None,
+        }
+    }
+
+    /// Guards a code block with 'guard_edits': the hash of the written code block is recorded in its tag and the
+    /// synced content it is based on is its snapshot. A code block which does not match its recorded hash anymore
+    /// was edited by hand and is kept, or merged with the changes of its snippet since the snapshot; a merged code
+    /// block keeps the manual edits in later syncs. Nothing is written.
+    fn guard_block(
+        &self,
+        md_file: &MdFile,
+        segment: &MdSegment,
+        snippet_id: &MdSnippetId,
+        synced: &SyncedBlock,
+        options: &SyncOptions,
+    ) -> GuardedBlock {
+        let current = format!("{}{}", segment.fence, segment.code_block);
+        let synced = format!("{}{}", synced.fence, synced.code_block);
+        let recorded = snippet_id.options.hash.as_deref();
+        let edited_by_hand = !options.force
+            && recorded.is_some_and(|recorded| recorded != Self::block_hash(&current));
+        // the synced code block the current one is based on; without, it is unknown which changes were made by hand
+        let snapshot = recorded
+            .filter(|_| !options.force)
+            .and_then(|recorded| self.snapshot(recorded));
+        let kept = |conflict: Option<GeoffreyError>| GuardedBlock {
+            text: segment.text.clone(),
+            block: current.clone(),
+            snapshot: None,
+            conflict,
+        };
+        let written = |block: String, base: String| {
+            let hash = Self::block_hash(&block);
+            GuardedBlock {
+                text: match recorded == Some(hash.as_str()) {
+                    true => segment.text.clone(),
+                    false => self.with_block_hash(md_file, &segment.text, &hash),
+                },
+                block,
+                snapshot: Some((hash, base)),
+                conflict: None,
+            }
+        };
+
+        match snapshot {
+            // the manual edits are kept while the snippet does not change, also if they were merged before
+            Some(snapshot) if snapshot != current && snapshot == synced => match edited_by_hand {
+                true => kept(Some(GeoffreyError::CodeBlockEditedByHand)),
+                false => kept(None),
+            },
+            Some(snapshot) if snapshot != current => {
+                let (merged, conflicts) = merge::merge(&snapshot, &current, &synced);
+                if conflicts > 0 {
+                    // the hash of the synced code block makes the resolved one count as edited by hand
+                    return GuardedBlock {
+                        block: merged,
+                        conflict: Some(GeoffreyError::CodeBlockMerged(conflicts)),
+                        ..written(synced.clone(), synced)
+                    };
+                }
+                self.warnings.warn(
+                    &md_file.path,
+                    Some(Self::tag_span(segment, snippet_id)),
+                    GeoffreyWarning::EditsMerged(snippet_id.path.clone()),
+                );
+                written(merged, synced)
+            }
+            None if edited_by_hand => kept(Some(GeoffreyError::CodeBlockEditedByHand)),
+            _ => written(synced.clone(), synced),
+        }
+    }
+
+    /// The code block with the `hash` as last synced, see `save_snapshot`
+    fn snapshot(&self, hash: &str) -> Option<String> {
+        fs::read_to_string(self.config_dir.join(Config::SNAPSHOT_DIR).join(hash)).ok()
+    }
+
+    /// Saves a synced code block in the snapshot directory under the hash of the written code block, which differs
+    /// for merged code blocks, as base for merging later manual edits
+    fn save_snapshot(&self, hash: &str, block: &str) -> Result<(), GeoffreyError> {
+        let snapshot_dir = self.config_dir.join(Config::SNAPSHOT_DIR);
+        let path = snapshot_dir.join(hash);
+        if self.snapshot(hash).as_deref() != Some(block) {
+            fs::create_dir_all(&snapshot_dir)?;
+            fs::write(path, block)?;
+        }
+
+        Ok(())
+    }
+
+    /// The FNV-1a hash of a code block with its opening fence as 16 hex digits; unlike the hasher of the standard
+    /// library, it is stable across versions
+    fn block_hash(block: &str) -> String {
+        provenance::hash(block)
+    }
+
+    /// The text of a segment with the option 'hash' of the tag in its last line set to `hash`
+    /// The text of a segment with the anchor '<a id="id"></a>' in the line before its tag, which ends the text;
+    /// an anchor already in that line is replaced
+    fn with_anchor(text: &str, id: &str, crlf: bool) -> String {
+        // a byte order mark stays at the start of the file
+        let bom_len = match text.starts_with('\u{feff}') {
+            true => '\u{feff}'.len_utf8(),
+            false => 0,
+        };
+        let tag_start = text
+            .trim_end_matches(['\r', '\n'])
+            .rfind('\n')
+            .map_or(bom_len, |index| index + 1);
+        let line_start = text[..tag_start]
+            .trim_end_matches(['\r', '\n'])
+            .rfind('\n')
+            .map_or(bom_len, |index| index + 1);
+        let previous = text[line_start..tag_start].trim();
+        let anchor_start = match previous.starts_with("<a id=\"") && previous.ends_with("\"></a>") {
+            true => line_start,
+            false => tag_start,
+        };
+
+        format!(
+            "{}<a id=\"{}\"></a>{}{}",
+            &text[..anchor_start],
+            id,
+            if crlf { "\r\n" } else { "\n" },
+            &text[tag_start..]
+        )
+    }
+
+    /// Checks that the anchors of the tags with the 'anchor' option are unique across the markdown files;
+    /// the files with a duplicate fail
+    fn check_unique_anchors(&mut self) {
+        let mut md_files = self.md_files.iter_mut().collect::<Vec<&mut MdFile>>();
+        md_files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mut anchors = HashMap::<String, (PathBuf, usize)>::new();
+        let mut failures = Vec::new();
+        for md_file in md_files {
+            if md_file.doc_comments {
+                continue;
+            }
+            for segment in &md_file.segments {
+                let Some(snippet_id) = &segment.snippet_id else {
+                    continue;
+                };
+                let Some(id) = snippet_id.anchor_id() else {
+                    continue;
+                };
+                match anchors.get(&id) {
+                    Some((path, line)) => {
+                        failures.push(FileError::new(
+                            md_file.path.clone(),
+                            GeoffreyError::DuplicateAnchor(id, path.clone(), *line)
+                                .at(Self::tag_span(segment, snippet_id)),
+                        ));
+                        md_file.failed = true;
+                    }
+                    None => {
+                        anchors.insert(id, (md_file.path.clone(), snippet_id.line));
+                    }
+                }
+            }
+        }
+
+        self.failures.extend(failures);
+    }
+
+    fn with_block_hash(&self, md_file: &MdFile, text: &str, hash: &str) -> String {
+        let line_start = text
+            .trim_end_matches(['\r', '\n'])
+            .rfind('\n')
+            .map_or(0, |index| index + 1);
+        // only the first line of the file can start with a byte order mark
+        let (offset, tag_line) = Self::split_tag_line(
+            md_file.doc_comments,
+            usize::from(line_start > 0),
+            &text[line_start..],
+        );
+        let Some(options) = self.re_tag.captures(tag_line).and_then(|caps| caps.get(4)) else {
+            return text.to_owned();
+        };
+
+        // the option is written last, after any quoted values which could contain 'hash='
+        let options_text = options.as_str();
+        let hashed_options = match options_text.rfind("hash=") {
+            Some(index) => {
+                let value = &options_text[index + "hash=".len()..];
+                let value_len = value
+                    .find(|c: char| !c.is_ascii_hexdigit())
+                    .unwrap_or(value.len());
+                format!(
+                    "{}hash={}{}",
+                    &options_text[..index],
+                    hash,
+                    &value[value_len..]
+                )
+            }
+            None => format!("{} hash={}", options_text, hash),
+        };
+        let start = line_start + offset + options.start();
+        format!(
+            "{}{}{}",
+            &text[..start],
+            hashed_options,
+            &text[start + options_text.len()..]
+        )
+    }
+
+    /// Writes the synced markdown file, or its output for templates, if it changed and returns the written file
+    fn write_md_file(
+        md_file: &MdFile,
+        original_file: &str,
+        synced_file: &str,
+    ) -> Result<Option<PathBuf>, GeoffreyError> {
+        let target = match &md_file.output {
+            Some(output) => {
+                if fs::read_to_string(output).is_ok_and(|rendered| rendered == synced_file) {
+                    return Ok(None);
+                }
+                if let Some(dir) = output.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                output
+            }
+            None if synced_file == original_file => return Ok(None),
+            None => &md_file.path,
+        };
+
+        // sync to file
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(md_file.output.is_some())
+            .truncate(true)
+            .open(target)?;
+
+        file.write_all(synced_file.as_bytes())?;
+        file.sync_all()?;
+
+        Ok(Some(target.clone()))
+    }
+
+    /// The diffs of all code blocks which are not in sync with their content files, sorted by markdown file and line;
+    /// nothing is written and files which cannot be rendered are returned as failures
+    pub fn diff(mut self, options: &SyncOptions) -> DiffReport {
+        let mut report = self.stale_blocks(options);
+        let kept_content = self.kept_content(options);
+        let mut failures = std::mem::take(&mut self.failures);
+        failures.retain(|failure| !kept_content.contains(&failure.path));
+        failures.append(&mut report.failures);
+        FileError::sort(&mut failures);
+        report.failures = failures;
+
+        report
+    }
+
+    /// Like `diff`, but keeps the documents, e.g. to answer further requests of a server: the failures are only the
+    /// ones of rendering, see `failures` for the ones of parsing
+    pub fn stale_blocks(&self, options: &SyncOptions) -> DiffReport {
+        let (results, skipped) = self.for_each_md_file(options, |md_file| {
+            let code_blocks = self.render_md_file(md_file, options)?;
+
+            Ok(md_file
+                .segments
+                .iter()
+                .zip(code_blocks)
+                .filter_map(|(segment, synced)| {
+                    let snippet_id = segment.snippet_id.as_ref()?;
+                    let synced =
+                        match self.guarded_block(md_file, segment, synced.as_ref(), options) {
+                            Some(guarded) => SyncedBlock::from_block(guarded.block),
+                            None => synced?,
+                        };
+                    if synced.fence == segment.fence && synced.code_block == segment.code_block {
+                        return None;
+                    }
+                    Some(Self::block_diff(md_file, snippet_id, segment, &synced))
+                })
+                .collect::<Vec<BlockDiff>>())
+        });
+
+        let mut diffs = Vec::new();
+        let mut failures = Vec::new();
+        for result in results {
+            match result {
+                Ok(md_diffs) => diffs.extend(md_diffs),
+                Err(md_failures) => failures.extend(md_failures),
+            }
+        }
+        FileError::sort(&mut failures);
+        diffs.sort_by(|a, b| (&a.md_file, a.line).cmp(&(&b.md_file, b.line)));
+
+        DiffReport {
+            diffs,
+            failures,
+            warnings: self.warnings.take(),
+            skipped,
+        }
+    }
+
+    /// The pending updates of all markdown files which are not in sync with their content files, with the git
+    /// toplevel as base of the patch, or the current directory outside of a git repository; nothing is written,
+    /// files which cannot be rendered are left out and the ones not started before the deadline are skipped
+    pub fn pending_edits(&self, options: &SyncOptions) -> Result<PendingEdits, GeoffreyError> {
+        let base = match self.git_toplevel.as_os_str().is_empty() {
+            true => std::env::current_dir()?,
+            false => self.git_toplevel.clone(),
+        };
+        let (results, skipped) =
+            self.for_each_md_file(options, |md_file| self.synced_md_edit(md_file, options));
+
+        let mut edits = results
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|edit| edit.original != edit.edited)
+            .collect::<Vec<FileEdit>>();
+        edits.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Ok(PendingEdits {
+            edits,
+            skipped,
+            base,
+        })
+    }
+
+    /// The edit of a markdown file from its current text to the one with the synced code blocks
+    fn synced_md_edit(
+        &self,
+        md_file: &MdFile,
+        options: &SyncOptions,
+    ) -> Result<FileEdit, Vec<GeoffreyError>> {
+        let code_blocks = self.render_md_file(md_file, options)?;
+
+        let mut original = String::new();
+        let mut edited = String::new();
+        for (segment, synced) in md_file.segments.iter().zip(code_blocks) {
+            original.push_str(&segment.text);
+            original.push_str(&segment.fence);
+            original.push_str(&segment.code_block);
+            let guarded = self.guarded_block(md_file, segment, synced.as_ref(), options);
+            edited.push_str(
+                &self
+                    .synced_text(md_file, segment, guarded.as_ref())
+                    .map_err(|error| vec![error])?,
+            );
+            match (guarded, synced) {
+                (Some(guarded), _) => edited.push_str(&guarded.block),
+                (None, Some(synced)) => {
+                    edited.push_str(&synced.fence);
+                    edited.push_str(&synced.code_block);
+                }
+                (None, None) => {
+                    edited.push_str(&segment.fence);
+                    edited.push_str(&segment.code_block);
+                }
+            }
+        }
+
+        Ok(FileEdit {
+            path: md_file.path.clone(),
+            original,
+            edited,
+        })
+    }
+
+    /// Parses `text` instead of the markdown file `path`, e.g. for build tools which transform the pages in memory;
+    /// `path` must be one of the markdown files of the documents and is still used to resolve relative content
+    /// paths and the config. Has to be called before parsing.
+    pub fn set_md_text(&mut self, path: &Path, text: String) -> Result<(), GeoffreyError> {
+        let md_file = self
+            .md_files
+            .iter_mut()
+            .find(|md_file| md_file.path == path)
+            .ok_or_else(|| GeoffreyError::NotAMarkdownFile(path.to_path_buf()))?;
+        md_file.text = Some(text);
+
+        Ok(())
+    }
+
+    /// The text of the parsed markdown file `path` with its code blocks synced, without writing anything; fails
+    /// with the errors of the file, including the ones from parsing it
+    pub fn synced_md_text(
+        mut self,
+        path: &Path,
+        options: &SyncOptions,
+    ) -> Result<String, Vec<FileError>> {
+        let md_file = self
+            .md_files
+            .iter()
+            .find(|md_file| md_file.path == path)
+            .ok_or_else(|| {
+                let error = GeoffreyError::NotAMarkdownFile(path.to_path_buf());
+                vec![FileError::new(path.to_path_buf(), error)]
+            })?;
+        if md_file.failed {
+            let mut failures = std::mem::take(&mut self.failures);
+            failures.retain(|failure| failure.path == path);
+            return Err(failures);
+        }
+
+        self.synced_md_edit(md_file, options)
+            .map(|edit| edit.edited)
+            .map_err(|errors| {
+                errors
+                    .into_iter()
+                    .map(|error| FileError::new(path.to_path_buf(), error))
+                    .collect()
+            })
+    }
+
+    /// The diff of a code block to the content it is synced to; the opening fence is part of the diff if it changed
+    fn block_diff(
+        md_file: &MdFile,
+        snippet_id: &MdSnippetId,
+        segment: &MdSegment,
+        synced: &SyncedBlock,
+    ) -> BlockDiff {
+        // the code blocks of glob tags are generated right after the tag without an opening fence and the ones
+        // of inline tags are within the line of the tag
+        let (code_line, current, synced) = if snippet_id.inline.is_some() {
+            (
+                snippet_id.line,
+                segment.code_block.clone(),
+                synced.code_block.clone(),
+            )
+        } else if synced.fence == segment.fence && !segment.fence.is_empty() {
+            (
+                snippet_id.line + 2,
+                segment.code_block.clone(),
+                synced.code_block.clone(),
+            )
+        } else {
+            (
+                snippet_id.line + 1,
+                format!("{}{}", segment.fence, segment.code_block),
+                format!("{}{}", synced.fence, synced.code_block),
+            )
+        };
+
+        BlockDiff {
+            md_file: md_file.path.clone(),
+            line: snippet_id.line,
+            code_line,
+            column: snippet_id.inline,
+            content_file: snippet_id.path.clone(),
+            snippet: snippet_id.tag.snippet(),
+            current,
+            synced,
+        }
+    }
+
+    /// Determines when the content of a snippet was last modified, on the file system and in git; unknown for
+    /// content which is not read from a file in the working tree, like sources, generated content and revisions
+    fn freshness(&self, snippet_id: &MdSnippetId) -> Freshness {
+        let content_file = match self.content.get(&snippet_id.path) {
+            Some(content_file) if content_file.revision.is_none() => content_file,
+            _ => return Freshness::default(),
+        };
+        let is_file = Self::repo_prefix(&snippet_id.path).is_none_or(|(prefix, _)| {
+            self.repos.contains_key(prefix) || self.sources.get(&snippet_id.path).is_none()
+        });
+        // the path of the content file resolved like when parsing it, also if it was skipped
+        let path = match Self::resolve_content_path(
+            &self.git_toplevel,
+            &self.settings.content_roots,
+            &self.repos,
+            &snippet_id.path,
+        ) {
+            Ok((path, _)) if is_file => path,
+            _ => return Freshness::default(),
+        };
+
+        let modified = fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .map(|modified| DateTime::<Utc>::from(modified).to_rfc3339());
+
+        // the line range of the snippet content without the markers; the whole file for full file tags
+        let line_range = content_file
+            .lookup
+            .get(snippet_id.tag.main())
+            .filter(|snip_desc| !snip_desc.tag.is_empty() && snip_desc.end > snip_desc.begin + 1)
+            .map(|snip_desc| (snip_desc.begin + 2, snip_desc.end));
+
+        let mut git_log = std::process::Command::new("git");
+        git_log
+            .arg("log")
+            .arg("-1")
+            .arg("--no-patch")
+            .arg("--format=%H %cI");
+        match line_range {
+            Some((first, last)) => git_log.arg(format!("-L{},{}:{}", first, last, path.display())),
+            None => git_log.arg("--").arg(&path),
+        };
+        if let Some(dir) = path.parent() {
+            git_log.current_dir(dir);
+        }
+
+        let commit = git_log
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .and_then(|output| String::from_utf8(output.stdout).ok())
+            .and_then(|stdout| {
+                stdout
+                    .trim()
+                    .split_once(' ')
+                    .map(|(hash, date)| CommitInfo {
+                        hash: hash.to_owned(),
+                        date: date.to_owned(),
+                    })
+            });
+
+        Freshness { modified, commit }
+    }
+
+    /// The opening fence of the code block; for elided tags with the 'highlight' option, with the attribute
+    /// highlighting the lines of the sub-snippets
+    fn synced_fence(
+        &self,
+        md_file: &MdFile,
+        segment: &MdSegment,
+        snippet_id: &MdSnippetId,
+    ) -> Result<String, GeoffreyError> {
+        match (&snippet_id.options.highlight, &snippet_id.tag) {
+            (Some(highlight), MdSnippetTag::ElidedSnippet { main, sub })
+                if snippet_id.glob.is_none()
+                    && snippet_id.inline.is_none()
+                    && !md_file.doc_comments =>
+            {
+                let lines = self.highlighted_lines(
+                    &snippet_id.path,
+                    main,
+                    sub,
+                    snippet_id.options.keep_markers,
+                )?;
+                highlight.fence(&segment.fence, &lines)
+            }
+            _ => Ok(segment.fence.clone()),
+        }
+    }
+
+    /// The lines of the rendered snippet `main`, starting at 1, which belong to one of the `sub` snippets
+    fn highlighted_lines(
+        &self,
+        path: &str,
+        main: &str,
+        sub: &[String],
+        keep_markers: bool,
+    ) -> Result<Vec<usize>, GeoffreyError> {
+        let content_file = match self.content.get(path) {
+            Some(content_file) if !self.failed_content.contains(path) => content_file,
+            _ => return Ok(Vec::new()),
+        };
+        let body = match self.selected_snippet(content_file, main) {
+            Some(result) => result?.1,
+            None => match content_file.lookup.get(main) {
+                Some(snip_desc) => snip_desc.end.min(snip_desc.begin + 1)..snip_desc.end,
+                // reported when rendering the snippet
+                None => return Ok(Vec::new()),
+            },
+        };
+        let sub = sub
+            .iter()
+            .filter_map(|tag| content_file.lookup.get(tag))
+            .collect::<Vec<&ContentSnippetDescription>>();
+
+        let re = content_file.style.marker_regex(&content_file.markers)?;
+        let mut rendered_line = 0;
+        let mut lines = Vec::new();
+        for index in body {
+            // marker lines are not rendered, unless they are kept
+            if !keep_markers && re.is_match(&content_file.data[index]) {
+                continue;
+            }
+            rendered_line += 1;
+            if sub
+                .iter()
+                .any(|snip_desc| snip_desc.begin < index && index < snip_desc.end)
+            {
+                lines.push(rendered_line);
+            }
+        }
+
+        Ok(lines)
+    }
+
+    /// Renders the code block of a geoffrey tag, applies the fallback if the snippet is missing and keeps
+    /// the code block if only whitespace changed and whitespace changes are ignored
+    fn synced_code_block(
+        &self,
+        md_file: &MdFile,
+        segment: &MdSegment,
+        snippet_id: &MdSnippetId,
+        options: &SyncOptions,
+    ) -> Result<String, GeoffreyError> {
+        if snippet_id.output && !options.run_snippets {
+            return Ok(segment.code_block.clone());
+        }
+        let code_block = self.rendered_code_block(md_file, segment, snippet_id, options)?;
+        if snippet_id.glob.is_none() && code_block.trim().is_empty() {
+            self.warnings.warn(
+                &md_file.path,
+                Some(Self::tag_span(segment, snippet_id)),
+                GeoffreyWarning::EmptySnippet(
+                    snippet_id.path.clone(),
+                    snippet_id.tag.main().to_owned(),
+                ),
+            );
+        }
+        if let Some(max_lines) = snippet_id.options.max_lines {
+            let lines = code_block.lines().count();
+            if lines > max_lines {
+                return Err(GeoffreyError::SnippetTooLong(
+                    snippet_id.path.clone(),
+                    snippet_id.tag.main().to_owned(),
+                    lines,
+                    max_lines,
+                ));
+            }
+        }
+        let code_block = self.with_variables(self.with_environment(
+            snippet_id,
+            Self::with_replacements(
+                snippet_id,
+                Self::with_dedent(
+                    snippet_id,
+                    Self::with_normalized_whitespace(snippet_id, code_block),
+                ),
+            )?,
+        )?)?;
+        if snippet_id.inline.is_some() {
+            return Self::inline_value(snippet_id, &code_block);
+        }
+        let code_block = match snippet_id.output {
+            true => self.snippet_output(snippet_id, &code_block)?,
+            false => code_block,
+        };
+        let code_block = self.with_max_width(md_file, segment, snippet_id, code_block);
+        if options.verify && snippet_id.glob.is_none() && !snippet_id.output {
+            self.verify_snippet(md_file, segment, snippet_id, &code_block)?;
+        }
+        // the output of a snippet has no comment syntax
+        let code_block = match self.provenance && snippet_id.glob.is_none() && !snippet_id.output {
+            true => self.with_provenance(segment, snippet_id, code_block),
+            false => code_block,
+        };
+        let code_block = self.with_line_endings(
+            md_file,
+            Self::with_comment_prefix(&snippet_id.comment_prefix, code_block),
+        );
+
+        if options.ignore_whitespace
+            && code_block != segment.code_block
+            && Self::equal_ignoring_whitespace(&code_block, &segment.code_block)
+        {
+            self.warnings.warn(
+                &md_file.path,
+                Some(Self::tag_span(segment, snippet_id)),
+                GeoffreyWarning::WhitespaceOnlyChange(snippet_id.path.clone()),
+            );
+            return Ok(segment.code_block.clone());
+        }
+
+        Ok(code_block)
+    }
+
+    /// The code block with the comment of its provenance as first line, in the comment style of its content file.
+    /// The commit of the current provenance comment is kept if the code did not change, since the last commit of
+    /// the content file changes with every commit, even one of other lines or of the synced markdown file itself.
+    fn with_provenance(
+        &self,
+        segment: &MdSegment,
+        snippet_id: &MdSnippetId,
+        code_block: String,
+    ) -> String {
+        let Some(content_file) = self.content.get(&snippet_id.path) else {
+            return code_block;
+        };
+        let mut provenance = Provenance::new(
+            snippet_id.path.clone(),
+            snippet_id.tag.snippet(),
+            None,
+            &code_block,
+        );
+        let current = segment
+            .code_block
+            .lines()
+            .next()
+            .and_then(Provenance::parse);
+        provenance.commit = match current {
+            Some(current)
+                if Provenance {
+                    commit: current.commit.clone(),
+                    ..provenance.clone()
+                } == current =>
+            {
+                current.commit
+            }
+            _ => self.last_commit(&content_file.path),
+        };
+
+        format!(
+            "{} {}\n{}",
+            content_file.style.comment, provenance, code_block
+        )
+    }
+
+    /// The abbreviated hash of the last commit which changed the file at `path`; `None` outside of git or for a
+    /// file which was never committed. It is looked up once per file.
+    fn last_commit(&self, path: &Path) -> Option<String> {
+        let mut commits = self.commits.lock().expect("could not lock mutex");
+        commits
+            .entry(path.to_owned())
+            .or_insert_with(|| Self::git_last_commit(path))
+            .clone()
+    }
+
+    fn git_last_commit(path: &Path) -> Option<String> {
+        let mut git_log = std::process::Command::new("git");
+        git_log
+            .args([
+                "log",
+                "-1",
+                "--no-patch",
+                "--abbrev=12",
+                "--format=%h",
+                "--",
+            ])
+            .arg(path);
+        if let Some(dir) = path.parent().filter(|dir| dir.is_dir()) {
+            git_log.current_dir(dir);
+        }
+
+        git_log
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .and_then(|output| String::from_utf8(output.stdout).ok())
+            .map(|stdout| stdout.trim().to_owned())
+            .filter(|commit| !commit.is_empty())
+    }
+
+    /// Compiles the rendered snippet with the command configured for the language of its code block or the
+    /// extension of its content file; the snippet is written to a file in a temporary directory, which is the
+    /// working directory of the command. Snippets without a command are not verified.
+    fn verify_snippet(
+        &self,
+        md_file: &MdFile,
+        segment: &MdSegment,
+        snippet_id: &MdSnippetId,
+        code_block: &str,
+    ) -> Result<(), GeoffreyError> {
+        let extension = Self::snippet_extension(snippet_id);
+        let fence_text = Self::line_text(md_file.doc_comments, &segment.fence).unwrap_or_default();
+        let Some(command) =
+            Self::snippet_command(&self.verify, fence::language(fence_text), extension)
+        else {
+            return Ok(());
+        };
+
+        Self::run_snippet(command, extension, code_block)
+            .map(|_| ())
+            .map_err(|reason| {
+                GeoffreyError::SnippetDoesNotCompile(
+                    snippet_id.path.clone(),
+                    snippet_id.tag.main().to_owned(),
+                    reason,
+                )
+            })
+    }
+
+    /// The standard output of the command configured in `run` for the extension of the content file, which runs
+    /// the rendered snippet of an output tag
+    fn snippet_output(
+        &self,
+        snippet_id: &MdSnippetId,
+        code_block: &str,
+    ) -> Result<String, GeoffreyError> {
+        let extension = Self::snippet_extension(snippet_id);
+        let fail = |reason: String| {
+            GeoffreyError::SnippetRunFailed(
+                snippet_id.path.clone(),
+                snippet_id.tag.main().to_owned(),
+                reason,
+            )
+        };
+        let command = Self::snippet_command(&self.run, None, extension).ok_or_else(|| {
+            fail(format!(
+                "no command is configured in '[run]' for '.{}' files",
+                extension
+            ))
+        })?;
+
+        let mut output = Self::run_snippet(command, extension, code_block).map_err(fail)?;
+        if !output.is_empty() && !output.ends_with('\n') {
+            output.push('\n');
+        }
+        Ok(output)
+    }
+
+    /// The extension of the content file of a tag, which is also the extension of the file the snippet is written
+    /// to by `run_snippet`
+    fn snippet_extension(snippet_id: &MdSnippetId) -> &str {
+        Path::new(&snippet_id.path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or("txt")
+    }
+
+    /// The command of `commands` for a snippet, configured by the language of its code block, the extension of its
+    /// content file or one of the languages fitting this extension, like 'rust' for '.rs'
+    fn snippet_command<'a>(
+        commands: &'a BTreeMap<String, String>,
+        language: Option<&str>,
+        extension: &str,
+    ) -> Option<&'a String> {
+        language
+            .into_iter()
+            .chain([extension])
+            .chain(fence::languages(extension).iter().copied())
+            .find_map(|name| commands.get(name))
+    }
+
+    /// Runs `command` with '{file}' replaced by a file with the snippet `code` in a temporary directory, which is
+    /// the working directory of the command and removed afterwards; returns the standard output
+    fn run_snippet(command: &str, extension: &str, code: &str) -> Result<String, String> {
+        // a new directory only accessible by the user, so no other user can plant or read the snippet
+        let dir = tempfile::Builder::new()
+            .prefix("geoffrey-snippet")
+            .tempdir()
+            .map_err(|e| e.to_string())?;
+        let file = format!("snippet.{}", extension);
+
+        // the files created by the command, like the outputs of a compiler, are removed with the directory
+        fs::write(dir.path().join(&file), code)
+            .map_err(|e| e.to_string())
+            .and_then(|_| source::run_command(dir.path(), &command.replace("{file}", &file)))
+    }
+
+    /// Wraps the lines of the code block wider than the maximum width of the tag or the config if the tag has
+    /// the 'wrap' flag, and warns about the lines which are still too wide
+    fn with_max_width(
+        &self,
+        md_file: &MdFile,
+        segment: &MdSegment,
+        snippet_id: &MdSnippetId,
+        code_block: String,
+    ) -> String {
+        let Some(max_width) = snippet_id.options.max_width.or(self.max_width) else {
+            return code_block;
+        };
+        let code_block = match snippet_id.options.wrap {
+            true => code_block
+                .split_inclusive('\n')
+                .map(|line| Self::wrapped_line(line, max_width))
+                .collect(),
+            false => code_block,
+        };
+
+        let too_wide = code_block
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| line.trim_end_matches('\r').chars().count() > max_width)
+            .map(|(index, _)| index + 1)
+            .collect::<Vec<usize>>();
+        if let Some(first) = too_wide.first() {
+            self.warnings.warn(
+                &md_file.path,
+                Some(Self::tag_span(segment, snippet_id)),
+                GeoffreyWarning::LinesTooWide(
+                    snippet_id.path.clone(),
+                    too_wide.len(),
+                    max_width,
+                    *first,
+                ),
+            );
+        }
+
+        code_block
+    }
+
+    /// Wraps the line at whitespace into lines of at most `max_width` characters, continued with the indentation
+    /// of the line and four more spaces; lines with quotes are kept, since they could be wrapped within a string
+    fn wrapped_line(line: &str, max_width: usize) -> String {
+        let content = line.trim_end_matches(['\r', '\n']);
+        if content.chars().count() <= max_width || content.contains(['"', '\'', '`']) {
+            return line.to_owned();
+        }
+        let eol = &line[content.len()..];
+        let line_break = if eol.is_empty() { "\n" } else { eol };
+        let indentation = &content[..content.len() - content.trim_start().len()];
+        let continuation = format!("{}    ", indentation);
+
+        let mut wrapped = String::from(indentation);
+        let mut width = indentation.chars().count();
+        let mut line_start = true;
+        for word in content.split_whitespace() {
+            let word_width = word.chars().count();
+            if !line_start && width + 1 + word_width > max_width {
+                wrapped.push_str(line_break);
+                wrapped.push_str(&continuation);
+                width = continuation.chars().count();
+                line_start = true;
+            }
+            if !line_start {
+                wrapped.push(' ');
+                width += 1;
+            }
+            wrapped.push_str(word);
+            width += word_width;
+            line_start = false;
+        }
+        wrapped.push_str(eol);
+
+        wrapped
+    }
+
+    /// The value of the inline code span of an inline tag, which is the single line of the rendered snippet
+    /// without backticks
+    fn inline_value(snippet_id: &MdSnippetId, code_block: &str) -> Result<String, GeoffreyError> {
+        let value = Self::single_line(&snippet_id.path, snippet_id.tag.main(), code_block)?;
+        if value.contains('`') {
+            return Err(GeoffreyError::InlineSnippetNotSingleLine(
+                snippet_id.path.clone(),
+                snippet_id.tag.main().to_owned(),
+            ));
+        }
+
+        Ok(value)
+    }
+
+    /// The only line of the rendered snippet `tag` of the content file `path` without the surrounding whitespace
+    fn single_line(path: &str, tag: &str, code_block: &str) -> Result<String, GeoffreyError> {
+        let value = code_block.trim();
+        if value.is_empty() || value.contains('\n') {
+            return Err(GeoffreyError::InlineSnippetNotSingleLine(
+                path.to_owned(),
+                tag.to_owned(),
+            ));
+        }
+
+        Ok(value.to_owned())
+    }
+
+    /// Converts the line endings of a code block to the configured ones or to the ones of the markdown file
+    fn with_line_endings(&self, md_file: &MdFile, code_block: String) -> String {
+        let crlf = match md_file.settings.eol {
+            Eol::Lf => false,
+            Eol::Crlf => true,
+            Eol::Preserve => md_file.crlf,
+        };
+
+        let code_block = code_block.replace("\r\n", "\n");
+        if crlf {
+            code_block.replace('\n', "\r\n")
+        } else {
+            code_block
+        }
+    }
+
+    /// Expands the tabs of the code block with 'expand-tabs' and removes the trailing whitespace of its lines with
+    /// 'strip-trailing-whitespace'; the tabs are expanded first, so that 'dedent' sees the indentation as spaces
+    fn with_normalized_whitespace(snippet_id: &MdSnippetId, code_block: String) -> String {
+        let options = &snippet_id.options;
+        if options.expand_tabs.is_none() && !options.strip_trailing_whitespace {
+            return code_block;
+        }
+
+        let mut normalized = String::with_capacity(code_block.len());
+        for line in code_block.split_inclusive('\n') {
+            let content = line.trim_end_matches(['\r', '\n']);
+            let eol = &line[content.len()..];
+            let content = match options.strip_trailing_whitespace {
+                true => content.trim_end(),
+                false => content,
+            };
+            match options.expand_tabs {
+                Some(tab_width) => {
+                    let mut column = 0;
+                    for c in content.chars() {
+                        if c == '\t' {
+                            let spaces = tab_width - column % tab_width;
+                            normalized.extend(std::iter::repeat_n(' ', spaces));
+                            column += spaces;
+                        } else {
+                            normalized.push(c);
+                            column += 1;
+                        }
+                    }
+                }
+                None => normalized.push_str(content),
+            }
+            normalized.push_str(eol);
+        }
+
+        normalized
+    }
+
+    /// Removes the leading whitespace common to all non-empty lines of the code block if the tag has the 'dedent' flag
+    fn with_dedent(snippet_id: &MdSnippetId, code_block: String) -> String {
+        if !snippet_id.options.dedent {
+            return code_block;
+        }
+
+        let indentation = |line: &str| line.len() - line.trim_start_matches([' ', '\t']).len();
+        let common = code_block
+            .split_inclusive('\n')
+            .filter(|line| !line.trim().is_empty())
+            .map(indentation)
+            .min()
+            .unwrap_or(0);
+
+        code_block
+            .split_inclusive('\n')
+            .map(|line| &line[indentation(line).min(common)..])
+            .collect()
+    }
+
+    /// Applies the replacements of the tag in order to each line of the code block
+    fn with_replacements(
+        snippet_id: &MdSnippetId,
+        code_block: String,
+    ) -> Result<String, GeoffreyError> {
+        let mut code_block = code_block;
+        for replace in &snippet_id.options.replace {
+            let re = Regex::new(&replace.pattern).map_err(|_| GeoffreyError::RegexError)?;
+            code_block = code_block
+                .split_inclusive('\n')
+                .map(|line| {
+                    let content = line.trim_end_matches(['\r', '\n']);
+                    format!(
+                        "{}{}",
+                        re.replace_all(content, replace.replacement.as_str()),
+                        &line[content.len()..]
+                    )
+                })
+                .collect();
+        }
+
+        Ok(code_block)
+    }
+
+    /// Substitutes the values of the environment of the tag for the '{{name}}' placeholders; placeholders
+    /// without a value are kept
+    fn with_environment(
+        &self,
+        snippet_id: &MdSnippetId,
+        code_block: String,
+    ) -> Result<String, GeoffreyError> {
+        let env = match &snippet_id.options.env {
+            Some(env) => env,
+            None => return Ok(code_block),
+        };
+        let values = self.envs.get(env).ok_or_else(|| {
+            GeoffreyError::UnknownEnvironment(env.clone(), snippet_id.path.clone())
+        })?;
+
+        Ok(values.iter().fold(code_block, |code_block, (name, value)| {
+            code_block.replace(&format!("{{{{{}}}}}", name), value)
+        }))
+    }
+
+    /// Prefixes the lines of a code block in a documentation comment with the comment prefix like ' * ',
+    /// without trailing whitespace for empty lines
+    fn with_comment_prefix(prefix: &str, code_block: String) -> String {
+        if prefix.is_empty() {
+            return code_block;
+        }
+
+        code_block
+            .split_inclusive('\n')
+            .map(|line| {
+                let eol = &line[line.trim_end_matches(['\r', '\n']).len()..];
+                if line.trim().is_empty() {
+                    format!("{}{}", prefix.trim_end(), eol)
+                } else {
+                    format!("{}{}", prefix, line)
+                }
+            })
+            .collect()
+    }
+
+    /// Compares code blocks line by line without trailing whitespace and with the indentation of each line replaced
+    /// by its nesting level, i.e. the rank of its width among the indentation widths of the code block, so that a
+    /// re-indented code block is equal but one with a changed relative indentation is not
+    fn equal_ignoring_whitespace(a: &str, b: &str) -> bool {
+        fn nesting(code_block: &str) -> Vec<(usize, &str)> {
+            let width = |line: &str| {
+                line.chars()
+                    .take_while(|c| c.is_whitespace())
+                    .map(|c| if c == '\t' { 4 } else { 1 })
+                    .sum::<usize>()
+            };
+            let mut widths = code_block
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(width)
+                .collect::<Vec<usize>>();
+            widths.sort_unstable();
+            widths.dedup();
+
+            code_block
+                .lines()
+                .map(|line| match line.trim() {
+                    "" => (0, ""),
+                    code => (widths.binary_search(&width(line)).unwrap_or_default(), code),
+                })
+                .collect()
+        }
+
+        nesting(a) == nesting(b)
+    }
+
+    /// Renders the code block of a geoffrey tag; for a glob tag, these are the code blocks of all matched files,
+    /// each preceded by a heading with the path of the file
+    fn rendered_code_block(
+        &self,
+        md_file: &MdFile,
+        segment: &MdSegment,
+        snippet_id: &MdSnippetId,
+        options: &SyncOptions,
+    ) -> Result<String, GeoffreyError> {
+        // highlighted sub-snippets are not elided
+        let full_snippet;
+        let tag = match (&snippet_id.options.highlight, &snippet_id.tag) {
+            (Some(_), MdSnippetTag::ElidedSnippet { main, .. }) if !md_file.doc_comments => {
+                full_snippet = MdSnippetTag::FullSnippet { main: main.clone() };
+                &full_snippet
+            }
+            (_, tag) => tag,
+        };
+
+        let matches = match &snippet_id.glob {
+            Some(matches) => matches,
+            None => {
+                return Ok(self
+                    .rendered_snippet(md_file, segment, &snippet_id.path, tag, options)?
+                    .unwrap_or_else(|| segment.code_block.clone()))
+            }
+        };
+
+        if matches.is_empty() {
+            self.warnings.warn(
+                &md_file.path,
+                Some(Self::tag_span(segment, snippet_id)),
+                GeoffreyWarning::GlobWithoutMatch(snippet_id.path.clone()),
+            );
+        }
+        let mut code_blocks = String::new();
+        let mut tabs = Vec::new();
+        for glob_match in matches {
+            if let Some(code_block) =
+                self.rendered_snippet(md_file, segment, &glob_match.path, &snippet_id.tag, options)?
+            {
+                let language = Path::new(&glob_match.path)
+                    .extension()
+                    .map_or(String::new(), |extension| {
+                        extension.to_string_lossy().into_owned()
+                    });
+                match snippet_id.options.tabs {
+                    Some(_) => tabs.push((language, code_block)),
+                    None => code_blocks.push_str(&format!(
+                        "#### {}\n```{}\n{}```\n",
+                        glob_match.heading, language, code_block
+                    )),
+                }
+            }
+        }
+
+        Ok(match snippet_id.options.tabs {
+            Some(tab_syntax) => tab_syntax.group(&tabs),
+            None => code_blocks,
+        })
+    }
+
+    /// The content files which failed but are only embedded by tags falling back to 'skip'; their code blocks are
+    /// kept with a warning at the tags, therefore the failures of the content files are not reported
+    fn kept_content(&self, options: &SyncOptions) -> HashSet<PathBuf> {
+        let mut skipped = HashMap::<&str, bool>::new();
+        for snippet_id in self
+            .md_files
+            .iter()
+            .flat_map(|md_file| md_file.segments.iter())
+            .filter_map(|segment| segment.snippet_id.as_ref())
+            .filter(|snippet_id| snippet_id.glob.is_none())
+        {
+            let skip = snippet_id.options.fallback.unwrap_or(options.fallback) == Fallback::Skip;
+            *skipped.entry(&snippet_id.path).or_insert(true) &= skip;
+        }
+
+        self.failed_content
+            .iter()
+            .filter(|path| skipped.get(path.as_str()) == Some(&true))
+            .map(PathBuf::from)
+            .collect()
+    }
+
+    /// Renders a snippet and applies the fallback if it or its content file is missing; `None` if it shall be
+    /// skipped
+    fn rendered_snippet(
+        &self,
+        md_file: &MdFile,
+        segment: &MdSegment,
+        path: &str,
+        tag: &MdSnippetTag,
+        options: &SyncOptions,
+    ) -> Result<Option<String>, GeoffreyError> {
+        let keep_markers = segment
+            .snippet_id
+            .as_ref()
+            .is_some_and(|snippet_id| snippet_id.options.keep_markers);
+        let context = segment
+            .snippet_id
+            .as_ref()
+            .and_then(|snippet_id| snippet_id.options.context)
+            .unwrap_or(0);
+        if self
+            .content
+            .get(path)
+            .is_some_and(|content_file| content_file.skipped)
+        {
+            return Ok(None);
+        }
+        if let Some(revisions) = segment
+            .snippet_id
+            .as_ref()
+            .and_then(|snippet_id| snippet_id.options.rev.as_ref())
+        {
+            let [old, new] = revisions
+                .keys(path)
+                .map(|(key, _)| self.render_snippet(&key, tag, keep_markers, context));
+            return Ok(Some(Revisions::diff(&old?, &new?)));
+        }
+        let fallback = segment
+            .snippet_id
+            .as_ref()
+            .and_then(|snippet_id| snippet_id.options.fallback)
+            .unwrap_or(options.fallback);
+        match self.render_snippet(path, tag, keep_markers, context) {
+            Err(
+                GeoffreyError::ContentFileNotFound(path, _)
+                | GeoffreyError::ContentFileInvalid(path),
+            ) if fallback == Fallback::Skip => {
+                self.warnings.warn(
+                    &md_file.path,
+                    segment
+                        .snippet_id
+                        .as_ref()
+                        .map(|snippet_id| Self::tag_span(segment, snippet_id)),
+                    GeoffreyWarning::ContentFallback(path),
+                );
+                Ok(None)
+            }
+            Err(GeoffreyError::ContentSnippetNotFound(path, tag, suggestions)) => match fallback {
+                Fallback::Error => Err(GeoffreyError::ContentSnippetNotFound(
+                    path,
+                    tag,
+                    suggestions,
+                )),
+                Fallback::FullFile => {
+                    self.warnings.warn(
+                        &md_file.path,
+                        segment
+                            .snippet_id
+                            .as_ref()
+                            .map(|snippet_id| Self::tag_span(segment, snippet_id)),
+                        GeoffreyWarning::SnippetFallback(
+                            path.clone(),
+                            tag.clone(),
+                            "embedding the full file".to_owned(),
+                        ),
+                    );
+                    self.render_snippet(&path, &MdSnippetTag::FullFile, keep_markers, 0)
+                        .map(Some)
+                }
+                Fallback::Skip => {
+                    self.warnings.warn(
+                        &md_file.path,
+                        segment
+                            .snippet_id
+                            .as_ref()
+                            .map(|snippet_id| Self::tag_span(segment, snippet_id)),
+                        GeoffreyWarning::SnippetFallback(path, tag, "skipping it".to_owned()),
+                    );
+                    Ok(None)
+                }
+            },
+            result => result.map(Some),
+        }
+    }
+
+    /// Renders a snippet without the marker lines, unless `keep_markers` is set
+    fn render_snippet(
+        &self,
+        path: &str,
+        snippet_tag: &MdSnippetTag,
+        keep_markers: bool,
+        context: usize,
+    ) -> Result<String, GeoffreyError> {
+        if self.failed_content.contains(path) {
+            return Err(GeoffreyError::ContentFileInvalid(path.to_owned()));
+        }
+
+        let content_cache = self
+            .content
+            .get(path)
+            .ok_or_else(|| GeoffreyError::ContentFileNotFound(path.to_owned(), Vec::new()))?;
+
+        let tag = snippet_tag.main();
+
+        // the lines of the snippet without the markers
+        let body;
+        let selected;
+        let snip_desc = match self.selected_snippet(content_cache, tag) {
+            Some(result) => {
+                (selected, body) = result?;
+                &selected
+            }
+            None => {
+                let snip_desc = content_cache.lookup.get(tag).ok_or_else(|| {
+                    GeoffreyError::ContentSnippetNotFound(
+                        path.to_owned(),
+                        tag.to_owned(),
+                        suggest::closest(tag, content_cache.lookup.keys().map(|tag| tag.as_str())),
+                    )
+                })?;
+                body = snip_desc.end.min(snip_desc.begin + 1)..snip_desc.end;
+                snip_desc
+            }
+        };
+
+        let mut ellipsis_lines = Vec::<(usize, usize, String)>::new();
+        let mut elided_lines = Vec::new();
+        if let MdSnippetTag::ElidedSnippet { main, sub } = snippet_tag {
+            let mut all_tags = Vec::<&str>::new();
+            all_tags.push(main);
+            sub.iter().for_each(|tag| all_tags.push(tag));
+
+            Self::has_elided_lines(&all_tags, &mut elided_lines, &mut ellipsis_lines, snip_desc);
+            if context > 0 {
+                let re = content_cache.style.marker_regex(&content_cache.markers)?;
+                Self::keep_context(
+                    &mut elided_lines,
+                    &ellipsis_lines,
+                    &content_cache.data,
+                    &re,
+                    context,
+                );
+            }
+            // the elided snippets are collected in traversal order; sort them into source order so the output
+            // does not depend on the nesting or on the order of the sub-snippets in the tag
+            ellipsis_lines.sort();
+            elided_lines.sort();
+
+            let mut empty_lines = Vec::new();
+            let mut potentially_remove = Vec::new();
+            let mut extend_empty_on_next_non_empty = false;
+
+            let mut current_line = body.start;
+            for elided in &elided_lines {
+                while *elided > current_line {
+                    let trimmed = content_cache.data[current_line].trim();
+                    if trimmed.is_empty() {
+                        potentially_remove.push(current_line);
+                    } else {
+                        if extend_empty_on_next_non_empty {
+                            empty_lines.extend_from_slice(&potentially_remove);
+                        }
+                        extend_empty_on_next_non_empty = false;
+                        potentially_remove.clear();
+                    }
+                    current_line += 1;
+                }
+                empty_lines.extend_from_slice(&potentially_remove);
+                potentially_remove.clear();
+                extend_empty_on_next_non_empty = true;
+                current_line += 1;
+            }
+            while body.end > current_line {
+                let trimmed = content_cache.data[current_line].trim();
+                if trimmed.is_empty() {
+                    potentially_remove.push(current_line);
+                } else {
+                    empty_lines.extend_from_slice(&potentially_remove);
+                    potentially_remove.clear();
+                    break;
+                }
+                current_line += 1;
+            }
+            empty_lines.extend_from_slice(&potentially_remove);
+            potentially_remove.clear();
+
+            elided_lines.extend_from_slice(&empty_lines);
+            elided_lines.sort();
+        }
+
+        let snippet = match snippet_tag {
+            MdSnippetTag::FullFile => content_cache.data[..]
+                .iter()
+                .map(|line| line as &str)
+                .collect::<Vec<&str>>(),
+            MdSnippetTag::FullSnippet { .. } => content_cache.data[body]
+                .iter()
+                .map(|line| line as &str)
+                .collect::<Vec<&str>>(),
+            MdSnippetTag::ElidedSnippet { .. } => {
+                let mut current_line = body.start;
+
+                let mut remaining_lines = Vec::<&str>::new();
+                let mut add_ellipsis_line = true;
+
+                for elided in &elided_lines {
+                    while *elided > current_line {
+                        remaining_lines.push(&content_cache.data[current_line]);
+                        current_line += 1;
+                        add_ellipsis_line = true;
+                    }
+
+                    // elided empty lines have no ellipsis; it is added with the elided snippet following them
+                    if add_ellipsis_line {
+                        if let Some(ellipsis) = ellipsis_lines.iter().find(|ellipsis| {
+                            ellipsis.0 <= current_line && current_line <= ellipsis.1
+                        }) {
+                            remaining_lines.push(&ellipsis.2);
+                            add_ellipsis_line = false;
+                        }
+                    }
+                    current_line += 1;
+                }
+                while body.end > current_line {
+                    remaining_lines.push(&content_cache.data[current_line]);
+                    current_line += 1;
+                }
+                remaining_lines
+            }
+        };
+
+        let re = content_cache.style.marker_regex(&content_cache.markers)?;
+        let mut rendered = String::new();
+        for line in snippet {
+            // skip tag lines
+            if keep_markers || !re.is_match(line) {
+                rendered.push_str(Self::strip_indentation(line, &snip_desc.indentation));
+            }
+        }
+
+        Ok(rendered)
+    }
+
+    /// Removes the indentation of the snippet marker from a line of the snippet; if the line is indented
+    /// differently, e.g. with spaces instead of tabs, its leading whitespace is removed up to the width of the
+    /// indentation instead, with tab stops every four columns
+    fn strip_indentation<'a>(line: &'a str, indentation: &str) -> &'a str {
+        if let Some(stripped) = line.strip_prefix(indentation) {
+            return stripped;
+        }
+
+        let advance = |column: usize, c: char| match c {
+            '\t' => (column / 4 + 1) * 4,
+            _ => column + 1,
+        };
+        let width = indentation.chars().fold(0, advance);
+        let mut column = 0;
+        let mut stripped = line;
+        for c in line.chars() {
+            if !matches!(c, ' ' | '\t') || advance(column, c) > width {
+                break;
+            }
+            column = advance(column, c);
+            stripped = &stripped[1..];
+        }
+
+        stripped
+    }
+
+    /// Searches `doc_path` recursively for markdown files; `visited` contains the canonical paths of the
+    /// directories and files already found, which prevents endless recursion on cyclic symlinks
+    fn find_md_files(
+        doc_path: &Path,
+        symlinks: Symlinks,
+        is_doc_file: fn(PathBuf) -> Result<PathBuf, GeoffreyError>,
+        visited: &mut HashSet<PathBuf>,
+        md_found_cb: &mut dyn FnMut(PathBuf),
+    ) -> Result<(), GeoffreyError> {
+        if !visited.insert(fs::canonicalize(doc_path)?) {
+            log::warn!(
+                "{:?}: skipping directory which was already visited",
+                doc_path
+            );
+            return Ok(());
+        }
+
+        for dir_entry in fs::read_dir(doc_path)? {
+            let dir_entry = dir_entry?;
+            let path = dir_entry.path();
+            if dir_entry.file_type()?.is_symlink() {
+                if symlinks == Symlinks::Skip {
+                    log::debug!("{:?}: skipping symlink", path);
+                    continue;
+                }
+                if !path.exists() {
+                    log::warn!("{:?}: skipping broken symlink", path);
+                    continue;
+                }
+            }
+
+            if path.is_dir() {
+                Self::find_md_files(&path, symlinks, is_doc_file, visited, md_found_cb)?;
+            } else if let Ok(path) = is_doc_file(path) {
+                if visited.insert(fs::canonicalize(&path)?) {
+                    md_found_cb(path);
+                } else {
+                    log::debug!("{:?}: skipping file which was already found", path);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn is_template_file(path: PathBuf) -> Result<PathBuf, GeoffreyError> {
+        match path
+            .to_str()
+            .and_then(|path| path.strip_suffix(TEMPLATE_EXTENSION))
+        {
+            Some(md_path) if Self::is_md_file(PathBuf::from(md_path)).is_ok() => Ok(path),
+            _ => Err(GeoffreyError::NotATemplateFile(path)),
+        }
+    }
+
+    /// Accepts markdown files and source files with documentation comments
+    fn is_md_file(path: PathBuf) -> Result<PathBuf, GeoffreyError> {
+        path.extension()
+            .as_ref()
+            .and_then(|ext_osstr| ext_osstr.to_str())
+            .filter(|ext| {
+                MD_EXTENSIONS
+                    .iter()
+                    .chain(DOC_COMMENT_EXTENSIONS)
+                    .any(|md_ext| ext.eq_ignore_ascii_case(md_ext))
+            })
+            .ok_or(GeoffreyError::NotAMarkdownFile(path.clone()))?;
+
+        Ok(path)
+    }
+
+    /// Whether the tags of the file are in documentation comments, i.e. it is a source file or a template of one
+    fn is_doc_comment_file(path: &Path) -> bool {
+        let path = path.to_string_lossy();
+        let path = path.strip_suffix(TEMPLATE_EXTENSION).unwrap_or(&path);
+        Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| {
+                DOC_COMMENT_EXTENSIONS
+                    .iter()
+                    .any(|doc_ext| ext.eq_ignore_ascii_case(doc_ext))
+            })
+    }
+
+    /// Splits a line of a documentation comment into the comment prefix like ' * ', '/// ' or '//! ' and the text
+    fn split_comment_prefix(line: &str) -> Option<(&str, &str)> {
+        let indentation = line.len() - line.trim_start_matches([' ', '\t']).len();
+        let marker = ["///", "//!", "*"]
+            .iter()
+            .find(|marker| line[indentation..].starts_with(*marker))?;
+        let mut len = indentation + marker.len();
+        if line[len..].starts_with(' ') {
+            len += 1;
+        }
+
+        Some(line.split_at(len))
+    }
+
+    /// The text of a line without the comment prefix in documentation comments; `None` if the line is not
+    /// part of a documentation comment
+    fn line_text(doc_comments: bool, line: &str) -> Option<&str> {
+        if doc_comments {
+            Self::split_comment_prefix(line).map(|(_, text)| text)
+        } else {
+            Some(line)
+        }
+    }
+
+    /// Matches a geoffrey tag or an output tag like '<!-- [geoffrey-output] [path] [snippet] -->', see
+    /// `is_output_tag`; group 1 is the path, group 3 the snippet and group 4 the options
+    fn tag_regex(keywords: &[String]) -> Result<Regex, GeoffreyError> {
+        let keywords = keywords
+            .iter()
+            .map(|keyword| format!("{}(?:{})?", regex::escape(keyword), OUTPUT_SUFFIX))
+            .collect::<Vec<String>>()
+            .join("|");
+        Regex::new(&format!(
+            r#"^(?:<!--|\{{/\*) *\[(?:{})\] *\[((?:[^\]\\]|\\.)*)\] *(\[(.*)\])?((?: *[\w\-]+(?:=(?:"(?:[^"\\]|\\.)*"|[\w\-\./~\^]*))?)*) *(?:-->|\*/\}})"#,
+            keywords
+        ))
+        .map_err(|_| GeoffreyError::RegexError)
+    }
+
+    /// Matches an inline tag like '<!--[geoffrey-inline][path][snippet]-->' anywhere in a line, with the same
+    /// groups as `tag_regex` followed by the optional inline code span; group 6 is the value of the code span
+    fn inline_tag_regex(keywords: &[String]) -> Result<Regex, GeoffreyError> {
+        let keywords = keywords
+            .iter()
+            .map(|keyword| format!("{}-inline", regex::escape(keyword)))
+            .collect::<Vec<String>>()
+            .join("|");
+        Regex::new(&format!(
+            r#"(?:<!--|\{{/\*) *\[(?:{})\] *\[((?:[^\]\\]|\\.)*)\] *(\[((?:[^\\]|\\.)*?)\])?((?: *[\w\-]+(?:=(?:"(?:[^"\\]|\\.)*"|[\w\-\./~\^]*))?)*) *(?:-->|\*/\}})(?:( *`)([^`\n]*)`)?"#,
+            keywords
+        ))
+        .map_err(|_| GeoffreyError::RegexError)
+    }
+
+    /// Whether the tag in `line`, which is matched by `tag_regex`, is an output tag
+    fn is_output_tag(line: &str) -> bool {
+        line.split_once('[')
+            .and_then(|(_, rest)| rest.split_once(']'))
+            .is_some_and(|(keyword, _)| keyword.trim().ends_with(OUTPUT_SUFFIX))
+    }
+
+    /// Matches the beginning of a geoffrey tag up to the keyword, to detect malformed tags
+    fn tag_start_regex(keywords: &[String]) -> Result<Regex, GeoffreyError> {
+        let keywords = keywords
+            .iter()
+            .map(|keyword| format!("{}(?:{})?", regex::escape(keyword), OUTPUT_SUFFIX))
+            .collect::<Vec<String>>()
+            .join("|");
+        Regex::new(&format!(r"^(?:<!--|\{{/\*) *\[(?:{})\]", keywords))
+            .map_err(|_| GeoffreyError::RegexError)
+    }
+
+    /// Matches the opt-out markers '<!-- [geoffrey-ignore] -->' for the next tag and '<!-- [geoffrey-off] -->' and
+    /// '<!-- [geoffrey-on] -->' around a range of tags; group 1 is 'ignore', 'off' or 'on'
+    fn opt_out_regex(keywords: &[String]) -> Result<Regex, GeoffreyError> {
+        let keywords = keywords
+            .iter()
+            .map(|keyword| regex::escape(keyword))
+            .collect::<Vec<String>>()
+            .join("|");
+        Regex::new(&format!(
+            r"^(?:<!--|\{{/\*) *\[(?:{})-(ignore|off|on)\] *(?:-->|\*/\}})",
+            keywords
+        ))
+        .map_err(|_| GeoffreyError::RegexError)
+    }
+
+    /// The offset of the character at which parsing the malformed tag `line` broke and what was expected
+    /// there; `start` is the end of the keyword; like `tag_regex`, the snippet ends at any closing bracket
+    /// and the furthest offset of these alternatives is reported
+    fn malformed_tag(line: &str, start: usize) -> Result<(usize, String), GeoffreyError> {
+        let re_path =
+            Regex::new(r"^\[(?:[^\]\\]|\\.)*\]").map_err(|_| GeoffreyError::RegexError)?;
+        let re_options = Regex::new(r#"^(?: *[\w\-]+(?:=(?:"(?:[^"\\]|\\.)*"|[\w\-]*))?)*"#)
+            .map_err(|_| GeoffreyError::RegexError)?;
+        let skip_spaces = |offset: usize| line.len() - line[offset..].trim_start_matches(' ').len();
+        let end_of_tag = if line.starts_with("{/*") {
+            "the end of the tag '*/}'"
+        } else {
+            "the end of the tag '-->'"
+        };
+
+        let path_start = skip_spaces(start);
+        let path_end = match re_path.find(&line[path_start..]) {
+            Some(path) => path_start + path.end(),
+            None => {
+                return Ok((
+                    path_start,
+                    "the content file path in brackets like '[path/to/file]'".to_owned(),
+                ))
+            }
+        };
+
+        let snippet_start = skip_spaces(path_end);
+        let mut snippet_ends = vec![path_end];
+        if line[snippet_start..].starts_with('[') {
+            snippet_ends.extend(
+                line[snippet_start..]
+                    .match_indices(']')
+                    .map(|(index, _)| snippet_start + index + 1),
+            );
+        }
+        let offset = snippet_ends
+            .into_iter()
+            .map(|end| skip_spaces(end + re_options.find(&line[end..]).map_or(0, |m| m.end())))
+            .max()
+            .unwrap_or(path_end);
+
+        if line[offset..].trim().is_empty() {
+            Ok((offset, end_of_tag.to_owned()))
+        } else {
+            Ok((
+                offset,
+                format!("an option like 'name=value' or {}", end_of_tag),
+            ))
+        }
+    }
+
+    /// The offset of the option in `options` which caused `error`, or 0 if it cannot be found
+    fn option_offset(options: &str, error: &GeoffreyError) -> usize {
+        let offset = match error {
+            GeoffreyError::InvalidTagOption(option) => options.find(option.as_str()),
+            GeoffreyError::DuplicateTagOption(name) => options.rfind(name.as_str()),
+            GeoffreyError::InvalidReplacement(..) => options.find("replace="),
+            _ => None,
+        };
+        offset.unwrap_or(0)
+    }
+
+    /// A bracketed snippet name of a tag, in which brackets and backslashes are escaped by a backslash
+    fn sub_tag_regex() -> Result<Regex, GeoffreyError> {
+        Regex::new(r"\[((?:[^\[\]\\]|\\.)*)\]").map_err(|_| GeoffreyError::RegexError)
+    }
+
+    /// The content file path and the snippet tag of a geoffrey tag matched by `tag_regex`
+    fn parse_tag(
+        caps: &regex::Captures,
+        re_sub_tag: &Regex,
+    ) -> Result<(String, MdSnippetTag, TagOptions), GeoffreyError> {
+        let path = caps.get(1).ok_or(GeoffreyError::RegexError)?.as_str();
+        let str_tag = caps.get(3).map_or("", |matcher| matcher.as_str().trim());
+
+        // the names of an elided snippet are bracketed themselves, while a single name may contain escaped brackets
+        let tag = match str_tag {
+            "" => MdSnippetTag::FullFile,
+            _ => {
+                let mut names = re_sub_tag
+                    .captures_iter(str_tag)
+                    .filter(|_| str_tag.starts_with('['))
+                    .map(|caps| {
+                        caps.get(1)
+                            .map(|matcher| unescape_tag_field(matcher.as_str()))
+                            .ok_or(GeoffreyError::RegexError)
+                    });
+
+                match names.next() {
+                    Some(main) => MdSnippetTag::ElidedSnippet {
+                        main: main?,
+                        sub: names.collect::<Result<Vec<String>, GeoffreyError>>()?,
+                    },
+                    None => MdSnippetTag::FullSnippet {
+                        main: unescape_tag_field(str_tag),
+                    },
+                }
+            }
+        };
+
+        let options = caps.get(4).map_or(Ok(TagOptions::default()), |matcher| {
+            matcher.as_str().parse()
+        })?;
+
+        Ok((unescape_tag_field(path), tag, options))
+    }
+
+    fn parse_single_md_file(
+        md_file: &mut MdFile,
+        content: &Mutex<&mut ContentMap>,
+        top_level_roots: &[PathBuf],
+        git_toplevel: &Path,
+        (re_tag, re_tag_start, re_inline_tag, re_opt_out): (&Regex, &Regex, &Regex, &Regex),
+        (tag_filters, variables): (&[String], &BTreeMap<String, String>),
+        tag_errors: &mut Vec<GeoffreyError>,
+    ) -> Result<(), GeoffreyError> {
+        let settings = md_file.settings.clone();
+        let mut reader: Box<dyn BufRead> = match &md_file.text {
+            Some(text) => Box::new(std::io::Cursor::new(text.clone().into_bytes())),
+            None => Box::new(BufReader::new(fs::File::open(md_file.path.clone())?)),
+        };
+
+        let re_sub_tag = Self::sub_tag_regex()?;
+
+        md_file.segments.push(MdSegment {
+            text: String::new(),
+            snippet_id: None,
+            fence: String::new(),
+            code_block: String::new(),
+        });
+        let mut segment = md_file.segments.last_mut().expect("just added");
+
+        // tags in code blocks which are not synced, like examples of geoffrey usage, are ignored
+        let mut unmanaged = FenceTracker::default();
+        // the tags opted out with the markers are kept as text, like their code blocks
+        let mut ignore_next_tag = false;
+        let mut off = false;
+        let mut in_ignored_glob = false;
+        let mut line_number = 0;
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 0 {
+            line_number += 1;
+            if line_number == 1 {
+                md_file.crlf = line.ends_with("\r\n");
+            }
+            segment.text.push_str(&line);
+            // a byte order mark is kept in the text of the segment and therefore preserved on write
+            let tag_line = match line_number {
+                1 => line.strip_prefix(BOM).unwrap_or(&line),
+                _ => &line,
+            };
+            // in source files, the tags are in documentation comments and preceded by the comment prefix
+            let (comment_prefix, tag_line) = if md_file.doc_comments {
+                match Self::split_comment_prefix(tag_line) {
+                    Some(split) => split,
+                    None => {
+                        line.clear();
+                        continue;
+                    }
+                }
+            } else {
+                if unmanaged.in_code_block(tag_line) {
+                    line.clear();
+                    continue;
+                }
+                ("", tag_line)
+            };
+            // the errors of a tag point to the character in the line at which they occurred
+            let prefix_len = line.len() - tag_line.len();
+            let tag_line_number = line_number;
+            let span_at =
+                |offset: usize| SourceSpan::new(tag_line_number, &line, prefix_len + offset);
+            if let Some(caps) = re_opt_out.captures(tag_line) {
+                match &caps[1] {
+                    "ignore" => ignore_next_tag = true,
+                    "off" => off = true,
+                    _ => off = false,
+                }
+                line.clear();
+                continue;
+            }
+            if in_ignored_glob {
+                // the generated code blocks of an ignored glob tag end with its end tag
+                in_ignored_glob = !Self::is_glob_end_tag(re_tag, tag_line);
+                line.clear();
+                continue;
+            }
+            let is_tag = re_tag.is_match(tag_line) || re_inline_tag.is_match(tag_line);
+            if off || (is_tag && ignore_next_tag) {
+                ignore_next_tag = false;
+                in_ignored_glob = re_tag.captures(tag_line).is_some_and(|caps| {
+                    caps.get(1).is_some_and(|path| Self::is_glob(path.as_str()))
+                });
+                line.clear();
+                continue;
+            }
+            if let Some(caps) = re_tag.captures(tag_line) {
+                // an invalid tag is kept as text, like its code block
+                let (tag_path, tag, options) = match Self::parse_tag(&caps, &re_sub_tag) {
+                    Ok(parsed) => parsed,
+                    Err(error) => {
+                        let options = caps.get(4).ok_or(GeoffreyError::RegexError)?;
+                        let offset = Self::option_offset(options.as_str(), &error);
+                        tag_errors.push(error.at(span_at(options.start() + offset)));
+                        line.clear();
+                        continue;
+                    }
+                };
+                let tag_path = match Self::expanded_path(tag_path, variables) {
+                    Ok(tag_path) => tag_path,
+                    Err(error) => {
+                        tag_errors
+                            .push(error.at(span_at(caps.get(1).map_or(0, |path| path.start()))));
+                        line.clear();
+                        continue;
+                    }
+                };
+                // the code block of a tag which is filtered out is an unmanaged code block
+                if !Self::tag_selected(tag_filters, &tag_path, &tag) {
+                    line.clear();
+                    continue;
+                }
+                let str_tag = caps.get(3).map_or("", |matcher| matcher.as_str().trim());
+                let path_offset = caps.get(1).map_or(0, |matcher| matcher.start());
+                let output = Self::is_output_tag(tag_line);
+                if output && Self::is_glob(&tag_path) {
+                    tag_errors.push(
+                        GeoffreyError::InvalidGlob(
+                            tag_path,
+                            "output tags run a single snippet".to_owned(),
+                        )
+                        .at(span_at(path_offset)),
+                    );
+                    line.clear();
+                    continue;
+                }
+
+                let glob = if Self::is_glob(&tag_path) {
+                    match Self::expand_glob(&settings, git_toplevel, &md_file.path, &tag_path) {
+                        Ok(matches) => Some(matches),
+                        Err(error) => {
+                            tag_errors.push(error.at(span_at(path_offset)));
+                            line.clear();
+                            continue;
+                        }
+                    }
+                } else {
+                    if options.tabs.is_some() {
+                        let offset = caps.get(4).map_or(0, |matcher| {
+                            matcher.start() + matcher.as_str().find("tabs=").unwrap_or(0)
+                        });
+                        tag_errors
+                            .push(GeoffreyError::TabsWithoutGlob(tag_path).at(span_at(offset)));
+                        line.clear();
+                        continue;
+                    }
+                    None
+                };
+                let content_paths = match &glob {
+                    Some(matches) => matches
+                        .iter()
+                        .map(|glob_match| (glob_match.path.clone(), glob_match.root.clone()))
+                        .collect(),
+                    None => vec![Self::content_key(
+                        &settings,
+                        top_level_roots,
+                        git_toplevel,
+                        &md_file.path,
+                        &tag_path,
+                    )],
+                };
+
+                log::debug!("{:?} '{}' - '{}'", md_file.path, tag_path, str_tag);
+
+                {
+                    let mut content = content.lock().expect("could not lock mutex");
+                    for (path, root) in content_paths.iter() {
+                        let mut content_file = ContentFile::new(
+                            PathBuf::from(path),
+                            CommentStyle::for_path(Path::new(path)),
+                        );
+                        content_file.root = root.clone();
+                        content.insert(path.clone(), content_file);
+                        for (key, rev) in options.rev.iter().flat_map(|rev| rev.keys(path)) {
+                            let mut content_file = ContentFile::new(
+                                PathBuf::from(path),
+                                CommentStyle::for_path(Path::new(path)),
+                            );
+                            content_file.revision = Some((path.clone(), rev.to_owned()));
+                            content.insert(key, content_file);
+                        }
+                    }
+                }
+                let is_glob = glob.is_some();
+                segment.snippet_id = Some(MdSnippetId {
+                    path: match glob {
+                        Some(_) => tag_path,
+                        None => content_paths[0].0.clone(),
+                    },
+                    tag,
+                    options,
+                    line: line_number,
+                    glob,
+                    comment_prefix: comment_prefix.to_owned(),
+                    inline: None,
+                    output,
+                });
+
+                let doc_comments = md_file.doc_comments;
+                // next line must be the begin of a code block; the code blocks of a glob tag are generated
+                let mut line = String::new();
+                let block_end = if is_glob {
+                    BlockEnd::GlobEndTag
+                } else if reader.read_line(&mut line)? > 0 {
+                    let block_end = match Self::line_text(doc_comments, &line) {
+                        Some(text) if doc_comments && fence::is_doxygen_code(text) => {
+                            Some(BlockEnd::EndCode)
+                        }
+                        Some(text) => CodeFence::open(text).map(BlockEnd::Fence),
+                        None => None,
+                    }
+                    .ok_or_else(|| {
+                        GeoffreyError::CodeBlockMustFollowTag(
+                            md_file.path.clone(),
+                            str_tag.to_owned(),
+                        )
+                        .at(SourceSpan::new(line_number + 1, &line, 0))
+                    })?;
+                    line_number += 1;
+                    segment.fence = line;
+                    block_end
+                } else {
+                    return Err(GeoffreyError::CodeBlockMustFollowTag(
+                        md_file.path.clone(),
+                        str_tag.to_owned(),
+                    )
+                    .at(span_at(tag_line.trim_end().len())));
+                };
+
+                // skip everything until the end of the code block, or the end tag of a glob tag,
+                // which is part of the next segment
+                let mut code_block = String::new();
+                let mut line = String::new();
+                let mut end_of_block_found = false;
+                while reader.read_line(&mut line)? > 0 {
+                    line_number += 1;
+                    let end_of_block =
+                        Self::line_text(doc_comments, &line).is_some_and(|text| match block_end {
+                            BlockEnd::Fence(fence) => fence.closes(text),
+                            BlockEnd::EndCode => fence::is_doxygen_endcode(text),
+                            BlockEnd::GlobEndTag => Self::is_glob_end_tag(re_tag, text),
+                        });
+                    if end_of_block {
+                        end_of_block_found = true;
+                        break;
+                    }
+                    code_block.push_str(&line);
+                    line.clear();
+                }
+                segment.code_block = code_block;
+
+                md_file.segments.push(MdSegment {
+                    text: line,
+                    snippet_id: None,
+                    fence: String::new(),
+                    code_block: String::new(),
+                });
+                segment = md_file.segments.last_mut().expect("just added");
+
+                if !end_of_block_found && is_glob {
+                    return Err(GeoffreyError::GlobEndTagMissing(
+                        md_file.path.clone(),
+                        caps.get(1)
+                            .map_or("", |matcher| matcher.as_str().trim())
+                            .to_owned(),
+                    )
+                    .at(span_at(0)));
+                }
+                if !end_of_block_found {
+                    return Err(GeoffreyError::CodeBlockEndMissing(
+                        md_file.path.clone(),
+                        str_tag.to_owned(),
+                    )
+                    .at(span_at(0)));
+                }
+            } else if re_inline_tag.is_match(tag_line) {
+                // the line is split after the opening backtick of each inline code span, whose value becomes
+                // the code block of the segment
+                let line_start = segment.text.len() - line.len();
+                let mut consumed = 0;
+                for caps in re_inline_tag.captures_iter(tag_line) {
+                    let tag_match = caps.get(0).ok_or(GeoffreyError::RegexError)?;
+                    let value = match caps.get(6) {
+                        Some(value) => value,
+                        None => {
+                            tag_errors.push(
+                                GeoffreyError::InvalidInlineTag(
+                                    "expected an inline code span like '`value`' after the tag"
+                                        .to_owned(),
+                                )
+                                .at(span_at(tag_match.end())),
+                            );
+                            continue;
+                        }
+                    };
+                    let (tag_path, tag, options) = match Self::parse_tag(&caps, &re_sub_tag) {
+                        Ok(parsed) => parsed,
+                        Err(error) => {
+                            tag_errors.push(error.at(span_at(tag_match.start())));
+                            continue;
+                        }
+                    };
+                    let tag_path = match Self::expanded_path(tag_path, variables) {
+                        Ok(tag_path) => tag_path,
+                        Err(error) => {
+                            tag_errors.push(
+                                error.at(span_at(caps.get(1).map_or(0, |path| path.start()))),
+                            );
+                            continue;
+                        }
+                    };
+                    if Self::is_glob(&tag_path) {
+                        let path_offset = caps.get(1).map_or(0, |matcher| matcher.start());
+                        tag_errors.push(
+                            GeoffreyError::InvalidInlineTag(
+                                "globs are only supported in tags followed by a code block"
+                                    .to_owned(),
+                            )
+                            .at(span_at(path_offset)),
+                        );
+                        continue;
+                    }
+                    if options.rev.is_some() {
+                        tag_errors.push(
+                            GeoffreyError::InvalidInlineTag(
+                                "the diff of the option 'rev' needs a code block".to_owned(),
+                            )
+                            .at(span_at(tag_match.start())),
+                        );
+                        continue;
+                    }
+                    if !Self::tag_selected(tag_filters, &tag_path, &tag) {
+                        continue;
+                    }
+
+                    let (path, root) = Self::content_key(
+                        &settings,
+                        top_level_roots,
+                        git_toplevel,
+                        &md_file.path,
+                        &tag_path,
+                    );
+                    {
+                        let mut content = content.lock().expect("could not lock mutex");
+                        let mut content_file = ContentFile::new(
+                            PathBuf::from(&path),
+                            CommentStyle::for_path(Path::new(&path)),
+                        );
+                        content_file.root = root;
+                        content.insert(path.clone(), content_file);
+                    }
+
+                    let value_start = prefix_len + value.start();
+                    let value_end = prefix_len + value.end();
+                    if consumed == 0 {
+                        segment.text.truncate(line_start);
+                    }
+                    segment.text.push_str(&line[consumed..value_start]);
+                    segment.snippet_id = Some(MdSnippetId {
+                        path,
+                        tag,
+                        options,
+                        line: line_number,
+                        glob: None,
+                        comment_prefix: comment_prefix.to_owned(),
+                        inline: Some(line[..value_start].chars().count()),
+                        output: false,
+                    });
+                    segment.code_block = line[value_start..value_end].to_owned();
+                    md_file.segments.push(MdSegment {
+                        text: String::new(),
+                        snippet_id: None,
+                        fence: String::new(),
+                        code_block: String::new(),
+                    });
+                    segment = md_file.segments.last_mut().expect("just added");
+                    consumed = value_end;
+                }
+                if consumed > 0 {
+                    segment.text.push_str(&line[consumed..]);
+                }
+            } else if let Some(start) = re_tag_start.find(tag_line) {
+                let (offset, expected) = Self::malformed_tag(tag_line, start.end())?;
+                tag_errors.push(GeoffreyError::MalformedTag(expected).at(span_at(offset)));
+            }
+            line.clear();
+        }
+        Ok(())
+    }
+
+    /// The file in the overrides directory at the same path relative to `base` as the content file `path`
+    fn shadowing_file(overrides: Option<&Path>, base: &Path, path: &Path) -> Option<PathBuf> {
+        let relative = path
+            .strip_prefix(base)
+            .ok()
+            .filter(|relative| relative.is_relative() && !base.as_os_str().is_empty())?;
+        Some(overrides?.join(relative)).filter(|shadow| shadow.is_file())
+    }
+
+    /// Parses the file shadowing the content file `path` and warns if its snippets differ from the ones of the
+    /// original, since the shadow is then likely outdated
+    fn parse_shadowed_content_file(
+        files: &dyn SnippetSource,
+        path: &PathBuf,
+        shadow: &PathBuf,
+        syntax: &ContentSyntax,
+        warnings: &Warnings,
+    ) -> Result<ContentFile, GeoffreyError> {
+        log::info!("{:?} is shadowed by {:?}", path, shadow);
+        let original = Self::parse_content_file(files, path, syntax)?;
+        let shadowed = Self::parse_content_file(files, shadow, syntax)?;
+
+        if Self::snippet_structure(&original) != Self::snippet_structure(&shadowed) {
+            warnings.warn(
+                shadow,
+                None,
+                GeoffreyWarning::ShadowDiverges(shadow.clone(), path.clone()),
+            );
+        }
+
+        Ok(shadowed)
+    }
+
+    /// The snippets of a content file with their directly nested snippets
+    fn snippet_structure(content_file: &ContentFile) -> BTreeMap<&str, Vec<&str>> {
+        content_file
+            .lookup
+            .iter()
+            .map(|(tag, snip_desc)| {
+                let mut nested = snip_desc
+                    .nested
+                    .iter()
+                    .map(|nested| nested.tag.as_str())
+                    .collect::<Vec<&str>>();
+                nested.sort();
+                (tag.as_str(), nested)
+            })
+            .collect()
+    }
+
+    /// Parses the content file at the absolute `path`, which is read with `files`, see `Sources::files`
+    fn parse_content_file(
+        files: &dyn SnippetSource,
+        path: &PathBuf,
+        syntax: &ContentSyntax,
+    ) -> Result<ContentFile, GeoffreyError> {
+        let argument = path
+            .to_str()
+            .ok_or_else(|| std::io::Error::other(format!("{:?} is not valid UTF-8", path)))?;
+        let data = files
+            .read(Path::new(""), argument)
+            .map_err(std::io::Error::other)?;
+        Self::parse_content(path.clone(), data.as_bytes(), syntax)
+    }
+
+    fn parse_content<R>(
+        path: PathBuf,
+        data: R,
+        syntax: &ContentSyntax,
+    ) -> Result<ContentFile, GeoffreyError>
+    where
+        R: std::io::Read,
+    {
+        let mut reader = BufReader::new(data);
+        let path = &path;
+
+        let mut content_file = ContentFile::new(path.clone(), CommentStyle::for_path(path));
+        content_file.markers = syntax.markers.clone();
+
+        let content_snippet = ContentSnippetDescription {
+            tag: String::new(),
+            indentation: String::new(),
+            ellipsis_line: String::new(),
+            begin: 0,
+            end: 0,
+            nested: Vec::new(),
+        };
+
+        let root_content_snippet = Self::parse_next_content_snippet(
+            path,
+            &mut reader,
+            &mut content_file,
+            (&mut Vec::new(), syntax.max_nesting_depth),
+            content_snippet,
+        )?;
+
+        if content_file
+            .lookup
+            .insert(root_content_snippet.tag.clone(), root_content_snippet)
+            .is_some()
+        {
+            return Err(GeoffreyError::ContentSnippetDoubleTag(
+                path.clone(),
+                "".to_owned(),
+            ))?;
+        }
+
+        Ok(content_file)
+    }
+
+    /// The span of the begin marker of a snippet
+    fn marker_span(content_file: &ContentFile, snippet: &ContentSnippetDescription) -> SourceSpan {
+        let text = content_file
+            .data
+            .get(snippet.begin)
+            .map_or("", |line| line.as_str());
+        SourceSpan::new(snippet.begin + 1, text, snippet.indentation.len())
+    }
+
+    /// Parses the lines up to the end marker of `current_snippet`; `chain` are the tags and the begin lines of
+    /// the snippets it is nested in, which may be at most `max_depth`
+    fn parse_next_content_snippet<R>(
+        path: &PathBuf,
+        reader: &mut BufReader<R>,
+        content_file: &mut ContentFile,
+        (chain, max_depth): (&mut Vec<(String, usize)>, usize),
+        mut current_snippet: ContentSnippetDescription,
+    ) -> Result<ContentSnippetDescription, GeoffreyError>
+    where
+        R: std::io::Read,
+    {
+        let re = content_file.style.marker_regex(&content_file.markers)?;
+
+        let mut line = String::new();
+        loop {
+            if reader.read_line(&mut line)? > 0 {
+                if let Some(caps) = re.captures(&line) {
+                    let new_tag = caps.get(2).ok_or(GeoffreyError::RegexError)?.as_str();
+
+                    if current_snippet.tag == new_tag {
+                        current_snippet.end = content_file.data.len();
+                        content_file.data.push(line);
+                        break Ok(current_snippet);
+                    } else if new_tag.is_empty() {
+                        let offset = caps.get(2).map_or(0, |matcher| matcher.start());
+                        break Err(GeoffreyError::ContentSnippetEmptyTag(path.clone())
+                            .at(SourceSpan::new(content_file.data.len() + 1, &line, offset)));
+                    } else if chain.iter().any(|(tag, _)| tag == new_tag)
+                        || chain.len() >= max_depth
+                    {
+                        // the chain without the root snippet of the whole file, like "'main' > 'inner'"
+                        let tag_chain = chain
+                            .iter()
+                            .map(|(tag, _)| tag.as_str())
+                            .chain([current_snippet.tag.as_str(), new_tag])
+                            .skip(1)
+                            .map(|tag| format!("'{}'", tag))
+                            .collect::<Vec<String>>()
+                            .join(" > ");
+                        let error = match chain.iter().find(|(tag, _)| tag == new_tag) {
+                            // without a later marker of the enclosing snippet, this is its end marker
+                            Some((tag, begin)) if !Self::marker_follows(reader, &re, new_tag) => {
+                                GeoffreyError::ContentSnippetsOverlap(
+                                    path.clone(),
+                                    tag.clone(),
+                                    begin + 1,
+                                    current_snippet.tag.clone(),
+                                    current_snippet.begin + 1,
+                                )
+                            }
+                            Some(_) => {
+                                GeoffreyError::ContentSnippetNestedInItself(path.clone(), tag_chain)
+                            }
+                            None => GeoffreyError::ContentSnippetNestingTooDeep(
+                                path.clone(),
+                                tag_chain,
+                                max_depth,
+                            ),
+                        };
+                        let offset = caps.get(2).map_or(0, |matcher| matcher.start());
+                        break Err(error.at(SourceSpan::new(
+                            content_file.data.len() + 1,
+                            &line,
+                            offset,
+                        )));
+                    } else {
+                        let indentation = caps
+                            .get(1)
+                            .ok_or(GeoffreyError::RegexError)?
+                            .as_str()
+                            .to_owned();
+
+                        let ellipsis_line = content_file.style.ellipsis_line(&indentation);
+
+                        let new_snippet = ContentSnippetDescription {
+                            tag: new_tag.to_owned(),
+                            indentation,
+                            ellipsis_line,
+                            begin: content_file.data.len(),
+                            end: 0,
+                            nested: Vec::new(),
+                        };
+
+                        content_file.data.push(line);
+                        line = String::new();
+
+                        chain.push((current_snippet.tag.clone(), current_snippet.begin));
+                        let nested_snippet = Self::parse_next_content_snippet(
+                            path,
+                            reader,
+                            content_file,
+                            (chain, max_depth),
+                            new_snippet,
+                        )?;
+                        chain.pop();
+
+                        if content_file
+                            .lookup
+                            .insert(nested_snippet.tag.clone(), nested_snippet.clone())
+                            .is_some()
+                        {
+                            return Err(GeoffreyError::ContentSnippetDoubleTag(
+                                path.clone(),
+                                nested_snippet.tag.clone(),
+                            )
+                            .at(Self::marker_span(content_file, &nested_snippet)))?;
+                        }
+
+                        current_snippet.nested.push(nested_snippet);
+                    }
+                } else {
+                    content_file.data.push(line);
+                    line = String::new();
+                }
+            } else {
+                if current_snippet.tag == line {
+                    current_snippet.end = content_file.data.len().max(1) - 1;
+                    break Ok(current_snippet);
+                } else {
+                    let span = Self::marker_span(content_file, &current_snippet);
+                    break Err(GeoffreyError::ContentSnippetEndTagNotFound(
+                        path.clone(),
+                        current_snippet.tag,
+                    )
+                    .at(span));
+                }
+            }
+        }
+    }
+
+    /// Whether one of the remaining lines of `reader` is a marker of the snippet `tag`
+    fn marker_follows<R>(reader: &mut BufReader<R>, re: &Regex, tag: &str) -> bool
+    where
+        R: std::io::Read,
+    {
+        reader.lines().map_while(Result::ok).any(|line| {
+            re.captures(&line)
+                .and_then(|caps| caps.get(2))
+                .is_some_and(|matcher| matcher.as_str() == tag)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::source::FileSource;
+
+    use anyhow::{anyhow, Result};
+    use tempfile::Builder;
+
+    use std::fs::{DirBuilder, File};
+
+    #[test]
+    fn document_new_with_non_existing_path_fails() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let mut doc_path = PathBuf::new();
+        doc_path.push(tmp_dir.path());
+        doc_path.push("hypnotoad");
+        match Documents::new(doc_path, Symlinks::Follow) {
+            Err(GeoffreyError::DocPathDoesNotExist(_)) => Ok(()),
+            _ => Err(anyhow!("Document::new with non existing path should fail!")),
+        }
+    }
+
+    #[test]
+    fn document_new_with_file_as_path_but_not_md_file_fails() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let mut doc_path = PathBuf::new();
+        doc_path.push(tmp_dir.path());
+        doc_path.push("hypnotoad.txt");
+
+        File::create(doc_path.clone())?;
+
+        match Documents::new(doc_path, Symlinks::Follow) {
+            Err(GeoffreyError::NotAMarkdownFile(_)) => Ok(()),
+            _ => Err(anyhow!(
+                "Document::new with file as path path but not md file should fail!"
+            )),
+        }
+    }
+
+    #[test]
+    fn document_new_with_file_as_path_which_is_md_file_succeeds() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let mut doc_path = PathBuf::new();
+        doc_path.push(tmp_dir.path());
+        doc_path.push("hypnotoad.md");
+
+        File::create(doc_path.clone())?;
+
+        let documents = Documents::new(doc_path.clone(), Symlinks::Follow)?;
+
+        assert_eq!(documents.md_files.len(), 1);
+        assert_eq!(documents.md_files[0].path, doc_path);
+
+        Ok(())
+    }
+
+    #[test]
+    fn document_new_with_empty_dir_as_path_fails() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let mut doc_path = PathBuf::new();
+        doc_path.push(tmp_dir.path());
+
+        match Documents::new(doc_path, Symlinks::Follow) {
+            Err(GeoffreyError::NoMarkdownFilesInPath(_)) => Ok(()),
+            _ => Err(anyhow!("Document::new with empty dir as path should fail!")),
+        }
+    }
+
+    #[test]
+    fn document_new_with_dir_as_path_and_single_md_file_succeeds() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let mut doc_path = PathBuf::new();
+        doc_path.push(tmp_dir.path());
+
+        let mut md_file = doc_path.clone();
+        md_file.push("hypnotoad.md");
+
+        File::create(md_file.clone())?;
+
+        let documents = Documents::new(doc_path.clone(), Symlinks::Follow)?;
+
+        assert_eq!(documents.md_files.len(), 1);
+        assert_eq!(documents.md_files[0].path, md_file);
+
+        Ok(())
+    }
+
+    #[test]
+    fn document_new_with_dir_as_path_and_multiple_md_file_succeeds() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let mut doc_path = PathBuf::new();
+        doc_path.push(tmp_dir.path());
+
+        let mut md_file_1 = doc_path.clone();
+        md_file_1.push("brain_slug.md");
+
+        let mut md_file_2 = doc_path.clone();
+        md_file_2.push("hypnotoad.md");
+
+        File::create(md_file_1.clone())?;
+        File::create(md_file_2.clone())?;
+
+        let mut documents = Documents::new(doc_path.clone(), Symlinks::Follow)?;
+
+        assert_eq!(documents.md_files.len(), 2);
+        let mut files = documents
+            .md_files
+            .drain(..)
+            .map(|md_file| md_file.path)
+            .collect::<Vec<PathBuf>>();
+        files.sort();
+        assert_eq!(files[0], md_file_1);
+        assert_eq!(files[1], md_file_2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn document_new_with_dir_as_path_and_md_file_in_nested_dir_succeeds() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let mut doc_path = PathBuf::new();
+        doc_path.push(tmp_dir.path());
+
+        let mut md_file_1 = doc_path.clone();
+        md_file_1.push("brain_slug.md");
+
+        let mut md_file_2_dir = doc_path.clone();
+        md_file_2_dir.push("hypnotoad");
+        DirBuilder::new().create(md_file_2_dir.clone())?;
+
+        let mut md_file_2 = md_file_2_dir.clone();
+        md_file_2.push("hypnotoad.md");
+
+        File::create(md_file_1.clone())?;
+        File::create(md_file_2.clone())?;
+
+        let mut documents = Documents::new(doc_path.clone(), Symlinks::Follow)?;
+
+        assert_eq!(documents.md_files.len(), 2);
+        let mut files = documents
+            .md_files
+            .drain(..)
+            .map(|md_file| md_file.path)
+            .collect::<Vec<PathBuf>>();
+        files.sort();
+        assert_eq!(files[0], md_file_1);
+        assert_eq!(files[1], md_file_2);
+
+        Ok(())
+    }
+
+    fn sync_md_with_content(
+        md: &str,
+        content: &str,
+        options: &SyncOptions,
+    ) -> Result<(String, Report)> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let content_path = tmp_dir.path().join("content.cpp");
+        fs::write(&content_path, content)?;
+
+        let md_path = tmp_dir.path().join("doc.md");
+        fs::write(
+            &md_path,
+            md.replace("CONTENT_PATH", content_path.to_str().expect("valid path")),
+        )?;
+
+        let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+        documents.parse()?;
+        let report = documents.sync(options)?;
+
+        Ok((
+            fs::read_to_string(md_path)?
+                .replace(content_path.to_str().expect("valid path"), "CONTENT_PATH"),
+            report,
+        ))
+    }
+
+    const CONTENT: &str =
+        "#include <cstdint>\n//! [answer]\nconstexpr uint8_t ANSWER{42U};\n//! [answer]\n";
+
+    #[test]
+    fn sync_with_missing_snippet_and_fallback_error_fails() -> Result<()> {
+        let md = "<!-- [geoffrey] [CONTENT_PATH] [question] -->\n```cpp\nold\n```\n";
+
+        let (synced, report) = sync_md_with_content(md, CONTENT, &SyncOptions::default())?;
+
+        assert_eq!(synced, md);
+        assert_eq!(report.summary.failed_files, 1);
+        assert!(report.diagnostics[0]
+            .message
+            .contains("The content snippet 'question'"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn sync_with_missing_snippet_and_fallback_skip_keeps_code_block() -> Result<()> {
+        let md = "<!-- [geoffrey] [CONTENT_PATH] [question] -->\n```cpp\nold\n```\n";
+        let options = SyncOptions {
+            fallback: Fallback::Skip,
+            ..Default::default()
+        };
+
+        assert_eq!(sync_md_with_content(md, CONTENT, &options)?.0, md);
+
+        Ok(())
+    }
+
+    #[test]
+    fn sync_with_fallback_skip_option_keeps_code_blocks_of_missing_content_files() -> Result<()> {
+        let md = "<!-- [geoffrey] [CONTENT_PATH.moved] [answer] fallback=skip -->\n```cpp\nold\n```\n\n<!-- [geoffrey] [CONTENT_PATH] [question] fallback=skip -->\n```cpp\nold\n```\n\n<!-- [geoffrey] [CONTENT_PATH] [answer] -->\n```cpp\n```\n";
+
+        let (synced, report) = sync_md_with_content(md, CONTENT, &SyncOptions::default())?;
+        assert_eq!(
+            synced,
+            md.replace("```cpp\n```", "```cpp\nconstexpr uint8_t ANSWER{42U};\n```")
+        );
+        assert_eq!(report.summary.failed_files, 0);
+        assert_eq!(
+            report
+                .warnings
+                .iter()
+                .map(|warning| warning.code.as_str())
+                .collect::<Vec<&str>>(),
+            ["W012", "W006"]
+        );
+
+        // without the option, the missing content file fails the run
+        let md = md.replace(" fallback=skip", "");
+        let (synced, report) = sync_md_with_content(&md, CONTENT, &SyncOptions::default())?;
+        assert_eq!(synced, md);
+        assert_eq!(report.summary.failed_files, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn sync_with_missing_snippet_and_fallback_full_file_embeds_content_file() -> Result<()> {
+        let md = "<!-- [geoffrey] [CONTENT_PATH] [question] -->\n```cpp\nold\n```\n";
+        let options = SyncOptions {
+            fallback: Fallback::FullFile,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            sync_md_with_content(md, CONTENT, &options)?.0,
+            "<!-- [geoffrey] [CONTENT_PATH] [question] -->\n```cpp\n#include <cstdint>\nconstexpr uint8_t ANSWER{42U};\n```\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn sync_with_content_from_configured_repository_succeeds() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let repo_dir = tmp_dir.path().join("product");
+        DirBuilder::new()
+            .recursive(true)
+            .create(repo_dir.join("src"))?;
+        fs::write(repo_dir.join("src").join("answer.cpp"), CONTENT)?;
+
+        let doc_dir = tmp_dir.path().join("docs");
+        DirBuilder::new().create(&doc_dir)?;
+        fs::write(
+            doc_dir.join(Config::FILE_NAME),
+            "[repos.product]\npath = \"../product\"\n",
+        )?;
+
+        let md_path = doc_dir.join("doc.md");
+        fs::write(
+            &md_path,
+            "<!-- [geoffrey] [product:src/answer.cpp] [answer] -->\n```cpp\n```\n",
+        )?;
+
+        let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+        documents.parse()?;
+        documents.sync(&SyncOptions::default())?;
+
+        assert_eq!(
+            fs::read_to_string(md_path)?,
+            "<!-- [geoffrey] [product:src/answer.cpp] [answer] -->\n```cpp\nconstexpr uint8_t ANSWER{42U};\n```\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_with_unknown_repository_fails() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let md_path = tmp_dir.path().join("doc.md");
+        fs::write(
+            &md_path,
+            "<!-- [geoffrey] [product:src/answer.cpp] [answer] -->\n```cpp\n```\n",
+        )?;
+
+        let mut documents = Documents::new(md_path, Symlinks::Follow)?;
+        documents.parse()?;
+        match documents.failures() {
+            [FileError {
+                error: GeoffreyError::UnknownRepository(repo, _),
+                ..
+            }] if repo == "product" => Ok(()),
+            _ => Err(anyhow!("parse with unknown repository should fail!")),
+        }
+    }
+
+    #[test]
+    fn content_path_with_unknown_repository_prefix_is_a_path() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+        git(tmp_dir.path(), &["init", "-q"])?;
+        fs::write(tmp_dir.path().join("notes:answer.cpp"), CONTENT)?;
+
+        let md_path = tmp_dir.path().join("doc.md");
+        fs::write(
+            &md_path,
+            "<!-- [geoffrey] [notes:answer.cpp] [answer] -->\n```cpp\n```\n",
+        )?;
+
+        let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+        documents.parse()?;
+        documents.sync(&SyncOptions::default())?;
+
+        assert_eq!(
+            fs::read_to_string(md_path)?,
+            "<!-- [geoffrey] [notes:answer.cpp] [answer] -->\n```cpp\nconstexpr uint8_t ANSWER{42U};\n```\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn repositories_are_cloned_only_if_referenced() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let origin = tmp_dir.path().join("origin");
+        fs::create_dir(&origin)?;
+        fs::write(origin.join("answer.cpp"), CONTENT)?;
+        git(&origin, &["init", "-q"])?;
+        git(&origin, &["add", "-A"])?;
+        git(&origin, &["commit", "-q", "-m", "init"])?;
+
+        let doc_dir = tmp_dir.path().join("docs");
+        fs::create_dir(&doc_dir)?;
+        fs::write(
+            doc_dir.join(Config::FILE_NAME),
+            format!(
+                "[repos.product]\npath = \"../product\"\nremote = \"{}\"\n",
+                origin.display()
+            ),
+        )?;
+        let md_path = doc_dir.join("doc.md");
+        fs::write(&md_path, "# Docs\n")?;
+
+        let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+        documents.parse()?;
+        documents.sync(&SyncOptions::default())?;
+        let product = tmp_dir.path().join("product");
+        assert!(!product.exists());
+
+        fs::write(
+            &md_path,
+            "<!-- [geoffrey] [product:answer.cpp] [answer] -->\n```cpp\n```\n",
+        )?;
+        let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+        assert!(!product.exists());
+        documents.parse()?;
+        assert!(product.join("answer.cpp").exists());
+        documents.sync(&SyncOptions::default())?;
+
+        assert_eq!(
+            fs::read_to_string(&md_path)?,
+            "<!-- [geoffrey] [product:answer.cpp] [answer] -->\n```cpp\nconstexpr uint8_t ANSWER{42U};\n```\n"
+        );
+
+        // a remote is no option of git
+        let injected = tmp_dir.path().join("injected");
+        fs::write(
+            doc_dir.join(Config::FILE_NAME),
+            format!(
+                "[repos.evil]\npath = \"../evil\"\nremote = \"--upload-pack=touch {}\"\n",
+                injected.display()
+            ),
+        )?;
+        fs::write(
+            &md_path,
+            "<!-- [geoffrey] [evil:answer.cpp] [answer] -->\n```cpp\n```\n",
+        )?;
+        let mut documents = Documents::new(md_path, Symlinks::Follow)?;
+        documents.parse()?;
+        assert!(matches!(
+            documents.failures(),
+            [FileError {
+                error: GeoffreyError::RepositoryCloneFailed(..),
+                ..
+            }]
+        ));
+        assert!(!injected.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn sync_summary_counts_updated_and_unchanged_snippets() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let content_path = tmp_dir.path().join("content.cpp");
+        fs::write(&content_path, CONTENT)?;
+
+        let md_path = tmp_dir.path().join("doc.md");
+        fs::write(
+            &md_path,
+            format!(
+                "<!-- [geoffrey] [{0}] [answer] -->\n```cpp\nconstexpr uint8_t ANSWER{{42U}};\n```\n\n<!-- [geoffrey] [{0}] -->\n```cpp\n```\n",
+                content_path.display()
+            ),
+        )?;
+
+        let mut documents = Documents::new(md_path, Symlinks::Follow)?;
+        documents.parse()?;
+        let summary = documents.sync(&SyncOptions::default())?.summary;
+
+        assert_eq!(summary.md_files, 1);
+        assert_eq!(summary.code_blocks, 2);
+        assert_eq!(summary.snippets_updated, 1);
+        assert_eq!(summary.snippets_unchanged, 1);
+        assert_eq!(summary.content_files, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn tags_lists_all_geoffrey_tags_with_line_numbers() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let md_path = tmp_dir.path().join("doc.md");
+        fs::write(
+            &md_path,
+            "# Doc\n<!-- [geoffrey] [src/main.cpp] -->\n```cpp\n```\n\n<!-- [geoffrey] [src/main.cpp] [[main] [answer]] -->\n```cpp\nfoo\n```\n",
+        )?;
+
+        let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+        documents.parse_md_files()?;
+        let tags = documents
+            .tags()
+            .iter()
+            .map(|tag| tag.to_string())
+            .collect::<Vec<String>>();
+
+        assert_eq!(
+            tags,
+            vec![
+                format!("{}:2: src/main.cpp", md_path.display()),
+                format!("{}:6: src/main.cpp [[main] [answer]]", md_path.display()),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn where_used_lists_the_definitions_and_the_embedding_tags() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let content_path = tmp_dir.path().join("content.cpp");
+        fs::write(
+            &content_path,
+            "//! [main]\nint main() {\n    //! [answer]\n    return 42;\n    //! [answer]\n}\n//! [main]\n",
+        )?;
+        let md_path = tmp_dir.path().join("doc.md");
+        fs::write(
+            &md_path,
+            format!(
+                "<!-- [geoffrey] [{0}] [answer] -->\n```cpp\n```\n<!-- [geoffrey] [{0}] [[main] [answer]] -->\n```cpp\n```\n<!-- [geoffrey] [{0}] [[main]] -->\n```cpp\n```\n",
+                content_path.display()
+            ),
+        )?;
+
+        let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+        documents.parse()?;
+        let usage = documents.where_used("answer");
+        assert_eq!(
+            usage.to_string(),
+            format!(
+                "{0}:3: [answer]\n{1}:1: {0} [answer]\n{1}:4: {0} [[main] [answer]]\n",
+                content_path.display(),
+                md_path.display()
+            )
+        );
+
+        let usage = documents.where_used("question");
+        assert!(usage.definitions.is_empty());
+        assert!(usage.references.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn rename_snippet_edits_the_markers_and_the_tags() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let content_path = tmp_dir.path().join("content.cpp");
+        let content = "//! [main]\nint main() {\n    //! [answer]\n    return 42;\n    //! [answer]\n}\n//! [main]\n";
+        fs::write(&content_path, content)?;
+        let other_path = tmp_dir.path().join("other.cpp");
+        fs::write(&other_path, "//! [question]\n//! [question]\n")?;
+        let md_path = tmp_dir.path().join("doc.md");
+        let md = format!(
+            "<!-- [geoffrey] [{0}] [answer] -->\n```cpp\n```\n<!-- [geoffrey] [{0}] [[main] [answer]] dedent -->\n```cpp\n```\nThe answer is <!--[geoffrey-inline][{0}][answer]-->`42`.\n<!-- [geoffrey] [{1}] [answer] -->\n```cpp\n```\n",
+            content_path.display(),
+            other_path.display()
+        );
+        fs::write(&md_path, &md)?;
+
+        let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+        documents.parse()?;
+        let edits = documents.rename_snippet("answer", "result")?;
+        let edited = edits
+            .iter()
+            .map(|edit| (edit.path.clone(), edit.edited.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            edited,
+            [
+                (content_path.clone(), content.replace("[answer]", "[result]")),
+                (
+                    md_path.clone(),
+                    format!(
+                        "<!-- [geoffrey] [{0}] [result] -->\n```cpp\n```\n<!-- [geoffrey] [{0}] [[main] [result]] dedent -->\n```cpp\n```\nThe answer is <!--[geoffrey-inline][{0}][result]-->`42`.\n<!-- [geoffrey] [{1}] [answer] -->\n```cpp\n```\n",
+                        content_path.display(),
+                        other_path.display()
+                    )
+                ),
+            ]
+        );
+        // nothing is written before the edits are applied
+        assert_eq!(fs::read_to_string(&md_path)?, md);
+
+        Documents::apply_edits(&edits)?;
+        assert_eq!(fs::read_to_string(&content_path)?, edited[0].1);
+        assert_eq!(fs::read_to_string(&md_path)?, edited[1].1);
+        assert_eq!(fs::read_dir(tmp_dir.path())?.count(), 3);
+
+        let mut documents = Documents::new(md_path, Symlinks::Follow)?;
+        documents.parse()?;
+        for (old, new) in [("result", "main"), ("answer", "other"), ("result", "a\nb")] {
+            assert!(matches!(
+                documents.rename_snippet(old, new),
+                Err(GeoffreyError::SnippetRenameFailed(..))
+            ));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn orphaned_markers_reports_unreferenced_markers() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let content_path = tmp_dir.path().join("content.cpp");
+        fs::write(
+            &content_path,
+            "//! [main]\nint main() {\n    //! [answer]\n    return 42;\n    //! [answer]\n}\n//! [main]\n//! [unused]\n//! [unused]\n",
+        )?;
+
+        let src_dir = tmp_dir.path().join("src");
+        DirBuilder::new().create(&src_dir)?;
+        let unreferenced_path = src_dir.join("unreferenced.cpp");
+        fs::write(&unreferenced_path, "//! [foo]\nfoo();\n//! [foo]\n")?;
+
+        let md_path = tmp_dir.path().join("doc.md");
+        fs::write(
+            &md_path,
+            format!(
+                "<!-- [geoffrey] [{}] [[main] [answer]] -->\n```cpp\n```\n",
+                content_path.display()
+            ),
+        )?;
+
+        let mut documents = Documents::new(md_path, Symlinks::Follow)?;
+        documents.parse()?;
+        let orphans = documents
+            .orphaned_markers(&[src_dir])?
+            .iter()
+            .map(|orphan| orphan.to_string())
+            .collect::<Vec<String>>();
+
+        assert_eq!(
+            orphans,
+            vec![
+                format!("{}:8: [unused]", content_path.display()),
+                format!("{}:1: [foo]", unreferenced_path.display()),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn sync_with_one_malformed_md_file_syncs_the_other_files() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let content_path = tmp_dir.path().join("content.cpp");
+        fs::write(&content_path, CONTENT)?;
+
+        let malformed_md = format!(
+            "<!-- [geoffrey] [{}] [answer] -->\nno code block\n",
+            content_path.display()
+        );
+        fs::write(tmp_dir.path().join("malformed.md"), &malformed_md)?;
+
+        let healthy_path = tmp_dir.path().join("healthy.md");
+        fs::write(
+            &healthy_path,
+            format!(
+                "<!-- [geoffrey] [{}] [answer] -->\n```cpp\n```\n",
+                content_path.display()
+            ),
+        )?;
+
+        let mut documents = Documents::new(tmp_dir.path().to_path_buf(), Symlinks::Follow)?;
+        documents.parse()?;
+        let report = documents.sync(&SyncOptions::default())?;
+
+        assert_eq!(report.summary.md_files, 2);
+        assert_eq!(report.summary.failed_files, 1);
+        assert_eq!(
+            report.diagnostics[0].file.as_deref(),
+            Some(tmp_dir.path().join("malformed.md").as_path())
+        );
+        assert_eq!(
+            fs::read_to_string(tmp_dir.path().join("malformed.md"))?,
+            malformed_md
+        );
+        assert!(fs::read_to_string(healthy_path)?.contains("constexpr uint8_t ANSWER{42U};"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn validate_reports_all_problems() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let content_path = tmp_dir.path().join("content.cpp");
+        fs::write(
+            &content_path,
+            "//! [main]\n//! [answer]\n42\n//! [answer]\n//! [main]\n//! [other]\n//! [other]\n",
+        )?;
+
+        let md_path = tmp_dir.path().join("doc.md");
+        let md = format!(
+            "<!-- [geoffrey] [{0}] [answr] -->\n```cpp\n```\n<!-- [geoffrey] [{0}] [[main] [other]] -->\n```cpp\n```\n<!-- [geoffrey] [{0}] [[main] [answer]] -->\n```cpp\n```\n<!-- [geoffrey] [{1}] -->\n```cpp\n```\n",
+            content_path.display(),
+            tmp_dir.path().join("missing.cpp").display()
+        );
+        fs::write(&md_path, &md)?;
+
+        let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+        documents.parse()?;
+        assert_eq!(
+            documents
+                .tag_problems()
+                .iter()
+                .map(|problem| problem.line)
+                .collect::<Vec<usize>>(),
+            [1, 4]
+        );
+        assert_eq!(
+            documents.definition(&md_path, 7),
+            Some(SnippetLocation {
+                path: content_path.clone(),
+                line: 1
+            })
+        );
+        assert_eq!(documents.definition(&md_path, 1), None);
+        assert_eq!(documents.definition(&md_path, 8), None);
+        let problems = documents.validate();
+
+        assert_eq!(problems.len(), 3);
+        assert!(problems
+            .iter()
+            .any(|problem| matches!(problem.error, GeoffreyError::ContentFileNotFound(..))));
+        assert!(problems.iter().any(|problem| matches!(
+            &problem.error,
+            GeoffreyError::ContentSnippetNotFound(_, tag, suggestions) if tag == "answr" && suggestions == &["answer"]
+        )));
+        assert!(problems.iter().any(|problem| matches!(
+            &problem.error,
+            GeoffreyError::SubSnippetNotNested(_, main, sub) if main == "main" && sub == "other"
+        )));
+        assert_eq!(fs::read_to_string(md_path)?, md);
+
+        Ok(())
+    }
+
+    #[test]
+    fn diff_reports_only_stale_blocks_without_writing() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let content_path = tmp_dir.path().join("content.cpp");
+        fs::write(&content_path, CONTENT)?;
+
+        let md_path = tmp_dir.path().join("doc.md");
+        let md = format!(
+            "<!-- [geoffrey] [{0}] [answer] -->\n```cpp\nconstexpr uint8_t ANSWER{{42U}};\n```\n<!-- [geoffrey] [{0}] -->\n```cpp\nstale\n```\n",
+            content_path.display()
+        );
+        fs::write(&md_path, &md)?;
+
+        let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+        documents.parse()?;
+        let DiffReport {
+            diffs, failures, ..
+        } = documents.diff(&SyncOptions::default());
+
+        assert!(failures.is_empty());
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].line, 5);
+        assert_eq!(diffs[0].code_line, 7);
+        assert_eq!(diffs[0].snippet, None);
+        assert_eq!(diffs[0].current, "stale\n");
+        assert_eq!(
+            diffs[0].synced,
+            "#include <cstdint>\nconstexpr uint8_t ANSWER{42U};\n"
+        );
+        assert_eq!(fs::read_to_string(md_path)?, md);
+
+        Ok(())
+    }
+
+    #[test]
+    fn sync_reports_the_changed_files() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let content_path = tmp_dir.path().join("content.cpp");
+        fs::write(&content_path, CONTENT)?;
+        let in_sync = format!(
+            "<!-- [geoffrey] [{}] [answer] -->\n```cpp\nconstexpr uint8_t ANSWER{{42U}};\n```\n",
+            content_path.display()
+        );
+        fs::write(tmp_dir.path().join("in-sync.md"), &in_sync)?;
+        fs::write(
+            tmp_dir.path().join("stale.md"),
+            in_sync.replace("42U", "41U"),
+        )?;
+
+        let mut documents = Documents::new(tmp_dir.path().to_path_buf(), Symlinks::Follow)?;
+        documents.parse()?;
+        let report = documents.sync(&SyncOptions::default())?;
+        assert_eq!(report.changed_files, [tmp_dir.path().join("stale.md")]);
+
+        let lines_path = tmp_dir.path().join("changed.txt");
+        report.write_changed_files(&lines_path)?;
+        assert_eq!(
+            fs::read_to_string(lines_path)?,
+            format!("{}\n", tmp_dir.path().join("stale.md").display())
+        );
+        let json_path = tmp_dir.path().join("changed.json");
+        report.write_changed_files(&json_path)?;
+        let changed: Vec<PathBuf> = serde_json::from_str(&fs::read_to_string(json_path)?)?;
+        assert_eq!(changed, report.changed_files);
+
+        let mut documents = Documents::new(tmp_dir.path().to_path_buf(), Symlinks::Follow)?;
+        documents.parse()?;
+        assert!(documents
+            .sync(&SyncOptions::default())?
+            .changed_files
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn patch_applies_the_pending_updates_with_git() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+        let repo = tmp_dir.path().canonicalize()?;
+
+        git(&repo, &["init", "-q"])?;
+        fs::write(repo.join("content.cpp"), CONTENT)?;
+        fs::create_dir(repo.join("doc"))?;
+        let in_sync =
+            "<!-- [geoffrey] [content.cpp] [answer] -->\n```cpp\nconstexpr uint8_t ANSWER{42U};\n```\n";
+        fs::write(repo.join("doc/in-sync.md"), in_sync)?;
+        let md_path = repo.join("doc/stale.md");
+        fs::write(
+            &md_path,
+            format!("# Answer\n\n{}", in_sync.replace("42U", "41U")),
+        )?;
+
+        let mut documents = Documents::new(repo.join("doc"), Symlinks::Follow)?;
+        documents.parse()?;
+        let pending = documents.pending_edits(&SyncOptions::default())?;
+        assert_eq!(pending.edits.len(), 1);
+        assert!(pending.skipped.is_empty());
+        let patch = pending.patch();
+        assert!(patch.starts_with("diff --git a/doc/stale.md b/doc/stale.md\n"));
+        assert!(!patch.contains("in-sync.md"));
+        // nothing is written
+        assert!(fs::read_to_string(&md_path)?.contains("41U"));
+
+        fs::write(repo.join("pending.patch"), patch)?;
+        git(&repo, &["apply", "pending.patch"])?;
+        assert_eq!(
+            fs::read_to_string(&md_path)?,
+            format!("# Answer\n\n{}", in_sync)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn opted_out_tags_are_kept_as_text() -> Result<()> {
+        let md = "<!-- [geoffrey-ignore] -->\n<!-- [geoffrey] [CONTENT_PATH] [answer] -->\n```cpp\nhand-tweaked\n```\n<!-- [geoffrey-off] -->\n<!-- [geoffrey] [CONTENT_PATH] [answer] -->\n```cpp\nfrozen\n```\nCall <!--[geoffrey-inline][CONTENT_PATH][answer]--> `frozen`\n<!-- [geoffrey-on] -->\n<!-- [geoffrey] [CONTENT_PATH] [answer] -->\n```cpp\nstale\n```\n";
+
+        let (synced, report) = sync_md_with_content(md, CONTENT, &SyncOptions::default())?;
+        assert_eq!(
+            synced,
+            md.replace("stale", "constexpr uint8_t ANSWER{42U};")
+        );
+        assert_eq!(report.summary.code_blocks, 1);
+        assert_eq!(report.summary.snippets_updated, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn code_blocks_edited_by_hand_are_kept_without_force() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        fs::write(
+            tmp_dir.path().join(Config::FILE_NAME),
+            "guard_edits = true\n",
+        )?;
+        let content_path = tmp_dir.path().join("content.cpp");
+        fs::write(&content_path, CONTENT)?;
+        let md_path = tmp_dir.path().join("doc.md");
+        fs::write(
+            &md_path,
+            format!(
+                "<!-- [geoffrey] [{}] [answer] dedent -->\n```cpp\n```\n",
+                content_path.display()
+            ),
+        )?;
+        let sync = |options: &SyncOptions| -> Result<Report> {
+            let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+            documents.parse()?;
+            Ok(documents.sync(options)?)
+        };
+
+        sync(&SyncOptions::default())?;
+        let synced = fs::read_to_string(&md_path)?;
+        let hash = Documents::block_hash("```cpp\nconstexpr uint8_t ANSWER{42U};\n");
+        assert!(synced.starts_with(&format!(
+            "<!-- [geoffrey] [{}] [answer] dedent hash={} -->\n",
+            content_path.display(),
+            hash
+        )));
+        // the recorded hash is kept
+        let report = sync(&SyncOptions::default())?;
+        assert!(report.changed_files.is_empty());
+
+        let edited = synced.replace("42U", "43U");
+        fs::write(&md_path, &edited)?;
+        // the diff and the patch keep the code block like the sync
+        let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+        documents.parse()?;
+        assert!(documents
+            .pending_edits(&SyncOptions::default())?
+            .edits
+            .is_empty());
+        assert!(documents.diff(&SyncOptions::default()).diffs.is_empty());
+        let report = sync(&SyncOptions::default())?;
+        assert_eq!(report.summary.failed_files, 1);
+        assert_eq!(report.diagnostics[0].code, "G051");
+        assert_eq!(fs::read_to_string(&md_path)?, edited);
+
+        sync(&SyncOptions {
+            force: true,
+            ..Default::default()
+        })?;
+        assert_eq!(fs::read_to_string(&md_path)?, synced);
+
+        Ok(())
+    }
+
+    #[test]
+    fn code_blocks_edited_by_hand_are_merged_with_changed_snippets() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        fs::write(
+            tmp_dir.path().join(Config::FILE_NAME),
+            "guard_edits = true\n",
+        )?;
+        let content_path = tmp_dir.path().join("content.cpp");
+        fs::write(
+            &content_path,
+            "//! [answer]\nint a = 1;\nint b = 2;\nint c = 3;\n//! [answer]\n",
+        )?;
+        let md_path = tmp_dir.path().join("doc.md");
+        fs::write(
+            &md_path,
+            format!(
+                "<!-- [geoffrey] [{}] [answer] -->\n```cpp\n```\n",
+                content_path.display()
+            ),
+        )?;
+        let sync = || -> Result<Report> {
+            let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+            documents.parse()?;
+            Ok(documents.sync(&SyncOptions::default())?)
+        };
+
+        sync()?;
+        assert!(tmp_dir.path().join(Config::SNAPSHOT_DIR).is_dir());
+        let synced = fs::read_to_string(&md_path)?;
+        fs::write(&md_path, synced.replace("int a = 1;", "int a = 10;"))?;
+        fs::write(
+            &content_path,
+            "//! [answer]\nint a = 1;\nint b = 2;\nint c = 30;\n//! [answer]\n",
+        )?;
+
+        let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+        documents.parse()?;
+        let pending = documents.pending_edits(&SyncOptions::default())?;
+        let report = sync()?;
+        assert!(report.diagnostics.is_empty());
+        assert_eq!(report.warnings[0].code, "W013");
+        let merged = fs::read_to_string(&md_path)?;
+        // the patch writes the same merged code block and hash as the sync
+        assert_eq!(pending.edits.len(), 1);
+        assert_eq!(pending.edits[0].edited, merged);
+        assert!(merged.contains("```cpp\nint a = 10;\nint b = 2;\nint c = 30;\n```\n"));
+        let hash = Documents::block_hash("```cpp\nint a = 10;\nint b = 2;\nint c = 30;\n");
+        assert!(merged.contains(&format!("hash={}", hash)));
+        // the merged code block keeps the manual edits in later syncs
+        let report = sync()?;
+        assert!(report.diagnostics.is_empty());
+        assert!(report.warnings.is_empty());
+        assert_eq!(fs::read_to_string(&md_path)?, merged);
+
+        // changes of the same lines on both sides are conflicts
+        fs::write(&md_path, merged.replace("int b = 2;", "int b = 20;"))?;
+        fs::write(
+            &content_path,
+            "//! [answer]\nint a = 1;\nint b = 200;\nint c = 30;\n//! [answer]\n",
+        )?;
+        let report = sync()?;
+        assert_eq!(report.diagnostics[0].code, "G052");
+        assert!(fs::read_to_string(&md_path)?.contains(
+            "<<<<<<< documentation\nint a = 10;\nint b = 20;\n=======\nint a = 1;\nint b = 200;\n>>>>>>> content\nint c = 30;\n"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn since_keeps_the_markdown_files_affected_by_changes() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+        let repo = tmp_dir.path().canonicalize()?;
+
+        git(&repo, &["init", "-q"])?;
+        for name in ["a", "b", "c"] {
+            fs::write(
+                repo.join(format!("{}.cpp", name)),
+                "//! [main]\nhello();\n//! [main]\n",
+            )?;
+            fs::write(
+                repo.join(format!("{}.md", name)),
+                format!("<!-- [geoffrey] [{}.cpp] [main] -->\n```cpp\n```\n", name),
+            )?;
+        }
+        git(&repo, &["add", "-A"])?;
+        git(&repo, &["commit", "-q", "-m", "docs"])?;
+        fs::write(repo.join("b.cpp"), "//! [main]\nbye();\n//! [main]\n")?;
+        fs::write(repo.join("c.md"), "# C\n")?;
+
+        let mut documents = Documents::new(repo.clone(), Symlinks::Follow)?;
+        documents.since("HEAD".to_owned());
+        documents.parse()?;
+        let mut md_paths = documents.md_paths();
+        md_paths.sort();
+        assert_eq!(md_paths, [repo.join("b.md"), repo.join("c.md")]);
+        let report = documents.sync(&SyncOptions::default())?;
+        assert_eq!(report.summary.snippets_updated, 1);
+        assert_eq!(
+            fs::read_to_string(repo.join("a.md"))?,
+            "<!-- [geoffrey] [a.cpp] [main] -->\n```cpp\n```\n"
+        );
+
+        let mut documents = Documents::new(repo, Symlinks::Follow)?;
+        documents.since("unknown-revision".to_owned());
+        assert!(matches!(
+            documents.parse(),
+            Err(GeoffreyError::GitDiffError(..))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn stage_adds_the_changed_files_to_the_index() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+        let repo = tmp_dir.path().canonicalize()?;
+
+        git(&repo, &["init", "-q"])?;
+        fs::write(repo.join("hello.cpp"), "//! [main]\nhello();\n//! [main]\n")?;
+        fs::write(
+            repo.join("doc.md"),
+            "<!-- [geoffrey] [hello.cpp] [main] -->\n```cpp\n```\n",
+        )?;
+        fs::write(repo.join("other.md"), "# Other\n")?;
+
+        let mut documents = Documents::new(repo.clone(), Symlinks::Follow)?;
+        documents.parse()?;
+        let report = documents.sync(&SyncOptions::default())?;
+        Documents::stage(&report.all_changed_files())?;
+
+        let staged = std::process::Command::new("git")
+            .args(["diff", "--cached", "--name-only"])
+            .current_dir(&repo)
+            .output()?;
+        assert_eq!(String::from_utf8(staged.stdout)?, "doc.md\n");
+
+        assert!(matches!(
+            Documents::stage(&[repo.join("missing.md")]),
+            Err(GeoffreyError::GitAddError(..))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn anchors_are_emitted_before_the_tags() -> Result<()> {
+        let md = "# Guide\n<!-- [geoffrey] [CONTENT_PATH] [answer] anchor -->\n```cpp\n```\n<!-- [geoffrey] [CONTENT_PATH] anchor=whole-file -->\n```cpp\n```\n";
+        let (synced, report) = sync_md_with_content(md, CONTENT, &SyncOptions::default())?;
+        assert_eq!(report.summary.failed_files, 0);
+        assert!(synced.starts_with(
+            "# Guide\n<a id=\"snippet-answer\"></a>\n<!-- [geoffrey] [CONTENT_PATH] [answer] anchor -->\n```cpp\n"
+        ));
+        assert!(synced.contains(
+            "```\n<a id=\"whole-file\"></a>\n<!-- [geoffrey] [CONTENT_PATH] anchor=whole-file -->\n"
+        ));
+
+        // the emitted anchors are kept or updated
+        let renamed = synced.replace("anchor=whole-file", "anchor=content");
+        let (resynced, _) = sync_md_with_content(&renamed, CONTENT, &SyncOptions::default())?;
+        assert_eq!(resynced, synced.replace("whole-file", "content"));
+
+        let duplicate = "<!-- [geoffrey] [CONTENT_PATH] [answer] anchor -->\n```cpp\n```\n<!-- [geoffrey] [CONTENT_PATH] anchor=snippet-answer -->\n```cpp\n```\n";
+        let (synced, report) = sync_md_with_content(duplicate, CONTENT, &SyncOptions::default())?;
+        assert_eq!(synced, duplicate);
+        assert_eq!(report.summary.failed_files, 1);
+        assert_eq!(report.diagnostics[0].code, "G056");
+
+        assert!("anchor=\"a b\"".parse::<TagOptions>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn lines_wider_than_max_width_are_reported_or_wrapped() -> Result<()> {
+        let content = "//! [call]\n    configure(first_option, second_option, third_option);\n    log(\"a long message which must not be wrapped\");\n//! [call]\n";
+
+        let md = "<!-- [geoffrey] [CONTENT_PATH] [call] dedent max-width=30 -->\n```cpp\n```\n";
+        let (synced, report) = sync_md_with_content(md, content, &SyncOptions::default())?;
+        assert!(synced.contains("configure(first_option, second_option, third_option);\n"));
+        assert_eq!(report.warnings.len(), 1);
+        assert_eq!(report.warnings[0].code, "W011");
+        assert!(report.warnings[0].message.starts_with("2 lines"));
+
+        let md =
+            "<!-- [geoffrey] [CONTENT_PATH] [call] dedent max-width=30 wrap -->\n```cpp\n```\n";
+        let (synced, report) = sync_md_with_content(md, content, &SyncOptions::default())?;
+        assert!(synced.contains(
+            "```cpp\nconfigure(first_option,\n    second_option,\n    third_option);\nlog(\"a long message which must not be wrapped\");\n```\n"
+        ));
+        // the line with the string is kept
+        assert_eq!(report.warnings.len(), 1);
+        assert!(report.warnings[0].message.starts_with("1 lines"));
+
+        assert_eq!(
+            Documents::wrapped_line("  a bb ccc", 5),
+            "  a\n      bb\n      ccc"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn tabs_are_expanded_and_trailing_whitespace_is_stripped() -> Result<()> {
+        let content = "//! [main]\n\tint main() {  \n\t\treturn\t0; \t\n\t}\n//! [main]\n";
+
+        let md = "<!-- [geoffrey] [CONTENT_PATH] [main] expand-tabs=4 strip-trailing-whitespace dedent -->\n```cpp\n```\n";
+        let (synced, _) = sync_md_with_content(md, content, &SyncOptions::default())?;
+        assert!(synced.ends_with("```cpp\nint main() {\n    return  0;\n}\n```\n"));
+
+        let md =
+            "<!-- [geoffrey] [CONTENT_PATH] [main] strip-trailing-whitespace -->\n```cpp\n```\n";
+        let (synced, _) = sync_md_with_content(md, content, &SyncOptions::default())?;
+        assert!(synced.ends_with("```cpp\n\tint main() {\n\t\treturn\t0;\n\t}\n```\n"));
+
+        assert!("expand-tabs=0".parse::<TagOptions>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn tab_indented_snippets_are_dedented() -> Result<()> {
+        let content = "int main() {\n\t//! [body]\n\tint answer = 42;\n\tif (answer) {\n\t\treturn answer;\n\t}\n    return 0;\n\t//! [body]\n}\n";
+        let md = "<!-- [geoffrey] [CONTENT_PATH] [body] -->\n```cpp\n```\n";
+        let (synced, _) = sync_md_with_content(md, content, &SyncOptions::default())?;
+        assert!(synced.ends_with(
+            "```cpp\nint answer = 42;\nif (answer) {\n\treturn answer;\n}\nreturn 0;\n```\n"
+        ));
+
+        assert_eq!(Documents::strip_indentation("\t\tx\n", "    "), "\tx\n");
+        assert_eq!(Documents::strip_indentation("  x\n", "\t"), "x\n");
+        assert_eq!(Documents::strip_indentation(" \tx\n", "\t"), "x\n");
+        assert_eq!(Documents::strip_indentation("x\n", "\t"), "x\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn tag_paths_and_snippets_may_contain_any_character_but_unescaped_brackets() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let content_dir = tmp_dir.path().join("c++ examples");
+        fs::create_dir(&content_dir)?;
+        fs::write(
+            content_dir.join("señal (v2).cpp"),
+            "//! [señal #1 (main)]\nint main() {\n    //! [args[0]]\n    return argv[0][0];\n    //! [args[0]]\n}\n//! [señal #1 (main)]\n",
+        )?;
+        let md_path = tmp_dir.path().join("doc.md");
+        let md = "<!-- [geoffrey] [c++ examples/señal (v2).cpp] [señal #1 (main)] -->\n```cpp\n```\n<!-- [geoffrey] [c++ examples/señal (v2).cpp] [[señal #1 (main)] [args\\[0\\]]] -->\n```cpp\n```\nThe code <!-- [geoffrey-inline] [c++ examples/señal (v2).cpp] [args\\[0\\]] -->`old`\n";
+        let dir = tmp_dir.path().to_str().expect("valid path");
+        fs::write(&md_path, md.replace("[c++", &format!("[{}/c++", dir)))?;
+
+        let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+        documents.parse()?;
+        documents.sync(&SyncOptions::default())?;
+        assert_eq!(
+            fs::read_to_string(&md_path)?.replace(&format!("[{}/c++", dir), "[c++"),
+            "<!-- [geoffrey] [c++ examples/señal (v2).cpp] [señal #1 (main)] -->\n```cpp\nint main() {\n    return argv[0][0];\n}\n```\n<!-- [geoffrey] [c++ examples/señal (v2).cpp] [[señal #1 (main)] [args\\[0\\]]] -->\n```cpp\nint main() {\n    return argv[0][0];\n}\n```\nThe code <!-- [geoffrey-inline] [c++ examples/señal (v2).cpp] [args\\[0\\]] -->`return argv[0][0];`\n"
+        );
+
+        // the escaped brackets are kept by formatting
+        let documents = Documents::new(md_path, Symlinks::Follow)?;
+        let (changed, failures) = documents.format_tags(false);
+        assert!(changed.is_empty() && failures.is_empty());
+
+        assert_eq!(unescape_tag_field(r"a\[0\] \\ \x"), r"a[0] \ \x");
+        assert_eq!(escape_tag_field(r"a[0] \ \x"), r"a\[0\] \\ \\x");
+
+        Ok(())
+    }
+
+    #[test]
+    fn variables_are_expanded_in_tag_paths() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let generated = tmp_dir.path().join("build").join("gen");
+        fs::create_dir_all(&generated)?;
+        fs::write(generated.join("answer.h"), CONTENT)?;
+        fs::write(tmp_dir.path().join("content.cpp"), CONTENT)?;
+        let doc = tmp_dir.path().join("doc");
+        fs::create_dir(&doc)?;
+        fs::write(
+            doc.join("values.toml"),
+            format!("[gen]\ndir = \"{}\"\n", generated.display()),
+        )?;
+        fs::write(
+            doc.join(Config::FILE_NAME),
+            "[variables]\nfile = \"values.toml\"\n",
+        )?;
+        std::env::set_var("GEOFFREY_TEST_CHECKOUT", tmp_dir.path());
+        let md = "<!-- [geoffrey] [${GEOFFREY_TEST_CHECKOUT}/content.cpp] [answer] -->\n```cpp\n```\n<!-- [geoffrey] [${gen.dir}/answer.h] [answer] -->\n```cpp\n```\n";
+        fs::write(doc.join("expanded.md"), md)?;
+        fs::write(
+            doc.join("unknown.md"),
+            "<!-- [geoffrey] [${GEOFFREY_TEST_UNSET}/content.cpp] [answer] -->\n```cpp\n```\n",
+        )?;
+
+        let mut documents = Documents::new(doc.clone(), Symlinks::Follow)?;
+        documents.parse()?;
+        let report = documents.sync(&SyncOptions::default())?;
+        std::env::remove_var("GEOFFREY_TEST_CHECKOUT");
+
+        // the tags keep the variables, so that the documentation works in every checkout
+        assert_eq!(
+            fs::read_to_string(doc.join("expanded.md"))?,
+            md.replace("```cpp\n```", "```cpp\nconstexpr uint8_t ANSWER{42U};\n```")
+        );
+        assert_eq!(report.diagnostics.len(), 1);
+        assert_eq!(report.diagnostics[0].code, "G057");
+        assert!(report.diagnostics[0]
+            .message
+            .contains("'GEOFFREY_TEST_UNSET'"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn crate_paths_are_resolved_with_cargo_metadata() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        fs::write(
+            tmp_dir.path().join("Cargo.toml"),
+            "[package]\nname = \"answer\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+        )?;
+        fs::create_dir(tmp_dir.path().join("src"))?;
+        fs::write(
+            tmp_dir.path().join("src").join("lib.rs"),
+            "//! [answer]\npub const ANSWER: u8 = 42;\n//! [answer]\n",
+        )?;
+        let md = "<!-- [geoffrey] [crate://answer/src/lib.rs] [answer] -->\n```rust\n```\n";
+        fs::write(tmp_dir.path().join("api.md"), md)?;
+        fs::write(
+            tmp_dir.path().join("unknown.md"),
+            "<!-- [geoffrey] [crate://question/src/lib.rs] -->\n```rust\n```\n",
+        )?;
+
+        let mut documents = Documents::new(tmp_dir.path().to_path_buf(), Symlinks::Follow)?;
+        documents.parse()?;
+        let report = documents.sync(&SyncOptions::default())?;
+
+        assert_eq!(
+            fs::read_to_string(tmp_dir.path().join("api.md"))?,
+            md.replace("```rust\n```", "```rust\npub const ANSWER: u8 = 42;\n```")
+        );
+        assert!(report.diagnostics.iter().any(
+            |diagnostic| diagnostic.code == "G059" && diagnostic.message.contains("'question'")
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn verify_fails_the_files_with_snippets_which_do_not_compile() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        fs::write(
+            tmp_dir.path().join(Config::FILE_NAME),
+            "[verify]\nsh = \"sh -n {file}\"\n",
+        )?;
+        let content_path = tmp_dir.path().join("install.sh");
+        fs::write(
+            &content_path,
+            "## [valid]\necho installed\n## [valid]\n## [broken]\nif true; then\n## [broken]\n",
+        )?;
+        let md = format!(
+            "<!-- [geoffrey] [{}] [valid] -->\n```sh\n```\n",
+            content_path.display()
+        );
+        fs::write(tmp_dir.path().join("valid.md"), &md)?;
+        let broken = md.replace("[valid]", "[broken]");
+        fs::write(tmp_dir.path().join("broken.md"), &broken)?;
+
+        let sync = |verify: bool| -> Result<Report> {
+            let mut documents = Documents::new(tmp_dir.path().to_path_buf(), Symlinks::Follow)?;
+            documents.parse()?;
+            Ok(documents.sync(&SyncOptions {
+                verify,
+                ..Default::default()
+            })?)
+        };
+
+        let report = sync(true)?;
+        assert_eq!(
+            fs::read_to_string(tmp_dir.path().join("valid.md"))?,
+            md.replace("```sh\n```", "```sh\necho installed\n```")
+        );
+        assert_eq!(
+            fs::read_to_string(tmp_dir.path().join("broken.md"))?,
+            broken
+        );
+        assert_eq!(report.diagnostics.len(), 1);
+        assert_eq!(report.diagnostics[0].code, "G060");
+        assert!(report.diagnostics[0].message.contains("'broken'"));
+
+        // without '--verify', the snippets are synced as they are
+        let report = sync(false)?;
+        assert!(report.diagnostics.is_empty());
+        assert_ne!(
+            fs::read_to_string(tmp_dir.path().join("broken.md"))?,
+            broken
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn output_tags_embed_the_output_of_their_snippet() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        fs::write(
+            tmp_dir.path().join(Config::FILE_NAME),
+            "[run]\nshell = \"sh {file}\"\n",
+        )?;
+        let content_path = tmp_dir.path().join("greet.sh");
+        fs::write(
+            &content_path,
+            "## [greet]\nname=geoffrey\necho \"hello $name\"\n## [greet]\n",
+        )?;
+        let md_path = tmp_dir.path().join("doc.md");
+        let md = "<!-- [geoffrey] [CONTENT_PATH] [greet] -->\n```sh\n```\nprints\n<!--[geoffrey-output]   [CONTENT_PATH] [greet]-->\n```text\nhello\n```\n"
+            .replace("CONTENT_PATH", &content_path.display().to_string());
+        fs::write(&md_path, &md)?;
+
+        let run_snippets = SyncOptions {
+            run_snippets: true,
+            ..Default::default()
+        };
+
+        // without running the snippets, the code blocks of output tags are kept
+        let mut documents = Documents::new(tmp_dir.path().to_path_buf(), Symlinks::Follow)?;
+        documents.parse()?;
+        let report = documents.sync(&SyncOptions::default())?;
+        assert!(report.diagnostics.is_empty());
+        assert!(fs::read_to_string(&md_path)?.ends_with("```text\nhello\n```\n"));
+
+        let mut documents = Documents::new(tmp_dir.path().to_path_buf(), Symlinks::Follow)?;
+        documents.parse()?;
+        let report = documents.sync(&run_snippets)?;
+        assert!(report.diagnostics.is_empty());
+        assert_eq!(
+            fs::read_to_string(&md_path)?,
+            md.replace(
+                "```sh\n```",
+                "```sh\nname=geoffrey\necho \"hello $name\"\n```"
+            )
+            .replace("```text\nhello\n```", "```text\nhello geoffrey\n```")
+        );
+
+        // formatting keeps the keyword of the output tag
+        let documents = Documents::new(tmp_dir.path().to_path_buf(), Symlinks::Follow)?;
+        let (changed, _) = documents.format_tags(false);
+        assert_eq!(changed, std::slice::from_ref(&md_path));
+        assert!(fs::read_to_string(&md_path)?.contains(&format!(
+            "<!-- [geoffrey-output] [{}] [greet] -->\n```text\nhello geoffrey\n```\n",
+            content_path.display()
+        )));
+
+        fs::write(tmp_dir.path().join(Config::FILE_NAME), "")?;
+        let mut documents = Documents::new(tmp_dir.path().to_path_buf(), Symlinks::Follow)?;
+        documents.parse()?;
+        let report = documents.sync(&run_snippets)?;
+        assert_eq!(report.diagnostics.len(), 1);
+        assert_eq!(report.diagnostics[0].code, "G061");
+
+        Ok(())
+    }
+
+    #[test]
+    fn failures_are_reported_sorted_by_file_and_line() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+        let content_path = tmp_dir.path().join("content.cpp");
+        fs::write(&content_path, CONTENT)?;
+
+        let md = format!(
+            "<!-- [geoffrey] [{0}] [second] -->\n```cpp\n```\n\n<!-- [geoffrey] [{0}] [first] -->\n```cpp\n```\n",
+            content_path.display()
+        );
+        for name in ["c.md", "a.md", "d.md", "b.md"] {
+            fs::write(tmp_dir.path().join(name), &md)?;
+        }
+
+        let mut documents = Documents::new(tmp_dir.path().to_path_buf(), Symlinks::Follow)?;
+        documents.parse()?;
+        assert_eq!(
+            documents.md_paths(),
+            ["a.md", "b.md", "c.md", "d.md"].map(|name| tmp_dir.path().join(name))
+        );
+        let report = documents.sync(&SyncOptions::default())?;
+
+        let locations = report
+            .diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let file = diagnostic.file.as_deref().and_then(Path::file_name);
+                let line = diagnostic.span.as_ref().map(|span| span.line);
+                (file.and_then(|file| file.to_str()), line)
+            })
+            .collect::<Vec<(Option<&str>, Option<usize>)>>();
+        let mut expected = Vec::new();
+        for name in ["a.md", "b.md", "c.md", "d.md"] {
+            expected.extend([(Some(name), Some(1)), (Some(name), Some(5))]);
+        }
+        assert_eq!(locations, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn markdown_text_in_memory_is_synced_without_writing() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+        let content_path = tmp_dir.path().join("content.cpp");
+        fs::write(&content_path, CONTENT)?;
+        let md_path = tmp_dir.path().join("doc.md");
+        fs::write(&md_path, "# on disk\n")?;
+
+        let text = format!(
+            "# in memory\n<!-- [geoffrey] [{}] [answer] -->\n```cpp\n```\n",
+            content_path.display()
+        );
+        let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+        documents.set_md_text(&md_path, text.clone())?;
+        documents.parse()?;
+        let synced = documents
+            .synced_md_text(&md_path, &SyncOptions::default())
+            .map_err(|failures| anyhow::anyhow!("{} failures", failures.len()))?;
+
+        assert_eq!(
+            synced,
+            text.replace("```cpp\n", "```cpp\nconstexpr uint8_t ANSWER{42U};\n")
+        );
+        assert_eq!(fs::read_to_string(&md_path)?, "# on disk\n");
+
+        let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+        documents.set_md_text(&md_path, text.replace("[answer]", "[answr]"))?;
+        documents.parse()?;
+        let failures = documents
+            .synced_md_text(&md_path, &SyncOptions::default())
+            .err()
+            .unwrap_or_default();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].error.code(), "G009");
+
+        let mut documents = Documents::new(md_path, Symlinks::Follow)?;
+        assert!(documents
+            .set_md_text(&tmp_dir.path().join("other.md"), text)
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn sync_after_deadline_skips_files() -> Result<()> {
+        let md = "<!-- [geoffrey] [CONTENT_PATH] [answer] -->\n```cpp\nold\n```\n";
+
+        let options = SyncOptions {
+            deadline: Some(Instant::now()),
+            ..Default::default()
+        };
+        let (synced, report) = sync_md_with_content(md, CONTENT, &options)?;
+
+        assert_eq!(synced, md);
+        assert_eq!(report.summary.skipped_files, 1);
+        assert_eq!(report.summary.failed_files, 0);
+        assert_eq!(report.summary.code_blocks, 0);
+        assert!(report.skipped[0].ends_with("doc.md"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn files_not_parsed_before_the_deadline_are_skipped() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+        let content_path = tmp_dir.path().join("content.cpp");
+        fs::write(&content_path, CONTENT)?;
+        let md_path = tmp_dir.path().join("doc.md");
+        let md = format!(
+            "<!-- [geoffrey] [{}] [answer] -->\n```cpp\nold\n```\n",
+            content_path.display()
+        );
+        fs::write(&md_path, &md)?;
+
+        // the markdown file is not parsed
+        let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+        documents.set_deadline(Instant::now());
+        documents.parse()?;
+        assert!(documents.failures().is_empty());
+        let pending = documents.pending_edits(&SyncOptions::default())?;
+        assert!(pending.edits.is_empty());
+        assert_eq!(pending.skipped, std::slice::from_ref(&md_path));
+
+        // the content file referenced by the markdown file is not parsed
+        let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+        documents.parse_md_files()?;
+        documents.set_deadline(Instant::now());
+        documents.parse_content_files()?;
+        assert!(documents.tag_problems().is_empty());
+        let report = documents.sync(&SyncOptions::default())?;
+        assert_eq!(report.skipped, std::slice::from_ref(&md_path));
+        assert_eq!(report.summary.failed_files, 0);
+        assert_eq!(fs::read_to_string(&md_path)?, md);
+
+        Ok(())
+    }
+
+    #[test]
+    fn sync_ignoring_whitespace_keeps_whitespace_only_changes() -> Result<()> {
+        let reindented = "<!-- [geoffrey] [CONTENT_PATH] [answer] -->\n```cpp\n  constexpr uint8_t ANSWER{42U};   \n```\n";
+        let changed = "<!-- [geoffrey] [CONTENT_PATH] [answer] -->\n```cpp\nconstexpr uint8_t ANSWER{41U};\n```\n";
+
+        let options = SyncOptions {
+            ignore_whitespace: true,
+            ..Default::default()
+        };
+
+        let (synced, report) = sync_md_with_content(reindented, CONTENT, &options)?;
+        assert_eq!(synced, reindented);
+        assert_eq!(report.summary.snippets_unchanged, 1);
+
+        let (synced, report) = sync_md_with_content(changed, CONTENT, &options)?;
+        assert_eq!(
+            synced,
+            "<!-- [geoffrey] [CONTENT_PATH] [answer] -->\n```cpp\nconstexpr uint8_t ANSWER{42U};\n```\n"
+        );
+        assert_eq!(report.summary.snippets_updated, 1);
+
+        let (synced, _) = sync_md_with_content(reindented, CONTENT, &SyncOptions::default())?;
+        assert_ne!(synced, reindented);
+
+        // the indentation is compared by nesting level
+        let code = "if ready:\n    run()\nstop()\n";
+        assert!(Documents::equal_ignoring_whitespace(
+            code,
+            "  if ready:  \n      run()\n  stop()\n"
+        ));
+        assert!(Documents::equal_ignoring_whitespace(
+            code,
+            "if ready:\n\trun()\nstop()\n"
+        ));
+        assert!(!Documents::equal_ignoring_whitespace(
+            code,
+            "if ready:\n    run()\n    stop()\n"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn sync_uses_line_endings_of_md_file() -> Result<()> {
+        let md = "# CRLF\r\n<!-- [geoffrey] [CONTENT_PATH] [answer] -->\r\n```cpp\r\n```\r\n";
+        let (synced, _) = sync_md_with_content(md, CONTENT, &SyncOptions::default())?;
+        assert_eq!(
+            synced,
+            "# CRLF\r\n<!-- [geoffrey] [CONTENT_PATH] [answer] -->\r\n```cpp\r\nconstexpr uint8_t ANSWER{42U};\r\n```\r\n"
+        );
+
+        let md = "# LF\n<!-- [geoffrey] [CONTENT_PATH] [answer] -->\n```cpp\n```\n";
+        let (synced, _) =
+            sync_md_with_content(md, &CONTENT.replace('\n', "\r\n"), &SyncOptions::default())?;
+        assert_eq!(
+            synced,
+            "# LF\n<!-- [geoffrey] [CONTENT_PATH] [answer] -->\n```cpp\nconstexpr uint8_t ANSWER{42U};\n```\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn sync_with_generated_content() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        fs::write(
+            tmp_dir.path().join(Config::FILE_NAME),
+            "[generated]\nbuild = \"printf 'built\\\\n' > built.txt\"\n\n[generated.outputs]\ngreeting = { command = \"cat built.txt\" }\nbuilt = { file = \"built.txt\" }\n",
+        )?;
+        let md_path = tmp_dir.path().join("doc.md");
+        fs::write(
+            &md_path,
+            "<!-- [geoffrey] [gen:greeting] -->\n```\n```\n<!-- [geoffrey] [gen:built] -->\n```\n```\n",
+        )?;
+        let failing_md_path = tmp_dir.path().join("failing.md");
+        let failing_md = "<!-- [geoffrey] [gen:missing] -->\n```\n```\n";
+        fs::write(&failing_md_path, failing_md)?;
+
+        let mut documents = Documents::new(tmp_dir.path().to_path_buf(), Symlinks::Follow)?;
+        documents.parse()?;
+        let report = documents.sync(&SyncOptions::default())?;
+
+        assert_eq!(
+            fs::read_to_string(md_path)?,
+            "<!-- [geoffrey] [gen:greeting] -->\n```\nbuilt\n```\n<!-- [geoffrey] [gen:built] -->\n```\nbuilt\n```\n"
+        );
+        assert_eq!(fs::read_to_string(failing_md_path)?, failing_md);
+        assert_eq!(report.summary.failed_files, 2);
+        assert!(report
+            .diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.message.contains("'missing' is not configured")));
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_are_followed_once_or_skipped() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        fs::write(tmp_dir.path().join("doc.md"), "# doc\n")?;
+        fs::create_dir(tmp_dir.path().join("sub"))?;
+        fs::write(tmp_dir.path().join("sub").join("other.md"), "# other\n")?;
+        std::os::unix::fs::symlink(tmp_dir.path(), tmp_dir.path().join("sub").join("cycle"))?;
+        std::os::unix::fs::symlink(
+            tmp_dir.path().join("doc.md"),
+            tmp_dir.path().join("linked.md"),
+        )?;
+        std::os::unix::fs::symlink(
+            tmp_dir.path().join("sub").join("other.md"),
+            tmp_dir.path().join("outside.md"),
+        )?;
+        std::os::unix::fs::symlink(
+            tmp_dir.path().join("missing.md"),
+            tmp_dir.path().join("broken.md"),
+        )?;
+
+        let documents = Documents::new(tmp_dir.path().to_path_buf(), Symlinks::Follow)?;
+        assert_eq!(documents.md_files.len(), 2);
+
+        let documents = Documents::new(tmp_dir.path().to_path_buf(), Symlinks::Skip)?;
+        let mut md_files = documents
+            .md_files
+            .iter()
+            .map(|md_file| md_file.path.strip_prefix(tmp_dir.path()))
+            .collect::<Result<Vec<&Path>, _>>()?;
+        md_files.sort();
+        assert_eq!(md_files, [Path::new("doc.md"), Path::new("sub/other.md")]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn format_tags_rewrites_only_tag_comments() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let md_path = tmp_dir.path().join("doc.md");
+        let md = "#  Title  \r\n<!--[geoffrey]   [src/main.cpp]-->\r\n```\r\n```\r\n<!--  [geoffrey] [src/main.cpp]  [ main ]   --> trailing\n```\n```\n<!-- [geoffrey] [src/main.cpp] [  [main]  [sub]] -->\n```\n```\n";
+        fs::write(&md_path, md)?;
+        let formatted_path = tmp_dir.path().join("formatted.md");
+        let formatted = "<!-- [geoffrey] [src/main.cpp] [[main]] -->\n```\n```\n";
+        fs::write(&formatted_path, formatted)?;
+
+        let documents = Documents::new(tmp_dir.path().to_path_buf(), Symlinks::Follow)?;
+        let (changed, failures) = documents.format_tags(true);
+        assert!(failures.is_empty());
+        assert_eq!(changed, std::slice::from_ref(&md_path));
+        assert_eq!(fs::read_to_string(&md_path)?, md);
+
+        let (changed, _) = documents.format_tags(false);
+        assert_eq!(changed, std::slice::from_ref(&md_path));
+        assert_eq!(
+            fs::read_to_string(&md_path)?,
+            "#  Title  \r\n<!-- [geoffrey] [src/main.cpp] -->\r\n```\r\n```\r\n<!-- [geoffrey] [src/main.cpp] [main] --> trailing\n```\n```\n<!-- [geoffrey] [src/main.cpp] [[main] [sub]] -->\n```\n```\n"
+        );
+        assert_eq!(fs::read_to_string(&formatted_path)?, formatted);
+
+        let (changed, _) = documents.format_tags(false);
+        assert!(changed.is_empty());
+
+        Ok(())
+    }
+
+    /// Selects the lines from the first one containing the argument to the first following line with '}'
+    struct BlockSelector;
+
+    impl SnippetSelector for BlockSelector {
+        fn select(&self, _path: &Path, content: &[String], argument: &str) -> Option<Range<usize>> {
+            let first = content.iter().position(|line| line.contains(argument))?;
+            let last = first
+                + content[first..]
+                    .iter()
+                    .position(|line| line.contains('}'))?;
+            Some(first..last + 1)
+        }
+    }
+
+    #[test]
+    fn sync_with_snippet_selectors() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let content_path = tmp_dir.path().join("content.cpp");
+        fs::write(
+            &content_path,
+            "#include <cstdint>\nnamespace {\n    int answer() {\n        //! [compute]\n        int a = 42;\n        //! [compute]\n        return a;\n    }\n}\n",
+        )?;
+
+        let md_path = tmp_dir.path().join("doc.md");
+        fs::write(
+            &md_path,
+            format!(
+                "<!-- [geoffrey] [{0}] [lines:5-7] -->\n```cpp\n```\n<!-- [geoffrey] [{0}] [[block:int answer]] -->\n```cpp\n```\n",
+                content_path.display()
+            ),
+        )?;
+        fs::write(
+            tmp_dir.path().join("missing.md"),
+            format!(
+                "<!-- [geoffrey] [{0}] [block:missing] -->\n```cpp\n```\n",
+                content_path.display()
+            ),
+        )?;
+
+        let mut documents = Documents::new(tmp_dir.path().to_path_buf(), Symlinks::Follow)?;
+        documents.register_selector("block", Box::new(BlockSelector));
+        documents.parse()?;
+        let report = documents.sync(&SyncOptions::default())?;
+
+        assert_eq!(
+            fs::read_to_string(&md_path)?.replace(&content_path.display().to_string(), "CONTENT_PATH"),
+            "<!-- [geoffrey] [CONTENT_PATH] [lines:5-7] -->\n```cpp\nint a = 42;\nreturn a;\n```\n<!-- [geoffrey] [CONTENT_PATH] [[block:int answer]] -->\n```cpp\nint answer() {\n    // ...\n    return a;\n}\n```\n"
+        );
+        assert_eq!(report.summary.failed_files, 1);
+
+        Ok(())
+    }
+
+    /// A source whose content is a snippet with the argument as value
+    struct ValueSource;
+
+    impl SnippetSource for ValueSource {
+        fn read(&self, _dir: &Path, argument: &str) -> Result<String, String> {
+            match argument.strip_suffix(".cpp") {
+                Some(value) => Ok(format!("//! [value]\n{}\n//! [value]\n", value)),
+                None => Err(format!("no value in '{}'", argument)),
+            }
+        }
+    }
+
+    #[test]
+    fn sync_with_snippet_sources() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        fs::write(tmp_dir.path().join("content.cpp"), CONTENT)?;
+        let md_path = tmp_dir.path().join("doc.md");
+        fs::write(
+            &md_path,
+            "<!-- [geoffrey] [value:42.cpp] [value] -->\n```cpp\n```\n<!-- [geoffrey] [file:content.cpp] [answer] -->\n```cpp\n```\n",
+        )?;
+        let invalid_path = tmp_dir.path().join("invalid.md");
+        fs::write(
+            &invalid_path,
+            "<!-- [geoffrey] [value:42] [value] -->\n```cpp\n```\n",
+        )?;
+
+        let mut documents = Documents::new(tmp_dir.path().to_path_buf(), Symlinks::Follow)?;
+        documents.register_source("value", Box::new(ValueSource));
+        documents.parse()?;
+        let failures = documents
+            .failures()
+            .iter()
+            .map(|failure| failure.error.code())
+            .collect::<Vec<&str>>();
+        assert_eq!(failures, ["G063"]);
+        let report = documents.sync(&SyncOptions::default())?;
+
+        assert_eq!(
+            fs::read_to_string(&md_path)?,
+            "<!-- [geoffrey] [value:42.cpp] [value] -->\n```cpp\n42\n```\n<!-- [geoffrey] [file:content.cpp] [answer] -->\n```cpp\nconstexpr uint8_t ANSWER{42U};\n```\n"
+        );
+        // the source and the markdown file referencing it
+        assert_eq!(report.summary.failed_files, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn sync_with_output_of_allowed_commands() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        fs::write(
+            tmp_dir.path().join(Config::FILE_NAME),
+            "allowed_commands = [\"echo Usage:\"]\n",
+        )?;
+        let md_path = tmp_dir.path().join("doc.md");
+        fs::write(
+            &md_path,
+            "<!-- [geoffrey] [cmd:echo Usage: tool --help] -->\n```\n```\n",
+        )?;
+        fs::write(
+            tmp_dir.path().join("denied.md"),
+            "<!-- [geoffrey] [cmd:echo hello] -->\n```\n```\n",
+        )?;
+
+        let mut documents = Documents::new(tmp_dir.path().to_path_buf(), Symlinks::Follow)?;
+        documents.parse()?;
+        let report = documents.sync(&SyncOptions::default())?;
+
+        assert_eq!(
+            fs::read_to_string(&md_path)?,
+            "<!-- [geoffrey] [cmd:echo Usage: tool --help] -->\n```\nUsage: tool --help\n```\n"
+        );
+        assert!(report
+            .diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.code == "G063"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn sync_with_provenance_emits_comment_after_the_opening_fence() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        fs::write(
+            tmp_dir.path().join(Config::FILE_NAME),
+            "paths = \"markdown-file\"\nprovenance = true\n",
+        )?;
+        fs::write(tmp_dir.path().join("content.cpp"), CONTENT)?;
+        fs::write(
+            tmp_dir.path().join("content.py"),
+            "## [answer]\nANSWER = 42\n## [answer]\n",
+        )?;
+        let md_path = tmp_dir.path().join("doc.md");
+        fs::write(
+            &md_path,
+            "<!-- [geoffrey] [content.cpp] [answer] -->\n```cpp\n```\n<!-- [geoffrey] [content.py] -->\n```python\n```\n",
+        )?;
+
+        let sync = || -> Result<Report> {
+            let mut documents = Documents::new(tmp_dir.path().to_path_buf(), Symlinks::Follow)?;
+            documents.parse()?;
+            Ok(documents.sync(&SyncOptions::default())?)
+        };
+        sync()?;
+
+        let synced = fs::read_to_string(&md_path)?;
+        let lines = synced.lines().collect::<Vec<&str>>();
+        assert!(
+            lines[2].starts_with("// geoffrey-provenance: "),
+            "{}",
+            synced
+        );
+        assert_eq!(lines[3], "constexpr uint8_t ANSWER{42U};");
+        let provenance = Provenance::parse(lines[2]).expect("provenance comment");
+        assert!(provenance.path.ends_with("content.cpp"));
+        assert_eq!(provenance.snippet.as_deref(), Some("answer"));
+        // the content file is not in a git repository
+        assert_eq!(provenance.commit, None);
+        assert!(provenance.verify("constexpr uint8_t ANSWER{42U};\n"));
+
+        assert!(
+            lines[7].starts_with("# geoffrey-provenance: "),
+            "{}",
+            synced
+        );
+        let provenance = Provenance::parse(lines[7]).expect("provenance comment");
+        assert_eq!(provenance.snippet, None);
+        assert_eq!(lines[8], "ANSWER = 42");
+        assert!(provenance.verify("ANSWER = 42\n"));
+
+        // the comment is stable
+        let report = sync()?;
+        assert_eq!(report.summary.snippets_updated, 0);
+        assert_eq!(fs::read_to_string(&md_path)?, synced);
+
+        // the commit is kept as long as the code did not change, so that new commits don't cause drift
+        let committed = synced.replacen("\"hash\"", "\"commit\":\"1a2b3c4d5e6f\",\"hash\"", 1);
+        fs::write(&md_path, &committed)?;
+        let report = sync()?;
+        assert_eq!(report.summary.snippets_updated, 0);
+        assert_eq!(fs::read_to_string(&md_path)?, committed);
+
+        fs::write(
+            tmp_dir.path().join("content.py"),
+            "## [answer]\nANSWER = 43\n## [answer]\n",
+        )?;
+        sync()?;
+        let lines = fs::read_to_string(&md_path)?
+            .lines()
+            .map(str::to_owned)
+            .collect::<Vec<String>>();
+        assert!(lines[2].contains("\"commit\":\"1a2b3c4d5e6f\""));
+        assert_eq!(
+            Provenance::parse(&lines[7])
+                .expect("provenance comment")
+                .commit,
+            None
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn sync_with_paths_relative_to_markdown_file() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        fs::create_dir(tmp_dir.path().join("src"))?;
+        fs::write(tmp_dir.path().join("src").join("content.cpp"), CONTENT)?;
+        fs::create_dir(tmp_dir.path().join("doc"))?;
+        // without git, the config is expected next to the documentation
+        fs::write(
+            tmp_dir.path().join("doc").join(Config::FILE_NAME),
+            "paths = \"markdown-file\"\n",
+        )?;
+        let md_path = tmp_dir.path().join("doc").join("doc.md");
+        fs::write(
+            &md_path,
+            "<!-- [geoffrey] [../src/content.cpp] [answer] -->\n```cpp\n```\n",
+        )?;
+
+        let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+        documents.parse()?;
+        let report = documents.sync(&SyncOptions::default())?;
+
+        assert_eq!(report.summary.failed_files, 0);
+        assert_eq!(
+            fs::read_to_string(md_path)?,
+            "<!-- [geoffrey] [../src/content.cpp] [answer] -->\n```cpp\nconstexpr uint8_t ANSWER{42U};\n```\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn byte_order_mark_is_preserved() -> Result<()> {
+        let md = "\u{feff}<!-- [geoffrey] [CONTENT_PATH] [answer] -->\n```cpp\n```\n";
+
+        let (synced, report) = sync_md_with_content(md, CONTENT, &SyncOptions::default())?;
+        assert_eq!(report.summary.code_blocks, 1);
+        assert_eq!(
+            synced,
+            "\u{feff}<!-- [geoffrey] [CONTENT_PATH] [answer] -->\n```cpp\nconstexpr uint8_t ANSWER{42U};\n```\n"
+        );
+
+        let (resynced, report) = sync_md_with_content(&synced, CONTENT, &SyncOptions::default())?;
+        assert_eq!(report.summary.snippets_unchanged, 1);
+        assert_eq!(resynced, synced);
+
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+        let md_path = tmp_dir.path().join("doc.md");
+        fs::write(
+            &md_path,
+            "\u{feff}<!--[geoffrey] [content.cpp]-->\n```\n```\n",
+        )?;
+        Documents::new(md_path.clone(), Symlinks::Follow)?.format_tags(false);
+        assert_eq!(
+            fs::read_to_string(&md_path)?,
+            "\u{feff}<!-- [geoffrey] [content.cpp] -->\n```\n```\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn sync_with_content_roots() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let first_root = tmp_dir.path().join("first");
+        let second_root = tmp_dir.path().join("second");
+        fs::create_dir_all(first_root.join("src"))?;
+        fs::create_dir_all(second_root.join("src"))?;
+        fs::write(first_root.join("src").join("first.cpp"), CONTENT)?;
+        fs::write(second_root.join("src").join("first.cpp"), "second root\n")?;
+        fs::write(second_root.join("src").join("second.cpp"), "second root\n")?;
+
+        let md_path = tmp_dir.path().join("doc.md");
+        fs::write(
+            &md_path,
+            "<!-- [geoffrey] [src/first.cpp] [answer] -->\n```cpp\n```\n<!-- [geoffrey] [src/second.cpp] -->\n```cpp\n```\n",
+        )?;
+
+        let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+        documents.set_content_roots(vec![first_root.clone(), second_root.clone()]);
+        documents.parse()?;
+        let report = documents.sync(&SyncOptions::default())?;
+
+        assert_eq!(
+            fs::read_to_string(md_path)?,
+            "<!-- [geoffrey] [src/first.cpp] [answer] -->\n```cpp\nconstexpr uint8_t ANSWER{42U};\n```\n<!-- [geoffrey] [src/second.cpp] -->\n```cpp\nsecond root\n```\n"
+        );
+        assert_eq!(report.blocks[0].content_root.as_ref(), Some(&first_root));
+        assert_eq!(report.blocks[1].content_root.as_ref(), Some(&second_root));
+
+        Ok(())
+    }
+
+    #[test]
+    fn nested_configs_override_settings_of_their_subtree() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        for root in ["first", "second"] {
+            fs::create_dir_all(tmp_dir.path().join(root).join("src"))?;
+            fs::write(
+                tmp_dir.path().join(root).join("src").join("content.cpp"),
+                format!("{}\n", root),
+            )?;
+        }
+        fs::write(
+            tmp_dir.path().join(Config::FILE_NAME),
+            "content_roots = [\"first\"]\n",
+        )?;
+        let md = "<!-- [geoffrey] [src/content.cpp] -->\n```cpp\n```\n";
+        for subtree in ["a", "b"] {
+            fs::create_dir(tmp_dir.path().join(subtree))?;
+            fs::write(tmp_dir.path().join(subtree).join("doc.md"), md)?;
+        }
+        fs::write(
+            tmp_dir.path().join("b").join(Config::FILE_NAME),
+            "content_roots = [\"../second\"]\neol = \"crlf\"\n",
+        )?;
+
+        let mut documents = Documents::new(tmp_dir.path().to_path_buf(), Symlinks::Follow)?;
+        documents.parse()?;
+        let report = documents.sync(&SyncOptions::default())?;
+
+        assert_eq!(report.summary.failed_files, 0);
+        assert_eq!(
+            fs::read_to_string(tmp_dir.path().join("a").join("doc.md"))?,
+            "<!-- [geoffrey] [src/content.cpp] -->\n```cpp\nfirst\n```\n"
+        );
+        assert_eq!(
+            fs::read_to_string(tmp_dir.path().join("b").join("doc.md"))?,
+            "<!-- [geoffrey] [src/content.cpp] -->\n```cpp\nsecond\r\n```\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn configured_keywords_are_accepted_and_formatted_to_the_first_one() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let content_path = tmp_dir.path().join("content.cpp");
+        fs::write(&content_path, "content\n")?;
+        fs::write(
+            tmp_dir.path().join(Config::FILE_NAME),
+            "keywords = [\"snippet\", \"geoffrey\"]\n",
+        )?;
+        let md_path = tmp_dir.path().join("doc.md");
+        fs::write(
+            &md_path,
+            format!(
+                "<!-- [snippet] [{0}] -->\n```cpp\n```\n<!-- [geoffrey] [{0}] -->\n```cpp\n```\n<!-- [include-code] [{0}] -->\n```cpp\n```\n",
+                content_path.display()
+            ),
+        )?;
+
+        let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+        documents.parse()?;
+        documents.sync(&SyncOptions::default())?;
+        assert_eq!(
+            fs::read_to_string(&md_path)?,
+            format!(
+                "<!-- [snippet] [{0}] -->\n```cpp\ncontent\n```\n<!-- [geoffrey] [{0}] -->\n```cpp\ncontent\n```\n<!-- [include-code] [{0}] -->\n```cpp\n```\n",
+                content_path.display()
+            )
+        );
+
+        let documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+        documents.format_tags(false);
+        assert_eq!(
+            fs::read_to_string(&md_path)?,
+            format!(
+                "<!-- [snippet] [{0}] -->\n```cpp\ncontent\n```\n<!-- [snippet] [{0}] -->\n```cpp\ncontent\n```\n<!-- [include-code] [{0}] -->\n```cpp\n```\n",
+                content_path.display()
+            )
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn elided_snippets_keep_context_lines() -> Result<()> {
+        let content = "//! [main]\nvoid run() {\n    //! [setup]\n    int a = 1;\n    int b = 2;\n    int c = 3;\n    int d = 4;\n    //! [setup]\n    //! [core]\n    compute(a, b, c, d);\n    //! [core]\n    //! [teardown]\n    release(a);\n    release(b);\n    //! [teardown]\n}\n//! [main]\n";
+        let md = "<!-- [geoffrey] [CONTENT_PATH] [[main] [core]] context=1 -->\n```cpp\n```\n";
+
+        let (synced, _) = sync_md_with_content(md, content, &SyncOptions::default())?;
+
+        // the teardown has no more than twice the context lines and is kept completely
+        assert_eq!(
+            synced,
+            "<!-- [geoffrey] [CONTENT_PATH] [[main] [core]] context=1 -->\n```cpp\nvoid run() {\n    int a = 1;\n    // ...\n    int d = 4;\n    compute(a, b, c, d);\n    release(a);\n    release(b);\n}\n```\n"
+        );
+        assert!("context=0".parse::<TagOptions>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn elided_snippets_are_rendered_in_source_order() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let content_path = tmp_dir.path().join("content.cpp");
+        fs::write(
+            &content_path,
+            "//! [main]\nint main() {\n    //! [setup]\n    int a = 1;\n    //! [setup]\n\n    if (a) {\n        //! [log]\n        log(a);\n        //! [log]\n        //! [kept]\n        return a;\n        //! [kept]\n    }\n    //! [cleanup]\n    return 0;\n    //! [cleanup]\n}\n//! [main]\n",
+        )?;
+
+        let rendered = "```cpp\nint main() {\n    // ...\n    if (a) {\n        // ...\n        return a;\n    }\n    // ...\n}\n```\n";
+        let md_path = tmp_dir.path().join("doc.md");
+        fs::write(
+            &md_path,
+            format!(
+                "<!-- [geoffrey] [{0}] [[main] [kept]] -->\n```cpp\n```\n<!-- [geoffrey] [{0}] [[main] [kept] [log]] -->\n```cpp\n```\n<!-- [geoffrey] [{0}] [[main] [log] [kept]] -->\n```cpp\n```\n",
+                content_path.display()
+            ),
+        )?;
+
+        // repeated runs must not change the placement of the ellipses
+        for _ in 0..2 {
+            let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+            documents.parse()?;
+            documents.sync(&SyncOptions::default())?;
+
+            assert_eq!(
+                fs::read_to_string(&md_path)?,
+                format!(
+                    "<!-- [geoffrey] [{0}] [[main] [kept]] -->\n{1}<!-- [geoffrey] [{0}] [[main] [kept] [log]] -->\n{2}<!-- [geoffrey] [{0}] [[main] [log] [kept]] -->\n{2}",
+                    content_path.display(),
+                    rendered,
+                    rendered.replace("        // ...\n", "        log(a);\n")
+                )
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn glob_tags_generate_a_code_block_per_file() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        fs::write(
+            tmp_dir.path().join(Config::FILE_NAME),
+            "paths = \"markdown-file\"\n",
+        )?;
+        let examples = tmp_dir.path().join("examples");
+        fs::create_dir(&examples)?;
+        fs::write(examples.join("b.cpp"), "int b;\n")?;
+        fs::write(examples.join("a.cpp"), "int a;\n")?;
+        fs::write(examples.join("notes.txt"), "notes\n")?;
+
+        let md_path = tmp_dir.path().join("doc.md");
+        fs::write(
+            &md_path,
+            "# Gallery\n<!-- [geoffrey] [examples/*.cpp] -->\n<!-- [geoffrey] [end] -->\nmore text\n",
+        )?;
+
+        let sync = || -> Result<Report> {
+            let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+            documents.parse()?;
+            Ok(documents.sync(&SyncOptions::default())?)
+        };
+
+        let report = sync()?;
+        assert_eq!(report.summary.failed_files, 0);
+        assert_eq!(
+            fs::read_to_string(&md_path)?,
+            "# Gallery\n<!-- [geoffrey] [examples/*.cpp] -->\n#### examples/a.cpp\n```cpp\nint a;\n```\n#### examples/b.cpp\n```cpp\nint b;\n```\n<!-- [geoffrey] [end] -->\nmore text\n"
+        );
+
+        fs::remove_file(examples.join("a.cpp"))?;
+        fs::write(examples.join("c.cpp"), "int c;\n")?;
+        sync()?;
+        assert_eq!(
+            fs::read_to_string(&md_path)?,
+            "# Gallery\n<!-- [geoffrey] [examples/*.cpp] -->\n#### examples/b.cpp\n```cpp\nint b;\n```\n#### examples/c.cpp\n```cpp\nint c;\n```\n<!-- [geoffrey] [end] -->\nmore text\n"
+        );
+
+        fs::write(&md_path, "<!-- [geoffrey] [examples/*.cpp] -->\n")?;
+        let mut documents = Documents::new(md_path, Symlinks::Follow)?;
+        documents.parse()?;
+        match documents.failures() {
+            [FileError {
+                error: GeoffreyError::GlobEndTagMissing(_, glob),
+                ..
+            }] if glob == "examples/*.cpp" => Ok(()),
+            _ => Err(anyhow!(
+                "parse without end tag of the glob tag should fail!"
+            )),
+        }
+    }
+
+    fn git(dir: &Path, args: &[&str]) -> Result<()> {
+        let status = std::process::Command::new("git")
+            .args([
+                "-c",
+                "user.name=geoffrey",
+                "-c",
+                "user.email=geoffrey@example.com",
+                "-c",
+                "protocol.file.allow=always",
+            ])
+            .args(args)
+            .current_dir(dir)
+            .output()?
+            .status;
+        if !status.success() {
+            return Err(anyhow!("git {:?} failed", args));
+        }
+
+        Ok(())
+    }
+
+    fn head(dir: &Path) -> Result<String> {
+        let output = std::process::Command::new("git")
+            .args(["rev-parse", "HEAD"])
+            .current_dir(dir)
+            .output()?;
+        Ok(String::from_utf8(output.stdout)?.trim().to_owned())
+    }
+
+    fn sync_freshness(doc_path: &Path) -> Result<Freshness> {
+        let mut documents = Documents::new(doc_path.to_path_buf(), Symlinks::Follow)?;
+        documents.parse()?;
+        let mut report = documents.sync(&SyncOptions {
+            freshness: true,
+            ..Default::default()
+        })?;
+        report.blocks[0]
+            .freshness
+            .take()
+            .ok_or_else(|| anyhow!("the block has no freshness"))
+    }
+
+    #[test]
+    fn freshness_is_the_last_commit_of_the_snippet_lines() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+        let repo = tmp_dir.path().canonicalize()?;
+        git(&repo, &["init", "-q"])?;
+        fs::write(repo.join(Config::FILE_NAME), "paths = \"markdown-file\"\n")?;
+        fs::create_dir(repo.join("src"))?;
+        fs::write(repo.join("src/answer.cpp"), CONTENT)?;
+        let doc_dir = repo.join("doc");
+        fs::create_dir(&doc_dir)?;
+        fs::write(
+            doc_dir.join("doc.md"),
+            "<!-- [geoffrey] [../src/answer.cpp] [answer] -->\n```cpp\n```\n",
+        )?;
+        git(&repo, &["add", "-A"])?;
+        git(&repo, &["commit", "-q", "-m", "answer"])?;
+        let synced = head(&repo)?;
+
+        // fresh: the snippet did not change since the commit of the synced code block
+        let freshness = sync_freshness(&doc_dir)?;
+        assert!(freshness.modified.is_some());
+        assert_eq!(freshness.commit.map(|commit| commit.hash), Some(synced));
+
+        // stale: the snippet changed in a later commit, unlike in the commit after it
+        fs::write(repo.join("src/answer.cpp"), CONTENT.replace("42U", "43U"))?;
+        git(&repo, &["commit", "-q", "-a", "-m", "change answer"])?;
+        let changed = head(&repo)?;
+        fs::write(
+            repo.join("src/answer.cpp"),
+            format!("{}// the end\n", CONTENT.replace("42U", "43U")),
+        )?;
+        git(&repo, &["commit", "-q", "-a", "-m", "append comment"])?;
+
+        let freshness = sync_freshness(&doc_dir)?;
+        assert_eq!(freshness.commit.map(|commit| commit.hash), Some(changed));
+
+        Ok(())
+    }
+
+    #[test]
+    fn freshness_of_sources_is_unknown() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+        git(tmp_dir.path(), &["init", "-q"])?;
+        fs::write(
+            tmp_dir.path().join(Config::FILE_NAME),
+            "allowed_commands = [\"echo\"]\n",
+        )?;
+        fs::write(
+            tmp_dir.path().join("doc.md"),
+            "<!-- [geoffrey] [cmd:echo 42] -->\n```\n```\n",
+        )?;
+
+        let freshness = sync_freshness(tmp_dir.path())?;
+        assert!(freshness.modified.is_none());
+        assert!(freshness.commit.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn git_submodules_are_listed_and_excluded() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+        let tmp_path = tmp_dir.path().canonicalize()?;
+
+        let product = tmp_path.join("product");
+        fs::create_dir(&product)?;
+        fs::write(product.join("README.md"), "# Product\n")?;
+        git(&product, &["init", "-q"])?;
+        git(&product, &["add", "-A"])?;
+        git(&product, &["commit", "-q", "-m", "init"])?;
+
+        let docs = tmp_path.join("docs");
+        fs::create_dir(&docs)?;
+        fs::write(docs.join("index.md"), "# Docs\n")?;
+        git(&docs, &["init", "-q"])?;
+        git(
+            &docs,
+            &[
+                "submodule",
+                "add",
+                "-q",
+                product.to_str().unwrap(),
+                "product",
+            ],
+        )?;
+
+        let submodules = Documents::git_submodules(&docs)?;
+        assert_eq!(submodules, [docs.join("product")]);
+
+        let mut documents = Documents::new(docs.clone(), Symlinks::Follow)?;
+        assert_eq!(documents.md_files.len(), 2);
+        documents.exclude_dirs(&submodules);
+        let md_files = documents
+            .md_files
+            .iter()
+            .map(|md_file| md_file.path.clone())
+            .collect::<Vec<PathBuf>>();
+        assert_eq!(md_files, [docs.join("index.md")]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn cancelled_documents_abort_parse_and_skip_sync() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let content_path = tmp_dir.path().join("content.cpp");
+        fs::write(&content_path, CONTENT)?;
+        let md_path = tmp_dir.path().join("doc.md");
+        let md = format!(
+            "<!-- [geoffrey] [{}] [answer] -->\n```cpp\n```\n",
+            content_path.display()
+        );
+        fs::write(&md_path, &md)?;
+
+        let token = CancellationToken::new();
+        let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+        documents.set_cancellation(token.clone());
+        token.cancel();
+        assert!(matches!(documents.parse(), Err(GeoffreyError::Cancelled)));
+
+        let token = CancellationToken::new();
+        let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+        documents.set_cancellation(token.clone());
+        documents.parse()?;
+        token.cancel();
+        let report = documents.sync(&SyncOptions::default())?;
+
+        assert_eq!(report.skipped, std::slice::from_ref(&md_path));
+        assert_eq!(fs::read_to_string(&md_path)?, md);
+
+        Ok(())
+    }
+
+    #[test]
+    fn templates_are_rendered_to_the_output_dir() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let content_path = tmp_dir.path().join("content.cpp");
+        fs::write(&content_path, CONTENT)?;
+
+        let templates = tmp_dir.path().join("templates");
+        fs::create_dir_all(templates.join("guide"))?;
+        let template = format!(
+            "# Guide\n<!-- [geoffrey] [{}] [answer] -->\n```cpp\n```\n",
+            content_path.display()
+        );
+        fs::write(templates.join("guide").join("intro.md.tpl"), &template)?;
+        fs::write(templates.join("notes.md"), "# Notes\n")?;
+
+        let output = tmp_dir.path().join("output");
+        let render = || -> Result<Report> {
+            let mut documents =
+                Documents::with_templates(templates.clone(), Symlinks::Follow, output.clone())?;
+            documents.parse()?;
+            Ok(documents.sync(&SyncOptions::default())?)
+        };
+
+        let report = render()?;
+        assert_eq!(report.summary.md_files, 1);
+        assert_eq!(
+            fs::read_to_string(output.join("guide").join("intro.md"))?,
+            format!(
+                "# Guide\n<!-- [geoffrey] [{}] [answer] -->\n```cpp\nconstexpr uint8_t ANSWER{{42U}};\n```\n",
+                content_path.display()
+            )
+        );
+        assert_eq!(
+            fs::read_to_string(templates.join("guide").join("intro.md.tpl"))?,
+            template
+        );
+        assert!(!output.join("notes.md").exists());
+
+        assert!(matches!(
+            Documents::with_templates(templates.join("notes.md"), Symlinks::Follow, output),
+            Err(GeoffreyError::NotATemplateFile(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn environments_substitute_placeholders() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        fs::write(
+            tmp_dir.path().join(Config::FILE_NAME),
+            "[envs.cloud]\nendpoint = \"https://api.example.com\"\n[envs.on-prem]\nendpoint = \"http://localhost:8080\"\n",
+        )?;
+        let content_path = tmp_dir.path().join("content.py");
+        fs::write(
+            &content_path,
+            "## [connect]\nclient = connect(\"{{endpoint}}\", \"{{token}}\")\n## [connect]\n",
+        )?;
+        let md_path = tmp_dir.path().join("doc.md");
+        fs::write(
+            &md_path,
+            format!(
+                "<!-- [geoffrey] [{0}] [connect]   env=cloud-->\n```py\n```\n<!-- [geoffrey] [{0}] [connect] env=on-prem -->\n```py\n```\n<!-- [geoffrey] [{0}] [connect] -->\n```py\n```\n",
+                content_path.display()
+            ),
+        )?;
+
+        let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+        documents.parse()?;
+        documents.sync(&SyncOptions::default())?;
+        let documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+        documents.format_tags(false);
+
+        assert_eq!(
+            fs::read_to_string(&md_path)?,
+            format!(
+                "<!-- [geoffrey] [{0}] [connect] env=cloud -->\n```py\nclient = connect(\"https://api.example.com\", \"{{{{token}}}}\")\n```\n<!-- [geoffrey] [{0}] [connect] env=on-prem -->\n```py\nclient = connect(\"http://localhost:8080\", \"{{{{token}}}}\")\n```\n<!-- [geoffrey] [{0}] [connect] -->\n```py\nclient = connect(\"{{{{endpoint}}}}\", \"{{{{token}}}}\")\n```\n",
+                content_path.display()
+            )
+        );
+
+        fs::write(
+            &md_path,
+            format!(
+                "<!-- [geoffrey] [{}] [connect] env=staging -->\n```py\n```\n",
+                content_path.display()
+            ),
+        )?;
+        let mut documents = Documents::new(md_path, Symlinks::Follow)?;
+        documents.parse()?;
+        let problems = documents.validate();
+        assert!(matches!(
+            &problems[..],
+            [FileError {
+                error: GeoffreyError::UnknownEnvironment(env, _),
+                ..
+            }] if env == "staging"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn overrides_shadow_content_files() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        // without git, the config is expected next to the documentation
+        fs::create_dir_all(tmp_dir.path().join("code").join("src"))?;
+        fs::create_dir_all(tmp_dir.path().join("doc").join("overrides").join("src"))?;
+        fs::write(
+            tmp_dir.path().join("doc").join(Config::FILE_NAME),
+            "content_roots = [\"../code\"]\noverrides = \"overrides\"\n",
+        )?;
+        let original = tmp_dir.path().join("code").join("src").join("main.cpp");
+        fs::write(
+            &original,
+            "//! [answer]\nconstexpr uint8_t ANSWER{compute()};\n//! [answer]\n",
+        )?;
+        fs::write(
+            tmp_dir.path().join("code").join("src").join("other.cpp"),
+            "other\n",
+        )?;
+        let shadow = tmp_dir
+            .path()
+            .join("doc")
+            .join("overrides")
+            .join("src")
+            .join("main.cpp");
+        fs::write(
+            &shadow,
+            "//! [answer]\nconstexpr uint8_t ANSWER{42U};\n//! [answer]\n",
+        )?;
+
+        let md_path = tmp_dir.path().join("doc").join("doc.md");
+        fs::write(
+            &md_path,
+            "<!-- [geoffrey] [src/main.cpp] [answer] -->\n```cpp\n```\n<!-- [geoffrey] [src/other.cpp] -->\n```cpp\n```\n",
+        )?;
+
+        let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+        documents.parse()?;
+        documents.sync(&SyncOptions::default())?;
+
+        assert_eq!(
+            fs::read_to_string(&md_path)?,
+            "<!-- [geoffrey] [src/main.cpp] [answer] -->\n```cpp\nconstexpr uint8_t ANSWER{42U};\n```\n<!-- [geoffrey] [src/other.cpp] -->\n```cpp\nother\n```\n"
+        );
+
+        let original =
+            Documents::parse_content_file(&FileSource, &original, &ContentSyntax::default())?;
+        assert_eq!(
+            Documents::snippet_structure(&original),
+            Documents::snippet_structure(&Documents::parse_content_file(
+                &FileSource,
+                &shadow,
+                &ContentSyntax::default()
+            )?)
+        );
+        fs::write(&shadow, "//! [simplified]\n42\n//! [simplified]\n")?;
+        assert_ne!(
+            Documents::snippet_structure(&original),
+            Documents::snippet_structure(&Documents::parse_content_file(
+                &FileSource,
+                &shadow,
+                &ContentSyntax::default()
+            )?)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn format_tags_rewrites_tangle_tags_and_rejects_duplicate_options() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let md_path = tmp_dir.path().join("doc.md");
+        fs::write(
+            &md_path,
+            "<!--[tangle]  [ hello.cpp ] [ 02 ]-->\n```cpp\n```\n<!-- [tangle] [hello.cpp] [0] --> main\n```cpp\n```\n",
+        )?;
+
+        let documents = Documents::new(tmp_dir.path().to_path_buf(), Symlinks::Follow)?;
+        let (changed, failures) = documents.format_tags(false);
+        assert!(failures.is_empty());
+        assert_eq!(changed, std::slice::from_ref(&md_path));
+        assert_eq!(
+            fs::read_to_string(&md_path)?,
+            "<!-- [tangle] [hello.cpp] [2] -->\n```cpp\n```\n<!-- [tangle] [hello.cpp] --> main\n```cpp\n```\n"
+        );
+
+        fs::write(
+            &md_path,
+            "<!-- [geoffrey] [src/main.cpp] env=a env=b -->\n```cpp\n```\n",
+        )?;
+        let (_, failures) = documents.format_tags(false);
+        assert!(matches!(
+            &failures[..],
+            [FileError {
+                error: GeoffreyError::DuplicateTagOption(option),
+                ..
+            }] if option == "env"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn tilde_fenced_code_blocks_are_synced() -> Result<()> {
+        let md = "<!-- [geoffrey] [CONTENT_PATH] [answer] -->\n~~~~cpp\n```\nold\n~~~\n~~~~\n";
+
+        let (synced, report) = sync_md_with_content(md, CONTENT, &SyncOptions::default())?;
+        assert_eq!(
+            synced,
+            "<!-- [geoffrey] [CONTENT_PATH] [answer] -->\n~~~~cpp\nconstexpr uint8_t ANSWER{42U};\n~~~~\n"
+        );
+        assert_eq!(report.summary.snippets_updated, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn info_strings_of_fences_are_preserved() -> Result<()> {
+        let md = "<!-- [geoffrey] [CONTENT_PATH] [answer] -->\n```cpp title=\"answer.hpp\" {1-3} linenums=\"1\"\nold\n```\n";
+
+        let (synced, report) = sync_md_with_content(md, CONTENT, &SyncOptions::default())?;
+        assert_eq!(
+            synced,
+            "<!-- [geoffrey] [CONTENT_PATH] [answer] -->\n```cpp title=\"answer.hpp\" {1-3} linenums=\"1\"\nconstexpr uint8_t ANSWER{42U};\n```\n"
+        );
+        assert_eq!(report.summary.snippets_updated, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn tags_in_unmanaged_code_blocks_are_ignored() -> Result<()> {
+        let md = "````md\n<!-- [geoffrey] [CONTENT_PATH] [answer] -->\n```cpp\n```\n````\n<!-- [geoffrey] [CONTENT_PATH] [answer] -->\n```cpp\n```\n";
+
+        let (synced, report) = sync_md_with_content(md, CONTENT, &SyncOptions::default())?;
+        assert_eq!(
+            synced,
+            "````md\n<!-- [geoffrey] [CONTENT_PATH] [answer] -->\n```cpp\n```\n````\n<!-- [geoffrey] [CONTENT_PATH] [answer] -->\n```cpp\nconstexpr uint8_t ANSWER{42U};\n```\n"
+        );
+        assert_eq!(report.summary.code_blocks, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn mdx_files_use_jsx_comment_tags() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let content_path = tmp_dir.path().join("content.cpp");
+        fs::write(&content_path, CONTENT)?;
+        let mdx_path = tmp_dir.path().join("doc.mdx");
+        fs::write(
+            &mdx_path,
+            format!(
+                "{{/*[geoffrey]  [{0}] [answer]*/}}\n```cpp\n```\n<!-- [geoffrey] [{0}] [answer] -->\n```cpp\n```\n",
+                content_path.display()
+            ),
+        )?;
+        let markdown_path = tmp_dir.path().join("notes.Markdown");
+        fs::write(
+            &markdown_path,
+            format!(
+                "<!-- [geoffrey] [{}] [answer] -->\n```cpp\n```\n",
+                content_path.display()
+            ),
+        )?;
+
+        let mut documents = Documents::new(tmp_dir.path().to_path_buf(), Symlinks::Follow)?;
+        documents.parse()?;
+        let report = documents.sync(&SyncOptions::default())?;
+        assert_eq!(report.summary.snippets_updated, 3);
+
+        let documents = Documents::new(tmp_dir.path().to_path_buf(), Symlinks::Follow)?;
+        let (changed, _) = documents.format_tags(false);
+        assert_eq!(changed, std::slice::from_ref(&mdx_path));
+        assert_eq!(
+            fs::read_to_string(&mdx_path)?,
+            format!(
+                "{{/* [geoffrey] [{0}] [answer] */}}\n```cpp\nconstexpr uint8_t ANSWER{{42U}};\n```\n{{/* [geoffrey] [{0}] [answer] */}}\n```cpp\nconstexpr uint8_t ANSWER{{42U}};\n```\n",
+                content_path.display()
+            )
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn code_blocks_in_doc_comments_of_headers_are_synced() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let content_path = tmp_dir.path().join("example.cpp");
+        fs::write(
+            &content_path,
+            "//! [usage]\nint main() {\n\n    return ANSWER;\n}\n//! [usage]\n",
+        )?;
+        let header_path = tmp_dir.path().join("answer.hpp");
+        let header = format!(
+            "/**\n * The answer\n *\n * <!--[geoffrey] [{0}] [usage]-->\n * @code\n * @endcode\n */\nconstexpr int ANSWER{{42}};\n\n/// <!-- [geoffrey] [{0}] [usage] -->\n/// ```cpp\n/// stale\n/// ```\nvoid answer();\n// <!-- [geoffrey] [{0}] [usage] -->\n",
+            content_path.display()
+        );
+        fs::write(&header_path, &header)?;
+
+        let mut documents = Documents::new(header_path.clone(), Symlinks::Follow)?;
+        documents.parse()?;
+        let report = documents.sync(&SyncOptions::default())?;
+        assert_eq!(report.summary.snippets_updated, 2);
+        assert_eq!(
+            fs::read_to_string(&header_path)?,
+            format!(
+                "/**\n * The answer\n *\n * <!--[geoffrey] [{0}] [usage]-->\n * @code\n * int main() {{\n *\n *     return ANSWER;\n * }}\n * @endcode\n */\nconstexpr int ANSWER{{42}};\n\n/// <!-- [geoffrey] [{0}] [usage] -->\n/// ```cpp\n/// int main() {{\n///\n///     return ANSWER;\n/// }}\n/// ```\nvoid answer();\n// <!-- [geoffrey] [{0}] [usage] -->\n",
+                content_path.display()
+            )
+        );
+
+        let documents = Documents::new(header_path.clone(), Symlinks::Follow)?;
+        let (changed, _) = documents.format_tags(false);
+        assert_eq!(changed, std::slice::from_ref(&header_path));
+        assert!(fs::read_to_string(&header_path)?.contains(&format!(
+            " * <!-- [geoffrey] [{}] [usage] -->\n",
+            content_path.display()
+        )));
+
+        Ok(())
+    }
+
+    #[test]
+    fn replace_options_are_applied_to_the_snippet_lines() -> Result<()> {
+        let md = r#"<!-- [geoffrey] [CONTENT_PATH] [answer] replace="uint8_t -> int" replace="ANSWER\{(\d+)U\} -> ANSWER = $1" -->
+```cpp
+```
+"#;
+
+        let (synced, report) = sync_md_with_content(md, CONTENT, &SyncOptions::default())?;
+        assert_eq!(report.summary.snippets_updated, 1);
+        assert!(synced.ends_with("```cpp\nconstexpr int ANSWER = 42;\n```\n"));
+
+        let options =
+            TagOptions::from_str(r#"  env=cloud replace="say \"hi\" -> a\\" replace="\d -> x"  "#)?;
+        assert_eq!(options.env.as_deref(), Some("cloud"));
+        assert_eq!(
+            options.replace,
+            [
+                Replacement {
+                    pattern: r#"say "hi""#.to_owned(),
+                    replacement: r"a\".to_owned()
+                },
+                Replacement {
+                    pattern: r"\d".to_owned(),
+                    replacement: "x".to_owned()
+                }
+            ]
+        );
+        assert_eq!(
+            options.to_string(),
+            r#" env=cloud replace="say \"hi\" -> a\\" replace="\d -> x""#
+        );
+        assert_eq!(TagOptions::from_str(&options.to_string())?, options);
+
+        assert!(matches!(
+            TagOptions::from_str(r#"replace="( -> x""#),
+            Err(GeoffreyError::InvalidReplacement(..))
+        ));
+        assert!(matches!(
+            TagOptions::from_str(r#"replace="unterminated"#),
+            Err(GeoffreyError::InvalidTagOption(..))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn highlight_options_keep_the_sub_snippets_and_highlight_their_lines() -> Result<()> {
+        const MAIN: &str = "//! [main]\nint main() {\n    //! [answer]\n    int answer{42};\n    //! [answer]\n    int other{0};\n    //! [print]\n    std::cout << answer\n              << std::endl;\n    //! [print]\n    return 0;\n}\n//! [main]\n";
+        let md = r#"<!-- [geoffrey] [CONTENT_PATH] [[main] [answer] [print]] highlight=hugo -->
+```cpp {hl_lines=[1]}
+```
+"#;
+
+        let (synced, report) = sync_md_with_content(md, MAIN, &SyncOptions::default())?;
+        assert_eq!(report.summary.snippets_updated, 1);
+        assert!(synced.ends_with(
+            "```cpp {hl_lines=[2,\"4-5\"]}\nint main() {\n    int answer{42};\n    int other{0};\n    std::cout << answer\n              << std::endl;\n    return 0;\n}\n```\n"
+        ));
+
+        let (resynced, report) = sync_md_with_content(&synced, MAIN, &SyncOptions::default())?;
+        assert_eq!(report.summary.snippets_updated, 0);
+        assert_eq!(resynced, synced);
+
+        let md = md.replace("highlight=hugo", "highlight=mkdocs");
+        let (synced, _) = sync_md_with_content(&md, MAIN, &SyncOptions::default())?;
+        assert!(synced.contains("```cpp {hl_lines=[1]} hl_lines=\"2 4-5\"\nint main() {\n"));
+
+        assert!(matches!(
+            TagOptions::from_str("highlight=sphinx"),
+            Err(GeoffreyError::InvalidTagOption(..))
+        ));
+        assert!(matches!(
+            TagOptions::from_str("highlight=hugo highlight=mkdocs"),
+            Err(GeoffreyError::DuplicateTagOption(..))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn flag_options_and_max_lines_are_parsed_and_applied() -> Result<()> {
+        const INDENTED: &str = "    int a{1};\n\n        int b{2};\n";
+        let md = "<!-- [geoffrey] [CONTENT_PATH] dedent max-lines=3 -->\n```cpp\n```\n";
+
+        let (synced, report) = sync_md_with_content(md, INDENTED, &SyncOptions::default())?;
+        assert_eq!(report.summary.snippets_updated, 1);
+        assert!(synced.ends_with("```cpp\nint a{1};\n\n    int b{2};\n```\n"));
+
+        let md = md.replace("max-lines=3", "max-lines=2");
+        let (synced, report) = sync_md_with_content(&md, INDENTED, &SyncOptions::default())?;
+        assert_eq!(synced, md);
+        assert_eq!(report.summary.failed_files, 1);
+        assert!(report.diagnostics[0].message.contains("has 3 lines"));
+
+        let md = "<!-- [geoffrey] [CONTENT_PATH] [answer] unknown -->\n```cpp\n```\n";
+        let (_, report) = sync_md_with_content(md, CONTENT, &SyncOptions::default())?;
+        assert_eq!(report.summary.failed_files, 1);
+        assert!(report.diagnostics[0]
+            .message
+            .contains("Invalid tag option 'unknown'"));
+
+        let options = TagOptions::from_str("max-lines=20  dedent env=cloud")?;
+        assert!(options.dedent);
+        assert_eq!(options.max_lines, Some(20));
+        assert_eq!(options.to_string(), " dedent env=cloud max-lines=20");
+
+        for invalid in ["dedent=yes", "env", "max-lines=0", "max-lines=many"] {
+            assert!(
+                matches!(
+                    TagOptions::from_str(invalid),
+                    Err(GeoffreyError::InvalidTagOption(..))
+                ),
+                "{}",
+                invalid
+            );
+        }
+        assert!(matches!(
+            TagOptions::from_str("dedent dedent"),
+            Err(GeoffreyError::DuplicateTagOption(..))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn malformed_tags_are_reported_at_the_character_which_broke_parsing() -> Result<()> {
+        let located = |md: &str| -> Result<(usize, usize, String)> {
+            let (synced, report) = sync_md_with_content(md, CONTENT, &SyncOptions::default())?;
+            assert_eq!(synced, md);
+            let diagnostic = &report.diagnostics[0];
+            let span = diagnostic.span.as_ref().ok_or(anyhow!("span is missing"))?;
+            Ok((span.line, span.column, diagnostic.message.clone()))
+        };
+
+        let (line, column, message) =
+            located("text\n<!-- [geoffrey] [a.cpp] [answer] env=x ! -->\n```cpp\n```\n")?;
+        assert_eq!((line, column), (2, 40));
+        assert!(
+            message.contains("expected an option like 'name=value' or the end of the tag '-->'")
+        );
+
+        let (line, column, message) = located("<!-- [geoffrey] (a.cpp) -->\n```cpp\n```\n")?;
+        assert_eq!((line, column), (1, 17));
+        assert!(message.contains("expected the content file path in brackets"));
+
+        let (line, column, message) = located("{/* [geoffrey] [a.cpp] [answer]\n```cpp\n```\n")?;
+        assert_eq!((line, column), (1, 32));
+        assert!(message.contains("expected the end of the tag '*/}'"));
+
+        let (_, report) = sync_md_with_content(
+            "<!-- [geoffrey] [CONTENT_PATH] [answer] env=x unknown -->\n```cpp\n```\n",
+            CONTENT,
+            &SyncOptions::default(),
+        )?;
+        let span = report.diagnostics[0]
+            .span
+            .as_ref()
+            .ok_or(anyhow!("span is missing"))?;
+        assert!(span.excerpt[span.column - 1..].starts_with("unknown -->"));
+
+        let (line, column, _) = located("<!-- [geoffrey] [CONTENT_PATH] [answer] -->\ntext\n")?;
+        assert_eq!((line, column), (2, 1));
+
+        let (_, report) = sync_md_with_content(
+            "<!-- [geoffrey] [CONTENT_PATH] [answer] -->\n```cpp\n```\n",
+            "int a;\n  //! [answer]\nint b;\n",
+            &SyncOptions::default(),
+        )?;
+        let diagnostic = report
+            .diagnostics
+            .iter()
+            .find(|diagnostic| diagnostic.span.is_some())
+            .ok_or(anyhow!("span is missing"))?;
+        assert_eq!(
+            diagnostic
+                .span
+                .as_ref()
+                .map(|span| (span.line, span.column)),
+            Some((2, 3))
+        );
+        assert!(diagnostic
+            .to_string()
+            .ends_with("\n  |\n2 |   //! [answer]\n  |   ^"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn all_errors_of_all_files_are_reported() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let content_path = tmp_dir.path().join("content.cpp");
+        fs::write(&content_path, CONTENT)?;
+        let content_path = content_path.to_str().expect("valid path");
+
+        let md_dir = tmp_dir.path().join("doc");
+        DirBuilder::new().create(&md_dir)?;
+        let invalid_tags = format!(
+            "<!-- [geoffrey] [{0}] [answer -->\n```cpp\n```\n<!-- [geoffrey] [{0}] [answer] unknown -->\n```cpp\n```\n",
+            content_path
+        );
+        fs::write(md_dir.join("a.md"), &invalid_tags)?;
+        let missing_snippets = format!(
+            "<!-- [geoffrey] [{0}] [question] -->\n```cpp\n```\n<!-- [geoffrey] [{0}] [answer] -->\n```cpp\n```\n<!-- [geoffrey] [{0}] [hint] -->\n```cpp\n```\n",
+            content_path
+        );
+        fs::write(md_dir.join("b.md"), &missing_snippets)?;
+        let valid = format!(
+            "<!-- [geoffrey] [{}] [answer] -->\n```cpp\n```\n",
+            content_path
+        );
+        fs::write(md_dir.join("c.md"), &valid)?;
+
+        let mut documents = Documents::new(md_dir.clone(), Symlinks::Follow)?;
+        documents.parse()?;
+        let report = documents.sync(&SyncOptions::default())?;
+
+        let mut locations = report
+            .diagnostics
+            .iter()
+            .map(|diagnostic| {
+                (
+                    diagnostic.file.clone().unwrap_or_default(),
+                    diagnostic.span.as_ref().map_or(0, |span| span.line),
+                    diagnostic.code.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        locations.sort();
+        assert_eq!(
+            locations,
+            [
+                (md_dir.join("a.md"), 1, "G040".to_owned()),
+                (md_dir.join("a.md"), 4, "G021".to_owned()),
+                (md_dir.join("b.md"), 1, "G009".to_owned()),
+                (md_dir.join("b.md"), 7, "G009".to_owned()),
+            ]
+        );
+        assert_eq!(report.summary.failed_files, 2);
+        assert_eq!(report.summary.snippets_updated, 1);
+        // files with errors are not written
+        assert_eq!(fs::read_to_string(md_dir.join("a.md"))?, invalid_tags);
+        assert_eq!(fs::read_to_string(md_dir.join("b.md"))?, missing_snippets);
+
+        Ok(())
+    }
+
+    #[test]
+    fn warnings_are_reported_without_failing_the_sync() -> Result<()> {
+        const NESTED: &str = "//! [main]\nint main() {\n    //! [outer]\n    //! [inner]\n    return 0;\n    //! [inner]\n    //! [outer]\n}\n//! [main]\n//! [empty]\n//! [empty]\n";
+        let md = "<!-- [geoffrey] [CONTENT_PATH] [empty] -->\n```python\n```\n<!-- [geoffrey] [CONTENT_PATH] [[main] [outer] [inner]] -->\n```c++\n```\n<!-- [geoffrey] [CONTENT_PATH] [main] -->\n```cpp\n```\n<!-- [geoffrey] [CONTENT_PATH] [main] -->\n```text\n```\n";
+
+        let (_, report) = sync_md_with_content(md, NESTED, &SyncOptions::default())?;
+        let warnings = report
+            .warnings
+            .iter()
+            .map(|warning| {
+                (
+                    warning.span.as_ref().map_or(0, |span| span.line),
+                    warning.code.as_str(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            warnings,
+            [(1, "W001"), (1, "W002"), (4, "W004"), (10, "W003")]
+        );
+        assert_eq!(report.summary.warnings, 4);
+        assert_eq!(report.summary.failed_files, 0);
+        assert_eq!(report.summary.snippets_updated, 3);
+        assert!(report.warnings[2]
+            .message
+            .contains("it is within the kept sub-snippet 'outer'"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn only_included_md_files_are_synced() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let content_path = tmp_dir.path().join("content.cpp");
+        fs::write(&content_path, CONTENT)?;
+
+        let md_dir = tmp_dir.path().join("doc");
+        DirBuilder::new().create(&md_dir)?;
+        let md = format!(
+            "<!-- [geoffrey] [{}] [answer] -->\n```cpp\n```\n",
+            content_path.to_str().expect("valid path")
+        );
+        fs::write(md_dir.join("guide.md"), &md)?;
+        fs::write(md_dir.join("other.md"), &md)?;
+
+        let mut documents = Documents::new(md_dir.clone(), Symlinks::Follow)?;
+        documents.include_md_files(&[format!("{}/gui*.md", md_dir.display())])?;
+        documents.parse()?;
+        let report = documents.sync(&SyncOptions::default())?;
+
+        assert_eq!(report.summary.md_files, 1);
+        assert!(fs::read_to_string(md_dir.join("guide.md"))?.contains("ANSWER"));
+        assert_eq!(fs::read_to_string(md_dir.join("other.md"))?, md);
+
+        let mut documents = Documents::new(md_dir, Symlinks::Follow)?;
+        assert!(matches!(
+            documents.include_md_files(&["[".to_owned()]),
+            Err(GeoffreyError::InvalidGlob(..))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn only_filtered_tags_are_synced_and_checked() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let content_path = tmp_dir.path().join("content.cpp");
+        fs::write(&content_path, CONTENT)?;
+        let content_path = content_path.to_str().expect("valid path");
+
+        let md_path = tmp_dir.path().join("doc.md");
+        let md = format!(
+            "<!-- [geoffrey] [{0}] [answer] -->\n```cpp\n```\n<!-- [geoffrey] [{0}] [question] -->\n```cpp\nold\n```\n<!-- [geoffrey] [missing.cpp] -->\n```cpp\n```\n",
+            content_path
+        );
+        fs::write(&md_path, &md)?;
+
+        let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+        documents.only_tags(vec![format!("{}#answer", content_path)]);
+        documents.parse()?;
+        let report = documents.sync(&SyncOptions::default())?;
+
+        assert!(report.diagnostics.is_empty());
+        assert_eq!(report.summary.code_blocks, 1);
+        assert_eq!(
+            fs::read_to_string(&md_path)?,
+            md.replacen(
+                "```cpp\n```",
+                "```cpp\nconstexpr uint8_t ANSWER{42U};\n```",
+                1
+            )
+        );
+
+        let mut documents = Documents::new(md_path, Symlinks::Follow)?;
+        documents.only_tags(vec![content_path.to_owned()]);
+        documents.parse()?;
+        let report = documents.sync(&SyncOptions::default())?;
+
+        assert_eq!(report.diagnostics.len(), 1);
+        assert_eq!(report.diagnostics[0].code, "G009");
+
+        Ok(())
+    }
+
+    #[test]
+    fn configured_marker_forms_are_accepted() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let content_path = tmp_dir.path().join("content.cpp");
+        fs::write(
+            &content_path,
+            "// [main]\nint main() {\n    /* [body] */\n    return 0;\n    /* [body] */\n}\n// [main]\n",
+        )?;
+        fs::write(
+            tmp_dir.path().join(Config::FILE_NAME),
+            "markers = [\"line\", \"block\"]\n",
+        )?;
+        let md_path = tmp_dir.path().join("doc.md");
+        fs::write(
+            &md_path,
+            format!(
+                "<!-- [geoffrey] [{0}] [main] -->\n```cpp\n```\n<!-- [geoffrey] [{0}] [[main] [body]] -->\n```cpp\n```\n",
+                content_path.display()
+            ),
+        )?;
+
+        let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+        documents.parse()?;
+        documents.sync(&SyncOptions::default())?;
+        assert_eq!(
+            fs::read_to_string(&md_path)?,
+            format!(
+                "<!-- [geoffrey] [{0}] [main] -->\n```cpp\nint main() {{\n    return 0;\n}}\n```\n<!-- [geoffrey] [{0}] [[main] [body]] -->\n```cpp\nint main() {{\n    return 0;\n}}\n```\n",
+                content_path.display()
+            )
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn keep_markers_option_keeps_the_nested_markers() -> Result<()> {
+        let content = "//! [main]\nint main() {\n    //! [setup]\n    return 0;\n    //! [setup]\n    //! [kept]\n    foo();\n    //! [kept]\n}\n//! [main]\n";
+
+        let md = "<!-- [geoffrey] [CONTENT_PATH] [[main] [kept]] keep-markers -->\n```cpp\n```\n";
+        assert_eq!(
+            sync_md_with_content(md, content, &SyncOptions::default())?.0,
+            "<!-- [geoffrey] [CONTENT_PATH] [[main] [kept]] keep-markers -->\n```cpp\nint main() {\n    // ...\n    //! [kept]\n    foo();\n    //! [kept]\n}\n```\n"
+        );
+
+        let md = "<!-- [geoffrey] [CONTENT_PATH] [[main] [kept]] highlight=mkdocs keep-markers -->\n```cpp\n```\n";
+        assert_eq!(
+            sync_md_with_content(md, content, &SyncOptions::default())?.0,
+            "<!-- [geoffrey] [CONTENT_PATH] [[main] [kept]] highlight=mkdocs keep-markers -->\n```cpp hl_lines=\"6\"\nint main() {\n    //! [setup]\n    return 0;\n    //! [setup]\n    //! [kept]\n    foo();\n    //! [kept]\n}\n```\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn snippets_defined_in_multiple_content_files_fail_with_unique_snippets() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
 
-        let mut content_file = ContentFile::new();
+        let first_path = tmp_dir.path().join("a.cpp");
+        fs::write(&first_path, CONTENT)?;
+        let second_path = tmp_dir.path().join("b.cpp");
+        fs::write(&second_path, format!("// other\n{}", CONTENT))?;
+        let md_path = tmp_dir.path().join("doc.md");
+        let md = format!(
+            "<!-- [geoffrey] [{}] [answer] -->\n```cpp\n```\n<!-- [geoffrey] [{}] [answer] -->\n```cpp\n```\n",
+            first_path.display(),
+            second_path.display()
+        );
+        fs::write(&md_path, &md)?;
 
-        let content_snippet = ContentSnippetDescription {
-            tag: String::new(),
-            indentation: String::new(),
-            ellipsis_line: String::new(),
-            begin: 0,
-            end: 0,
-            nested: Vec::new(),
-        };
+        let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+        documents.parse()?;
+        assert!(documents.failures().is_empty());
 
-        let root_content_snippet = Self::parse_next_content_snippet(
-            &path,
-            &mut reader,
-            &mut content_file,
-            content_snippet,
+        fs::write(
+            tmp_dir.path().join(Config::FILE_NAME),
+            "unique_snippets = true\n",
         )?;
+        let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+        documents.parse()?;
+        let failures = documents.failures();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].path, second_path);
+        assert_eq!(failures[0].error.code(), "G041");
+        assert_eq!(failures[0].span.as_ref().map(|span| span.line), Some(3));
+        assert!(failures[0]
+            .to_string()
+            .contains(&format!("'{}' at line 2", first_path.display())));
 
-        if content_file
-            .lookup
-            .insert(root_content_snippet.tag.clone(), root_content_snippet)
-            .is_some()
-        {
-            return Err(GeoffreyError::ContentSnippetDoubleTag(
-                path.clone(),
-                "".to_owned(),
-            ))?;
-        }
+        let report = documents.sync(&SyncOptions::default())?;
+        assert_eq!(report.summary.failed_files, 2);
+        assert_eq!(fs::read_to_string(&md_path)?, md);
 
-        Ok(content_file)
+        Ok(())
     }
 
-    fn parse_next_content_snippet<R>(
-        path: &PathBuf,
-        reader: &mut BufReader<R>,
-        content_file: &mut ContentFile,
-        mut current_snippet: ContentSnippetDescription,
-    ) -> Result<ContentSnippetDescription, GeoffreyError>
-    where
-        R: std::io::Read,
-    {
-        let re = Regex::new(r"( *)//! \[(.*)\]").map_err(|_| GeoffreyError::RegexError)?;
+    #[test]
+    fn oversized_and_binary_files_are_skipped_with_a_warning() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
 
-        let mut line = String::new();
-        loop {
-            if reader.read_line(&mut line)? > 0 {
-                if let Some(caps) = re.captures(&line) {
-                    let new_tag = caps.get(2).ok_or(GeoffreyError::RegexError)?.as_str();
+        let content_path = tmp_dir.path().join("content.cpp");
+        fs::write(&content_path, CONTENT)?;
+        let binary_path = tmp_dir.path().join("binary.cpp");
+        fs::write(&binary_path, b"//! [answer]\n\0\x01\x02\n//! [answer]\n")?;
+        let md_dir = tmp_dir.path().join("md");
+        fs::create_dir(&md_dir)?;
+        fs::write(
+            md_dir.join(Config::FILE_NAME),
+            "max_content_file_size = 100\nmax_md_file_size = 200\n",
+        )?;
+        let md = format!(
+            "<!-- [geoffrey] [{}] [answer] -->\n```cpp\nstale\n```\n<!-- [geoffrey] [{}] [answer] -->\n```cpp\nkept\n```\n",
+            content_path.display(),
+            binary_path.display()
+        );
+        fs::write(md_dir.join("doc.md"), &md)?;
+        let large_md = format!("{}\n{}", md, "x".repeat(200));
+        fs::write(md_dir.join("large.md"), &large_md)?;
 
-                    if current_snippet.tag == new_tag {
-                        current_snippet.end = content_file.data.len();
-                        content_file.data.push(line);
-                        break Ok(current_snippet);
-                    } else if new_tag.len() == 0 {
-                        break Err(GeoffreyError::ContentSnippetEmptyTag(path.clone()));
-                    } else {
-                        let indentation = caps
-                            .get(1)
-                            .ok_or(GeoffreyError::RegexError)?
-                            .as_str()
-                            .to_owned();
+        let mut documents = Documents::new(md_dir.clone(), Symlinks::Follow)?;
+        documents.parse()?;
+        let report = documents.sync(&SyncOptions::default())?;
 
-                        let ellipsis_line = format!("{}// ...\n", indentation);
+        let mut warnings = report
+            .warnings
+            .iter()
+            .map(|warning| {
+                (
+                    warning.file.clone().unwrap_or_default(),
+                    warning.code.as_str(),
+                )
+            })
+            .collect::<Vec<_>>();
+        warnings.sort();
+        assert_eq!(
+            warnings,
+            [
+                (binary_path.clone(), "W009"),
+                (md_dir.join("large.md"), "W009")
+            ]
+        );
+        assert_eq!(report.summary.failed_files, 0);
+        assert_eq!(
+            fs::read_to_string(md_dir.join("doc.md"))?,
+            md.replace("stale", "constexpr uint8_t ANSWER{42U};")
+        );
+        assert_eq!(fs::read_to_string(md_dir.join("large.md"))?, large_md);
 
-                        let new_snippet = ContentSnippetDescription {
-                            tag: new_tag.to_owned(),
-                            indentation,
-                            ellipsis_line,
-                            begin: content_file.data.len(),
-                            end: 0,
-                            nested: Vec::new(),
-                        };
+        Ok(())
+    }
 
-                        content_file.data.push(line);
-                        line = String::new();
+    #[test]
+    fn deeply_nested_and_self_nested_snippets_fail_with_the_tag_chain() -> Result<()> {
+        let syntax = ContentSyntax {
+            max_nesting_depth: 2,
+            ..ContentSyntax::default()
+        };
+        let parse = |content: &str| {
+            Documents::parse_content(PathBuf::from("content.cpp"), content.as_bytes(), &syntax)
+                .map(|_| ())
+        };
 
-                        let nested_snippet = Self::parse_next_content_snippet(
-                            &path,
-                            reader,
-                            content_file,
-                            new_snippet,
-                        )?;
+        assert!(parse("//! [a]\n//! [b]\n//! [b]\n//! [a]\n").is_ok());
 
-                        if content_file
-                            .lookup
-                            .insert(nested_snippet.tag.clone(), nested_snippet.clone())
-                            .is_some()
-                        {
-                            return Err(GeoffreyError::ContentSnippetDoubleTag(
-                                path.clone(),
-                                nested_snippet.tag.clone(),
-                            ))?;
-                        }
+        match parse("//! [a]\n//! [b]\n    //! [c]\n    //! [c]\n//! [b]\n//! [a]\n") {
+            Err(GeoffreyError::Located(span, error)) => {
+                assert_eq!(span.line, 3);
+                assert_eq!(
+                    error.to_string(),
+                    "The snippets in the content file 'content.cpp' are nested deeper than the limit of 2: 'a' > 'b' > 'c'"
+                );
+            }
+            result => return Err(anyhow!("unexpected result {:?}", result)),
+        }
 
-                        current_snippet.nested.push(nested_snippet);
-                    }
-                } else {
-                    content_file.data.push(line);
-                    line = String::new();
-                }
-            } else {
-                if current_snippet.tag == line {
-                    current_snippet.end = content_file.data.len().max(1) - 1;
-                    break Ok(current_snippet);
-                } else {
-                    break Err(GeoffreyError::ContentSnippetEndTagNotFound(
-                        path.clone(),
-                        current_snippet.tag,
-                    ));
-                }
+        match parse("//! [a]\n//! [b]\n//! [a]\n//! [b]\n//! [a]\n") {
+            Err(GeoffreyError::Located(span, error)) => {
+                assert_eq!(span.line, 3);
+                assert_eq!(
+                    error.to_string(),
+                    "The snippet is opened again within itself in the content file 'content.cpp': 'a' > 'b' > 'a'"
+                );
+            }
+            result => return Err(anyhow!("unexpected result {:?}", result)),
+        }
+
+        match parse("//! [a]\nint a;\n//! [b]\nint b;\n//! [a]\n//! [b]\n") {
+            Err(GeoffreyError::Located(span, error)) => {
+                assert_eq!(span.line, 5);
+                assert_eq!(
+                    error.to_string(),
+                    "The snippets 'a' from line 1 and 'b' from line 3 overlap in the content file 'content.cpp'; snippets may only be nested"
+                );
             }
+            result => return Err(anyhow!("unexpected result {:?}", result)),
         }
+
+        Ok(())
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
+    #[test]
+    fn overlapping_snippets_fail_unlike_snippets_opened_again_within_themselves() -> Result<()> {
+        let parse = |content: &str| match Documents::parse_content(
+            PathBuf::from("content.cpp"),
+            content.as_bytes(),
+            &ContentSyntax::default(),
+        ) {
+            Err(GeoffreyError::Located(span, error)) => Ok((span.line, *error)),
+            result => Err(anyhow!("unexpected result {:?}", result.map(|_| ()))),
+        };
 
-    use anyhow::{anyhow, Result};
-    use tempfile::Builder;
+        // the end marker of 'a' within 'b', which ends after it
+        match parse("//! [a]\nint a;\n//! [b]\nint b;\n//! [a]\nint c;\n//! [b]\n")? {
+            (5, GeoffreyError::ContentSnippetsOverlap(_, outer, 1, inner, 3)) => {
+                assert_eq!((outer.as_str(), inner.as_str()), ("a", "b"));
+            }
+            result => return Err(anyhow!("unexpected error {:?}", result)),
+        }
 
-    use std::fs::{DirBuilder, File};
+        // the inner snippet is never closed
+        match parse("//! [a]\n//! [b]\nint b;\n//! [a]\nint a;\n")? {
+            (4, error @ GeoffreyError::ContentSnippetsOverlap(..)) => {
+                assert_eq!(error.code(), "G049");
+                assert_eq!(
+                    error.to_string(),
+                    "The snippets 'a' from line 1 and 'b' from line 2 overlap in the content file 'content.cpp'; snippets may only be nested"
+                );
+            }
+            result => return Err(anyhow!("unexpected error {:?}", result)),
+        }
 
-    #[test]
-    fn document_new_with_non_existing_path_fails() -> Result<()> {
-        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+        // the end marker of the outermost snippet within two nested ones
+        match parse("//! [a]\n//! [b]\n//! [c]\n//! [a]\n//! [c]\n//! [b]\n")? {
+            (4, GeoffreyError::ContentSnippetsOverlap(_, outer, 1, inner, 3)) => {
+                assert_eq!((outer.as_str(), inner.as_str()), ("a", "c"));
+            }
+            result => return Err(anyhow!("unexpected error {:?}", result)),
+        }
 
-        let mut doc_path = PathBuf::new();
-        doc_path.push(tmp_dir.path());
-        doc_path.push("hypnotoad");
-        match Documents::new(doc_path) {
-            Err(GeoffreyError::DocPathDoesNotExist(_)) => Ok(()),
-            _ => Err(anyhow!("Document::new with non existing path should fail!")),
+        // with later markers of 'a', its marker within 'b' opens it again instead of ending it
+        for content in [
+            "//! [a]\n//! [b]\n//! [a]\n//! [a]\n//! [b]\n//! [a]\n",
+            "//! [a]\n//! [b]\n//! [a]\n//! [b]\n//! [a]\n",
+        ] {
+            match parse(content)? {
+                (3, error @ GeoffreyError::ContentSnippetNestedInItself(..)) => {
+                    assert_eq!(error.code(), "G043");
+                }
+                result => return Err(anyhow!("unexpected error {:?}", result)),
+            }
         }
+
+        Ok(())
     }
 
     #[test]
-    fn document_new_with_file_as_path_but_not_md_file_fails() -> Result<()> {
-        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+    fn inline_tags_sync_their_code_spans() -> Result<()> {
+        let content = "//! [port]\nconstexpr uint16_t PORT{8080U};\n//! [port]\n//! [version]\n  1.2.3\n//! [version]\n";
 
-        let mut doc_path = PathBuf::new();
-        doc_path.push(tmp_dir.path());
-        doc_path.push("hypnotoad.txt");
+        let md = "Listens on `<!--[geoffrey-inline][CONTENT_PATH][port] replace=\"^.*\\{(\\d+)U.*$ -> $1\"-->`80` for\nversion <!-- [geoffrey-inline] [CONTENT_PATH] [version] -->`1.0.0` and `1.0.0`.\n```md\n<!--[geoffrey-inline][CONTENT_PATH][port]-->`80`\n```\n";
+        let (synced, report) = sync_md_with_content(md, content, &SyncOptions::default())?;
+        assert_eq!(
+            synced,
+            "Listens on `<!--[geoffrey-inline][CONTENT_PATH][port] replace=\"^.*\\{(\\d+)U.*$ -> $1\"-->`8080` for\nversion <!-- [geoffrey-inline] [CONTENT_PATH] [version] -->`1.2.3` and `1.0.0`.\n```md\n<!--[geoffrey-inline][CONTENT_PATH][port]-->`80`\n```\n"
+        );
+        assert_eq!(report.summary.snippets_updated, 2);
 
-        File::create(doc_path.clone())?;
+        // the whole file is not a single line
+        let md = "a <!--[geoffrey-inline][CONTENT_PATH]-->`x`\n";
+        let (_, report) = sync_md_with_content(md, content, &SyncOptions::default())?;
+        assert_eq!(report.diagnostics.len(), 1);
+        assert_eq!(report.diagnostics[0].code, "G045");
 
-        match Documents::new(doc_path) {
-            Err(GeoffreyError::NotAMarkdownFile(_)) => Ok(()),
-            _ => Err(anyhow!(
-                "Document::new with file as path path but not md file should fail!"
-            )),
-        }
+        let md = "b <!--[geoffrey-inline][CONTENT_PATH][port]--> 80\n";
+        let (synced, report) = sync_md_with_content(md, content, &SyncOptions::default())?;
+        assert_eq!(synced, md);
+        assert_eq!(report.diagnostics.len(), 1);
+        assert_eq!(report.diagnostics[0].code, "G044");
+
+        Ok(())
     }
 
     #[test]
-    fn document_new_with_file_as_path_which_is_md_file_succeeds() -> Result<()> {
+    fn variables_are_substituted_in_code_blocks_and_templates() -> Result<()> {
         let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
 
-        let mut doc_path = PathBuf::new();
-        doc_path.push(tmp_dir.path());
-        doc_path.push("hypnotoad.md");
+        let content_path = tmp_dir.path().join("content.cpp");
+        fs::write(
+            &content_path,
+            "//! [version]\n  1.2.3\n//! [version]\n//! [usage]\nconnect(\"{{geoffrey:host}}\", {{geoffrey:server.port}});\n//! [usage]\n",
+        )?;
+        let templates = tmp_dir.path().join("templates");
+        fs::create_dir(&templates)?;
+        fs::write(
+            templates.join("values.toml"),
+            "host = \"localhost\"\n[server]\nport = 8080\n",
+        )?;
+        fs::write(
+            templates.join(Config::FILE_NAME),
+            format!(
+                "[variables]\nfile = \"values.toml\"\n[variables.snippets]\nversion = \"{}#version\"\n",
+                content_path.display()
+            ),
+        )?;
+        let template = format!(
+            "# Version {{{{geoffrey:version}}}}\n<!-- [geoffrey] [{}] [usage] -->\n```cpp\n```\n",
+            content_path.display()
+        );
+        fs::write(templates.join("guide.md.tpl"), &template)?;
 
-        File::create(doc_path.clone())?;
+        let output = tmp_dir.path().join("output");
+        let render = || -> Result<Report> {
+            let mut documents =
+                Documents::with_templates(templates.clone(), Symlinks::Follow, output.clone())?;
+            documents.parse()?;
+            Ok(documents.sync(&SyncOptions::default())?)
+        };
 
-        let documents = Documents::new(doc_path.clone())?;
+        render()?;
+        assert_eq!(
+            fs::read_to_string(output.join("guide.md"))?,
+            format!(
+                "# Version 1.2.3\n<!-- [geoffrey] [{}] [usage] -->\n```cpp\nconnect(\"localhost\", 8080);\n```\n",
+                content_path.display()
+            )
+        );
 
-        assert_eq!(documents.md_files.len(), 1);
-        assert_eq!(documents.md_files[0].path, doc_path);
+        fs::write(
+            templates.join("guide.md.tpl"),
+            template.replace("geoffrey:version", "geoffrey:release"),
+        )?;
+        let report = render()?;
+        assert_eq!(report.diagnostics.len(), 1);
+        assert_eq!(report.diagnostics[0].code, "G046");
 
         Ok(())
     }
 
     #[test]
-    fn document_new_with_empty_dir_as_path_fails() -> Result<()> {
+    fn tabs_options_render_a_tab_group_per_glob() -> Result<()> {
         let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
 
-        let mut doc_path = PathBuf::new();
-        doc_path.push(tmp_dir.path());
+        fs::write(
+            tmp_dir.path().join(Config::FILE_NAME),
+            "paths = \"markdown-file\"\n",
+        )?;
+        let examples = tmp_dir.path().join("examples");
+        fs::create_dir(&examples)?;
+        fs::write(
+            examples.join("hello.cpp"),
+            "//! [main]\nint main() {\n\n}\n//! [main]\n",
+        )?;
+        fs::write(
+            examples.join("hello.rs"),
+            "//! [main]\nfn main() {}\n//! [main]\n",
+        )?;
 
-        match Documents::new(doc_path) {
-            Err(GeoffreyError::NoMarkdownFilesInPath(_)) => Ok(()),
-            _ => Err(anyhow!("Document::new with empty dir as path should fail!")),
+        let md_path = tmp_dir.path().join("doc.md");
+        let sync = |md: &str| -> Result<String> {
+            fs::write(&md_path, md)?;
+            let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+            documents.parse()?;
+            let report = documents.sync(&SyncOptions::default())?;
+            assert_eq!(report.summary.failed_files, 0);
+            Ok(fs::read_to_string(&md_path)?)
+        };
+
+        let md = "<!-- [geoffrey] [examples/hello.*] [main] tabs=docusaurus -->\n<!-- [geoffrey] [end] -->\n";
+        assert_eq!(
+            sync(md)?,
+            "<!-- [geoffrey] [examples/hello.*] [main] tabs=docusaurus -->\n<Tabs>\n<TabItem value=\"cpp\" label=\"C++\">\n\n```cpp\nint main() {\n\n}\n```\n\n</TabItem>\n<TabItem value=\"rs\" label=\"Rust\">\n\n```rs\nfn main() {}\n```\n\n</TabItem>\n</Tabs>\n<!-- [geoffrey] [end] -->\n"
+        );
+
+        let md = "<!-- [geoffrey] [examples/hello.*] [main] tabs=mkdocs -->\n<!-- [geoffrey] [end] -->\n";
+        let synced = sync(md)?;
+        assert_eq!(
+            synced,
+            "<!-- [geoffrey] [examples/hello.*] [main] tabs=mkdocs -->\n=== \"C++\"\n\n    ```cpp\n    int main() {\n\n    }\n    ```\n\n=== \"Rust\"\n\n    ```rs\n    fn main() {}\n    ```\n\n<!-- [geoffrey] [end] -->\n"
+        );
+        // the tab group is kept in sync like the code blocks of a glob tag
+        assert_eq!(sync(&synced)?, synced);
+
+        assert!(matches!(
+            TagOptions::from_str("tabs=sphinx"),
+            Err(GeoffreyError::InvalidTagOption(..))
+        ));
+        assert_eq!(fence::language_name("hxx"), "C++");
+        assert_eq!(fence::language_name("kt"), "kt");
+
+        fs::write(
+            &md_path,
+            "<!-- [geoffrey] [examples/hello.cpp] [main] tabs=mkdocs -->\n```cpp\n```\n",
+        )?;
+        let mut documents = Documents::new(md_path, Symlinks::Follow)?;
+        documents.parse()?;
+        match documents.failures() {
+            [FileError { error, .. }] if error.code() == "G047" => Ok(()),
+            _ => Err(anyhow!("parse of a tabs option without glob should fail!")),
         }
     }
 
     #[test]
-    fn document_new_with_dir_as_path_and_single_md_file_succeeds() -> Result<()> {
+    fn rev_options_render_the_diff_between_revisions() -> Result<()> {
         let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+        let repo = tmp_dir.path().canonicalize()?;
 
-        let mut doc_path = PathBuf::new();
-        doc_path.push(tmp_dir.path());
-
-        let mut md_file = doc_path.clone();
-        md_file.push("hypnotoad.md");
-
-        File::create(md_file.clone())?;
+        git(&repo, &["init", "-q"])?;
+        fs::write(
+            repo.join("hello.cpp"),
+            "//! [main]\nint main() {\n    init();\n    return 0;\n}\n//! [main]\n",
+        )?;
+        git(&repo, &["add", "-A"])?;
+        git(&repo, &["commit", "-q", "-m", "v1"])?;
+        git(&repo, &["tag", "v1.0"])?;
+        fs::write(
+            repo.join("hello.cpp"),
+            "//! [main]\nint main() {\n    init(Options{});\n    return 0;\n}\n//! [main]\n",
+        )?;
+        git(&repo, &["commit", "-q", "-a", "-m", "v2"])?;
+        git(&repo, &["tag", "v2.0"])?;
+        fs::write(repo.join("hello.cpp"), "//! [other]\n//! [other]\n")?;
 
-        let documents = Documents::new(doc_path.clone())?;
+        let md_path = repo.join("migration.md");
+        fs::write(
+            &md_path,
+            "<!-- [geoffrey] [hello.cpp] [main] rev=v1.0..v2.0 -->\n```diff\n```\n",
+        )?;
+        let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+        documents.parse()?;
+        // the snippet is only needed at the revisions
+        assert!(documents.tag_problems().is_empty());
+        // the markers at the revisions are not orphaned
+        let orphans = documents.orphaned_markers(&[])?;
+        assert_eq!(orphans.len(), 1);
+        assert_eq!(orphans[0].tag, "other");
+        let report = documents.sync(&SyncOptions::default())?;
+        assert_eq!(report.summary.failed_files, 0);
+        assert_eq!(
+            fs::read_to_string(&md_path)?,
+            "<!-- [geoffrey] [hello.cpp] [main] rev=v1.0..v2.0 -->\n```diff\n int main() {\n-    init();\n+    init(Options{});\n     return 0;\n }\n```\n"
+        );
 
-        assert_eq!(documents.md_files.len(), 1);
-        assert_eq!(documents.md_files[0].path, md_file);
+        assert!(matches!(
+            TagOptions::from_str("rev=v1.0"),
+            Err(GeoffreyError::InvalidTagOption(..))
+        ));
+        assert!(matches!(
+            TagOptions::from_str("rev=--output=x..v2.0"),
+            Err(GeoffreyError::InvalidTagOption(..))
+        ));
+        assert!(Documents::show_revision(&md_path, "--output=x").is_err());
 
-        Ok(())
+        fs::write(
+            &md_path,
+            "<!-- [geoffrey] [hello.cpp] [main] rev=v0.9..v2.0 -->\n```diff\n```\n",
+        )?;
+        let mut documents = Documents::new(md_path, Symlinks::Follow)?;
+        documents.parse()?;
+        match documents.failures() {
+            [FileError {
+                error: GeoffreyError::RevisionNotReadable(path, rev, _),
+                ..
+            }] if path == "hello.cpp" && rev == "v0.9" => Ok(()),
+            _ => Err(anyhow!("parse with an unknown revision should fail!")),
+        }
     }
 
     #[test]
-    fn document_new_with_dir_as_path_and_multiple_md_file_succeeds() -> Result<()> {
+    fn renamed_content_files_are_suggested_and_fixed() -> Result<()> {
         let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+        let repo = tmp_dir.path().canonicalize()?;
 
-        let mut doc_path = PathBuf::new();
-        doc_path.push(tmp_dir.path());
-
-        let mut md_file_1 = doc_path.clone();
-        md_file_1.push("brain_slug.md");
-
-        let mut md_file_2 = doc_path.clone();
-        md_file_2.push("hypnotoad.md");
-
-        File::create(md_file_1.clone())?;
-        File::create(md_file_2.clone())?;
+        git(&repo, &["init", "-q"])?;
+        fs::create_dir(repo.join("src"))?;
+        fs::write(
+            repo.join("src/hello.cpp"),
+            "//! [main]\nhello();\n//! [main]\n",
+        )?;
+        git(&repo, &["add", "-A"])?;
+        git(&repo, &["commit", "-q", "-m", "hello"])?;
+        fs::create_dir(repo.join("lib"))?;
+        git(&repo, &["mv", "src/hello.cpp", "lib/hello.cpp"])?;
+        git(&repo, &["commit", "-q", "-m", "move"])?;
+        // the staged rename is followed as well
+        git(&repo, &["mv", "lib/hello.cpp", "lib/greeting.cpp"])?;
 
-        let mut documents = Documents::new(doc_path.clone())?;
+        let md_path = repo.join("doc.md");
+        fs::write(
+            &md_path,
+            "<!-- [geoffrey] [src/hello.cpp] [main] -->\n```cpp\n```\nCall <!--[geoffrey-inline][src/hello.cpp][main]--> `` first\n",
+        )?;
+        let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+        documents.parse()?;
+        match documents.failures() {
+            [FileError {
+                error: GeoffreyError::ContentFileNotFound(path, suggestions),
+                ..
+            }] if path == "src/hello.cpp" && suggestions == &["lib/greeting.cpp"] => (),
+            _ => return Err(anyhow!("parse with a renamed content file should fail!")),
+        }
 
-        assert_eq!(documents.md_files.len(), 2);
-        let mut files = documents
-            .md_files
-            .drain(..)
-            .map(|md_file| md_file.path)
-            .collect::<Vec<PathBuf>>();
-        files.sort();
-        assert_eq!(files[0], md_file_1);
-        assert_eq!(files[1], md_file_2);
+        Documents::apply_edits(&documents.renamed_path_edits()?)?;
+        assert_eq!(
+            fs::read_to_string(&md_path)?,
+            "<!-- [geoffrey] [lib/greeting.cpp] [main] -->\n```cpp\n```\nCall <!--[geoffrey-inline][lib/greeting.cpp][main]--> `` first\n"
+        );
+        let mut documents = Documents::new(md_path, Symlinks::Follow)?;
+        documents.parse()?;
+        assert!(documents.failures().is_empty());
+        assert!(documents.renamed_path_edits()?.is_empty());
 
         Ok(())
     }
 
     #[test]
-    fn document_new_with_dir_as_path_and_md_file_in_nested_dir_succeeds() -> Result<()> {
+    fn snippet_names_not_matching_the_tag_pattern_are_warned_about() -> Result<()> {
         let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
 
-        let mut doc_path = PathBuf::new();
-        doc_path.push(tmp_dir.path());
-
-        let mut md_file_1 = doc_path.clone();
-        md_file_1.push("brain_slug.md");
-
-        let mut md_file_2_dir = doc_path.clone();
-        md_file_2_dir.push("hypnotoad");
-        DirBuilder::new().create(md_file_2_dir.clone())?;
-
-        let mut md_file_2 = md_file_2_dir.clone();
-        md_file_2.push("hypnotoad.md");
-
-        File::create(md_file_1.clone())?;
-        File::create(md_file_2.clone())?;
+        fs::write(
+            tmp_dir.path().join(Config::FILE_NAME),
+            "tag_pattern = \"^[a-z0-9]+(-[a-z0-9]+)*$\"\n",
+        )?;
+        let content_path = tmp_dir.path().join("content.cpp");
+        fs::write(
+            &content_path,
+            "//! [mainLoop]\nrun();\n//! [mainLoop]\n//! [setup-env]\nsetup();\n//! [setup-env]\n",
+        )?;
+        let md_path = tmp_dir.path().join("doc.md");
+        fs::write(
+            &md_path,
+            format!(
+                "<!-- [geoffrey] [{0}] [mainLoop] -->\n```cpp\n```\n<!-- [geoffrey] [{0}] [setup-env] -->\n```cpp\n```\n<!-- [geoffrey] [{0}] [lines:2-2] -->\n```cpp\n```\n",
+                content_path.display()
+            ),
+        )?;
 
-        let mut documents = Documents::new(doc_path.clone())?;
+        let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+        documents.parse()?;
+        let report = documents.sync(&SyncOptions::default())?;
+        assert_eq!(report.summary.failed_files, 0);
+        let mut warnings = report
+            .warnings
+            .iter()
+            .map(|warning| {
+                (
+                    warning.code.as_str(),
+                    warning.file.clone(),
+                    warning.span.as_ref().map(|span| span.line),
+                )
+            })
+            .collect::<Vec<_>>();
+        warnings.sort();
+        assert_eq!(
+            warnings,
+            [
+                ("W010", Some(content_path), Some(1)),
+                ("W010", Some(md_path), Some(1)),
+            ]
+        );
+        assert_eq!(
+            report.warnings[0].message,
+            "The snippet name 'mainLoop' does not match the pattern '^[a-z0-9]+(-[a-z0-9]+)*$'"
+        );
 
-        assert_eq!(documents.md_files.len(), 2);
-        let mut files = documents
-            .md_files
-            .drain(..)
-            .map(|md_file| md_file.path)
-            .collect::<Vec<PathBuf>>();
-        files.sort();
-        assert_eq!(files[0], md_file_1);
-        assert_eq!(files[1], md_file_2);
+        fs::write(
+            tmp_dir.path().join(Config::FILE_NAME),
+            "tag_pattern = \"^[a-z\"\n",
+        )?;
+        assert!(matches!(
+            Config::load(tmp_dir.path()),
+            Err(GeoffreyError::ConfigError(..))
+        ));
 
         Ok(())
     }
@@ -1,7 +1,12 @@
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::suggest::did_you_mean;
+
+use serde::Serialize;
 use thiserror::Error;
 
+use std::collections::HashSet;
+use std::fmt;
 use std::path::PathBuf;
 
 #[derive(Error, Debug)]
@@ -14,14 +19,20 @@ pub enum GeoffreyError {
     NoMarkdownFilesInPath(PathBuf),
     #[error("The provided doc path '{0}' is not a markdown file")]
     NotAMarkdownFile(PathBuf),
+    #[error("The provided doc path '{0}' is not a markdown template ending with '.md.tpl'")]
+    NotATemplateFile(PathBuf),
     #[error("Could not get git toplevel")]
     GitToplevelError,
     #[error("Regex error")]
     RegexError,
-    #[error("The content file '{0}' was not found")]
-    ContentFileNotFound(String),
-    #[error("The content snippet '{1}' in the content file '{0}' was not found")]
-    ContentSnippetNotFound(String, String),
+    #[error("The content file '{0}' was not found{}", did_you_mean(.1))]
+    ContentFileNotFound(String, Vec<String>),
+    #[error("The content file '{0}' could not be parsed")]
+    ContentFileInvalid(String),
+    #[error("The content snippet '{1}' in the content file '{0}' was not found{}", did_you_mean(.2))]
+    ContentSnippetNotFound(String, String, Vec<String>),
+    #[error("The sub-snippet '{2}' is not nested in the snippet '{1}' of the content file '{0}'")]
+    SubSnippetNotNested(String, String, String),
     #[error("End tag '{1}' in content file '{0}' not found")]
     ContentSnippetEndTagNotFound(PathBuf, String),
     #[error("Empty tag detected in content file '{0}'")]
@@ -36,6 +47,560 @@ pub enum GeoffreyError {
         "The end of the code block of snippet tag '{1}' in the markdown file '{0}' is not present"
     )]
     CodeBlockEndMissing(PathBuf, String),
+    #[error("Invalid fallback '{0}'; expected one of 'full-file', 'skip' or 'error'")]
+    InvalidFallback(String),
+    #[error("Invalid symlink policy '{0}'; expected one of 'follow' or 'skip'")]
+    InvalidSymlinks(String),
+    #[error(
+        "Invalid line range '{0}'; expected 'first-last' or a single line, starting at line 1"
+    )]
+    InvalidLineRange(String),
+    #[error("The line range {1}-{2} exceeds the {3} lines of '{0}'")]
+    LineRangeOutOfBounds(PathBuf, usize, usize, usize),
+    #[error("Invalid tag name '{0}'")]
+    InvalidTagName(String),
+    #[error("Invalid tag option '{0}'; expected 'dedent', 'keep-markers', 'context=<n>', 'env=<name>', 'highlight=hugo|mkdocs', 'tabs=docusaurus|mkdocs', 'rev=<old>..<new>', 'max-lines=<n>' or 'replace=\"<pattern> -> <replacement>\"'")]
+    InvalidTagOption(String),
+    #[error("The environment '{0}' of a tag for '{1}' is not configured")]
+    UnknownEnvironment(String, String),
+    #[error("Invalid config file '{0}': {1}")]
+    ConfigError(PathBuf, String),
+    #[error("The repository '{0}' does not exist at '{1}' and has no remote to clone from")]
+    RepositoryNotFound(String, PathBuf),
+    #[error("Could not clone the repository '{0}' from '{1}'")]
+    RepositoryCloneFailed(String, String),
+    #[error("The repository '{0}' of the content file '{1}' is not configured")]
+    UnknownRepository(String, String),
+    #[error("The selector of '{1}' selected lines beyond the end of the content file '{0}'")]
+    SelectionOutOfBounds(String, String),
+    #[error("The generated content '{0}' could not be created: {1}")]
+    GeneratedContentFailed(String, String),
+    #[error("Could not list the git submodules of '{0}': {1}")]
+    GitSubmodulesError(PathBuf, String),
+    #[error("Invalid glob '{0}': {1}")]
+    InvalidGlob(String, String),
+    #[error("The end tag of the glob tag '{1}' in the markdown file '{0}' is not present")]
+    GlobEndTagMissing(PathBuf, String),
+    #[error("The operation was cancelled")]
+    Cancelled,
+    #[error("Invalid tangle path '{0}'; expected a relative path without '..'")]
+    InvalidTanglePath(String),
+    #[error("Could not write the report '{0}': {1}")]
+    ReportError(PathBuf, String),
     #[error("Error accessing file")]
     IoError(#[from] std::io::Error),
+    #[error("Invalid compatibility version '{0}'; expected '1'")]
+    InvalidCompat(String),
+    #[error("The tag option '{0}' is given more than once")]
+    DuplicateTagOption(String),
+    #[error("Invalid replacement '{0}'; expected '<pattern> -> <replacement>' with a regular expression as pattern: {1}")]
+    InvalidReplacement(String, String),
+    #[error("The content snippet '{1}' in the content file '{0}' has {2} lines, more than 'max-lines={3}'")]
+    SnippetTooLong(String, String, usize, usize),
+    #[error("Malformed geoffrey tag; expected {0}")]
+    MalformedTag(String),
+    #[error("The snippet '{0}' is defined in the content file '{3}' and in '{1}' at line {2}; snippets must be unique with 'unique_snippets'")]
+    DuplicateSnippetAcrossFiles(String, PathBuf, usize, PathBuf),
+    #[error("The snippets in the content file '{0}' are nested deeper than the limit of {2}: {1}")]
+    ContentSnippetNestingTooDeep(PathBuf, String, usize),
+    #[error("The snippet is opened again within itself in the content file '{0}': {1}")]
+    ContentSnippetNestedInItself(PathBuf, String),
+    #[error("Invalid inline geoffrey tag; {0}")]
+    InvalidInlineTag(String),
+    #[error("The content snippet '{1}' in the content file '{0}' is not a single line as needed for inline tags and variables, or contains a backtick in an inline tag")]
+    InlineSnippetNotSingleLine(String, String),
+    #[error("The variable '{0}' is not defined or could not be resolved")]
+    UnknownVariable(String),
+    #[error("The option 'tabs' of the tag for '{0}' needs a glob path like 'examples/hello.*'")]
+    TabsWithoutGlob(String),
+    #[error("The content file '{0}' could not be read at the revision '{1}': {2}")]
+    RevisionNotReadable(String, String, String),
+    #[error("The snippets '{1}' from line {2} and '{3}' from line {4} overlap in the content file '{0}'; snippets may only be nested")]
+    ContentSnippetsOverlap(PathBuf, String, usize, String, usize),
+    #[error("Cannot rename the snippet '{0}' to '{1}'; {2}")]
+    SnippetRenameFailed(String, String, String),
+    #[error("The code block was edited by hand since it was synced; sync with '--force' to overwrite it")]
+    CodeBlockEditedByHand,
+    #[error("The code block was edited by hand and its snippet changed; merging both changes left {0} conflicts")]
+    CodeBlockMerged(usize),
+    #[error("Could not list the files changed since '{0}': {1}")]
+    GitDiffError(String, String),
+    #[error("Could not stage '{0}' with 'git add': {1}")]
+    GitAddError(PathBuf, String),
+    #[error("Invalid diff format '{0}'; expected one of 'unified' or 'rdjsonl'")]
+    InvalidDiffFormat(String),
+    #[error(
+        "The anchor '{0}' is already emitted for the tag in line {2} of the markdown file '{1}'"
+    )]
+    DuplicateAnchor(String, PathBuf, usize),
+    #[error("The variable '{0}' in the content file path '{1}' is neither set in the environment nor in the values file of 'geoffrey.toml'")]
+    UnknownPathVariable(String, String),
+    #[error("The crate of the content file '{0}' could not be located with 'cargo metadata': {1}")]
+    CargoMetadataFailed(String, String),
+    #[error("The crate '{0}' of the content file '{1}' is neither a member nor a dependency of the cargo workspace")]
+    UnknownCrate(String, String),
+    #[error("The snippet '{1}' of the content file '{0}' does not compile: {2}")]
+    SnippetDoesNotCompile(String, String, String),
+    #[error("Running the snippet '{1}' of the content file '{0}' for its output failed: {2}")]
+    SnippetRunFailed(String, String, String),
+    #[error("Another geoffrey run holds the lock '{0}'")]
+    LockedByOtherRun(PathBuf),
+    #[error("The content '{0}' could not be read from its source: {1}")]
+    SourceNotReadable(String, String),
+    #[error("line {}, column {}: {1}", .0.line, .0.column)]
+    Located(SourceSpan, Box<GeoffreyError>),
+}
+
+impl GeoffreyError {
+    /// Suggestions to fix the error, e.g. similar snippet names
+    pub fn suggestions(&self) -> &[String] {
+        match self {
+            GeoffreyError::ContentFileNotFound(_, suggestions) => suggestions,
+            GeoffreyError::ContentSnippetNotFound(_, _, suggestions) => suggestions,
+            GeoffreyError::Located(_, error) => error.suggestions(),
+            _ => &[],
+        }
+    }
+
+    /// The stable code of the error in the diagnostics; codes are never changed or reused, new errors get
+    /// new codes
+    pub fn code(&self) -> &'static str {
+        match self {
+            GeoffreyError::DocPathDoesNotExist(..) => "G001",
+            GeoffreyError::NoMarkdownFilesInPath(..) => "G002",
+            GeoffreyError::NotAMarkdownFile(..) => "G003",
+            GeoffreyError::NotATemplateFile(..) => "G004",
+            GeoffreyError::GitToplevelError => "G005",
+            GeoffreyError::RegexError => "G006",
+            GeoffreyError::ContentFileNotFound(..) => "G007",
+            GeoffreyError::ContentFileInvalid(..) => "G008",
+            GeoffreyError::ContentSnippetNotFound(..) => "G009",
+            GeoffreyError::SubSnippetNotNested(..) => "G010",
+            GeoffreyError::ContentSnippetEndTagNotFound(..) => "G011",
+            GeoffreyError::ContentSnippetEmptyTag(..) => "G012",
+            GeoffreyError::ContentSnippetDoubleTag(..) => "G013",
+            GeoffreyError::CodeBlockMustFollowTag(..) => "G014",
+            GeoffreyError::CodeBlockEndMissing(..) => "G015",
+            GeoffreyError::InvalidFallback(..) => "G016",
+            GeoffreyError::InvalidSymlinks(..) => "G017",
+            GeoffreyError::InvalidLineRange(..) => "G018",
+            GeoffreyError::LineRangeOutOfBounds(..) => "G019",
+            GeoffreyError::InvalidTagName(..) => "G020",
+            GeoffreyError::InvalidTagOption(..) => "G021",
+            GeoffreyError::UnknownEnvironment(..) => "G022",
+            GeoffreyError::ConfigError(..) => "G023",
+            GeoffreyError::RepositoryNotFound(..) => "G024",
+            GeoffreyError::RepositoryCloneFailed(..) => "G025",
+            GeoffreyError::UnknownRepository(..) => "G026",
+            GeoffreyError::SelectionOutOfBounds(..) => "G027",
+            GeoffreyError::GeneratedContentFailed(..) => "G028",
+            GeoffreyError::GitSubmodulesError(..) => "G029",
+            GeoffreyError::InvalidGlob(..) => "G030",
+            GeoffreyError::GlobEndTagMissing(..) => "G031",
+            GeoffreyError::Cancelled => "G032",
+            GeoffreyError::InvalidTanglePath(..) => "G033",
+            GeoffreyError::ReportError(..) => "G034",
+            GeoffreyError::IoError(..) => "G035",
+            GeoffreyError::InvalidCompat(..) => "G036",
+            GeoffreyError::DuplicateTagOption(..) => "G037",
+            GeoffreyError::InvalidReplacement(..) => "G038",
+            GeoffreyError::SnippetTooLong(..) => "G039",
+            GeoffreyError::MalformedTag(..) => "G040",
+            GeoffreyError::DuplicateSnippetAcrossFiles(..) => "G041",
+            GeoffreyError::ContentSnippetNestingTooDeep(..) => "G042",
+            GeoffreyError::ContentSnippetNestedInItself(..) => "G043",
+            GeoffreyError::InvalidInlineTag(..) => "G044",
+            GeoffreyError::InlineSnippetNotSingleLine(..) => "G045",
+            GeoffreyError::UnknownVariable(..) => "G046",
+            GeoffreyError::TabsWithoutGlob(..) => "G047",
+            GeoffreyError::RevisionNotReadable(..) => "G048",
+            GeoffreyError::ContentSnippetsOverlap(..) => "G049",
+            GeoffreyError::SnippetRenameFailed(..) => "G050",
+            GeoffreyError::CodeBlockEditedByHand => "G051",
+            GeoffreyError::CodeBlockMerged(..) => "G052",
+            GeoffreyError::GitDiffError(..) => "G053",
+            GeoffreyError::GitAddError(..) => "G054",
+            GeoffreyError::InvalidDiffFormat(..) => "G055",
+            GeoffreyError::DuplicateAnchor(..) => "G056",
+            GeoffreyError::UnknownPathVariable(..) => "G057",
+            GeoffreyError::CargoMetadataFailed(..) => "G058",
+            GeoffreyError::UnknownCrate(..) => "G059",
+            GeoffreyError::SnippetDoesNotCompile(..) => "G060",
+            GeoffreyError::SnippetRunFailed(..) => "G061",
+            GeoffreyError::LockedByOtherRun(..) => "G062",
+            GeoffreyError::SourceNotReadable(..) => "G063",
+            GeoffreyError::Located(_, error) => error.code(),
+        }
+    }
+
+    /// Attaches the location in the source file at which the error occurred; an existing location is kept
+    pub fn at(self, span: SourceSpan) -> Self {
+        match self {
+            GeoffreyError::Located(..) => self,
+            error => GeoffreyError::Located(span, Box::new(error)),
+        }
+    }
+}
+
+/// The location of an error in a file with the text of the line, to show the character at which it occurred
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SourceSpan {
+    /// the line, starting at 1
+    pub line: usize,
+    /// the column in characters, starting at 1
+    pub column: usize,
+    /// the text of the line without the line ending
+    pub excerpt: String,
+}
+
+impl SourceSpan {
+    /// The span of the character at the byte `offset` of `text`, which is the line `line`
+    pub fn new(line: usize, text: &str, offset: usize) -> Self {
+        let excerpt = text.trim_end_matches(['\r', '\n']);
+        let offset = (0..=offset.min(excerpt.len()))
+            .rev()
+            .find(|offset| excerpt.is_char_boundary(*offset))
+            .unwrap_or(0);
+        Self {
+            line,
+            column: excerpt[..offset].chars().count() + 1,
+            excerpt: excerpt.to_owned(),
+        }
+    }
+}
+
+/// The excerpt with a caret below the column, like
+/// ```text
+///   |
+/// 3 | <!-- [geoffrey] [main.cpp] [main] bad -->
+///   |                                   ^
+/// ```
+impl fmt::Display for SourceSpan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let gutter = " ".repeat(self.line.to_string().len());
+        // tabs are kept so that the caret is aligned like the excerpt
+        let indentation = self
+            .excerpt
+            .chars()
+            .take(self.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+        writeln!(f, "{} |", gutter)?;
+        writeln!(f, "{} | {}", self.line, self.excerpt)?;
+        write!(f, "{} | {}^", gutter, indentation)
+    }
+}
+
+/// A problem which does not prevent the sync, like an empty snippet; with '--deny-warnings' it fails the run
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum GeoffreyWarning {
+    #[error("The language '{0}' of the code block does not match the content file '{1}'")]
+    FenceLanguageMismatch(String, String),
+    #[error("The content snippet '{1}' in the content file '{0}' is empty")]
+    EmptySnippet(String, String),
+    #[error("The content snippet '{1}' in the content file '{0}' is already embedded at line {2}")]
+    DoubleReferencedSnippet(String, String, usize),
+    #[error("The sub-snippet '{0}' has no effect; {1}")]
+    UnusedSubSnippet(String, String),
+    #[error("Only whitespace changed in '{0}'; keeping the code block")]
+    WhitespaceOnlyChange(String),
+    #[error("The content snippet '{1}' was not found in '{0}'; {2}")]
+    SnippetFallback(String, String, String),
+    #[error("The glob '{0}' does not match any file")]
+    GlobWithoutMatch(String),
+    #[error("'{0}' diverges structurally from '{1}'; the snippets or their nesting differ")]
+    ShadowDiverges(PathBuf, PathBuf),
+    #[error("The file '{0}' is skipped since {1}")]
+    FileSkipped(PathBuf, String),
+    #[error("The snippet name '{0}' does not match the pattern '{1}'")]
+    TagNameMismatch(String, String),
+    #[error("{1} lines of the code block from '{0}' are wider than {2} columns, the first one is line {3}")]
+    LinesTooWide(String, usize, usize, usize),
+    #[error("The content file '{0}' could not be read; keeping the code block")]
+    ContentFallback(String),
+    #[error(
+        "The code block from '{0}' was edited by hand and merged with the changes of its snippet"
+    )]
+    EditsMerged(String),
+}
+
+impl GeoffreyWarning {
+    /// The stable code of the warning in the diagnostics, like the ones of `GeoffreyError::code`
+    pub fn code(&self) -> &'static str {
+        match self {
+            GeoffreyWarning::FenceLanguageMismatch(..) => "W001",
+            GeoffreyWarning::EmptySnippet(..) => "W002",
+            GeoffreyWarning::DoubleReferencedSnippet(..) => "W003",
+            GeoffreyWarning::UnusedSubSnippet(..) => "W004",
+            GeoffreyWarning::WhitespaceOnlyChange(..) => "W005",
+            GeoffreyWarning::SnippetFallback(..) => "W006",
+            GeoffreyWarning::GlobWithoutMatch(..) => "W007",
+            GeoffreyWarning::ShadowDiverges(..) => "W008",
+            GeoffreyWarning::FileSkipped(..) => "W009",
+            GeoffreyWarning::TagNameMismatch(..) => "W010",
+            GeoffreyWarning::LinesTooWide(..) => "W011",
+            GeoffreyWarning::ContentFallback(..) => "W012",
+            GeoffreyWarning::EditsMerged(..) => "W013",
+        }
+    }
+}
+
+/// An error which occurred while processing a specific file
+#[derive(Debug)]
+pub struct FileError {
+    pub path: PathBuf,
+    pub error: GeoffreyError,
+    /// the location of the error in the file, if known
+    pub span: Option<Box<SourceSpan>>,
+}
+
+impl FileError {
+    /// The error of the file `path`; the location of a `GeoffreyError::Located` is moved to `span`
+    pub fn new(path: PathBuf, error: GeoffreyError) -> Self {
+        match error {
+            GeoffreyError::Located(span, error) => Self {
+                path,
+                error: *error,
+                span: Some(Box::new(span)),
+            },
+            error => Self {
+                path,
+                error,
+                span: None,
+            },
+        }
+    }
+
+    /// The number of files with failures; a file may have several
+    pub fn distinct_files(failures: &[FileError]) -> usize {
+        failures
+            .iter()
+            .map(|failure| &failure.path)
+            .collect::<HashSet<&PathBuf>>()
+            .len()
+    }
+
+    /// Sorts the failures by path and location, keeping the order of the failures at the same location, so
+    /// that the results of the parallel processing are reported in the same order on every run
+    pub fn sort(failures: &mut [FileError]) {
+        failures.sort_by(|a, b| {
+            let location = |failure: &FileError| {
+                let span = failure.span.as_deref();
+                (
+                    failure.path.clone(),
+                    span.map(|span| span.line),
+                    span.map(|span| span.column),
+                )
+            };
+            location(a).cmp(&location(b))
+        });
+    }
+}
+
+/// A problem with a geoffrey tag of a markdown file, like a missing content file or snippet
+#[derive(Debug)]
+pub struct TagProblem {
+    pub md_file: PathBuf,
+    /// the line of the tag, starting at 1
+    pub line: usize,
+    pub error: GeoffreyError,
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.span {
+            Some(span) => write!(
+                f,
+                "{}:{}:{}: [{}] {}\n{}",
+                self.path.display(),
+                span.line,
+                span.column,
+                self.error.code(),
+                self.error,
+                span
+            ),
+            None => write!(
+                f,
+                "{}: [{}] {}",
+                self.path.display(),
+                self.error.code(),
+                self.error
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn error_codes_are_stable() {
+        // the codes are part of the compatibility policy, see '--compat'; never change this test
+        assert_eq!(
+            GeoffreyError::DocPathDoesNotExist(PathBuf::new()).code(),
+            "G001"
+        );
+        assert_eq!(
+            GeoffreyError::ContentFileNotFound(String::new(), Vec::new()).code(),
+            "G007"
+        );
+        assert_eq!(
+            GeoffreyError::ContentSnippetNotFound(String::new(), String::new(), Vec::new()).code(),
+            "G009"
+        );
+        assert_eq!(GeoffreyError::Cancelled.code(), "G032");
+        assert_eq!(
+            GeoffreyError::IoError(std::io::Error::other("io")).code(),
+            "G035"
+        );
+        assert_eq!(GeoffreyError::InvalidCompat(String::new()).code(), "G036");
+        assert_eq!(
+            GeoffreyError::DuplicateTagOption(String::new()).code(),
+            "G037"
+        );
+        assert_eq!(
+            GeoffreyError::InvalidReplacement(String::new(), String::new()).code(),
+            "G038"
+        );
+        assert_eq!(
+            GeoffreyError::SnippetTooLong(String::new(), String::new(), 0, 0).code(),
+            "G039"
+        );
+        assert_eq!(GeoffreyError::MalformedTag(String::new()).code(), "G040");
+        assert_eq!(
+            GeoffreyError::DuplicateSnippetAcrossFiles(
+                String::new(),
+                PathBuf::new(),
+                0,
+                PathBuf::new()
+            )
+            .code(),
+            "G041"
+        );
+        assert_eq!(
+            GeoffreyError::ContentSnippetNestingTooDeep(PathBuf::new(), String::new(), 0).code(),
+            "G042"
+        );
+        assert_eq!(
+            GeoffreyError::ContentSnippetNestedInItself(PathBuf::new(), String::new()).code(),
+            "G043"
+        );
+        assert_eq!(
+            GeoffreyError::InvalidInlineTag(String::new()).code(),
+            "G044"
+        );
+        assert_eq!(
+            GeoffreyError::InlineSnippetNotSingleLine(String::new(), String::new()).code(),
+            "G045"
+        );
+        assert_eq!(GeoffreyError::UnknownVariable(String::new()).code(), "G046");
+        assert_eq!(GeoffreyError::TabsWithoutGlob(String::new()).code(), "G047");
+        assert_eq!(
+            GeoffreyError::RevisionNotReadable(String::new(), String::new(), String::new()).code(),
+            "G048"
+        );
+        assert_eq!(
+            GeoffreyError::ContentSnippetsOverlap(
+                PathBuf::new(),
+                String::new(),
+                0,
+                String::new(),
+                0
+            )
+            .code(),
+            "G049"
+        );
+        assert_eq!(
+            GeoffreyError::SnippetRenameFailed(String::new(), String::new(), String::new()).code(),
+            "G050"
+        );
+        assert_eq!(GeoffreyError::CodeBlockEditedByHand.code(), "G051");
+        assert_eq!(GeoffreyError::CodeBlockMerged(0).code(), "G052");
+        assert_eq!(
+            GeoffreyError::GitDiffError(String::new(), String::new()).code(),
+            "G053"
+        );
+        assert_eq!(
+            GeoffreyError::GitAddError(PathBuf::new(), String::new()).code(),
+            "G054"
+        );
+        assert_eq!(
+            GeoffreyError::InvalidDiffFormat(String::new()).code(),
+            "G055"
+        );
+        assert_eq!(
+            GeoffreyError::DuplicateAnchor(String::new(), PathBuf::new(), 0).code(),
+            "G056"
+        );
+        assert_eq!(
+            GeoffreyError::UnknownPathVariable(String::new(), String::new()).code(),
+            "G057"
+        );
+        assert_eq!(
+            GeoffreyError::CargoMetadataFailed(String::new(), String::new()).code(),
+            "G058"
+        );
+        assert_eq!(
+            GeoffreyError::UnknownCrate(String::new(), String::new()).code(),
+            "G059"
+        );
+        assert_eq!(
+            GeoffreyError::SnippetDoesNotCompile(String::new(), String::new(), String::new())
+                .code(),
+            "G060"
+        );
+        assert_eq!(
+            GeoffreyError::SnippetRunFailed(String::new(), String::new(), String::new()).code(),
+            "G061"
+        );
+        assert_eq!(
+            GeoffreyError::LockedByOtherRun(PathBuf::new()).code(),
+            "G062"
+        );
+        assert_eq!(
+            GeoffreyError::SourceNotReadable(String::new(), String::new()).code(),
+            "G063"
+        );
+        assert_eq!(
+            GeoffreyWarning::FenceLanguageMismatch(String::new(), String::new()).code(),
+            "W001"
+        );
+        assert_eq!(
+            GeoffreyWarning::ShadowDiverges(PathBuf::new(), PathBuf::new()).code(),
+            "W008"
+        );
+        assert_eq!(
+            GeoffreyWarning::FileSkipped(PathBuf::new(), String::new()).code(),
+            "W009"
+        );
+        assert_eq!(
+            GeoffreyWarning::TagNameMismatch(String::new(), String::new()).code(),
+            "W010"
+        );
+        assert_eq!(
+            GeoffreyWarning::LinesTooWide(String::new(), 0, 0, 0).code(),
+            "W011"
+        );
+        assert_eq!(
+            GeoffreyWarning::ContentFallback(String::new()).code(),
+            "W012"
+        );
+        assert_eq!(GeoffreyWarning::EditsMerged(String::new()).code(), "W013");
+    }
+
+    #[test]
+    fn located_errors_show_the_excerpt_with_a_caret() {
+        let error = GeoffreyError::MalformedTag("'-->'".to_owned())
+            .at(SourceSpan::new(12, "\tä [x\r\n", 4))
+            .at(SourceSpan::new(1, "", 0));
+        assert_eq!(error.code(), "G040");
+
+        let failure = FileError::new(PathBuf::from("doc.md"), error);
+        assert_eq!(failure.span.as_ref().map(|span| span.column), Some(4));
+        assert_eq!(
+            failure.to_string(),
+            "doc.md:12:4: [G040] Malformed geoffrey tag; expected '-->'\n   |\n12 | \tä [x\n   | \t  ^"
+        );
+    }
 }
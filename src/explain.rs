@@ -0,0 +1,713 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Longer descriptions of the diagnostic codes with typical fixes, see `geoffrey explain <code>`.
+
+/// The explanations of the codes of `GeoffreyError` and `GeoffreyWarning`, sorted by code
+const EXPLANATIONS: &[(&str, &str)] = &[
+    (
+        "G001",
+        "The doc path passed to geoffrey does not exist or cannot be read.
+
+Typical fixes:
+- check the spelling of the path and the working directory geoffrey is run from
+- check the read permissions of the path",
+    ),
+    (
+        "G002",
+        "The doc path does not contain any markdown files, or they cannot be read. Markdown files have the
+extension '.md', '.mdx', '.markdown' or '.mdown'; C++ headers and Java files are also scanned for tags in
+their documentation comments.
+
+Typical fixes:
+- pass the directory with the documentation instead of its parent or a sibling
+- check that the files are not excluded, e.g. by '--symlinks skip'",
+    ),
+    (
+        "G003",
+        "The doc path is a file, but not a markdown file.
+
+Typical fixes:
+- pass a file with a markdown extension or the directory containing it",
+    ),
+    (
+        "G004",
+        "'geoffrey render' expects markdown templates ending with '.md.tpl', but the doc path is a file with
+another extension.
+
+Typical fixes:
+- rename the template to end with '.md.tpl'
+- use 'geoffrey <doc path>' to sync markdown files in place",
+    ),
+    (
+        "G005",
+        "The git toplevel of the documentation could not be determined. The content paths of the tags are
+relative to it, unless they start with './' or '../'.
+
+Typical fixes:
+- check that git is installed and the documentation is within a git repository
+- pass '--content-root' to resolve the content paths without git",
+    ),
+    (
+        "G006",
+        "A regular expression used internally could not be built. This is a bug in geoffrey.
+
+Typical fixes:
+- report the issue with the geoffrey version and the tag or content file which caused it",
+    ),
+    (
+        "G007",
+        "The content file referenced by a tag does not exist. Content paths are relative to the git toplevel,
+to the content roots if configured, or to the markdown file if they start with './' or '../'.
+
+Typical fixes:
+- check the spelling of the path in the tag
+- update the tag if the content file was moved or renamed; renames in git are suggested and rewritten
+  with '--fix-paths'
+- check the 'content_roots' in the geoffrey.toml or the '--content-root' arguments",
+    ),
+    (
+        "G008",
+        "The content file referenced by a tag could not be parsed, e.g. because of unbalanced snippet markers.
+The error of the content file itself is reported separately.
+
+Typical fixes:
+- fix the error reported for the content file",
+    ),
+    (
+        "G009",
+        "The snippet referenced by a tag is not marked in the content file. Similarly named snippets are
+suggested in the message.
+
+Typical fixes:
+- check the spelling of the snippet name in the tag
+- add the snippet markers to the content file, e.g. with 'geoffrey mark'
+- use '--fallback full-file' or '--fallback skip' while the snippet is being renamed",
+    ),
+    (
+        "G010",
+        "A sub-snippet of an elided tag like '[[main] [sub]]' is not nested within the main snippet.
+
+Typical fixes:
+- check that the sub-snippet markers are between the markers of the main snippet
+- remove the sub-snippet from the tag",
+    ),
+    (
+        "G011",
+        "A snippet in a content file is opened by a marker like '//! [name]' but not closed by a second marker
+with the same name.
+
+Typical fixes:
+- add the closing marker at the end of the snippet
+- check that nested snippets are closed before the snippet containing them",
+    ),
+    (
+        "G012",
+        "A snippet marker in a content file has no name, like '//! []'.
+
+Typical fixes:
+- give the snippet a name or remove the marker",
+    ),
+    (
+        "G013",
+        "Two snippets in a content file have the same name. Snippet names must be unique per file.
+
+Typical fixes:
+- rename one of the snippets and update the tags referencing it",
+    ),
+    (
+        "G014",
+        "A geoffrey tag must be immediately followed by a fenced code block, which is replaced by the snippet.
+
+Typical fixes:
+- add an empty code block like '```cpp' and '```' right after the tag
+- remove empty lines between the tag and the code block",
+    ),
+    (
+        "G015",
+        "The code block following a tag is not closed before the end of the file.
+
+Typical fixes:
+- add the closing fence with at least as many backticks or tildes as the opening fence",
+    ),
+    (
+        "G016",
+        "The value of '--fallback' is not supported.
+
+Typical fixes:
+- use one of 'error', 'full-file' or 'skip'",
+    ),
+    (
+        "G017",
+        "The value of '--symlinks' is not supported.
+
+Typical fixes:
+- use one of 'follow' or 'skip'",
+    ),
+    (
+        "G018",
+        "A line range, e.g. of the 'lines' selector or of 'geoffrey mark', is not valid.
+
+Typical fixes:
+- use 'first-last' like '10-20' or a single line, starting at line 1",
+    ),
+    (
+        "G019",
+        "A line range exceeds the number of lines of the file.
+
+Typical fixes:
+- update the line range after the file was shortened",
+    ),
+    (
+        "G020",
+        "The name of a snippet marker is not valid.
+
+Typical fixes:
+- use a name of letters, digits, whitespace, '.', '-' and ':'",
+    ),
+    (
+        "G021",
+        "An option after the snippet of a tag is unknown or has an invalid value. The options are
+'dedent', 'keep-markers', 'context=<n>', 'env=<name>', 'highlight=hugo|mkdocs',
+'tabs=docusaurus|mkdocs', 'rev=<old>..<new>', 'max-lines=<n>' and 'replace=\"<pattern> -> <replacement>\"'.
+
+Typical fixes:
+- check the spelling of the option
+- quote values containing whitespace",
+    ),
+    (
+        "G022",
+        "The environment of a tag's 'env' option is not configured in the geoffrey.toml.
+
+Typical fixes:
+- add the environment to the '[envs]' table of the geoffrey.toml
+- check the spelling of the environment in the tag",
+    ),
+    (
+        "G023",
+        "The geoffrey.toml could not be read or is not valid.
+
+Typical fixes:
+- fix the TOML syntax or the setting named in the message",
+    ),
+    (
+        "G024",
+        "A repository configured in the geoffrey.toml does not exist locally and has no remote to clone from.
+
+Typical fixes:
+- clone the repository to the configured path
+- add the remote of the repository to the geoffrey.toml",
+    ),
+    (
+        "G025",
+        "A repository configured in the geoffrey.toml could not be cloned.
+
+Typical fixes:
+- check the remote URL and the network access
+- check the credentials for private repositories",
+    ),
+    (
+        "G026",
+        "A content path like 'repo:path/to/file' refers to a repository which is not configured, and no file
+with this path exists.
+
+Typical fixes:
+- add the repository to the '[repos]' table of the geoffrey.toml
+- check the spelling of the repository name in the tag",
+    ),
+    (
+        "G027",
+        "A selector like 'lines:40-78' selected lines beyond the end of the content file.
+
+Typical fixes:
+- update the selector after the content file was shortened
+- mark the lines as named snippet, which moves with the code",
+    ),
+    (
+        "G028",
+        "The command configured to generate content failed.
+
+Typical fixes:
+- run the configured command manually and fix its error",
+    ),
+    (
+        "G029",
+        "The git submodules could not be listed for '--recurse-submodules'.
+
+Typical fixes:
+- check that the doc path is within a git repository and 'git submodule' works there",
+    ),
+    (
+        "G030",
+        "The glob pattern of a tag is not valid.
+
+Typical fixes:
+- check the pattern, e.g. for unbalanced brackets",
+    ),
+    (
+        "G031",
+        "The code blocks of a glob tag are generated up to an end tag, which is missing.
+
+Typical fixes:
+- add the end tag after the generated code blocks",
+    ),
+    (
+        "G032",
+        "The run was cancelled, e.g. by an editor because the documentation changed during the run.
+
+Typical fixes:
+- none; the next run picks up the changes",
+    ),
+    (
+        "G033",
+        "The path of a tangle tag is absolute or leaves the output directory.
+
+Typical fixes:
+- use a relative path without '..'",
+    ),
+    (
+        "G034",
+        "The JSON or HTML report could not be written.
+
+Typical fixes:
+- check that the directory of the report exists and is writable",
+    ),
+    (
+        "G035",
+        "A file could not be read or written.
+
+Typical fixes:
+- check the permissions of the files and directories named in the log",
+    ),
+    (
+        "G036",
+        "The version passed to '--compat' is not supported.
+
+Typical fixes:
+- use '--compat 1'",
+    ),
+    (
+        "G037",
+        "A tag option which may be given only once is given more than once.
+
+Typical fixes:
+- remove the duplicate option from the tag",
+    ),
+    (
+        "G038",
+        "The value of a 'replace' option is not '<pattern> -> <replacement>' or the pattern is not a valid
+regular expression.
+
+Typical fixes:
+- separate pattern and replacement by ' -> ' with spaces
+- escape special characters of the pattern like '(' or '.'",
+    ),
+    (
+        "G039",
+        "The snippet has more lines than allowed by the 'max-lines' option of the tag.
+
+Typical fixes:
+- embed a smaller snippet or elide nested snippets with '[[main] [sub]]'
+- raise the limit of the tag",
+    ),
+    (
+        "G040",
+        "A line starts like a geoffrey tag, e.g. with '<!-- [geoffrey]', but cannot be parsed. The caret points
+to the character at which the parsing broke.
+
+Typical fixes:
+- enclose the content path and the snippet in brackets, like '[path/to/file] [snippet]'
+- close the tag with '-->', or '*/}' for JSX comments, on the same line",
+    ),
+    (
+        "G041",
+        "With 'unique_snippets = true' in the geoffrey.toml, a snippet name may only be defined in one of the
+content files referenced by the documentation, but it is defined in two of them. Both content files are
+treated as invalid.
+
+Typical fixes:
+- rename one of the snippets and update the tags referencing it",
+    ),
+    (
+        "G042",
+        "The snippets of a content file are nested deeper than 'max_nesting_depth' from 'geoffrey.toml', which
+defaults to 32. The error shows the chain of snippets up to the marker exceeding the limit, which often
+points to begin markers without matching end markers.
+
+Typical fixes:
+- add the missing end markers
+- raise 'max_nesting_depth' if the nesting is intended",
+    ),
+    (
+        "G043",
+        "A snippet marker opens a snippet again within itself, e.g. '[a]' within '[b]' within '[a]'. Snippets may
+only be nested, not overlap. The error shows the chain of snippets up to the repeated one.
+
+Typical fixes:
+- close the inner snippets before the end marker of the outer one
+- rename one of the snippets if they are different snippets",
+    ),
+    (
+        "G044",
+        "An inline tag like '<!--[geoffrey-inline][path][snippet]-->' is not followed by an inline code span
+or references a glob. The value of the code span right after the tag is synced with the snippet.
+
+Typical fixes:
+- add a code span like '`8080`' directly after the tag
+- use a tag followed by a code block for globs",
+    ),
+    (
+        "G045",
+        "The snippet of an inline tag or of a variable in 'geoffrey.toml' does not render to a single line, or the
+line of an inline tag contains a backtick which would end the inline code span.
+
+Typical fixes:
+- move the snippet markers around the one line with the value
+- use a tag followed by a code block for longer snippets",
+    ),
+    (
+        "G046",
+        "A '{{geoffrey:name}}' placeholder in a synced code block or a markdown template references a variable
+which is neither in the values file nor in the snippet variables of 'geoffrey.toml', or whose snippet could
+not be resolved, which is then reported as well.
+
+Typical fixes:
+- fix the name of the variable in the placeholder
+- add the variable to the values file or to '[variables.snippets]'",
+    ),
+    (
+        "G047",
+        "The option 'tabs' renders the code blocks of the files matched by a glob as a tab group, so it needs a
+tag whose path is a glob like 'examples/hello.*'.
+
+Typical fixes:
+- replace the extension of the path by '*' to match the files of all languages
+- remove the option from tags for a single file",
+    ),
+    (
+        "G048",
+        "A tag with the option 'rev=<old>..<new>' renders the diff of the snippet between two git revisions,
+but the content file could not be read at one of them with 'git show', e.g. because the revision does not
+exist or the file was added later.
+
+Typical fixes:
+- fetch the tags or branches of the revisions
+- check the spelling of the revisions
+- use a revision at which the content file exists",
+    ),
+    (
+        "G049",
+        "Two snippets of a content file overlap, e.g. '[a]' begins, '[b]' begins and '[a]' ends before '[b]'.
+Snippets may only be nested. The error points to the end marker within the other snippet and shows the lines
+of both begin markers.
+
+Typical fixes:
+- move the end marker of the inner snippet before the one of the outer snippet
+- split the snippets so that they do not share lines",
+    ),
+    (
+        "G050",
+        "'geoffrey rename-tag' cannot rename the snippet, since it is not defined in the content files referenced
+by the documentation, the new name is already defined in one of these files or the new name is not valid in
+geoffrey tags.
+
+Typical fixes:
+- check the spelling of the old name with 'geoffrey where-used'
+- choose a new name of letters, digits, whitespace, '.', '-' and ':' which is not used yet",
+    ),
+    (
+        "G051",
+        "With 'guard_edits' in the geoffrey.toml, each synced code block is recorded with the hash of its content
+in the option 'hash' of its tag. The content of the code block does not match this hash anymore, i.e. it was
+edited by hand, and is therefore not overwritten.
+
+Typical fixes:
+- move the manual changes into the content file or a tag option like 'replace'
+- keep the code block with '<!-- [geoffrey-ignore] -->' before its tag
+- discard the manual changes by syncing with '--force'",
+    ),
+    (
+        "G052",
+        "With 'guard_edits' in the geoffrey.toml, a code block was edited by hand and its snippet changed since the
+last sync. Both changes were merged line by line, with the code block as last synced from the snapshot directory
+'.geoffrey-snapshots' as base, and written with conflicts: lines changed differently on both sides are kept from
+both sides between '<<<<<<< documentation', '=======' and '>>>>>>> content' lines. Merges without conflicts are
+reported as warning W013.
+
+Typical fixes:
+- review the merged code block and resolve the conflicts
+- move the manual changes into the content file and sync with '--force'",
+    ),
+    (
+        "G053",
+        "The files changed since the revision given with '--since' could not be listed with 'git diff'.
+
+Typical fixes:
+- check that the doc path is within a git repository
+- check that the revision exists, e.g. fetch the base branch in shallow CI clones",
+    ),
+    (
+        "G054",
+        "A markdown file changed by the sync could not be staged with 'git add' for '--stage'. The file itself was
+written.
+
+Typical fixes:
+- check that the file is within a git repository and not ignored by a '.gitignore'
+- remove a stale '.git/index.lock' left by a crashed git process",
+    ),
+    (
+        "G055",
+        "The value of 'geoffrey diff --format' is not supported.
+
+Typical fixes:
+- use one of 'unified' or 'rdjsonl'",
+    ),
+    (
+        "G056",
+        "Two tags with the 'anchor' option get the same anchor id, so that links to it would be ambiguous. Without
+an explicit id, the id is derived from the snippet name, e.g. 'snippet-main', therefore embedding a snippet twice
+with 'anchor' gives the same id twice.
+
+Typical fixes:
+- give one of the tags an explicit id like 'anchor=main-setup'
+- keep the 'anchor' option only on the tag which should be linked to",
+    ),
+    (
+        "G057",
+        "A '${name}' in the content file path of a tag is replaced by the environment variable 'name' or, if it
+is not set, by the variable 'name' of the values file configured in '[variables]' of 'geoffrey.toml'. The
+variables from snippets are resolved only after the tags are parsed and cannot be used in paths.
+
+Typical fixes:
+- set the environment variable, e.g. in the CI job running geoffrey
+- add the variable to the values file",
+    ),
+    (
+        "G058",
+        "The content paths like 'crate://serde/src/lib.rs' are resolved with 'cargo metadata', which is run in the
+directory of the geoffrey.toml and failed there, e.g. because it is not within a cargo workspace or the
+dependencies could not be fetched.
+
+Typical fixes:
+- run 'cargo metadata --format-version 1' in the directory of the geoffrey.toml and fix its error
+- fetch the dependencies with 'cargo fetch' before running geoffrey offline",
+    ),
+    (
+        "G059",
+        "A content path like 'crate://name/path/to/file' refers to a crate which is neither a member of the cargo
+workspace of the geoffrey.toml nor one of its dependencies.
+
+Typical fixes:
+- check the spelling of the crate name, which is the package name from its Cargo.toml
+- add the crate as dependency of a workspace member",
+    ),
+    (
+        "G060",
+        "With '--verify', each synced snippet is written to a file in a temporary directory and compiled with the
+command configured in '[verify]' of the geoffrey.toml for the language of its code block or the extension of
+its content file. The command failed, therefore the snippet would be a broken example and the markdown file
+is not written.
+
+Typical fixes:
+- fix the snippet in the content file, or choose snippet markers around code which compiles on its own
+- adjust the command, e.g. add include paths or compiler flags",
+    ),
+    (
+        "G061",
+        "The code block of an output tag like '<!-- [geoffrey-output] [path] [snippet] -->' is the standard output
+of the snippet, which is written to a file in a temporary directory and run with the command configured in
+'[run]' of the geoffrey.toml for the extension of the content file. No command is configured, or the command
+failed.
+
+Typical fixes:
+- configure a command like 'py = \"python3 {file}\"' in '[run]'
+- fix the snippet or the command, e.g. run it manually with the snippet in a file",
+    ),
+    (
+        "G062",
+        "Another geoffrey run, e.g. the daemon or a sync in a second terminal, is writing the same documentation and
+holds the lock file 'geoffrey.lock' in the git directory, or in the documentation folder outside of a git
+repository. With '--no-wait', geoffrey fails instead of waiting for the other run. The lock is released when the
+other run exits, also if it crashed.
+
+Typical fixes:
+- run again after the other run finished, or run without '--no-wait' to wait for it",
+    ),
+    (
+        "G063",
+        "A tag references content from a source like 'git:v1.0:src/main.rs' or 'https://example.com/main.rs', but
+the source could not read it, e.g. because the revision or the url does not exist or the network is not
+reachable. The built-in sources are 'file', 'git', 'http', 'https' and 'cmd' for the output of a command; library
+users may register more. A command of a 'cmd' tag is only run if it starts with one of the 'allowed_commands' of
+the 'geoffrey.toml'.
+
+Typical fixes:
+- check the revision, the path, the url or the command in the tag
+- add the command to 'allowed_commands'
+- for 'http' and 'https', check that 'curl' is installed and the url can be downloaded",
+    ),
+    (
+        "W001",
+        "The language of the code block does not fit the extension of the content file, e.g. 'python' for a
+'.cpp' file. Generic languages like 'text' fit any file.
+
+Typical fixes:
+- correct the language after the opening fence
+- check that the tag references the intended content file",
+    ),
+    (
+        "W002",
+        "The embedded snippet is empty, e.g. because its markers are adjacent.
+
+Typical fixes:
+- move the snippet markers around the intended lines",
+    ),
+    (
+        "W003",
+        "The same snippet is embedded the same way more than once in a markdown file.
+
+Typical fixes:
+- remove the repeated code block or link to the first one",
+    ),
+    (
+        "W004",
+        "A sub-snippet of an elided tag has no effect since it is listed twice or is within another sub-snippet
+which is kept anyway.
+
+Typical fixes:
+- remove the sub-snippet from the tag",
+    ),
+    (
+        "W005",
+        "With '--ignore-whitespace', the code block differs from the content only in whitespace and is kept.
+
+Typical fixes:
+- sync without '--ignore-whitespace' once to update the indentation",
+    ),
+    (
+        "W006",
+        "The snippet of a tag was not found and the fallback of '--fallback' or of the 'fallback' option of the tag
+was used instead.
+
+Typical fixes:
+- fix the snippet name in the tag or add the snippet markers to the content file",
+    ),
+    (
+        "W007",
+        "The glob of a tag does not match any file, so no code blocks are generated.
+
+Typical fixes:
+- check the pattern and the location of the content files",
+    ),
+    (
+        "W008",
+        "A file in the overrides directory shadows a content file but has different snippets or nesting, so it
+is likely outdated.
+
+Typical fixes:
+- update the shadowing file to the snippets of the original",
+    ),
+    (
+        "W009",
+        "A markdown or content file is larger than the configured limit or contains binary data, i.e. a NUL byte
+near the start, and is not read. The code blocks referencing a skipped content file are kept as they are.
+
+Typical fixes:
+- raise 'max_content_file_size' or 'max_md_file_size' in 'geoffrey.toml' if the file is legitimately large
+- reference a text file instead of a binary one",
+    ),
+    (
+        "W010",
+        "A snippet name in a content file or in a geoffrey tag does not match the 'tag_pattern' from
+'geoffrey.toml', e.g. 'mainLoop' for the kebab-case pattern '^[a-z0-9]+(-[a-z0-9]+)*$'. Selectors like
+'lines:4-8' in tags are not checked.
+
+Typical fixes:
+- rename the snippet markers and the tags referencing them
+- adjust 'tag_pattern' if the convention changed",
+    ),
+    (
+        "W011",
+        "Lines of a synced code block are wider than the 'max-width' of the tag or the 'max_width' from
+'geoffrey.toml'. Wide lines are cut off or overflow the page when the documentation is rendered to PDF or
+printed. The width is counted in characters.
+
+Typical fixes:
+- shorten the lines in the content file
+- add the 'wrap' option to the tag to wrap the lines at whitespace; lines with quotes are not wrapped",
+    ),
+    (
+        "W012",
+        "The content file of a tag was not found or could not be parsed, and the code block is kept as it is
+since the tag falls back to 'skip' with '--fallback skip' or the 'fallback=skip' option. The content file
+itself is not reported as failed then.
+
+Typical fixes:
+- fix the path in the tag once the content file is in place again, e.g. after a refactoring",
+    ),
+    (
+        "W013",
+        "With 'guard_edits' in the geoffrey.toml, a code block was edited by hand and its snippet changed since the
+last sync. Both changes were merged line by line without conflicts, with the code block as last synced from the
+snapshot directory '.geoffrey-snapshots' as base. The merged code block is written and its hash recorded, so the
+manual edits are kept by the following syncs.
+
+Typical fixes:
+- review the merged code block
+- move the manual changes into the content file and sync with '--force'",
+    ),
+];
+
+/// The explanation of a diagnostic code like 'G007' or 'W001'; the number may have leading zeros or be
+/// lowercase like 'g0007'
+pub fn explanation(code: &str) -> Option<&'static str> {
+    let code = normalized(code)?;
+    EXPLANATIONS
+        .iter()
+        .find(|(explained, _)| *explained == code)
+        .map(|(_, explanation)| *explanation)
+}
+
+/// The code in the form 'G007'
+fn normalized(code: &str) -> Option<String> {
+    let code = code.trim();
+    let prefix = code.chars().next()?.to_ascii_uppercase();
+    let number = code[1..].parse::<u16>().ok()?;
+    Some(format!("{}{:03}", prefix, number))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::error::{GeoffreyError, GeoffreyWarning};
+
+    #[test]
+    fn every_code_is_explained_once() {
+        let codes = EXPLANATIONS
+            .iter()
+            .map(|(code, _)| code.to_string())
+            .collect::<Vec<String>>();
+        let expected = (1..=63)
+            .map(|number| format!("G{:03}", number))
+            .chain((1..=13).map(|number| format!("W{:03}", number)))
+            .collect::<Vec<String>>();
+        assert_eq!(codes, expected);
+
+        // the last codes of the enums, which must be extended together with the explanations
+        assert_eq!(
+            GeoffreyError::SourceNotReadable(String::new(), String::new()).code(),
+            "G063"
+        );
+        assert_eq!(GeoffreyWarning::EditsMerged(String::new()).code(), "W013");
+    }
+
+    #[test]
+    fn codes_are_normalized() {
+        assert!(explanation("G007").is_some_and(|text| text.contains("content file")));
+        assert_eq!(explanation("g0007"), explanation("G007"));
+        assert_eq!(explanation("W1"), explanation("W001"));
+        assert_eq!(explanation("G999"), None);
+        assert_eq!(explanation("GXYZ"), None);
+        assert_eq!(explanation(""), None);
+    }
+}
@@ -0,0 +1,215 @@
+// SPDX-License-Identifier: Apache-2.0
+
+/// The fence which opens a code block, like '```cpp' or '~~~'
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct CodeFence {
+    marker: char,
+    len: usize,
+}
+
+impl CodeFence {
+    /// The fence opening a code block in `line`, i.e. at least three backticks or tildes followed by an optional
+    /// info string; the info string of a backtick fence must not contain backticks
+    pub(crate) fn open(line: &str) -> Option<Self> {
+        let line = line.trim_start();
+        let marker = line.chars().next().filter(|c| *c == '`' || *c == '~')?;
+        let len = line.chars().take_while(|c| *c == marker).count();
+        if len < 3 || (marker == '`' && line[len..].contains('`')) {
+            return None;
+        }
+
+        Some(Self { marker, len })
+    }
+
+    /// Whether `line` closes the code block, i.e. consists of at least as many of the same fence characters
+    pub(crate) fn closes(&self, line: &str) -> bool {
+        let line = line.trim();
+        line.len() >= self.len && line.chars().all(|c| c == self.marker)
+    }
+}
+
+/// Whether `line` opens a code block in a doxygen or javadoc comment with '@code' or '\code', optionally
+/// followed by the language like '{.cpp}'
+pub(crate) fn is_doxygen_code(line: &str) -> bool {
+    let line = line.trim();
+    ["@code", "\\code"].iter().any(|command| {
+        line.strip_prefix(command)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('{'))
+    })
+}
+
+/// Whether `line` closes a code block in a doxygen or javadoc comment with '@endcode' or '\endcode'
+pub(crate) fn is_doxygen_endcode(line: &str) -> bool {
+    matches!(line.trim(), "@endcode" | "\\endcode")
+}
+
+/// The languages of code blocks for the extensions of content files
+const LANGUAGES: &[(&[&str], &[&str])] = &[
+    (&["c"], &["c"]),
+    (&["h"], &["c", "cpp", "c++"]),
+    (
+        &["cpp", "cc", "cxx", "hpp", "hh", "hxx", "inl"],
+        &["cpp", "c++", "cxx", "cc", "hpp"],
+    ),
+    (&["rs"], &["rust", "rs"]),
+    (&["py"], &["python", "py", "python3"]),
+    (&["java"], &["java"]),
+    (&["js", "mjs"], &["javascript", "js"]),
+    (&["ts"], &["typescript", "ts"]),
+    (&["go"], &["go", "golang"]),
+    (&["sh", "bash"], &["sh", "bash", "shell", "zsh"]),
+    (&["toml"], &["toml"]),
+    (&["yaml", "yml"], &["yaml", "yml"]),
+    (&["json"], &["json", "jsonc", "json5"]),
+];
+
+/// The names of the languages for the extensions of content files, e.g. for the labels of tabs
+const LANGUAGE_NAMES: &[(&[&str], &str)] = &[
+    (&["c", "h"], "C"),
+    (&["cpp", "cc", "cxx", "hpp", "hh", "hxx", "inl"], "C++"),
+    (&["rs"], "Rust"),
+    (&["py"], "Python"),
+    (&["java"], "Java"),
+    (&["js", "mjs"], "JavaScript"),
+    (&["ts"], "TypeScript"),
+    (&["go"], "Go"),
+    (&["sh", "bash"], "Shell"),
+    (&["toml"], "TOML"),
+    (&["yaml", "yml"], "YAML"),
+    (&["json"], "JSON"),
+];
+
+/// Languages which fit any content file
+const GENERIC_LANGUAGES: &[&str] = &["text", "txt", "plaintext", "console", "output", "diff"];
+
+/// The language of the info string of the opening fence `line`, like 'cpp' for '```cpp title="a.cpp"'
+pub(crate) fn language(line: &str) -> Option<&str> {
+    let line = line.trim();
+    let info = line.trim_start_matches(['`', '~']).trim_start();
+    let language = info.split(|c: char| c.is_whitespace() || c == '{').next()?;
+    Some(language).filter(|language| !language.is_empty())
+}
+
+/// The languages of code blocks which fit a content file with `extension`; none for unknown extensions
+pub(crate) fn languages(extension: &str) -> &'static [&'static str] {
+    let extension = extension.to_ascii_lowercase();
+    LANGUAGES
+        .iter()
+        .find(|(extensions, _)| extensions.contains(&extension.as_str()))
+        .map_or(&[], |(_, languages)| languages)
+}
+
+/// Whether `language` fits a content file with `extension`; unknown extensions fit any language
+pub(crate) fn language_matches(language: &str, extension: &str) -> bool {
+    let language = language.to_ascii_lowercase();
+    let extension = extension.to_ascii_lowercase();
+    GENERIC_LANGUAGES.contains(&language.as_str())
+        || LANGUAGES
+            .iter()
+            .find(|(extensions, _)| extensions.contains(&extension.as_str()))
+            .is_none_or(|(_, languages)| languages.contains(&language.as_str()))
+}
+
+/// The name of the language of a content file with `extension`, like 'C++' for 'cpp'; the extension itself for
+/// unknown ones
+pub(crate) fn language_name(extension: &str) -> String {
+    let lowercase = extension.to_ascii_lowercase();
+    LANGUAGE_NAMES
+        .iter()
+        .find(|(extensions, _)| extensions.contains(&lowercase.as_str()))
+        .map_or_else(|| extension.to_owned(), |(_, name)| (*name).to_owned())
+}
+
+/// Tracks whether the lines of a markdown file are within a fenced code block
+#[derive(Debug, Default)]
+pub(crate) struct FenceTracker(Option<CodeFence>);
+
+impl FenceTracker {
+    /// Whether `line` opens, is within or closes a code block; the lines must be passed in order
+    pub(crate) fn in_code_block(&mut self, line: &str) -> bool {
+        match self.0 {
+            Some(fence) => {
+                if fence.closes(line) {
+                    self.0 = None;
+                }
+                true
+            }
+            None => {
+                self.0 = CodeFence::open(line);
+                self.0.is_some()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fences_are_closed_by_the_same_marker() {
+        let backticks = CodeFence::open("```cpp\n").expect("backtick fence");
+        assert!(backticks.closes("```\n"));
+        assert!(backticks.closes("  ````\r\n"));
+        assert!(!backticks.closes("~~~\n"));
+        assert!(!backticks.closes("``\n"));
+        assert!(!backticks.closes("``` cpp\n"));
+
+        let tildes = CodeFence::open("~~~~ python\n").expect("tilde fence");
+        assert!(tildes.closes("~~~~\n"));
+        assert!(!tildes.closes("~~~\n"));
+        assert!(!tildes.closes("```\n"));
+
+        assert_eq!(
+            CodeFence::open("~~~ `backticks` in info\n"),
+            Some(CodeFence {
+                marker: '~',
+                len: 3
+            })
+        );
+        assert_eq!(
+            CodeFence::open("```cpp title=\"foo.cpp\" {1-3}\n"),
+            Some(CodeFence {
+                marker: '`',
+                len: 3
+            })
+        );
+        assert_eq!(CodeFence::open("``` `cpp`\n"), None);
+        assert_eq!(CodeFence::open("``\n"), None);
+        assert_eq!(CodeFence::open("text ```\n"), None);
+    }
+
+    #[test]
+    fn doxygen_code_commands_are_recognized() {
+        assert!(is_doxygen_code("@code\n"));
+        assert!(is_doxygen_code("\\code{.cpp}\n"));
+        assert!(!is_doxygen_code("@codes\n"));
+        assert!(is_doxygen_endcode("  \\endcode\r\n"));
+        assert!(!is_doxygen_endcode("@endcode ok\n"));
+    }
+
+    #[test]
+    fn languages_of_fences_are_matched_to_extensions() {
+        assert_eq!(language("```cpp title=\"a.cpp\"\n"), Some("cpp"));
+        assert_eq!(language("~~~ Rust{.numberLines}\n"), Some("Rust"));
+        assert_eq!(language("```\n"), None);
+        assert_eq!(language("``` {.cpp}\n"), None);
+
+        assert!(language_matches("Rust", "rs"));
+        assert!(language_matches("c++", "hpp"));
+        assert!(language_matches("text", "rs"));
+        assert!(language_matches("python", "unknown"));
+        assert!(!language_matches("python", "cpp"));
+    }
+
+    #[test]
+    fn fence_tracker_includes_the_fences_in_the_code_block() {
+        let mut tracker = FenceTracker::default();
+        let in_code_block = ["text", "````md", "```", "````", "text"]
+            .iter()
+            .map(|line| tracker.in_code_block(line))
+            .collect::<Vec<bool>>();
+
+        assert_eq!(in_code_block, [false, true, true, true, false]);
+    }
+}
@@ -0,0 +1,330 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::report::{BlockStatus, Report};
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.8em; text-align: left; }
+pre { background: #f6f8fa; padding: 0.8em; overflow-x: auto; }
+.failed { color: #b31d28; }
+.skipped { color: #b08800; }
+.updated { color: #005cc5; }
+.in-sync { color: #22863a; }
+.del { background: #ffeef0; }
+.add { background: #e6ffed; }
+.hunk { color: #6f42c1; }
+li.failed, li.skipped { white-space: pre-wrap; font-family: monospace; }";
+
+/// The status of a markdown file in the report
+#[derive(Debug, Default)]
+struct DocumentStatus {
+    updated: usize,
+    unchanged: usize,
+    failed: bool,
+    skipped: bool,
+}
+
+impl DocumentStatus {
+    fn label(&self) -> &'static str {
+        if self.failed {
+            "failed"
+        } else if self.skipped {
+            "skipped"
+        } else if self.updated > 0 {
+            "updated"
+        } else {
+            "in-sync"
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn percentage(part: usize, total: usize) -> String {
+    if total == 0 {
+        "-".to_owned()
+    } else {
+        format!("{:.1}%", 100.0 * part as f64 / total as f64)
+    }
+}
+
+/// Renders the report as standalone HTML page
+pub fn render(report: &Report) -> String {
+    // writing to a String cannot fail
+    render_to_string(report).expect("writing to a string")
+}
+
+fn render_to_string(report: &Report) -> Result<String, std::fmt::Error> {
+    let summary = &report.summary;
+    let mut html = String::new();
+
+    writeln!(html, "<!DOCTYPE html>")?;
+    writeln!(html, "<html>\n<head>\n<meta charset=\"utf-8\">")?;
+    writeln!(html, "<title>geoffrey report</title>")?;
+    writeln!(html, "<style>\n{}\n</style>\n</head>\n<body>", STYLE)?;
+    writeln!(html, "<h1>geoffrey report</h1>")?;
+
+    // summary and coverage
+    writeln!(html, "<h2>Summary</h2>\n<table>")?;
+    let mut row =
+        |name: &str, value: String| writeln!(html, "<tr><th>{}</th><td>{}</td></tr>", name, value);
+    row("markdown files scanned", summary.md_files.to_string())?;
+    row("code blocks managed", summary.code_blocks.to_string())?;
+    row("snippets updated", summary.snippets_updated.to_string())?;
+    row("snippets unchanged", summary.snippets_unchanged.to_string())?;
+    row(
+        "code blocks in sync before the run",
+        percentage(summary.snippets_unchanged, summary.code_blocks),
+    )?;
+    if let Some(coverage) = &report.coverage {
+        let referenced = coverage.snippet_markers - coverage.unused_markers.len();
+        row(
+            "snippet markers referenced",
+            format!(
+                "{} of {} ({})",
+                referenced,
+                coverage.snippet_markers,
+                percentage(referenced, coverage.snippet_markers)
+            ),
+        )?;
+    }
+    row("content files parsed", summary.content_files.to_string())?;
+    row("failed files", summary.failed_files.to_string())?;
+    row("skipped files", summary.skipped_files.to_string())?;
+    row("warnings", summary.warnings.to_string())?;
+    row("elapsed time", format!("{:.3}s", summary.elapsed_secs))?;
+    writeln!(html, "</table>")?;
+
+    // per submodule summary with '--recurse-submodules'
+    if !report.submodules.is_empty() {
+        writeln!(html, "<h2>Submodules</h2>\n<table>")?;
+        writeln!(
+            html,
+            "<tr><th>submodule</th><th>markdown files</th><th>code blocks</th><th>updated</th><th>failed files</th></tr>"
+        )?;
+        for submodule in &report.submodules {
+            let summary = &submodule.report.summary;
+            writeln!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                escape(&submodule.path.display().to_string()),
+                summary.md_files,
+                summary.code_blocks,
+                summary.snippets_updated,
+                summary.failed_files
+            )?;
+        }
+        writeln!(html, "</table>")?;
+    }
+
+    // the documents of the submodules are listed together with the ones of the superproject
+    let reports = std::iter::once(report)
+        .chain(report.submodules.iter().map(|submodule| &submodule.report))
+        .collect::<Vec<&Report>>();
+    let blocks = reports
+        .iter()
+        .flat_map(|report| report.blocks.iter())
+        .collect::<Vec<_>>();
+
+    // per document status
+    let mut documents = BTreeMap::<&Path, DocumentStatus>::new();
+    for block in &blocks {
+        let document = documents.entry(&block.md_file).or_default();
+        match block.status {
+            BlockStatus::Updated => document.updated += 1,
+            BlockStatus::Unchanged => document.unchanged += 1,
+        }
+    }
+    // the diagnostics of content files are listed with the documents referencing them
+    let document_paths = reports
+        .iter()
+        .flat_map(|report| report.documents.iter())
+        .map(PathBuf::as_path)
+        .collect::<HashSet<&Path>>();
+    for diagnostic in report.all_diagnostics() {
+        if let Some(file) = diagnostic
+            .file
+            .as_deref()
+            .filter(|file| document_paths.contains(file))
+        {
+            documents.entry(file).or_default().failed = true;
+        }
+    }
+    for file in reports.iter().flat_map(|report| report.skipped.iter()) {
+        documents.entry(file).or_default().skipped = true;
+    }
+
+    writeln!(html, "<h2>Documents</h2>\n<table>")?;
+    writeln!(
+        html,
+        "<tr><th>document</th><th>status</th><th>updated</th><th>unchanged</th></tr>"
+    )?;
+    for (path, document) in &documents {
+        writeln!(
+            html,
+            "<tr><td>{}</td><td class=\"{1}\">{1}</td><td>{2}</td><td>{3}</td></tr>",
+            escape(&path.display().to_string()),
+            document.label(),
+            document.updated,
+            document.unchanged
+        )?;
+    }
+    writeln!(html, "</table>")?;
+
+    // diffs of the blocks which were out of sync
+    writeln!(html, "<h2>Drifted Code Blocks</h2>")?;
+    let diffs = blocks
+        .iter()
+        .filter_map(|block| block.diff.as_ref())
+        .collect::<Vec<_>>();
+    if diffs.is_empty() {
+        writeln!(html, "<p>none</p>")?;
+    }
+    for diff in diffs {
+        writeln!(
+            html,
+            "<h3>{}:{}</h3>\n<pre>",
+            escape(&diff.md_file.display().to_string()),
+            diff.line
+        )?;
+        for line in diff.unified().lines() {
+            let class = if line.starts_with("---") || line.starts_with("+++") {
+                ""
+            } else if line.starts_with("@@") {
+                "hunk"
+            } else if line.starts_with('-') {
+                "del"
+            } else if line.starts_with('+') {
+                "add"
+            } else {
+                ""
+            };
+            writeln!(html, "<span class=\"{}\">{}</span>", class, escape(line))?;
+        }
+        writeln!(html, "</pre>")?;
+    }
+
+    // snippet markers which are not referenced
+    if let Some(coverage) = &report.coverage {
+        writeln!(html, "<h2>Unused Snippet Markers</h2>")?;
+        if coverage.unused_markers.is_empty() {
+            writeln!(html, "<p>none</p>")?;
+        } else {
+            writeln!(html, "<ul>")?;
+            for marker in &coverage.unused_markers {
+                writeln!(html, "<li>{}</li>", escape(&marker.to_string()))?;
+            }
+            writeln!(html, "</ul>")?;
+        }
+    }
+
+    if report.all_diagnostics().next().is_some() {
+        writeln!(html, "<h2>Diagnostics</h2>\n<ul>")?;
+        for diagnostic in report.all_diagnostics() {
+            writeln!(
+                html,
+                "<li class=\"failed\">{}</li>",
+                escape(&diagnostic.to_string())
+            )?;
+        }
+        writeln!(html, "</ul>")?;
+    }
+
+    if report.all_warnings().next().is_some() {
+        writeln!(html, "<h2>Warnings</h2>\n<ul>")?;
+        for warning in report.all_warnings() {
+            writeln!(
+                html,
+                "<li class=\"skipped\">{}</li>",
+                escape(&warning.to_string())
+            )?;
+        }
+        writeln!(html, "</ul>")?;
+    }
+
+    writeln!(html, "</body>\n</html>")?;
+
+    Ok(html)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::diff::BlockDiff;
+    use crate::report::{BlockReport, Coverage, Diagnostic, OrphanedMarker};
+
+    use std::path::PathBuf;
+
+    #[test]
+    fn html_report_contains_status_diffs_and_unused_markers() {
+        let report = Report {
+            blocks: vec![BlockReport {
+                md_file: PathBuf::from("doc.md"),
+                line: 3,
+                content_file: "main.cpp".to_owned(),
+                snippet: None,
+                status: BlockStatus::Updated,
+                content_root: None,
+                freshness: None,
+                diff: Some(BlockDiff {
+                    md_file: PathBuf::from("doc.md"),
+                    line: 3,
+                    code_line: 5,
+                    column: None,
+                    content_file: "main.cpp".to_owned(),
+                    snippet: None,
+                    current: "a < b\n".to_owned(),
+                    synced: "a > b\n".to_owned(),
+                }),
+            }],
+            coverage: Some(Coverage {
+                snippet_markers: 2,
+                unused_markers: vec![OrphanedMarker {
+                    content_file: PathBuf::from("main.cpp"),
+                    line: 7,
+                    tag: "unused".to_owned(),
+                }],
+            }),
+            ..Default::default()
+        };
+
+        let html = render(&report);
+
+        assert!(html.contains("<td>doc.md</td><td class=\"updated\">updated</td>"));
+        assert!(html.contains("<span class=\"del\">-a &lt; b</span>"));
+        assert!(html.contains("<span class=\"add\">+a &gt; b</span>"));
+        assert!(html.contains("1 of 2 (50.0%)"));
+        assert!(html.contains("<li>main.cpp:7: [unused]</li>"));
+    }
+
+    #[test]
+    fn html_report_marks_only_the_documents_as_failed() {
+        let diagnostic = |file: &str| Diagnostic {
+            code: "G002".to_owned(),
+            file: Some(PathBuf::from(file)),
+            span: None,
+            message: "failed".to_owned(),
+            suggestions: Vec::new(),
+        };
+        let report = Report {
+            diagnostics: vec![diagnostic("guide.mdx"), diagnostic("main.cpp")],
+            documents: vec![PathBuf::from("guide.mdx"), PathBuf::from("api.hpp")],
+            ..Default::default()
+        };
+
+        let html = render(&report);
+
+        assert!(html.contains("<td>guide.mdx</td><td class=\"failed\">failed</td>"));
+        assert!(!html.contains("<td>main.cpp</td>"));
+    }
+}
@@ -0,0 +1,159 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::config::Config;
+use crate::error::GeoffreyError;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The starter config written by `geoffrey init`, with the most common settings at their defaults
+const STARTER_CONFIG: &str = r#"# The configuration of geoffrey; all settings are optional and shown with their defaults
+
+# The directory the content file paths in geoffrey tags are relative to; 'git-toplevel' or 'markdown-file'
+# paths = "git-toplevel"
+
+# The line endings of the inserted code blocks; 'lf', 'crlf' or 'preserve' for the ones of the markdown file
+# eol = "preserve"
+
+# Directories in which the content files are searched in order instead of the git toplevel
+# content_roots = ["src", "examples"]
+
+# The comment forms accepted for snippet markers; 'doxygen' for '//! [tag]', 'line' for '// [tag]'
+# and 'block' for '/* [tag] */'
+# markers = ["doxygen"]
+
+# Whether a snippet name may only be defined in one content file
+# unique_snippets = false
+"#;
+
+/// The directory with the example files, relative to the initialized directory
+const EXAMPLE_DIR: &str = "geoffrey-example";
+
+const EXAMPLE_SOURCE: &str = r#"#include <iostream>
+
+//! [main]
+int main() {
+    //! [greeting]
+    std::cout << "Hello geoffrey!" << std::endl;
+    //! [greeting]
+    return 0;
+}
+//! [main]
+"#;
+
+const EXAMPLE_MD: &str = r#"# Example
+
+The greeting, synced from the snippet 'greeting' of the source file
+
+<!-- [geoffrey] [geoffrey-example/example.cpp] [greeting] -->
+```cpp
+std::cout << "Hello geoffrey!" << std::endl;
+```
+
+The main function with the greeting elided
+
+<!-- [geoffrey] [geoffrey-example/example.cpp] [[main]] -->
+```cpp
+int main() {
+    // ...
+    return 0;
+}
+```
+"#;
+
+/// A short overview of the snippet markers and geoffrey tags, printed by `geoffrey init`
+pub const CHEAT_SHEET: &str = r#"Snippet markers in content files
+  //! [name]                  begins and ends the snippet 'name'; '## [name]' in files with hash comments
+
+Geoffrey tags in markdown files, each followed by a code block
+  <!-- [geoffrey] [path] -->                   the whole file
+  <!-- [geoffrey] [path] [name] -->            the snippet 'name' with its nested snippets
+  <!-- [geoffrey] [path] [[name]] -->          the snippet with the nested snippets elided
+  <!-- [geoffrey] [path] [[name] [sub]] -->    the snippet with only the nested snippet 'sub' kept
+  <!-- [geoffrey] [path] [name] dedent -->     tag options follow the snippet, see the README
+
+Commands
+  geoffrey <doc path>                          syncs the code blocks
+  geoffrey check <doc path>                    checks the tags and code blocks without writing
+  geoffrey diff <doc path>                     shows the code blocks which are not in sync
+  geoffrey mark <file> --lines 4-8 --tag name  inserts snippet markers
+  geoffrey explain G007                        explains an error or warning code"#;
+
+/// Creates a starter 'geoffrey.toml' in `dir` and with `example` a tagged source file and a markdown page in
+/// the 'geoffrey-example' subdirectory; the tag of the example expects `dir` to be the git toplevel. Existing
+/// files are kept. Returns the created files.
+pub fn init(dir: &Path, example: bool) -> Result<Vec<PathBuf>, GeoffreyError> {
+    let mut files = vec![(dir.join(Config::FILE_NAME), STARTER_CONFIG)];
+    if example {
+        let example_dir = dir.join(EXAMPLE_DIR);
+        files.push((example_dir.join("example.cpp"), EXAMPLE_SOURCE));
+        files.push((example_dir.join("example.md"), EXAMPLE_MD));
+    }
+
+    let mut created = Vec::new();
+    for (path, content) in files {
+        if path.exists() {
+            log::warn!("{:?} already exists; keeping it", path);
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, content)?;
+        log::info!("created {:?}", path);
+        created.push(path);
+    }
+
+    Ok(created)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::documents::{Documents, Symlinks, SyncOptions};
+
+    use anyhow::Result;
+    use tempfile::Builder;
+
+    #[test]
+    fn init_creates_a_valid_config_and_an_example_in_sync() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let created = init(tmp_dir.path(), true)?;
+        assert_eq!(
+            created,
+            [
+                tmp_dir.path().join(Config::FILE_NAME),
+                tmp_dir.path().join(EXAMPLE_DIR).join("example.cpp"),
+                tmp_dir.path().join(EXAMPLE_DIR).join("example.md"),
+            ]
+        );
+        Config::load(tmp_dir.path())?;
+
+        // the tag paths are relative to the git toplevel, which the temporary directory is not part of
+        let md_path = tmp_dir.path().join(EXAMPLE_DIR).join("example.md");
+        fs::write(
+            &md_path,
+            EXAMPLE_MD.replace(
+                "geoffrey-example/",
+                &format!("{}/", tmp_dir.path().join(EXAMPLE_DIR).display()),
+            ),
+        )?;
+        let mut documents = Documents::new(md_path.clone(), Symlinks::Follow)?;
+        documents.parse()?;
+        let report = documents.sync(&SyncOptions::default())?;
+        assert_eq!(report.summary.snippets_updated, 0);
+        assert_eq!(report.summary.failed_files, 0);
+
+        // existing files are kept
+        fs::write(tmp_dir.path().join(Config::FILE_NAME), "eol = \"lf\"\n")?;
+        assert!(init(tmp_dir.path(), true)?.is_empty());
+        assert_eq!(
+            fs::read_to_string(tmp_dir.path().join(Config::FILE_NAME))?,
+            "eol = \"lf\"\n"
+        );
+
+        Ok(())
+    }
+}
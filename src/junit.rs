@@ -0,0 +1,216 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::report::{BlockStatus, Diagnostic, Report};
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+
+/// The outcome of a markdown file, which is a test case of the JUnit report
+#[derive(Debug, Default)]
+struct TestCase<'a> {
+    /// the unified diffs of the code blocks which were out of sync
+    drifted: Vec<String>,
+    diagnostics: Vec<&'a Diagnostic>,
+    skipped: bool,
+}
+
+impl TestCase<'_> {
+    fn failed(&self) -> bool {
+        !self.drifted.is_empty() || !self.diagnostics.is_empty()
+    }
+
+    /// The message and type of the failure; the code of the first diagnostic or 'drift'
+    fn failure(&self) -> (String, &str) {
+        match self.diagnostics.first() {
+            Some(diagnostic) => (diagnostic.message.clone(), &diagnostic.code),
+            None => (
+                format!("{} code blocks were out of sync", self.drifted.len()),
+                "drift",
+            ),
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Renders the report as JUnit XML with a test case per markdown file, which fails if code blocks were out of
+/// sync or errors occurred in it; errors without a markdown file are test cases on their own
+pub fn render(report: &Report) -> String {
+    // writing to a String cannot fail
+    render_to_string(report).expect("writing to a string")
+}
+
+fn render_to_string(report: &Report) -> Result<String, std::fmt::Error> {
+    // the documents of the submodules are listed together with the ones of the superproject
+    let reports = std::iter::once(report)
+        .chain(report.submodules.iter().map(|submodule| &submodule.report))
+        .collect::<Vec<&Report>>();
+
+    let mut test_cases = BTreeMap::<String, TestCase>::new();
+    let name = |path: &Path| path.display().to_string();
+    for block in reports.iter().flat_map(|report| report.blocks.iter()) {
+        let test_case = test_cases.entry(name(&block.md_file)).or_default();
+        if let (BlockStatus::Updated, Some(diff)) = (&block.status, &block.diff) {
+            test_case.drifted.push(diff.unified());
+        }
+    }
+    for diagnostic in report.all_diagnostics() {
+        let file = diagnostic
+            .file
+            .as_deref()
+            .map_or_else(|| "geoffrey".to_owned(), name);
+        test_cases
+            .entry(file)
+            .or_default()
+            .diagnostics
+            .push(diagnostic);
+    }
+    for file in reports.iter().flat_map(|report| report.skipped.iter()) {
+        test_cases.entry(name(file)).or_default().skipped = true;
+    }
+
+    let failures = test_cases
+        .values()
+        .filter(|test_case| test_case.failed())
+        .count();
+    let skipped = test_cases
+        .values()
+        .filter(|test_case| !test_case.failed() && test_case.skipped)
+        .count();
+    let mut xml = String::new();
+    writeln!(xml, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(
+        xml,
+        "<testsuites name=\"geoffrey\" tests=\"{0}\" failures=\"{1}\" skipped=\"{2}\" time=\"{3:.3}\">",
+        test_cases.len(),
+        failures,
+        skipped,
+        report.summary.elapsed_secs
+    )?;
+    writeln!(
+        xml,
+        "  <testsuite name=\"geoffrey\" tests=\"{0}\" failures=\"{1}\" skipped=\"{2}\" time=\"{3:.3}\">",
+        test_cases.len(),
+        failures,
+        skipped,
+        report.summary.elapsed_secs
+    )?;
+    for (file, test_case) in &test_cases {
+        write!(
+            xml,
+            "    <testcase name=\"{}\" classname=\"geoffrey\"",
+            escape(file)
+        )?;
+        if test_case.failed() {
+            let (message, kind) = test_case.failure();
+            writeln!(xml, ">")?;
+            writeln!(
+                xml,
+                "      <failure message=\"{}\" type=\"{}\">",
+                escape(&message),
+                escape(kind)
+            )?;
+            for diagnostic in &test_case.diagnostics {
+                writeln!(xml, "{}", escape(&diagnostic.to_string()))?;
+            }
+            for diff in &test_case.drifted {
+                write!(xml, "{}", escape(diff))?;
+            }
+            writeln!(xml, "      </failure>")?;
+            writeln!(xml, "    </testcase>")?;
+        } else if test_case.skipped {
+            writeln!(xml, ">")?;
+            writeln!(
+                xml,
+                "      <skipped message=\"the timeout was reached before the file was synced\"/>"
+            )?;
+            writeln!(xml, "    </testcase>")?;
+        } else {
+            writeln!(xml, "/>")?;
+        }
+    }
+    writeln!(xml, "  </testsuite>")?;
+    writeln!(xml, "</testsuites>")?;
+
+    Ok(xml)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::diff::BlockDiff;
+    use crate::report::BlockReport;
+
+    use std::path::PathBuf;
+
+    fn block(md_file: &str, diff: Option<BlockDiff>) -> BlockReport {
+        BlockReport {
+            md_file: PathBuf::from(md_file),
+            line: 1,
+            content_file: "main.cpp".to_owned(),
+            snippet: Some("main".to_owned()),
+            status: match diff {
+                Some(_) => BlockStatus::Updated,
+                None => BlockStatus::Unchanged,
+            },
+            content_root: None,
+            freshness: None,
+            diff,
+        }
+    }
+
+    #[test]
+    fn each_markdown_file_is_a_test_case() {
+        let report = Report {
+            blocks: vec![
+                block("doc/in-sync.md", None),
+                block(
+                    "doc/drifted.md",
+                    Some(BlockDiff {
+                        md_file: PathBuf::from("doc/drifted.md"),
+                        line: 1,
+                        code_line: 3,
+                        column: None,
+                        content_file: "main.cpp".to_owned(),
+                        snippet: Some("main".to_owned()),
+                        current: "old();\n".to_owned(),
+                        synced: "new();\n".to_owned(),
+                    }),
+                ),
+            ],
+            diagnostics: vec![Diagnostic {
+                code: "G009".to_owned(),
+                file: Some(PathBuf::from("doc/broken.md")),
+                span: None,
+                message: "The snippet 'main' was not found".to_owned(),
+                suggestions: Vec::new(),
+            }],
+            skipped: vec![PathBuf::from("doc/late.md")],
+            ..Default::default()
+        };
+
+        let xml = render(&report);
+        assert!(xml.contains(
+            "<testsuite name=\"geoffrey\" tests=\"4\" failures=\"2\" skipped=\"1\" time=\"0.000\">"
+        ));
+        assert!(xml.contains("<testcase name=\"doc/in-sync.md\" classname=\"geoffrey\"/>"));
+        assert!(xml.contains(
+            "<testcase name=\"doc/drifted.md\" classname=\"geoffrey\">\n      <failure message=\"1 code blocks were out of sync\" type=\"drift\">\n"
+        ));
+        assert!(xml.contains("-old();\n+new();\n"));
+        assert!(xml.contains(
+            "<failure message=\"The snippet &apos;main&apos; was not found\" type=\"G009\">\ndoc/broken.md: [G009] The snippet &apos;main&apos; was not found\n"
+        ));
+        assert!(
+            xml.contains("<testcase name=\"doc/late.md\" classname=\"geoffrey\">\n      <skipped ")
+        );
+    }
+}
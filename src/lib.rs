@@ -0,0 +1,30 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Syncs source code to markdown code blocks.
+//!
+//! The markdown files are parsed for geoffrey tags which reference content files and snippets therein,
+//! which are then rendered into the code blocks following the tags, see `documents::Documents`.
+
+pub mod compat;
+pub mod config;
+pub mod diff;
+pub mod doctor;
+pub mod documents;
+pub mod error;
+pub mod explain;
+pub mod init;
+pub mod lock;
+pub mod marker;
+pub mod provenance;
+pub mod report;
+pub mod selector;
+pub mod source;
+pub mod tangle;
+
+mod cargo;
+mod fence;
+mod html;
+mod junit;
+mod merge;
+mod rdjson;
+mod suggest;
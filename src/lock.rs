@@ -0,0 +1,90 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::error::GeoffreyError;
+
+use std::fs::{File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+
+/// The name of the lock file in the git directory or, outside of a git repository, in the documentation folder
+pub const LOCK_FILE: &str = "geoffrey.lock";
+
+/// An advisory lock which keeps two geoffrey runs, e.g. the daemon and a manual run, from writing the same
+/// markdown files at the same time; it is released when dropped or when the process exits, so a crashed run
+/// does not leave a stale lock behind
+#[derive(Debug)]
+pub struct RunLock {
+    // the lock is held as long as the file is open
+    _file: File,
+}
+
+impl RunLock {
+    /// Locks the documentation in `doc_path`, which is a markdown file or a folder; if another run holds the lock,
+    /// waits for it with `wait` and fails otherwise
+    pub fn acquire(doc_path: &Path, wait: bool) -> Result<Self, GeoffreyError> {
+        if !doc_path.exists() {
+            return Err(GeoffreyError::DocPathDoesNotExist(doc_path.to_path_buf()));
+        }
+        let path = lock_path(doc_path);
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)?;
+        match file.try_lock() {
+            Ok(()) => (),
+            Err(TryLockError::WouldBlock) if wait => {
+                log::info!("waiting for the other geoffrey run holding {:?}", path);
+                file.lock()?;
+            }
+            Err(TryLockError::WouldBlock) => return Err(GeoffreyError::LockedByOtherRun(path)),
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+        log::debug!("locked {:?}", path);
+
+        Ok(Self { _file: file })
+    }
+}
+
+/// The lock file of the documentation in `doc_path`: in the git directory of its repository, so that it never
+/// shows up as untracked file, or in the documentation folder outside of a git repository
+fn lock_path(doc_path: &Path) -> PathBuf {
+    let dir = match doc_path.is_dir() {
+        true => doc_path,
+        false => doc_path.parent().unwrap_or_else(|| Path::new(".")),
+    };
+    let git_dir = std::process::Command::new("git")
+        .args(["rev-parse", "--absolute-git-dir"])
+        .current_dir(dir)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|git_dir| PathBuf::from(git_dir.trim()));
+
+    git_dir.unwrap_or_else(|| dir.to_path_buf()).join(LOCK_FILE)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use anyhow::Result;
+    use tempfile::Builder;
+
+    #[test]
+    fn a_second_run_without_waiting_fails_while_the_lock_is_held() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let lock = RunLock::acquire(tmp_dir.path(), false)?;
+        assert!(matches!(
+            RunLock::acquire(tmp_dir.path(), false),
+            Err(GeoffreyError::LockedByOtherRun(_))
+        ));
+
+        drop(lock);
+        let _lock = RunLock::acquire(tmp_dir.path(), false)?;
+        assert!(tmp_dir.path().join(LOCK_FILE).is_file());
+
+        Ok(())
+    }
+}
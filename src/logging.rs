@@ -1,8 +1,68 @@
 // SPDX-License-Identifier: Apache-2.0
 
-use flexi_logger::{style, DeferredNow, FlexiLoggerError, Logger};
+use flexi_logger::writers::LogWriter;
+use flexi_logger::{style, DeferredNow, Duplicate, FlexiLoggerError, LogTarget, Logger};
 use yansi::Paint;
 
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+
+/// When the log messages and the diffs are colored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    /// if the 'NO_COLOR' environment variable is not set and stdout and stderr are terminals
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Color::Auto),
+            "always" => Ok(Color::Always),
+            "never" => Ok(Color::Never),
+            _ => Err(format!("invalid color '{}'", s)),
+        }
+    }
+}
+
+impl Color {
+    pub fn enabled(self) -> bool {
+        match self {
+            Color::Auto => {
+                std::env::var_os("NO_COLOR").is_none_or(|no_color| no_color.is_empty())
+                    && std::io::stdout().is_terminal()
+                    && std::io::stderr().is_terminal()
+            }
+            Color::Always => true,
+            Color::Never => false,
+        }
+    }
+}
+
+/// The size in bytes at which the log file is rotated
+const LOG_FILE_SIZE: u64 = 10_000_000;
+/// The number of rotated log files which are kept besides the current one
+const LOG_FILES_KEPT: usize = 5;
+
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.3f";
+
+fn level_text(level: log::Level) -> &'static str {
+    match level {
+        log::Level::Error => "[Error]",
+        log::Level::Warn => "[Warn ]",
+        log::Level::Info => "[Info ]",
+        log::Level::Debug => "[Debug]",
+        log::Level::Trace => "[Trace]",
+    }
+}
+
 fn format(
     w: &mut dyn std::io::Write,
     now: &mut DeferredNow,
@@ -10,28 +70,216 @@ fn format(
 ) -> Result<(), std::io::Error> {
     let level = record.level();
 
-    let level_text = match level {
-        log::Level::Error => "[Error]",
-        log::Level::Warn => "[Warn ]",
-        log::Level::Info => "[Info ]",
-        log::Level::Debug => "[Debug]",
-        log::Level::Trace => "[Trace]",
-    };
+    write!(
+        w,
+        "{} {} {}",
+        Paint::fixed(8, now.now().format(TIMESTAMP_FORMAT)).dimmed(),
+        style(level, level_text(level)),
+        &record.args()
+    )
+}
 
+/// Like `format` but without colors, for the log file
+fn file_format(
+    w: &mut dyn std::io::Write,
+    now: &mut DeferredNow,
+    record: &log::Record,
+) -> Result<(), std::io::Error> {
     write!(
         w,
         "{} {} {}",
-        Paint::fixed(8, now.now().format("%Y-%m-%d %H:%M:%S%.3f")).dimmed(),
-        style(level, level_text),
+        now.now().format(TIMESTAMP_FORMAT),
+        level_text(record.level()),
         &record.args()
     )
 }
 
-pub fn try_init(log_level: &str) -> Result<(), FlexiLoggerError> {
-    Logger::with_str(log_level)
+/// Appends the log messages to the file at `path`, which is rotated to e.g. 'geoffrey_r00000.log' for
+/// 'geoffrey.log' and so on once it exceeds `max_size` bytes; the oldest rotated files are removed
+struct LogFile {
+    path: PathBuf,
+    max_size: u64,
+    /// the open file and its size
+    file: Mutex<(File, u64)>,
+}
+
+impl LogFile {
+    fn open(path: &Path, max_size: u64) -> io::Result<Self> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_size,
+            file: Mutex::new((file, size)),
+        })
+    }
+
+    /// The path of the rotated file with the number `index`
+    fn rotated(&self, index: usize) -> PathBuf {
+        let (prefix, suffix) = self.rotated_affixes();
+        self.path
+            .with_file_name(format!("{}{:05}{}", prefix, index, suffix))
+    }
+
+    /// The file name of the rotated files before and after the number
+    fn rotated_affixes(&self) -> (String, String) {
+        let stem = self
+            .path
+            .file_stem()
+            .map_or("geoffrey".into(), |stem| stem.to_string_lossy());
+        let suffix = self.path.extension().map_or(String::new(), |extension| {
+            format!(".{}", extension.to_string_lossy())
+        });
+        (format!("{}_r", stem), suffix)
+    }
+
+    /// The numbers of the existing rotated files
+    fn rotated_indices(&self) -> io::Result<Vec<usize>> {
+        let (prefix, suffix) = self.rotated_affixes();
+        let dir = self.path.parent().filter(|dir| !dir.as_os_str().is_empty());
+        let mut indices = Vec::new();
+        for entry in fs::read_dir(dir.unwrap_or(Path::new(".")))? {
+            let name = entry?.file_name();
+            let index = name
+                .to_str()
+                .and_then(|name| name.strip_prefix(&prefix)?.strip_suffix(&suffix))
+                .filter(|index| index.len() == 5)
+                .and_then(|index| index.parse::<usize>().ok());
+            indices.extend(index);
+        }
+        indices.sort_unstable();
+
+        Ok(indices)
+    }
+
+    /// Renames the file to the next rotated file, keeping the last `LOG_FILES_KEPT` ones, and starts a new one
+    fn rotate(&self, file: &mut (File, u64)) -> io::Result<()> {
+        let mut indices = self.rotated_indices()?;
+        let next = indices.last().map_or(0, |last| last + 1);
+        fs::rename(&self.path, self.rotated(next))?;
+        indices.push(next);
+        for index in &indices[..indices.len().saturating_sub(LOG_FILES_KEPT)] {
+            fs::remove_file(self.rotated(*index))?;
+        }
+        *file = (
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?,
+            0,
+        );
+
+        Ok(())
+    }
+}
+
+impl LogWriter for LogFile {
+    fn write(&self, now: &mut DeferredNow, record: &log::Record) -> io::Result<()> {
+        let mut line = Vec::new();
+        file_format(&mut line, now, record)?;
+        line.push(b'\n');
+
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        if file.1 > 0 && file.1 + line.len() as u64 > self.max_size {
+            self.rotate(&mut file)?;
+        }
+        file.0.write_all(&line)?;
+        file.1 += line.len() as u64;
+
+        Ok(())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.file
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .0
+            .flush()
+    }
+
+    fn max_log_level(&self) -> log::LevelFilter {
+        log::LevelFilter::Trace
+    }
+}
+
+/// Starts the logger; without `color`, the ANSI codes are also disabled for all other output, e.g. the diffs.
+/// With `log_file`, the messages are also appended to this file, see `LogFile`
+pub fn try_init(
+    log_level: &str,
+    color: Color,
+    log_file: Option<&Path>,
+) -> Result<(), FlexiLoggerError> {
+    if !color.enabled() {
+        Paint::disable();
+    }
+
+    let mut logger = Logger::with_str(log_level)
         .set_palette("9;11;10;7;8".to_owned())
-        .format_for_stderr(format)
-        .start()?;
+        .format_for_stderr(format);
+    if let Some(log_file) = log_file {
+        logger = logger
+            .log_target(LogTarget::Writer(Box::new(LogFile::open(
+                log_file,
+                LOG_FILE_SIZE,
+            )?)))
+            .duplicate_to_stderr(Duplicate::All);
+    }
+    logger.start()?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use anyhow::Result;
+    use tempfile::Builder;
+
+    fn log(log_file: &LogFile, message: &str) -> Result<()> {
+        log_file.write(
+            &mut DeferredNow::new(),
+            &log::Record::builder()
+                .level(log::Level::Info)
+                .args(format_args!("{}", message))
+                .build(),
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    fn messages_are_appended_to_the_log_file_which_is_rotated() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+        let path = tmp_dir.path().join("logs").join("geoffrey.log");
+        fs::create_dir(tmp_dir.path().join("logs"))?;
+        fs::write(&path, "earlier run\n")?;
+
+        let log_file = LogFile::open(&path, 100)?;
+        log(&log_file, "synced 'README.md'")?;
+        log_file.flush()?;
+        let logged = fs::read_to_string(&path)?;
+        assert!(logged.starts_with("earlier run\n"));
+        assert!(logged.ends_with(" [Info ] synced 'README.md'\n"));
+
+        for index in 0..LOG_FILES_KEPT + 2 {
+            log(&log_file, &format!("{:0>60}", index))?;
+        }
+        let logged = fs::read_to_string(&path)?;
+        assert_eq!(logged.lines().count(), 1);
+        assert!(logged.ends_with(&format!("{:0>60}\n", LOG_FILES_KEPT + 1)));
+        let rotated = (0..LOG_FILES_KEPT + 2)
+            .filter(|index| log_file.rotated(*index).exists())
+            .collect::<Vec<usize>>();
+        assert_eq!(rotated, (2..LOG_FILES_KEPT + 2).collect::<Vec<usize>>());
+        assert_eq!(
+            log_file.rotated(0),
+            tmp_dir.path().join("logs").join("geoffrey_r00000.log")
+        );
+
+        Ok(())
+    }
+}
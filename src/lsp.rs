@@ -0,0 +1,479 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use geoffrey::diff::BlockDiff;
+use geoffrey::documents::{Documents, SyncOptions};
+use geoffrey::error::GeoffreyError;
+
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+/// The JSON-RPC error code for requests with an unknown method
+const METHOD_NOT_FOUND: i64 = -32601;
+
+const SEVERITY_ERROR: u8 = 1;
+const SEVERITY_WARNING: u8 = 2;
+
+/// Serves the language server protocol on `reader` and `writer` until the client sends 'exit'; `open` creates
+/// the documents for a markdown file, which are parsed from the file system, therefore the diagnostics are
+/// published when a file is opened or saved
+pub fn serve<R, W, F>(mut reader: R, mut writer: W, open: F, options: &SyncOptions) -> Result<()>
+where
+    R: BufRead,
+    W: Write,
+    F: Fn(&Path) -> Result<Documents, GeoffreyError>,
+{
+    let server = Server { open, options };
+    while let Some(message) = read_message(&mut reader)? {
+        if message["method"] == "exit" {
+            return Ok(());
+        }
+        for response in server.handle(&message) {
+            write_message(&mut writer, &response)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads a message with a 'Content-Length' header; `None` at the end of the input
+fn read_message<R: BufRead>(reader: &mut R) -> Result<Option<Value>> {
+    let mut content_length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = Some(value.trim().parse::<usize>()?);
+            }
+        }
+    }
+
+    let content_length =
+        content_length.ok_or_else(|| anyhow!("message without 'Content-Length' header"))?;
+    let mut content = vec![0; content_length];
+    reader.read_exact(&mut content)?;
+
+    Ok(Some(serde_json::from_slice(&content)?))
+}
+
+fn write_message<W: Write>(writer: &mut W, message: &Value) -> Result<()> {
+    let content = serde_json::to_string(message)?;
+    write!(
+        writer,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+struct Server<'a, F> {
+    open: F,
+    options: &'a SyncOptions,
+}
+
+impl<F> Server<'_, F>
+where
+    F: Fn(&Path) -> Result<Documents, GeoffreyError>,
+{
+    /// The responses and notifications to send for a request or notification of the client
+    fn handle(&self, message: &Value) -> Vec<Value> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        log::debug!("{}", method);
+
+        let id = match message.get("id") {
+            Some(id) => id.clone(),
+            None => {
+                return match method {
+                    "textDocument/didOpen" | "textDocument/didSave" => {
+                        self.publish_diagnostics(params).into_iter().collect()
+                    }
+                    // other notifications like 'initialized' or 'textDocument/didChange' are ignored
+                    _ => Vec::new(),
+                };
+            }
+        };
+
+        let result = match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": { "openClose": true, "save": true },
+                    "definitionProvider": true,
+                    "codeActionProvider": true,
+                },
+                "serverInfo": { "name": "geoffrey", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "shutdown" => Ok(Value::Null),
+            "textDocument/definition" => self.definition(params),
+            "textDocument/codeAction" => self.code_actions(params),
+            _ => {
+                return vec![json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": {
+                        "code": METHOD_NOT_FOUND,
+                        "message": format!("unknown method '{}'", method),
+                    },
+                })]
+            }
+        };
+
+        let result = result.unwrap_or_else(|e| {
+            log::warn!("{} failed: {}", method, e);
+            Value::Null
+        });
+        vec![json!({ "jsonrpc": "2.0", "id": id, "result": result })]
+    }
+
+    /// The parsed documents of the markdown file of the text document in `params`
+    fn documents(&self, params: &Value) -> Result<(PathBuf, Documents)> {
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .ok_or_else(|| anyhow!("text document without uri"))?;
+        let path = uri_to_path(uri).ok_or_else(|| anyhow!("unsupported uri '{}'", uri))?;
+        let mut documents = (self.open)(&path)?;
+        documents.parse()?;
+
+        Ok((path, documents))
+    }
+
+    fn publish_diagnostics(&self, params: &Value) -> Option<Value> {
+        let uri = params["textDocument"]["uri"].as_str()?;
+        let diagnostics = match self.documents(params) {
+            Ok((_, documents)) => self.diagnostics(documents),
+            Err(e) => vec![diagnostic(1, SEVERITY_ERROR, code(&e), e.to_string())],
+        };
+
+        Some(json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": { "uri": uri, "diagnostics": diagnostics },
+        }))
+    }
+
+    /// The invalid tags, missing content files and snippets, and stale code blocks of the documents
+    fn diagnostics(&self, documents: Documents) -> Vec<Value> {
+        let tags = documents.tags();
+        let mut diagnostics = Vec::new();
+
+        // failures of content files are reported at the tags referencing them, other failures at their location
+        // or at the first line
+        for failure in documents.failures() {
+            let mut lines = tags
+                .iter()
+                .filter(|tag| Path::new(&tag.content_file) == failure.path)
+                .map(|tag| tag.line)
+                .collect::<Vec<usize>>();
+            if lines.is_empty() {
+                lines.push(failure.span.as_ref().map_or(1, |span| span.line));
+            }
+            for line in lines {
+                diagnostics.push(diagnostic(
+                    line,
+                    SEVERITY_ERROR,
+                    failure.error.code(),
+                    failure.error.to_string(),
+                ));
+            }
+        }
+
+        for problem in documents.tag_problems() {
+            diagnostics.push(diagnostic(
+                problem.line,
+                SEVERITY_ERROR,
+                problem.error.code(),
+                problem.error.to_string(),
+            ));
+        }
+
+        let report = documents.diff(self.options);
+        for warning in &report.warnings {
+            let line = warning.span.as_ref().map_or(1, |span| span.line);
+            diagnostics.push(diagnostic(
+                line,
+                SEVERITY_WARNING,
+                &warning.code,
+                warning.message.clone(),
+            ));
+        }
+
+        for diff in report.diffs {
+            diagnostics.push(json!({
+                "range": line_range(diff.line),
+                "severity": SEVERITY_WARNING,
+                "source": "geoffrey",
+                "message": format!("The code block is not in sync with '{}'", source(&diff)),
+            }));
+        }
+
+        diagnostics
+    }
+
+    /// The location of the content snippet referenced by the tag at the position in `params`
+    fn definition(&self, params: &Value) -> Result<Value> {
+        let (path, documents) = self.documents(params)?;
+        let line = position_line(&params["position"])?;
+
+        Ok(match documents.definition(&path, line) {
+            Some(location) => json!({
+                "uri": path_to_uri(&location.path),
+                "range": {
+                    "start": { "line": location.line - 1, "character": 0 },
+                    "end": { "line": location.line - 1, "character": 0 },
+                },
+            }),
+            None => Value::Null,
+        })
+    }
+
+    /// A 'sync this block' action for the stale code block at the range in `params`
+    fn code_actions(&self, params: &Value) -> Result<Value> {
+        let uri = &params["textDocument"]["uri"];
+        let (_, documents) = self.documents(params)?;
+        let line = position_line(&params["range"]["start"])?;
+
+        let actions = documents
+            .diff(self.options)
+            .diffs
+            .into_iter()
+            .filter(|diff| {
+                // from the tag to the end of the code block
+                diff.line <= line && line <= diff.code_line + diff.current.lines().count()
+            })
+            .map(|diff| {
+                let first = diff.code_line - 1;
+                // the value of an inline code span is replaced within its line
+                let (start, end) = match diff.column {
+                    Some(column) => (
+                        json!({ "line": first, "character": column }),
+                        json!({ "line": first, "character": column + diff.current.chars().count() }),
+                    ),
+                    None => (
+                        json!({ "line": first, "character": 0 }),
+                        json!({ "line": first + diff.current.lines().count(), "character": 0 }),
+                    ),
+                };
+                json!({
+                    "title": format!("Sync this block with '{}'", source(&diff)),
+                    "kind": "quickfix",
+                    "edit": {
+                        "changes": {
+                            uri.as_str().unwrap_or_default(): [{
+                                "range": { "start": start, "end": end },
+                                "newText": diff.synced,
+                            }],
+                        },
+                    },
+                })
+            })
+            .collect::<Vec<Value>>();
+
+        Ok(Value::Array(actions))
+    }
+}
+
+fn code(error: &anyhow::Error) -> &'static str {
+    error
+        .downcast_ref::<GeoffreyError>()
+        .map_or("", |error| error.code())
+}
+
+/// The content file and snippet of a code block
+fn source(diff: &BlockDiff) -> String {
+    match &diff.snippet {
+        Some(snippet) => format!("{} [{}]", diff.content_file, snippet),
+        None => diff.content_file.clone(),
+    }
+}
+
+/// A diagnostic for the whole `line`, starting at 1
+fn diagnostic(line: usize, severity: u8, code: &str, message: String) -> Value {
+    json!({
+        "range": line_range(line),
+        "severity": severity,
+        "code": code,
+        "source": "geoffrey",
+        "message": message,
+    })
+}
+
+fn line_range(line: usize) -> Value {
+    json!({
+        "start": { "line": line - 1, "character": 0 },
+        "end": { "line": line, "character": 0 },
+    })
+}
+
+/// The line of an LSP position, starting at 1
+fn position_line(position: &Value) -> Result<usize> {
+    position["line"]
+        .as_u64()
+        .map(|line| line as usize + 1)
+        .ok_or_else(|| anyhow!("invalid position {}", position))
+}
+
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let mut bytes = uri.strip_prefix("file://")?.bytes();
+    let mut path = Vec::new();
+    while let Some(byte) = bytes.next() {
+        if byte == b'%' {
+            let hex = [bytes.next()?, bytes.next()?];
+            path.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            path.push(byte);
+        }
+    }
+
+    String::from_utf8(path).ok().map(PathBuf::from)
+}
+
+fn path_to_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+
+    uri
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use geoffrey::documents::Symlinks;
+
+    use std::fs;
+    use tempfile::Builder;
+
+    fn message(message: Value) -> String {
+        let content = message.to_string();
+        format!("Content-Length: {}\r\n\r\n{}", content.len(), content)
+    }
+
+    fn request(id: u64, method: &str, params: Value) -> String {
+        message(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))
+    }
+
+    #[test]
+    fn lsp_serves_diagnostics_definitions_and_code_actions() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let content_path = tmp_dir.path().join("content file.cpp");
+        fs::write(&content_path, "// intro\n//! [answer]\n42\n//! [answer]\n")?;
+        let md_path = tmp_dir.path().join("doc.md");
+        fs::write(
+            &md_path,
+            format!(
+                "# Doc\n<!-- [geoffrey] [{}] [answer] -->\n```cpp\nstale\n```\n",
+                content_path.display()
+            ),
+        )?;
+        let broken_path = tmp_dir.path().join("broken.md");
+        fs::write(
+            &broken_path,
+            format!(
+                "# Broken\n\n<!-- [geoffrey] [{}] [answr] -->\n```cpp\n```\n",
+                content_path.display()
+            ),
+        )?;
+
+        let uri = path_to_uri(&md_path);
+        assert_eq!(uri_to_path(&uri), Some(md_path.clone()));
+        let did_open = |uri: &str| {
+            message(json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/didOpen",
+                "params": { "textDocument": { "uri": uri } },
+            }))
+        };
+        let input = [
+            request(1, "initialize", json!({})),
+            did_open(&uri),
+            did_open(&path_to_uri(&broken_path)),
+            request(
+                2,
+                "textDocument/definition",
+                json!({ "textDocument": { "uri": uri }, "position": { "line": 1, "character": 5 } }),
+            ),
+            request(
+                3,
+                "textDocument/codeAction",
+                json!({ "textDocument": { "uri": uri }, "range": {
+                    "start": { "line": 3, "character": 0 }, "end": { "line": 3, "character": 0 }
+                } }),
+            ),
+            request(4, "textDocument/hover", json!({})),
+            request(5, "shutdown", Value::Null),
+            message(json!({ "jsonrpc": "2.0", "method": "exit" })),
+        ]
+        .concat();
+
+        let mut output = Vec::new();
+        serve(
+            input.as_bytes(),
+            &mut output,
+            |path| Documents::new(path.to_path_buf(), Symlinks::Follow),
+            &SyncOptions::default(),
+        )?;
+
+        let mut output = output.as_slice();
+        let mut messages = Vec::new();
+        while let Some(message) = read_message(&mut output)? {
+            messages.push(message);
+        }
+        assert_eq!(messages.len(), 7);
+
+        assert_eq!(
+            messages[0]["result"]["capabilities"]["definitionProvider"],
+            true
+        );
+
+        let diagnostics = &messages[1]["params"]["diagnostics"];
+        assert_eq!(messages[1]["params"]["uri"], uri);
+        assert_eq!(diagnostics.as_array().map(Vec::len), Some(1));
+        assert_eq!(diagnostics[0]["severity"], SEVERITY_WARNING);
+        assert_eq!(diagnostics[0]["range"]["start"]["line"], 1);
+
+        let diagnostics = &messages[2]["params"]["diagnostics"];
+        assert_eq!(diagnostics.as_array().map(Vec::len), Some(1));
+        assert_eq!(diagnostics[0]["code"], "G009");
+        assert_eq!(diagnostics[0]["range"]["start"]["line"], 2);
+
+        assert_eq!(
+            messages[3]["result"],
+            json!({
+                "uri": path_to_uri(&content_path),
+                "range": {
+                    "start": { "line": 1, "character": 0 },
+                    "end": { "line": 1, "character": 0 },
+                },
+            })
+        );
+
+        let edit = &messages[4]["result"][0]["edit"]["changes"][&uri][0];
+        assert_eq!(edit["range"]["start"]["line"], 3);
+        assert_eq!(edit["range"]["end"]["line"], 4);
+        assert_eq!(edit["newText"], "42\n");
+
+        assert_eq!(messages[5]["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(messages[6]["result"], Value::Null);
+
+        Ok(())
+    }
+}
@@ -1,27 +1,814 @@
 // SPDX-License-Identifier: Apache-2.0
 
-mod documents;
-mod error;
+mod daemon;
 mod logging;
+mod lsp;
 mod params;
+mod serve;
+mod socket;
 
-use anyhow::{Context, Result};
-use structopt::StructOpt;
+use geoffrey::config::Config;
+use geoffrey::error::{FileError, GeoffreyError};
+use geoffrey::lock::RunLock;
+use geoffrey::{diff, doctor, documents, explain, init, marker, report};
 
-fn main() -> Result<()> {
-    logging::try_init("trace").context("failed to initialize logger")?;
+use anyhow::{anyhow, Context, Result};
+use clap::Parser;
+use rayon::prelude::*;
 
-    let params = params::Params::from_args();
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, Instant};
 
-    let absolute_doc_path = if params.doc_path.is_relative() {
-        std::env::current_dir()?.join(params.doc_path)
+fn absolute(path: PathBuf) -> Result<PathBuf, GeoffreyError> {
+    Ok(if path.is_relative() {
+        std::env::current_dir()?.join(path)
     } else {
-        params.doc_path
+        path
+    })
+}
+
+/// The options of the command line which apply to all opened documentation
+struct OpenOptions {
+    symlinks: documents::Symlinks,
+    content_roots: Vec<PathBuf>,
+    include: Vec<String>,
+    only_tags: Vec<String>,
+    since: Option<String>,
+    fix_paths: bool,
+    /// wait for other runs writing the same documentation instead of failing
+    wait: bool,
+    /// the files which are not parsed before this point in time are skipped
+    deadline: Option<Instant>,
+}
+
+/// Creates the documents for `doc_path` with the options from the command line
+fn open_documents(
+    doc_path: PathBuf,
+    options: &OpenOptions,
+) -> Result<documents::Documents, GeoffreyError> {
+    configure(
+        documents::Documents::new(absolute(doc_path)?, options.symlinks)?,
+        options,
+    )
+}
+
+/// Locks the documentation in `doc_path` against other runs writing it until the lock is dropped
+fn lock(doc_path: &Path, options: &OpenOptions) -> Result<RunLock, GeoffreyError> {
+    RunLock::acquire(&absolute(doc_path.to_path_buf())?, options.wait)
+}
+
+/// Overrides the content roots of the documents with the ones from the command line, if any, and applies the
+/// filters for markdown files and tags
+fn configure(
+    mut documents: documents::Documents,
+    options: &OpenOptions,
+) -> Result<documents::Documents, GeoffreyError> {
+    if !options.content_roots.is_empty() {
+        documents.set_content_roots(
+            options
+                .content_roots
+                .iter()
+                .cloned()
+                .map(absolute)
+                .collect::<Result<Vec<PathBuf>, _>>()?,
+        );
+    }
+
+    if !options.include.is_empty() {
+        // the patterns are relative to the current directory like the doc path
+        let patterns = options
+            .include
+            .iter()
+            .map(|pattern| {
+                Ok(absolute(PathBuf::from(pattern))?
+                    .components()
+                    .filter(|component| *component != Component::CurDir)
+                    .collect::<PathBuf>()
+                    .to_string_lossy()
+                    .into_owned())
+            })
+            .collect::<Result<Vec<String>, GeoffreyError>>()?;
+        documents.include_md_files(&patterns)?;
+    }
+    documents.only_tags(options.only_tags.clone());
+    if let Some(rev) = &options.since {
+        documents.since(rev.clone());
+    }
+    if let Some(deadline) = options.deadline {
+        documents.set_deadline(deadline);
+    }
+
+    Ok(documents)
+}
+
+/// Syncs the documentation in `doc_path` and separately the one of each git submodule within it, which
+/// resolves its content in its own git toplevel; the reports of the submodules are added as sections
+fn sync_submodules(
+    doc_path: PathBuf,
+    open_options: &OpenOptions,
+    options: &documents::SyncOptions,
+) -> Result<report::Report, GeoffreyError> {
+    let doc_path = absolute(doc_path)?.canonicalize()?;
+    let submodules = documents::Documents::git_submodules(&doc_path)?;
+
+    let sync = |path: &Path| {
+        let mut documents = open_documents(path.to_path_buf(), open_options)?;
+        // the markdown files of nested submodules are synced with their own submodule
+        let nested = submodules
+            .iter()
+            .filter(|submodule| submodule.starts_with(path) && *submodule != path)
+            .cloned()
+            .collect::<Vec<PathBuf>>();
+        documents.exclude_dirs(&nested);
+        documents.parse()?;
+        if fix_paths(&documents, open_options)? {
+            documents = open_documents(path.to_path_buf(), open_options)?;
+            documents.exclude_dirs(&nested);
+            documents.parse()?;
+        }
+        documents.sync(options)
+    };
+
+    let mut report = sync(&doc_path)?;
+    let submodule_reports = submodules
+        .par_iter()
+        .map(|submodule| (submodule, sync(submodule)))
+        .collect::<Vec<_>>();
+    for (submodule, result) in submodule_reports {
+        match result {
+            Ok(submodule_report) => report.add_submodule(submodule.clone(), submodule_report),
+            Err(GeoffreyError::NoMarkdownFilesInPath(_)) => {
+                log::debug!("no markdown files in submodule {}", submodule.display())
+            }
+            Err(e) => report.add_submodule(
+                submodule.clone(),
+                report::Report {
+                    summary: report::Summary {
+                        failed_files: 1,
+                        ..Default::default()
+                    },
+                    diagnostics: vec![report::Diagnostic {
+                        file: Some(submodule.clone()),
+                        ..report::Diagnostic::from(&e)
+                    }],
+                    ..Default::default()
+                },
+            ),
+        }
+    }
+
+    Ok(report)
+}
+
+/// Rewrites the tags of content files renamed in git if `--fix-paths` is given; returns whether any markdown
+/// file was changed, in which case the documents have to be parsed again
+fn fix_paths(
+    documents: &documents::Documents,
+    options: &OpenOptions,
+) -> Result<bool, GeoffreyError> {
+    if !options.fix_paths {
+        return Ok(false);
+    }
+    let edits = documents.renamed_path_edits()?;
+    documents::Documents::apply_edits(&edits)?;
+
+    Ok(!edits.is_empty())
+}
+
+/// Logs the files which failed to parse and fails if there are any
+fn check_failures(documents: &documents::Documents) -> Result<()> {
+    let failures = documents.failures();
+    failures
+        .iter()
+        .for_each(|failure| log::error!("{}", failure));
+    if !failures.is_empty() {
+        return Err(anyhow!(
+            "{} files failed",
+            FileError::distinct_files(failures)
+        ));
+    }
+
+    Ok(())
+}
+
+/// Checks the documentation without writing it: fails if a tag references a missing content file or snippet, or
+/// if a code block is not in sync with its snippet. The pending updates are written as patch to `emit_patch`.
+/// Returns the markdown files which were not checked because the timeout was reached.
+fn check(
+    documents: documents::Documents,
+    emit_patch: Option<&Path>,
+    options: &documents::SyncOptions,
+) -> Result<Vec<PathBuf>> {
+    let pending = documents.pending_edits(options)?;
+    if let Some(emit_patch) = emit_patch {
+        std::fs::write(emit_patch, pending.patch())
+            .with_context(|| format!("failed to write '{}'", emit_patch.display()))?;
+    }
+    let problems = documents.validate();
+    problems
+        .iter()
+        .for_each(|problem| log::error!("{}", problem));
+    if !problems.is_empty() {
+        return Err(anyhow!("found {} problems", problems.len()));
+    }
+    if !pending.edits.is_empty() {
+        return Err(anyhow!(
+            "the code blocks of {} markdown files are not in sync; run 'geoffrey sync' to update them",
+            pending.edits.len()
+        ));
+    }
+    if pending.skipped.is_empty() {
+        log::info!("no problems found");
+    }
+
+    Ok(pending.skipped)
+}
+
+/// Logs the markdown files which were skipped because the timeout was reached
+fn log_timeout(skipped: &[PathBuf]) {
+    skipped
+        .iter()
+        .for_each(|path| log::warn!("skipped {}", path.display()));
+    log::error!("timeout reached; {} files were skipped", skipped.len());
+}
+
+fn main() {
+    let start = Instant::now();
+
+    let params = params::Params::parse();
+    let compat = params.compat;
+
+    if let Err(e) = run(params, start) {
+        eprintln!("Error: {:?}", e);
+        std::process::exit(compat.exit_code_failure());
+    }
+}
+
+/// The log file of '--log-file' or else of the config for the working directory, relative to the config file
+fn log_file(params: &params::Params) -> Option<PathBuf> {
+    params.log_file.clone().or_else(|| {
+        let config_dir = documents::Documents::config_dir(Path::new(".")).ok()?;
+        let log_file = Config::load(&config_dir).ok()?.log_file?;
+        Some(config_dir.join(log_file))
+    })
+}
+
+fn run(params: params::Params, start: Instant) -> Result<()> {
+    logging::try_init(
+        params.log_level(),
+        params.color,
+        log_file(&params).as_deref(),
+    )
+    .context("failed to initialize logger")?;
+
+    let open_options = OpenOptions {
+        symlinks: params.symlinks,
+        content_roots: params.content_root.clone(),
+        include: params.include.clone(),
+        only_tags: params.only_tag.clone(),
+        since: params.since.clone(),
+        fix_paths: params.fix_paths,
+        wait: !params.no_wait,
+        deadline: params
+            .timeout
+            .map(|timeout| start + Duration::from_secs(timeout)),
     };
 
-    let mut documents = documents::Documents::new(absolute_doc_path)?;
-    documents.parse()?;
-    documents.sync()?;
+    let sync_options = documents::SyncOptions {
+        fallback: params.fallback,
+        freshness: params.report_json.is_some(),
+        deadline: params
+            .timeout
+            .map(|timeout| start + Duration::from_secs(timeout)),
+        ignore_whitespace: params.ignore_whitespace,
+        coverage: params.report_html.is_some(),
+        force: params.force,
+        verify: params.verify,
+        // running snippets executes code, so only the commands which write the documentation do it
+        run_snippets: params.run_snippets
+            && matches!(
+                params.cmd,
+                None | Some(params::Command::Sync { .. }) | Some(params::Command::Render { .. })
+            ),
+    };
+
+    match params.cmd {
+        Some(params::Command::Explain { code }) => {
+            let explanation =
+                explain::explanation(&code).ok_or_else(|| anyhow!("unknown code '{}'", code))?;
+            println!("{}", explanation);
+            return Ok(());
+        }
+        Some(params::Command::Init { dir, example }) => {
+            init::init(&dir, example)?;
+            println!("{}", init::CHEAT_SHEET);
+            return Ok(());
+        }
+        Some(params::Command::Doctor { doc_path }) => {
+            let checks = doctor::diagnose(&doc_path, open_options.symlinks);
+            checks.iter().for_each(|check| println!("{}", check));
+            let failed = checks.iter().filter(|check| !check.passed()).count();
+            if failed > 0 {
+                return Err(anyhow!("{} checks failed", failed));
+            }
+            return Ok(());
+        }
+        Some(params::Command::Mark { file, lines, tag }) => {
+            marker::mark(&file, lines, &tag)?;
+            return Ok(());
+        }
+        Some(params::Command::List { doc_path, json }) => {
+            let mut documents = open_documents(doc_path, &open_options)?;
+            documents.parse_md_files()?;
+            let tags = documents.tags();
+            if json {
+                println!("{}", serde_json::to_string_pretty(&tags)?);
+            } else {
+                tags.iter().for_each(|tag| println!("{}", tag));
+            }
+            return check_failures(&documents);
+        }
+        Some(params::Command::Validate { doc_path }) => {
+            let mut documents = open_documents(doc_path, &open_options)?;
+            documents.parse()?;
+            let problems = documents.validate();
+            problems
+                .iter()
+                .for_each(|problem| log::error!("{}", problem));
+            if !problems.is_empty() {
+                return Err(anyhow!("found {} problems", problems.len()));
+            }
+            log::info!("no problems found");
+            return Ok(());
+        }
+        Some(params::Command::Check {
+            doc_path,
+            emit_patch,
+        }) => {
+            let mut documents = open_documents(doc_path, &open_options)?;
+            documents.parse()?;
+            let skipped = check(documents, emit_patch.as_deref(), &sync_options)?;
+            if !skipped.is_empty() {
+                log_timeout(&skipped);
+                std::process::exit(params.compat.exit_code_timeout());
+            }
+            return Ok(());
+        }
+        Some(params::Command::Orphans {
+            doc_path,
+            scan,
+            json,
+        }) => {
+            let mut documents = open_documents(doc_path, &open_options)?;
+            documents.parse()?;
+            let scan = scan
+                .into_iter()
+                .map(absolute)
+                .collect::<Result<Vec<PathBuf>, _>>()?;
+            let orphans = documents.orphaned_markers(&scan)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&orphans)?);
+            } else {
+                orphans.iter().for_each(|orphan| println!("{}", orphan));
+            }
+            check_failures(&documents)?;
+            if !orphans.is_empty() {
+                return Err(anyhow!("found {} orphaned snippet markers", orphans.len()));
+            }
+            return Ok(());
+        }
+        Some(params::Command::WhereUsed {
+            snippet,
+            doc_path,
+            json,
+        }) => {
+            let mut documents = open_documents(doc_path, &open_options)?;
+            documents.parse()?;
+            let usage = documents.where_used(&snippet);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&usage)?);
+            } else {
+                print!("{}", usage);
+            }
+            check_failures(&documents)?;
+            if usage.definitions.is_empty() && usage.references.is_empty() {
+                return Err(anyhow!(
+                    "the snippet '{}' is neither defined nor embedded",
+                    snippet
+                ));
+            }
+            return Ok(());
+        }
+        Some(params::Command::RenameTag {
+            old,
+            new,
+            doc_path,
+            dry_run,
+        }) => {
+            let _lock = (!dry_run)
+                .then(|| lock(&doc_path, &open_options))
+                .transpose()?;
+            let mut documents = open_documents(doc_path, &open_options)?;
+            documents.parse()?;
+            // a partially parsed documentation would be renamed partially
+            check_failures(&documents)?;
+            let edits = documents.rename_snippet(&old, &new)?;
+            if dry_run {
+                edits.iter().for_each(|edit| print!("{}", edit));
+            } else {
+                documents::Documents::apply_edits(&edits)?;
+                edits
+                    .iter()
+                    .for_each(|edit| println!("{}", edit.path.display()));
+            }
+            return Ok(());
+        }
+        Some(params::Command::Fmt { doc_path, check }) => {
+            let _lock = (!check)
+                .then(|| lock(&doc_path, &open_options))
+                .transpose()?;
+            let documents = open_documents(doc_path, &open_options)?;
+            let (changed, failures) = documents.format_tags(check);
+            changed
+                .iter()
+                .for_each(|path| println!("{}", path.display()));
+            failures
+                .iter()
+                .for_each(|failure| log::error!("{}", failure));
+            if !failures.is_empty() {
+                return Err(anyhow!("{} files failed", failures.len()));
+            }
+            if check && !changed.is_empty() {
+                return Err(anyhow!("{} files are not formatted", changed.len()));
+            }
+            return Ok(());
+        }
+        Some(params::Command::Tangle { doc_path, output }) => {
+            let documents = open_documents(doc_path, &open_options)?;
+            for file in documents.tangle()? {
+                let path = output.join(&file.path);
+                if std::fs::read_to_string(&path).is_ok_and(|content| content == file.content) {
+                    continue;
+                }
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                std::fs::write(&path, &file.content)
+                    .with_context(|| format!("failed to write '{}'", path.display()))?;
+                println!("{}", path.display());
+            }
+            return Ok(());
+        }
+        Some(params::Command::Diff { .. })
+        | Some(params::Command::Daemon { .. })
+        | Some(params::Command::Lsp)
+        | Some(params::Command::Serve { .. })
+        | Some(params::Command::Render { .. })
+        | Some(params::Command::Sync { .. })
+        | None => (),
+    }
+
+    if let Some(params::Command::Daemon { socket, root }) = params.cmd {
+        // each request is a new run, therefore the timeout does not apply
+        let sync_options = documents::SyncOptions {
+            deadline: None,
+            ..sync_options
+        };
+        let open_options = OpenOptions {
+            deadline: None,
+            ..open_options
+        };
+        #[cfg(not(unix))]
+        return Err(anyhow!(
+            "the daemon is only supported on unix, not on {:?} in {:?}",
+            socket,
+            root
+        ));
+        #[cfg(unix)]
+        return daemon::serve(&socket, &root, |path| {
+            lock(path, &open_options)
+                .and_then(|_lock| {
+                    let mut documents = open_documents(path.to_path_buf(), &open_options)?;
+                    documents.parse()?;
+                    documents.sync(&sync_options)
+                })
+                .unwrap_or_else(|e| report::Report {
+                    diagnostics: vec![report::Diagnostic::from(&e)],
+                    ..Default::default()
+                })
+        });
+    }
+
+    if let Some(params::Command::Lsp) = params.cmd {
+        let sync_options = documents::SyncOptions {
+            deadline: None,
+            ..sync_options
+        };
+        let open_options = OpenOptions {
+            deadline: None,
+            ..open_options
+        };
+        let stdin = std::io::stdin();
+        return lsp::serve(
+            stdin.lock(),
+            std::io::stdout(),
+            |path| open_documents(path.to_path_buf(), &open_options),
+            &sync_options,
+        );
+    }
+
+    if let Some(params::Command::Serve { socket, root }) = params.cmd {
+        let sync_options = documents::SyncOptions {
+            deadline: None,
+            ..sync_options
+        };
+        let open_options = OpenOptions {
+            deadline: None,
+            ..open_options
+        };
+        let mut server = serve::Server::new(
+            |path: &Path| open_documents(path.to_path_buf(), &open_options),
+            &sync_options,
+            socket::Root::new(&root)?,
+            open_options.wait,
+        );
+        return match socket {
+            #[cfg(unix)]
+            Some(socket) => serve::serve_socket(&socket, &mut server),
+            #[cfg(not(unix))]
+            Some(_) => Err(anyhow!("'--socket' is only supported on unix")),
+            None => {
+                let stdin = std::io::stdin();
+                serve::serve(stdin.lock(), std::io::stdout(), &mut server)
+            }
+        };
+    }
+
+    if let Some(params::Command::Diff { doc_path, format }) = params.cmd {
+        let mut documents = open_documents(doc_path, &open_options)?;
+        documents.parse()?;
+        let report = documents.diff(&sync_options);
+        match format {
+            diff::DiffFormat::Unified => {
+                report.diffs.iter().for_each(|diff| print!("{}", diff));
+                report
+                    .failures
+                    .iter()
+                    .for_each(|failure| log::error!("{}", failure));
+            }
+            // reviewdog resolves the paths relative to the directory it runs in
+            diff::DiffFormat::Rdjsonl => print!("{}", report.rdjsonl(&std::env::current_dir()?)),
+        }
+        log::info!("{} stale snippets", report.diffs.len());
+        if !report.skipped.is_empty() {
+            log_timeout(&report.skipped);
+            std::process::exit(params.compat.exit_code_timeout());
+        }
+        if !report.failures.is_empty() {
+            return Err(anyhow!(
+                "{} files failed",
+                FileError::distinct_files(&report.failures)
+            ));
+        }
+        if params.deny_warnings && !report.warnings.is_empty() {
+            return Err(anyhow!(
+                "{} warnings with '--deny-warnings'",
+                report.warnings.len()
+            ));
+        }
+        return Ok(());
+    }
+
+    // the lock is held until the end of the run
+    let _lock;
+    let result = if let Some(params::Command::Render { doc_path, output }) = params.cmd {
+        _lock = lock(&doc_path, &open_options)?;
+        absolute(output)
+            .and_then(|output| {
+                documents::Documents::with_templates(absolute(doc_path)?, params.symlinks, output)
+            })
+            .and_then(|documents| configure(documents, &open_options))
+            .and_then(|mut documents| {
+                documents.parse()?;
+                documents.sync(&sync_options)
+            })
+    } else {
+        let doc_path = match params.cmd {
+            Some(params::Command::Sync { doc_path }) => Some(doc_path),
+            _ => params.doc_path,
+        }
+        .ok_or_else(|| anyhow!("the path to the markdown documentation is missing"))?;
+        _lock = lock(&doc_path, &open_options)?;
+        if params.recurse_submodules {
+            sync_submodules(doc_path, &open_options, &sync_options)
+        } else {
+            open_documents(doc_path.clone(), &open_options).and_then(|mut documents| {
+                documents.parse()?;
+                if fix_paths(&documents, &open_options)? {
+                    documents = open_documents(doc_path, &open_options)?;
+                    documents.parse()?;
+                }
+                documents.sync(&sync_options)
+            })
+        }
+    };
+    let mut report = match result {
+        Ok(report) => report,
+        Err(e) => {
+            let report = report::Report {
+                schema_version: params.compat.schema_version(),
+                diagnostics: vec![report::Diagnostic::from(&e)],
+                ..Default::default()
+            };
+            if let Some(report_json) = params.report_json {
+                report.write_json(&report_json)?;
+            }
+            if let Some(report_html) = params.report_html {
+                report.write_html(&report_html)?;
+            }
+            if let Some(report_junit) = params.report_junit {
+                report.write_junit(&report_junit)?;
+            }
+            if let Some(changed_files_out) = params.changed_files_out {
+                report.write_changed_files(&changed_files_out)?;
+            }
+            return Err(e.into());
+        }
+    };
+    report.schema_version = params.compat.schema_version();
+    report.summary.elapsed_secs = start.elapsed().as_secs_f64();
+    report.summary.log();
+    // all errors are listed together at the end, after the files which could be synced were processed
+    let diagnostics = report.all_diagnostics().collect::<Vec<_>>();
+    if !diagnostics.is_empty() {
+        log::error!("{} errors:", diagnostics.len());
+        diagnostics
+            .iter()
+            .for_each(|diagnostic| log::error!("{}", diagnostic));
+    }
+    if params.deny_warnings {
+        report
+            .all_warnings()
+            .for_each(|warning| log::error!("{}", warning));
+    }
+
+    if let Some(report_json) = params.report_json {
+        report.write_json(&report_json)?;
+    }
+
+    if let Some(report_html) = params.report_html {
+        report.write_html(&report_html)?;
+    }
+
+    if let Some(report_junit) = params.report_junit {
+        report.write_junit(&report_junit)?;
+    }
+
+    if let Some(changed_files_out) = params.changed_files_out {
+        report.write_changed_files(&changed_files_out)?;
+    }
+
+    if params.stage {
+        documents::Documents::stage(&report.all_changed_files())?;
+    }
+
+    let skipped = report.all_skipped();
+    if !skipped.is_empty() {
+        log_timeout(&skipped);
+        std::process::exit(params.compat.exit_code_timeout());
+    }
+
+    if report.summary.failed_files > 0 {
+        return Err(anyhow!("{} files failed", report.summary.failed_files));
+    }
+
+    if params.deny_warnings && report.summary.warnings > 0 {
+        return Err(anyhow!(
+            "{} warnings with '--deny-warnings'",
+            report.summary.warnings
+        ));
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::fs;
+    use tempfile::Builder;
+
+    #[test]
+    fn check_fails_if_code_blocks_are_not_in_sync() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let content_path = tmp_dir.path().join("content.cpp");
+        fs::write(&content_path, "//! [answer]\n42\n//! [answer]\n")?;
+        let md_path = tmp_dir.path().join("doc.md");
+        let md = format!(
+            "<!-- [geoffrey] [{}] [answer] -->\n```cpp\nstale\n```\n",
+            content_path.display()
+        );
+        fs::write(&md_path, &md)?;
+
+        let parsed = || -> Result<documents::Documents> {
+            let mut documents =
+                documents::Documents::new(md_path.clone(), documents::Symlinks::Follow)?;
+            documents.parse()?;
+            Ok(documents)
+        };
+        let options = documents::SyncOptions::default();
+
+        let error = check(parsed()?, None, &options).expect_err("stale code block");
+        assert!(error.to_string().contains("not in sync"), "{}", error);
+        assert_eq!(fs::read_to_string(&md_path)?, md);
+
+        fs::write(&md_path, md.replace("stale", "42"))?;
+        assert!(check(parsed()?, None, &options)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn check_counts_the_stale_files_and_returns_the_skipped_ones() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let content_path = tmp_dir.path().join("content.cpp");
+        fs::write(&content_path, "//! [answer]\n42\n//! [answer]\n")?;
+        let md_path = tmp_dir.path().join("doc.md");
+        // a documented git diff is no stale file
+        let md = format!(
+            "```diff\ndiff --git a/a b/a\ndiff --git a/b b/b\n```\n\n<!-- [geoffrey] [{}] [answer] -->\n```cpp\nstale\n```\n",
+            content_path.display()
+        );
+        fs::write(&md_path, &md)?;
+
+        let mut documents =
+            documents::Documents::new(md_path.clone(), documents::Symlinks::Follow)?;
+        documents.parse()?;
+        let error = check(documents, None, &documents::SyncOptions::default())
+            .expect_err("stale code block");
+        assert!(
+            error
+                .to_string()
+                .contains("of 1 markdown files are not in sync"),
+            "{}",
+            error
+        );
+
+        let mut documents =
+            documents::Documents::new(md_path.clone(), documents::Symlinks::Follow)?;
+        documents.parse()?;
+        let options = documents::SyncOptions {
+            deadline: Some(Instant::now()),
+            ..Default::default()
+        };
+        assert_eq!(check(documents, None, &options)?, vec![md_path]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn check_with_emit_patch_writes_patch_for_git_apply_and_fails() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+        let git = |args: &[&str]| -> Result<std::process::Output> {
+            Ok(std::process::Command::new("git")
+                .args(args)
+                .current_dir(tmp_dir.path())
+                .output()?)
+        };
+        git(&["init", "-q"])?;
+
+        fs::write(
+            tmp_dir.path().join("content.cpp"),
+            "//! [answer]\n42\n//! [answer]\n",
+        )?;
+        let md_path = tmp_dir.path().join("doc.md");
+        let md = "# Answer\n\n<!-- [geoffrey] [content.cpp] [answer] -->\n```cpp\nstale\n```\n";
+        fs::write(&md_path, md)?;
+        let patch_path = tmp_dir.path().join("geoffrey.patch");
+
+        let mut documents =
+            documents::Documents::new(tmp_dir.path().to_path_buf(), documents::Symlinks::Follow)?;
+        documents.parse()?;
+        let error = check(
+            documents,
+            Some(&patch_path),
+            &documents::SyncOptions::default(),
+        )
+        .expect_err("pending updates");
+        assert!(error.to_string().contains("not in sync"), "{}", error);
+        assert_eq!(fs::read_to_string(&md_path)?, md);
+
+        let applied = git(&["apply", "geoffrey.patch"])?;
+        assert!(
+            applied.status.success(),
+            "{}",
+            String::from_utf8_lossy(&applied.stderr)
+        );
+        assert_eq!(fs::read_to_string(&md_path)?, md.replace("stale", "42"));
+
+        Ok(())
+    }
+}
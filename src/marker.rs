@@ -0,0 +1,301 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::error::GeoffreyError;
+
+use regex::Regex;
+use serde::Deserialize;
+
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/// The comment syntax used for snippet markers and ellipsis lines in a content file
+#[derive(Debug)]
+pub struct CommentStyle {
+    /// the line comment of the language, used for ellipsis lines
+    pub comment: &'static str,
+    /// the doxygen snippet marker prefix
+    pub marker: &'static str,
+    /// the start of a block comment, which is closed by ' */'
+    block: Option<&'static str>,
+    extensions: &'static [&'static str],
+}
+
+const HASH_STYLE: CommentStyle = CommentStyle {
+    comment: "#",
+    marker: "##",
+    block: None,
+    extensions: &[
+        "py", "sh", "bash", "zsh", "cmake", "rb", "pl", "r", "toml", "yaml", "yml",
+    ],
+};
+
+const C_STYLE: CommentStyle = CommentStyle {
+    comment: "//",
+    marker: "//!",
+    block: Some("/*"),
+    extensions: &[],
+};
+
+impl CommentStyle {
+    /// The comment style for the content file at `path`, derived from its extension and falling back to the C style
+    pub fn for_path(path: &Path) -> &'static CommentStyle {
+        let ext = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        if HASH_STYLE.extensions.contains(&ext.as_str()) || path.ends_with("CMakeLists.txt") {
+            &HASH_STYLE
+        } else {
+            &C_STYLE
+        }
+    }
+
+    /// Regex matching a snippet marker in one of the `forms` with the indentation as first and the tag as second
+    /// capture group
+    pub fn marker_regex(&self, forms: &[MarkerForm]) -> Result<Regex, GeoffreyError> {
+        let mut prefixes = forms
+            .iter()
+            .filter_map(|form| match form {
+                MarkerForm::Doxygen => Some(self.marker),
+                MarkerForm::Line => Some(self.comment),
+                MarkerForm::Block => self.block,
+            })
+            .collect::<Vec<&str>>();
+        // the longest prefix first, since the line comment is a prefix of the doxygen marker
+        prefixes.sort_by_key(|prefix| std::cmp::Reverse(prefix.len()));
+        let prefixes = prefixes
+            .iter()
+            .map(|prefix| regex::escape(prefix))
+            .collect::<Vec<String>>()
+            .join("|");
+        let block_end = if forms.contains(&MarkerForm::Block) && self.block.is_some() {
+            r"(?: \*/)?"
+        } else {
+            ""
+        };
+
+        Regex::new(&format!(r"([ \t]*)(?:{}) \[(.*)\]{}", prefixes, block_end))
+            .map_err(|_| GeoffreyError::RegexError)
+    }
+
+    pub fn marker_line(&self, indentation: &str, tag: &str) -> String {
+        format!("{}{} [{}]", indentation, self.marker, tag)
+    }
+
+    pub fn ellipsis_line(&self, indentation: &str) -> String {
+        format!("{}{} ...\n", indentation, self.comment)
+    }
+}
+
+/// A comment form accepted for snippet markers in content files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MarkerForm {
+    /// '//! [tag]', or '## [tag]' in files with hash comments, like the snippet markers of doxygen
+    Doxygen,
+    /// '// [tag]', or '# [tag]' in files with hash comments
+    Line,
+    /// '/* [tag] */' in files with C style comments
+    Block,
+}
+
+/// An inclusive range of 1-based line numbers, e.g. '40-78' or '42'
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineRange {
+    pub first: usize,
+    pub last: usize,
+}
+
+impl FromStr for LineRange {
+    type Err = GeoffreyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || GeoffreyError::InvalidLineRange(s.to_owned());
+        let (first, last) = s.split_once('-').unwrap_or((s, s));
+        let first = first.trim().parse::<usize>().map_err(|_| invalid())?;
+        let last = last.trim().parse::<usize>().map_err(|_| invalid())?;
+
+        if first == 0 || first > last {
+            return Err(invalid());
+        }
+
+        Ok(Self { first, last })
+    }
+}
+
+/// Wraps the lines of `range` in the content file at `path` with begin and end markers for `tag`
+pub fn mark(path: &Path, range: LineRange, tag: &str) -> Result<(), GeoffreyError> {
+    if tag.trim().is_empty() || tag.contains(']') || tag.contains('\n') {
+        return Err(GeoffreyError::InvalidTagName(tag.to_owned()));
+    }
+
+    let style = CommentStyle::for_path(path);
+    let content = fs::read_to_string(path)?;
+    let mut lines = content.split_inclusive('\n').collect::<Vec<&str>>();
+
+    if range.last > lines.len() {
+        return Err(GeoffreyError::LineRangeOutOfBounds(
+            path.to_path_buf(),
+            range.first,
+            range.last,
+            lines.len(),
+        ));
+    }
+
+    let re = style.marker_regex(&[MarkerForm::Doxygen])?;
+    if lines.iter().any(|line| {
+        re.captures(line)
+            .and_then(|caps| caps.get(2))
+            .is_some_and(|existing| existing.as_str() == tag)
+    }) {
+        return Err(GeoffreyError::ContentSnippetDoubleTag(
+            path.to_path_buf(),
+            tag.to_owned(),
+        ));
+    }
+
+    let eol = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let indentation = lines[range.first - 1..range.last]
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start_matches(' ').len())
+        .min()
+        .unwrap_or(0);
+    let indentation = " ".repeat(indentation);
+    let marker = style.marker_line(&indentation, tag) + eol;
+
+    // the last line might not be terminated, which would glue the end marker to it
+    let terminated_last_line;
+    if !lines[range.last - 1].ends_with('\n') {
+        terminated_last_line = lines[range.last - 1].to_owned() + eol;
+        lines[range.last - 1] = &terminated_last_line;
+    }
+
+    lines.insert(range.last, &marker);
+    lines.insert(range.first - 1, &marker);
+
+    fs::write(path, lines.concat())?;
+
+    log::info!(
+        "marked lines {}-{} of {:?} as '{}'",
+        range.first,
+        range.last,
+        path,
+        tag
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use anyhow::Result;
+    use tempfile::Builder;
+
+    #[test]
+    fn line_range_parses_ranges_and_single_lines() -> Result<()> {
+        assert_eq!(
+            "40-78".parse::<LineRange>()?,
+            LineRange {
+                first: 40,
+                last: 78
+            }
+        );
+        assert_eq!(
+            "42".parse::<LineRange>()?,
+            LineRange {
+                first: 42,
+                last: 42
+            }
+        );
+        assert!("0-3".parse::<LineRange>().is_err());
+        assert!("5-3".parse::<LineRange>().is_err());
+        assert!("foo".parse::<LineRange>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn mark_wraps_range_with_indented_markers() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+        let path = tmp_dir.path().join("main.cpp");
+        fs::write(&path, "int main() {\n    foo();\n\n    bar();\n}")?;
+
+        mark(&path, "2-4".parse()?, "calls")?;
+        mark(&path, "7".parse()?, "end")?;
+
+        assert_eq!(
+            fs::read_to_string(&path)?,
+            "int main() {\n    //! [calls]\n    foo();\n\n    bar();\n    //! [calls]\n//! [end]\n}\n//! [end]\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn mark_uses_comment_style_of_the_language() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+        let path = tmp_dir.path().join("main.py");
+        fs::write(&path, "import os\nprint(os.name)\n")?;
+
+        mark(&path, "2".parse()?, "print")?;
+
+        assert_eq!(
+            fs::read_to_string(&path)?,
+            "import os\n## [print]\nprint(os.name)\n## [print]\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn marker_regex_accepts_the_configured_forms() -> Result<()> {
+        let tag = |style: &CommentStyle, forms: &[MarkerForm], line: &str| {
+            style.marker_regex(forms).map(|re| {
+                re.captures(line)
+                    .map(|caps| (caps[1].to_owned(), caps[2].to_owned()))
+            })
+        };
+        let found = |indentation: &str, tag: &str| Some((indentation.to_owned(), tag.to_owned()));
+
+        let all = [MarkerForm::Doxygen, MarkerForm::Line, MarkerForm::Block];
+        assert_eq!(tag(&C_STYLE, &all, "  //! [a]\n")?, found("  ", "a"));
+        assert_eq!(tag(&C_STYLE, &all, "\t //! [a]\n")?, found("\t ", "a"));
+        assert_eq!(tag(&C_STYLE, &all, "  // [a]\n")?, found("  ", "a"));
+        assert_eq!(tag(&C_STYLE, &all, "  /* [a] */\n")?, found("  ", "a"));
+        assert_eq!(tag(&HASH_STYLE, &all, "  # [a]\n")?, found("  ", "a"));
+        assert_eq!(tag(&HASH_STYLE, &all, "  ## [a]\n")?, found("  ", "a"));
+
+        assert_eq!(tag(&C_STYLE, &[MarkerForm::Doxygen], "// [a]\n")?, None);
+        assert_eq!(tag(&C_STYLE, &[MarkerForm::Line], "//! [a]\n")?, None);
+        assert_eq!(tag(&C_STYLE, &[MarkerForm::Line], "/* [a] */\n")?, None);
+        assert_eq!(
+            tag(&C_STYLE, &[MarkerForm::Block], "/* [a] */\n")?,
+            found("", "a")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn mark_with_existing_tag_fails() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+        let path = tmp_dir.path().join("main.cpp");
+        fs::write(&path, "//! [foo]\nfoo();\n//! [foo]\nbar();\n")?;
+
+        assert!(matches!(
+            mark(&path, "4".parse()?, "foo"),
+            Err(GeoffreyError::ContentSnippetDoubleTag(_, _))
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use similar::{capture_diff_slices, Algorithm, DiffOp};
+
+/// The marker lines around the conflicting lines of both sides
+const CONFLICT_BEGIN: &str = "<<<<<<< documentation\n";
+const CONFLICT_SEPARATOR: &str = "=======\n";
+const CONFLICT_END: &str = ">>>>>>> content\n";
+
+/// For each line of `base`, the index of the same line in `other` if it is unchanged
+fn unchanged_lines(base: &[&str], other: &[&str]) -> Vec<Option<usize>> {
+    let mut unchanged = vec![None; base.len()];
+    for op in capture_diff_slices(Algorithm::Myers, base, other) {
+        if let DiffOp::Equal {
+            old_index,
+            new_index,
+            len,
+        } = op
+        {
+            for offset in 0..len {
+                unchanged[old_index + offset] = Some(new_index + offset);
+            }
+        }
+    }
+
+    unchanged
+}
+
+/// Appends the lines and terminates the last one, so that a following marker line starts on its own line
+fn push_lines(merged: &mut String, lines: &[&str]) {
+    for line in lines {
+        merged.push_str(line);
+    }
+    if !merged.is_empty() && !merged.ends_with('\n') {
+        merged.push('\n');
+    }
+}
+
+/// Merges the changes from `base` to `ours` and from `base` to `theirs` line by line; lines changed differently on
+/// both sides are kept from both sides between conflict markers. Returns the merged text and the number of conflicts.
+pub fn merge(base: &str, ours: &str, theirs: &str) -> (String, usize) {
+    let base = base.split_inclusive('\n').collect::<Vec<&str>>();
+    let ours = ours.split_inclusive('\n').collect::<Vec<&str>>();
+    let theirs = theirs.split_inclusive('\n').collect::<Vec<&str>>();
+    let ours_unchanged = unchanged_lines(&base, &ours);
+    let theirs_unchanged = unchanged_lines(&base, &theirs);
+
+    let mut merged = String::new();
+    let mut conflicts = 0;
+    let (mut base_start, mut ours_start, mut theirs_start) = (0, 0, 0);
+    // the chunks between the lines unchanged on both sides are merged; the end is the last stable position
+    for line in 0..=base.len() {
+        let (ours_line, theirs_line) = match line == base.len() {
+            true => (ours.len(), theirs.len()),
+            false => match (ours_unchanged[line], theirs_unchanged[line]) {
+                (Some(ours_line), Some(theirs_line)) => (ours_line, theirs_line),
+                _ => continue,
+            },
+        };
+
+        let base_chunk = &base[base_start..line];
+        let ours_chunk = &ours[ours_start..ours_line];
+        let theirs_chunk = &theirs[theirs_start..theirs_line];
+        if ours_chunk == base_chunk {
+            merged.extend(theirs_chunk.iter().copied());
+        } else if theirs_chunk == base_chunk || ours_chunk == theirs_chunk {
+            merged.extend(ours_chunk.iter().copied());
+        } else {
+            merged.push_str(CONFLICT_BEGIN);
+            push_lines(&mut merged, ours_chunk);
+            merged.push_str(CONFLICT_SEPARATOR);
+            push_lines(&mut merged, theirs_chunk);
+            merged.push_str(CONFLICT_END);
+            conflicts += 1;
+        }
+
+        if line < base.len() {
+            merged.push_str(base[line]);
+        }
+        base_start = line + 1;
+        ours_start = ours_line + 1;
+        theirs_start = theirs_line + 1;
+    }
+
+    (merged, conflicts)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn changes_of_both_sides_are_merged() {
+        let base = "fn answer() {\n    let x = 1;\n    42\n}\n";
+        let ours = "fn answer() {\n    // the answer\n    let x = 1;\n    42\n}\n";
+        let theirs = "fn answer() {\n    let x = 1;\n    43\n}\n";
+
+        assert_eq!(
+            merge(base, ours, theirs),
+            (
+                "fn answer() {\n    // the answer\n    let x = 1;\n    43\n}\n".to_owned(),
+                0
+            )
+        );
+    }
+
+    #[test]
+    fn different_changes_of_the_same_lines_are_conflicts() {
+        let base = "a\nb\nc\n";
+        let ours = "a\nB\nc\n";
+        let theirs = "a\nbb\nc\n";
+
+        assert_eq!(
+            merge(base, ours, theirs),
+            (
+                "a\n<<<<<<< documentation\nB\n=======\nbb\n>>>>>>> content\nc\n".to_owned(),
+                1
+            )
+        );
+        assert_eq!(merge(base, ours, ours), (ours.to_owned(), 0));
+    }
+}
@@ -1,13 +1,431 @@
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::logging::Color;
+
+use geoffrey::compat::Compat;
+use geoffrey::diff::DiffFormat;
+use geoffrey::documents::{Fallback, Symlinks};
+use geoffrey::marker::LineRange;
+
+use clap::builder::{PossibleValuesParser, TypedValueParser};
+use clap::{ArgAction, Parser, Subcommand};
+
 use std::path::PathBuf;
-use structopt::StructOpt;
 
 /// Syncs source code to markdown code blocks
-#[derive(StructOpt, Debug)]
-#[structopt(rename_all = "kebab-case")]
+#[derive(Parser, Debug)]
+#[command(version)]
 pub struct Params {
-    /// Path to file or folder with the markdown documentation to sync
-    #[structopt(parse(from_os_str))]
-    pub doc_path: PathBuf,
+    #[command(subcommand)]
+    pub cmd: Option<Command>,
+
+    /// Path to file or folder with the markdown documentation to sync; short for 'geoffrey sync <doc path>'
+    pub doc_path: Option<PathBuf>,
+
+    /// Increases the log output; '-v' for debug and '-vv' for trace messages
+    #[arg(
+        short,
+        long,
+        global = true,
+        action = ArgAction::Count,
+        conflicts_with = "quiet"
+    )]
+    pub verbose: u8,
+
+    /// Only prints errors
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// Sets the log level explicitly and overrides '--verbose' and '--quiet'
+    #[arg(
+        long,
+        global = true,
+        value_parser = ["off", "error", "warn", "info", "debug", "trace"]
+    )]
+    pub log_level: Option<String>,
+
+    /// Whether the log messages and diffs are colored; 'auto' colors them if the 'NO_COLOR' environment variable
+    /// is not set and the output goes to a terminal
+    #[arg(
+        long,
+        global = true,
+        default_value = "auto",
+        value_parser = PossibleValuesParser::new(["auto", "always", "never"]).try_map(|s| s.parse::<Color>())
+    )]
+    pub color: Color,
+
+    /// Also appends the log messages to the given file, which is rotated once it gets too large; overrides
+    /// 'log_file' of the config
+    #[arg(long, global = true)]
+    pub log_file: Option<PathBuf>,
+
+    /// Directories in which the content files are searched in order instead of the git toplevel;
+    /// overrides the content roots from the config
+    #[arg(long, global = true)]
+    pub content_root: Vec<PathBuf>,
+
+    /// Whether symlinked markdown files and directories are followed or skipped
+    #[arg(
+        long,
+        global = true,
+        default_value = "follow",
+        value_parser = PossibleValuesParser::new(["follow", "skip"]).try_map(|s| s.parse::<Symlinks>())
+    )]
+    pub symlinks: Symlinks,
+
+    /// Only processes the markdown files matching the glob, e.g. 'doc/guides/*.md'; can be repeated
+    #[arg(long, global = true)]
+    pub include: Vec<String>,
+
+    /// Only processes the tags with the content path, or the content path and snippet like 'source/main.cpp#main',
+    /// as written in the tag; the other code blocks are left untouched; can be repeated
+    #[arg(long, global = true)]
+    pub only_tag: Vec<String>,
+
+    /// Only processes the markdown files which changed since the git revision, like 'origin/main', or which
+    /// reference content files that changed since
+    #[arg(long, global = true)]
+    pub since: Option<String>,
+
+    /// Keeps code blocks which differ from the content only in indentation or trailing whitespace
+    #[arg(long, global = true)]
+    pub ignore_whitespace: bool,
+
+    /// Fails the run if there are warnings, like an empty snippet or a code block language which does not
+    /// fit the content file
+    #[arg(long, global = true)]
+    pub deny_warnings: bool,
+
+    /// Keeps the diagnostic codes, the schema of the JSON report and the exit codes of the given major version
+    #[arg(
+        long,
+        global = true,
+        default_value = "1",
+        value_parser = PossibleValuesParser::new(["1"]).try_map(|s| s.parse::<Compat>())
+    )]
+    pub compat: Compat,
+
+    /// Stops starting new markdown files after the given number of seconds and exits with code 124
+    #[arg(long, global = true)]
+    pub timeout: Option<u64>,
+
+    /// Syncs the markdown files of each git submodule within the doc path separately, with the content
+    /// resolved in the submodule; the report contains a section per submodule
+    #[arg(long, global = true)]
+    pub recurse_submodules: bool,
+
+    /// Rewrites the paths of tags whose content file is missing because it was renamed in git, before syncing
+    #[arg(long, global = true)]
+    pub fix_paths: bool,
+
+    /// Overwrites code blocks which were edited by hand since they were synced, see 'guard_edits' in the config
+    #[arg(long, global = true)]
+    pub force: bool,
+
+    /// Compiles each synced snippet with the command configured in 'verify' of the config for its language and
+    /// fails the markdown files with snippets which do not compile
+    #[arg(long, global = true)]
+    pub verify: bool,
+
+    /// Runs the snippets of output tags with the commands configured in 'run' of the config when syncing or
+    /// rendering; other commands never run them and keep the code blocks of output tags
+    #[arg(long, global = true)]
+    pub run_snippets: bool,
+
+    /// Fails instead of waiting if another geoffrey run, e.g. the daemon, is writing the same documentation
+    #[arg(long, global = true)]
+    pub no_wait: bool,
+
+    /// Writes a JSON report with the summary of the run to the given path
+    #[arg(long, global = true)]
+    pub report_json: Option<PathBuf>,
+
+    /// Writes an HTML page with the status of each document, the diffs of the updated code blocks
+    /// and the unused snippet markers to the given path
+    #[arg(long, global = true)]
+    pub report_html: Option<PathBuf>,
+
+    /// Writes a JUnit XML report to the given path, with a test case per markdown file which fails if code
+    /// blocks were out of sync or errors occurred
+    #[arg(long, global = true)]
+    pub report_junit: Option<PathBuf>,
+
+    /// Writes the paths of the markdown files changed by the sync to the given path, one per line or as JSON
+    /// array if the path ends with '.json'
+    #[arg(long, global = true)]
+    pub changed_files_out: Option<PathBuf>,
+
+    /// Stages the markdown files changed by the sync with 'git add', e.g. in a pre-commit hook
+    #[arg(long, global = true)]
+    pub stage: bool,
+
+    /// The behavior when a snippet is not found in the content file; with 'skip', also when the content file
+    /// cannot be read
+    #[arg(
+        long,
+        global = true,
+        default_value = "error",
+        value_parser = PossibleValuesParser::new(["full-file", "skip", "error"])
+            .try_map(|s| s.parse::<Fallback>())
+    )]
+    pub fallback: Fallback,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Syncs the code blocks of the markdown documentation with their content files; the default command
+    Sync {
+        /// Path to file or folder with the markdown documentation
+        doc_path: PathBuf,
+    },
+    /// Creates a starter 'geoffrey.toml' and prints an overview of the snippet markers and geoffrey tags
+    Init {
+        /// The directory for the config, usually the git toplevel
+        #[arg(default_value = ".")]
+        dir: PathBuf,
+
+        /// Also creates an example source file with snippet markers and a markdown page embedding them
+        #[arg(long)]
+        example: bool,
+    },
+    /// Inserts snippet markers into a content file
+    Mark {
+        /// Path to the content file
+        file: PathBuf,
+
+        /// The lines to wrap with the snippet markers, e.g. '40-78'
+        #[arg(long)]
+        lines: LineRange,
+
+        /// The name of the snippet
+        #[arg(long)]
+        tag: String,
+    },
+    /// Lists all geoffrey tags in the markdown documentation
+    List {
+        /// Path to file or folder with the markdown documentation
+        doc_path: PathBuf,
+
+        /// Prints the tags as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Checks that all referenced content files and snippets exist without writing anything
+    Validate {
+        /// Path to file or folder with the markdown documentation
+        doc_path: PathBuf,
+    },
+    /// Checks that all referenced content files and snippets exist and that the code blocks are in sync with them,
+    /// without writing anything
+    Check {
+        /// Path to file or folder with the markdown documentation
+        doc_path: PathBuf,
+
+        /// Writes the pending updates of the code blocks as patch for 'git apply' to the given path
+        #[arg(long)]
+        emit_patch: Option<PathBuf>,
+    },
+    /// Rewrites the geoffrey tags into their canonical form without touching any other content
+    Fmt {
+        /// Path to file or folder with the markdown documentation
+        doc_path: PathBuf,
+
+        /// Only lists the files with tags which are not in canonical form and fails if there are any
+        #[arg(long)]
+        check: bool,
+    },
+    /// Shows the diff of every code block which is not in sync with its content file
+    Diff {
+        /// Path to file or folder with the markdown documentation
+        #[arg(default_value = ".")]
+        doc_path: PathBuf,
+
+        /// 'unified' prints a diff per code block and 'rdjsonl' a JSON line per stale code block, failure and
+        /// warning for 'reviewdog -f=rdjsonl'
+        #[arg(
+            long,
+            default_value = "unified",
+            value_parser = PossibleValuesParser::new(["unified", "rdjsonl"]).try_map(|s| s.parse::<DiffFormat>())
+        )]
+        format: DiffFormat,
+    },
+    /// Renders the markdown templates ending with '.md.tpl' to markdown files in the output directory
+    Render {
+        /// Path to file or folder with the markdown templates
+        doc_path: PathBuf,
+
+        /// The directory for the rendered markdown files
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Assembles source files from the code blocks following '<!-- [tangle] [path] -->' tags
+    Tangle {
+        /// Path to file or folder with the markdown documentation
+        doc_path: PathBuf,
+
+        /// The directory for the assembled source files
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Serves 'resync <path>' requests of build servers on a unix socket, one per line, and answers each with a
+    /// line of JSON
+    Daemon {
+        /// The unix socket to listen on
+        #[arg(long, default_value = "geoffrey.sock")]
+        socket: PathBuf,
+
+        /// The folder with the documentation; requests for paths outside of it are refused
+        #[arg(long, default_value = ".")]
+        root: PathBuf,
+    },
+    /// Serves JSON-RPC requests 'sync', 'check', 'listTags' and 'diff' with the params '{"path": "doc"}', one per
+    /// line, on stdin and stdout or a unix socket, keeping the parsed documentation in memory between requests
+    Serve {
+        /// Listen on this unix socket instead of stdin and stdout
+        #[arg(long)]
+        socket: Option<PathBuf>,
+
+        /// The folder with the documentation; requests for paths outside of it are refused
+        #[arg(long, default_value = ".")]
+        root: PathBuf,
+    },
+    /// Serves the language server protocol on stdin and stdout for editors, with diagnostics for invalid tags
+    /// and stale code blocks, go-to-definition from a tag to its snippet and code actions to sync a code block
+    Lsp,
+    /// Lists snippet markers in content files which are not referenced by the markdown documentation
+    Orphans {
+        /// Path to file or folder with the markdown documentation
+        doc_path: PathBuf,
+
+        /// Additional folders to scan for content files with snippet markers
+        #[arg(long)]
+        scan: Vec<PathBuf>,
+
+        /// Prints the orphaned markers as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Prints the content files defining a snippet and the geoffrey tags embedding it, e.g. before renaming it
+    #[command(visible_alias = "grep")]
+    WhereUsed {
+        /// The name of the snippet
+        snippet: String,
+
+        /// Path to file or folder with the markdown documentation
+        #[arg(default_value = ".")]
+        doc_path: PathBuf,
+
+        /// Prints the definitions and tags as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Renames a snippet in the markers of the content files defining it and in the geoffrey tags embedding it;
+    /// either all files are changed or none
+    RenameTag {
+        /// The current name of the snippet
+        old: String,
+
+        /// The new name of the snippet
+        new: String,
+
+        /// Path to file or folder with the markdown documentation
+        #[arg(default_value = ".")]
+        doc_path: PathBuf,
+
+        /// Prints the diff of the changes without writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Checks the doc path, the git toplevel, the config, the permissions of the markdown files and the
+    /// referenced content files and prints a checklist
+    Doctor {
+        /// Path to file or folder with the markdown documentation
+        #[arg(default_value = ".")]
+        doc_path: PathBuf,
+    },
+    /// Prints a longer description and typical fixes of an error or warning code like 'G007'
+    Explain {
+        /// The code printed with the error or warning
+        code: String,
+    },
+}
+
+impl Params {
+    /// The log level derived from '--log-level', '--verbose' and '--quiet' with 'info' as default
+    pub fn log_level(&self) -> &str {
+        if let Some(log_level) = &self.log_level {
+            return log_level;
+        }
+
+        if self.quiet {
+            return "error";
+        }
+
+        match self.verbose {
+            0 => "info",
+            1 => "debug",
+            _ => "trace",
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use clap::CommandFactory;
+
+    use std::path::Path;
+
+    #[test]
+    fn params_are_consistent() {
+        Params::command().debug_assert();
+    }
+
+    #[test]
+    fn log_level_is_derived_from_flags() {
+        let log_level = |args: &[&str]| {
+            Params::parse_from(["geoffrey", "doc"].iter().chain(args.iter()))
+                .log_level()
+                .to_owned()
+        };
+
+        assert_eq!(log_level(&[]), "info");
+        assert_eq!(log_level(&["-v"]), "debug");
+        assert_eq!(log_level(&["-vv"]), "trace");
+        assert_eq!(log_level(&["-q"]), "error");
+        assert_eq!(log_level(&["-q", "--log-level", "warn"]), "warn");
+    }
+
+    #[test]
+    fn color_defaults_to_auto() {
+        assert_eq!(Params::parse_from(["geoffrey", "doc"]).color, Color::Auto);
+        assert_eq!(
+            Params::parse_from(["geoffrey", "diff", "doc", "--color", "never"]).color,
+            Color::Never
+        );
+        assert!(Params::try_parse_from(["geoffrey", "doc", "--color", "sometimes"]).is_err());
+    }
+
+    #[test]
+    fn sync_is_the_default_command() {
+        let params = Params::parse_from(["geoffrey", "doc", "--fallback", "skip"]);
+        assert!(params.cmd.is_none());
+        assert_eq!(params.doc_path, Some(PathBuf::from("doc")));
+        assert_eq!(params.fallback, Fallback::Skip);
+
+        let params =
+            Params::parse_from(["geoffrey", "sync", "doc", "--report-json", "report.json"]);
+        assert!(
+            matches!(params.cmd, Some(Command::Sync { doc_path }) if doc_path == Path::new("doc"))
+        );
+        assert_eq!(params.report_json, Some(PathBuf::from("report.json")));
+
+        let params = Params::parse_from(["geoffrey", "check", "doc"]);
+        assert!(matches!(params.cmd, Some(Command::Check { .. })));
+        let params = Params::parse_from(["geoffrey", "validate", "doc"]);
+        assert!(matches!(params.cmd, Some(Command::Validate { .. })));
+
+        assert!(Params::try_parse_from(["geoffrey", "doc", "--symlinks", "never"]).is_err());
+    }
 }
@@ -0,0 +1,100 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use serde::{Deserialize, Serialize};
+
+use std::fmt;
+
+/// The origin of a synced code block, which is emitted with `provenance` of the config as comment in the first line
+/// of the code block, like
+/// '// geoffrey-provenance: {"path":"src/main.cpp","snippet":"answer","commit":"1a2b3c4d5e6f","hash":"0123456789abcdef"}'.
+///
+/// The hash covers the lines of the code block after the comment with '\n' line endings, so that tools can verify
+/// that the code block was not edited since it was synced; geoffrey itself reports such a code block as outdated.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Provenance {
+    /// the content file path as written in the geoffrey tag
+    pub path: String,
+    /// the embedded snippet or `None` for the full file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+    /// the last commit which changed the content file when the code was synced; `None` outside of git
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    /// the hash of the code, see `hash`
+    pub hash: String,
+}
+
+impl Provenance {
+    /// The keyword after the comment prefix which starts a provenance comment
+    pub const KEYWORD: &'static str = "geoffrey-provenance:";
+
+    pub fn new(path: String, snippet: Option<String>, commit: Option<String>, code: &str) -> Self {
+        Self {
+            path,
+            snippet,
+            commit,
+            hash: hash(code),
+        }
+    }
+
+    /// The provenance in a comment line of a code block with any comment prefix, or `None` if the line is no
+    /// provenance comment
+    pub fn parse(line: &str) -> Option<Self> {
+        let (_, json) = line.split_once(Self::KEYWORD)?;
+        serde_json::from_str(json.trim()).ok()
+    }
+
+    /// Whether `code`, the lines of the code block after the provenance comment, is the code it was synced with
+    pub fn verify(&self, code: &str) -> bool {
+        self.hash == hash(&code.replace("\r\n", "\n"))
+    }
+}
+
+impl fmt::Display for Provenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let json = serde_json::to_string(self).map_err(|_| fmt::Error)?;
+        write!(f, "{} {}", Self::KEYWORD, json)
+    }
+}
+
+/// The FNV-1a hash of `text` as 16 hex digits; unlike the hasher of the standard library, it is stable across
+/// versions
+pub fn hash(text: &str) -> String {
+    let hash = text.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn provenance_comments_are_parsed_back_and_verify_the_code() {
+        let code = "int a = 42;\nreturn a;\n";
+        let provenance = Provenance::new(
+            "src/main.cpp".to_owned(),
+            Some("answer".to_owned()),
+            None,
+            code,
+        );
+        let line = format!("    // {}", provenance);
+        assert_eq!(
+            line,
+            format!(
+                "    // geoffrey-provenance: {{\"path\":\"src/main.cpp\",\"snippet\":\"answer\",\"hash\":\"{}\"}}",
+                hash(code)
+            )
+        );
+
+        let parsed = Provenance::parse(&line).expect("provenance comment");
+        assert_eq!(parsed, provenance);
+        assert!(parsed.verify(code));
+        assert!(parsed.verify(&code.replace('\n', "\r\n")));
+        assert!(!parsed.verify("int a = 43;\nreturn a;\n"));
+
+        assert_eq!(Provenance::parse("// geoffrey-provenance: {"), None);
+        assert_eq!(Provenance::parse("int a = 42;"), None);
+    }
+}
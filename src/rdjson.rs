@@ -0,0 +1,208 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::diff::{BlockDiff, DiffReport};
+use crate::report;
+
+use serde::Serialize;
+
+use std::path::Path;
+
+/// A diagnostic in the Reviewdog Diagnostic Format, see
+/// https://github.com/reviewdog/reviewdog/tree/master/proto/rdf
+#[derive(Debug, Serialize)]
+struct Diagnostic {
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    location: Option<Location>,
+    severity: &'static str,
+    source: Source,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<Code>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    suggestions: Vec<Suggestion>,
+}
+
+#[derive(Debug, Serialize)]
+struct Location {
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    range: Option<Range>,
+}
+
+#[derive(Debug, Serialize)]
+struct Range {
+    start: Position,
+    /// exclusive; the start if the range is empty
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end: Option<Position>,
+}
+
+/// The line and column, both starting at 1
+#[derive(Debug, Serialize)]
+struct Position {
+    line: usize,
+    column: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct Source {
+    name: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+struct Code {
+    value: String,
+}
+
+/// The text replacing the range, which reviewdog posts as suggested change
+#[derive(Debug, Serialize)]
+struct Suggestion {
+    range: Range,
+    text: String,
+}
+
+/// The path relative to `base` like reviewdog expects it, or the full path if it is outside of `base`
+fn relative_path(path: &Path, base: &Path) -> String {
+    path.strip_prefix(base)
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
+
+/// The stale code block with a suggestion replacing its lines with the synced ones; inline code spans are
+/// reported without suggestion, since reviewdog expects the columns in bytes
+fn stale_block(diff: &BlockDiff, base: &Path) -> Diagnostic {
+    let source = match &diff.snippet {
+        Some(snippet) => format!("the snippet '{}' of '{}'", snippet, diff.content_file),
+        None => format!("'{}'", diff.content_file),
+    };
+    let start = Position {
+        line: diff.code_line,
+        column: diff.column.map_or(1, |column| column + 1),
+    };
+    let suggestions = match diff.column {
+        Some(_) => Vec::new(),
+        None => vec![Suggestion {
+            range: Range {
+                start: Position {
+                    line: diff.code_line,
+                    column: 1,
+                },
+                end: Some(Position {
+                    line: diff.code_line + diff.current.lines().count(),
+                    column: 1,
+                }),
+            },
+            text: diff.synced.clone(),
+        }],
+    };
+
+    Diagnostic {
+        message: format!("The code block is not in sync with {}", source),
+        location: Some(Location {
+            path: relative_path(&diff.md_file, base),
+            range: Some(Range { start, end: None }),
+        }),
+        severity: "ERROR",
+        source: Source { name: "geoffrey" },
+        code: None,
+        suggestions,
+    }
+}
+
+fn diagnostic(diagnostic: &report::Diagnostic, severity: &'static str, base: &Path) -> Diagnostic {
+    Diagnostic {
+        message: diagnostic.message.clone(),
+        location: diagnostic.file.as_ref().map(|file| Location {
+            path: relative_path(file, base),
+            range: diagnostic.span.as_ref().map(|span| Range {
+                start: Position {
+                    line: span.line,
+                    column: span.column,
+                },
+                end: None,
+            }),
+        }),
+        severity,
+        source: Source { name: "geoffrey" },
+        code: Some(Code {
+            value: diagnostic.code.clone(),
+        }),
+        suggestions: Vec::new(),
+    }
+}
+
+/// The stale code blocks, failures and warnings of the diff as JSON lines for 'reviewdog -f=rdjsonl', with
+/// the paths relative to `base`
+pub fn render(report: &DiffReport, base: &Path) -> String {
+    let failures = report
+        .failures
+        .iter()
+        .map(|failure| diagnostic(&report::Diagnostic::from(failure), "ERROR", base));
+    let warnings = report
+        .warnings
+        .iter()
+        .map(|warning| diagnostic(warning, "WARNING", base));
+
+    report
+        .diffs
+        .iter()
+        .map(|diff| stale_block(diff, base))
+        .chain(failures)
+        .chain(warnings)
+        .map(|diagnostic| {
+            serde_json::to_string(&diagnostic).expect("the diagnostic is serializable") + "\n"
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::error::{FileError, GeoffreyError, SourceSpan};
+
+    use std::path::PathBuf;
+
+    #[test]
+    fn stale_blocks_and_failures_are_rendered_as_json_lines() {
+        let report = DiffReport {
+            diffs: vec![BlockDiff {
+                md_file: PathBuf::from("/repo/doc/guide.md"),
+                line: 3,
+                code_line: 5,
+                column: None,
+                content_file: "src/main.cpp".to_owned(),
+                snippet: Some("main".to_owned()),
+                current: "old();\nold();\n".to_owned(),
+                synced: "new();\n".to_owned(),
+            }],
+            failures: vec![FileError::new(
+                PathBuf::from("/repo/doc/intro.md"),
+                GeoffreyError::ContentSnippetNotFound(
+                    "src/main.cpp".to_owned(),
+                    "init".to_owned(),
+                    Vec::new(),
+                )
+                .at(SourceSpan::new(
+                    7,
+                    "<!-- [geoffrey] [src/main.cpp] [init] -->",
+                    32,
+                )),
+            )],
+            warnings: Vec::new(),
+            skipped: Vec::new(),
+        };
+
+        let lines = render(&report, Path::new("/repo"));
+        let lines = lines.lines().collect::<Vec<&str>>();
+        assert_eq!(
+            lines[0],
+            r#"{"message":"The code block is not in sync with the snippet 'main' of 'src/main.cpp'","location":{"path":"doc/guide.md","range":{"start":{"line":5,"column":1}}},"severity":"ERROR","source":{"name":"geoffrey"},"suggestions":[{"range":{"start":{"line":5,"column":1},"end":{"line":7,"column":1}},"text":"new();\n"}]}"#
+        );
+        assert!(lines[1].starts_with(
+            r#"{"message":"The content snippet 'init' in the content file 'src/main.cpp' was not found","location":{"path":"doc/intro.md","range":{"start":{"line":7,"column":33}}},"severity":"ERROR""#
+        ));
+        assert!(lines[1].ends_with(r#""code":{"value":"G009"}}"#));
+    }
+}
@@ -0,0 +1,424 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::compat::Compat;
+use crate::diff::BlockDiff;
+use crate::error::{FileError, GeoffreyError, GeoffreyWarning, SourceSpan};
+use crate::html;
+use crate::junit;
+
+use serde::Serialize;
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Statistics of a geoffrey run
+#[derive(Debug, Default, Serialize)]
+pub struct Summary {
+    pub md_files: usize,
+    pub code_blocks: usize,
+    pub snippets_updated: usize,
+    pub snippets_unchanged: usize,
+    pub content_files: usize,
+    pub failed_files: usize,
+    /// markdown files which were not synced because the timeout was reached
+    pub skipped_files: usize,
+    pub warnings: usize,
+    pub elapsed_secs: f64,
+}
+
+impl Summary {
+    /// Adds the statistics of `other`, e.g. of a submodule; the elapsed time is not added
+    pub fn add(&mut self, other: &Summary) {
+        self.md_files += other.md_files;
+        self.code_blocks += other.code_blocks;
+        self.snippets_updated += other.snippets_updated;
+        self.snippets_unchanged += other.snippets_unchanged;
+        self.content_files += other.content_files;
+        self.failed_files += other.failed_files;
+        self.skipped_files += other.skipped_files;
+        self.warnings += other.warnings;
+    }
+
+    pub fn log(&self) {
+        log::info!("#### summary");
+        log::info!("markdown files scanned: {}", self.md_files);
+        log::info!("code blocks managed:    {}", self.code_blocks);
+        log::info!("snippets updated:       {}", self.snippets_updated);
+        log::info!("snippets unchanged:     {}", self.snippets_unchanged);
+        log::info!("content files parsed:   {}", self.content_files);
+        log::info!("failed files:           {}", self.failed_files);
+        log::info!("skipped files:          {}", self.skipped_files);
+        log::info!("warnings:               {}", self.warnings);
+        log::info!("elapsed time:           {:.3}s", self.elapsed_secs);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BlockStatus {
+    Updated,
+    Unchanged,
+}
+
+/// The latest commit which touched a snippet
+#[derive(Debug, Serialize)]
+pub struct CommitInfo {
+    pub hash: String,
+    pub date: String,
+}
+
+/// When the content of a snippet was last modified
+#[derive(Debug, Default, Serialize)]
+pub struct Freshness {
+    /// the modification time of the content file
+    pub modified: Option<String>,
+    /// the latest commit touching the lines of the snippet
+    pub commit: Option<CommitInfo>,
+}
+
+/// A code block managed by a geoffrey tag
+#[derive(Debug, Serialize)]
+pub struct BlockReport {
+    pub md_file: PathBuf,
+    pub line: usize,
+    pub content_file: String,
+    /// the embedded snippet or `None` for the full file
+    pub snippet: Option<String>,
+    pub status: BlockStatus,
+    /// the content root in which the content file was found
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_root: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub freshness: Option<Freshness>,
+    /// the diff of the update if the code block was out of sync
+    #[serde(skip)]
+    pub diff: Option<BlockDiff>,
+}
+
+/// A geoffrey tag in a markdown file
+#[derive(Debug, Serialize)]
+pub struct TagEntry {
+    pub md_file: PathBuf,
+    pub line: usize,
+    pub content_file: String,
+    /// the embedded snippet or `None` for the full file
+    pub snippet: Option<String>,
+    /// whether nested snippets are elided
+    pub elided: bool,
+    /// the nested snippets which are not elided
+    pub sub_snippets: Vec<String>,
+}
+
+impl fmt::Display for TagEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}",
+            self.md_file.display(),
+            self.line,
+            self.content_file
+        )?;
+        if let Some(snippet) = &self.snippet {
+            if self.elided {
+                write!(f, " [[{}]", snippet)?;
+                for sub_snippet in &self.sub_snippets {
+                    write!(f, " [{}]", sub_snippet)?;
+                }
+                write!(f, "]")?;
+            } else {
+                write!(f, " [{}]", snippet)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The location of the content referenced by a geoffrey tag
+#[derive(Debug, PartialEq, Serialize)]
+pub struct SnippetLocation {
+    pub path: PathBuf,
+    /// the line of the begin marker of the snippet or 1 for the full file
+    pub line: usize,
+}
+
+/// Where a snippet name is defined in the referenced content files and which geoffrey tags embed it
+#[derive(Debug, Serialize)]
+pub struct SnippetUsage {
+    pub snippet: String,
+    pub definitions: Vec<SnippetLocation>,
+    /// the tags embedding the snippet, either as main snippet or as kept sub-snippet
+    pub references: Vec<TagEntry>,
+}
+
+/// One line per definition like 'src/main.cpp:12: [main]' followed by one line per tag like `TagEntry`
+impl fmt::Display for SnippetUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for definition in &self.definitions {
+            writeln!(
+                f,
+                "{}:{}: [{}]",
+                definition.path.display(),
+                definition.line,
+                self.snippet
+            )?;
+        }
+        for reference in &self.references {
+            writeln!(f, "{}", reference)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A snippet marker in a content file which is not referenced by any geoffrey tag
+#[derive(Debug, Serialize)]
+pub struct OrphanedMarker {
+    pub content_file: PathBuf,
+    pub line: usize,
+    pub tag: String,
+}
+
+impl fmt::Display for OrphanedMarker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: [{}]",
+            self.content_file.display(),
+            self.line,
+            self.tag
+        )
+    }
+}
+
+/// How many of the snippet markers in the referenced content files are used by the documentation
+#[derive(Debug, Serialize)]
+pub struct Coverage {
+    pub snippet_markers: usize,
+    pub unused_markers: Vec<OrphanedMarker>,
+}
+
+/// An error which occurred during a geoffrey run
+#[derive(Debug, Serialize)]
+pub struct Diagnostic {
+    /// the stable code of the error, see `GeoffreyError::code`
+    pub code: String,
+    /// the file in which the error occurred
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    /// the location in the file, if known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub span: Option<SourceSpan>,
+    pub message: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub suggestions: Vec<String>,
+}
+
+impl From<&GeoffreyError> for Diagnostic {
+    fn from(error: &GeoffreyError) -> Self {
+        let (span, error) = match error {
+            GeoffreyError::Located(span, error) => (Some(span.clone()), error.as_ref()),
+            error => (None, error),
+        };
+        Self {
+            code: error.code().to_owned(),
+            file: None,
+            span,
+            message: error.to_string(),
+            suggestions: error.suggestions().to_vec(),
+        }
+    }
+}
+
+impl Diagnostic {
+    /// The diagnostic of a warning in `file` at the location `span`
+    pub fn warning(file: &Path, span: Option<SourceSpan>, warning: &GeoffreyWarning) -> Self {
+        Self {
+            code: warning.code().to_owned(),
+            file: Some(file.to_path_buf()),
+            span,
+            message: warning.to_string(),
+            suggestions: Vec::new(),
+        }
+    }
+}
+
+impl From<&FileError> for Diagnostic {
+    fn from(failure: &FileError) -> Self {
+        Self {
+            file: Some(failure.path.clone()),
+            span: failure.span.as_deref().cloned(),
+            ..Self::from(&failure.error)
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.file, &self.span) {
+            (Some(file), Some(span)) => write!(
+                f,
+                "{}:{}:{}: [{}] {}\n{}",
+                file.display(),
+                span.line,
+                span.column,
+                self.code,
+                self.message,
+                span
+            ),
+            (Some(file), None) => {
+                write!(f, "{}: [{}] {}", file.display(), self.code, self.message)
+            }
+            (None, _) => write!(f, "[{}] {}", self.code, self.message),
+        }
+    }
+}
+
+/// The machine readable report of a geoffrey run
+#[derive(Debug, Serialize)]
+pub struct Report {
+    /// the version of the schema of the report, see `Compat::schema_version`
+    pub schema_version: u32,
+    pub summary: Summary,
+    pub blocks: Vec<BlockReport>,
+    pub diagnostics: Vec<Diagnostic>,
+    /// the problems which did not prevent the sync, see `GeoffreyWarning`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<Diagnostic>,
+    /// the markdown files which were not synced because the timeout was reached
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<PathBuf>,
+    /// the markdown files, or the outputs of the templates, which were written because they changed
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub changed_files: Vec<PathBuf>,
+    /// the documents of the run, i.e. the markdown files, templates and source files with tags in documentation
+    /// comments; only used to tell their diagnostics from the ones of content files in the HTML report
+    #[serde(skip)]
+    pub documents: Vec<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coverage: Option<Coverage>,
+    /// the reports of the git submodules with '--recurse-submodules'; their statistics are part of the summary
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub submodules: Vec<SubmoduleReport>,
+}
+
+/// The report of a git submodule of a documentation superproject
+#[derive(Debug, Serialize)]
+pub struct SubmoduleReport {
+    pub path: PathBuf,
+    #[serde(flatten)]
+    pub report: Report,
+}
+
+impl Default for Report {
+    fn default() -> Self {
+        Self {
+            schema_version: Compat::default().schema_version(),
+            summary: Summary::default(),
+            blocks: Vec::new(),
+            diagnostics: Vec::new(),
+            warnings: Vec::new(),
+            skipped: Vec::new(),
+            changed_files: Vec::new(),
+            documents: Vec::new(),
+            coverage: None,
+            submodules: Vec::new(),
+        }
+    }
+}
+
+impl Report {
+    /// Adds the report of a git submodule as separate section and its statistics to the summary
+    pub fn add_submodule(&mut self, path: PathBuf, report: Report) {
+        self.summary.add(&report.summary);
+        self.submodules.push(SubmoduleReport { path, report });
+    }
+
+    /// The diagnostics of this report and the ones of the submodules
+    pub fn all_diagnostics(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics.iter().chain(
+            self.submodules
+                .iter()
+                .flat_map(|submodule| submodule.report.diagnostics.iter()),
+        )
+    }
+
+    /// The warnings of this report and the ones of the submodules
+    pub fn all_warnings(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.warnings.iter().chain(
+            self.submodules
+                .iter()
+                .flat_map(|submodule| submodule.report.warnings.iter()),
+        )
+    }
+
+    /// The skipped markdown files of this report and the ones of the submodules
+    pub fn all_skipped(&self) -> Vec<PathBuf> {
+        self.skipped
+            .iter()
+            .chain(
+                self.submodules
+                    .iter()
+                    .flat_map(|submodule| submodule.report.skipped.iter()),
+            )
+            .cloned()
+            .collect()
+    }
+
+    /// The changed files of this report and the ones of the submodules
+    pub fn all_changed_files(&self) -> Vec<PathBuf> {
+        self.changed_files
+            .iter()
+            .chain(
+                self.submodules
+                    .iter()
+                    .flat_map(|submodule| submodule.report.changed_files.iter()),
+            )
+            .cloned()
+            .collect()
+    }
+
+    /// Writes the changed files for build systems, as JSON array if `path` has the extension 'json' and
+    /// otherwise one per line
+    pub fn write_changed_files(&self, path: &Path) -> Result<(), GeoffreyError> {
+        let changed_files = self.all_changed_files();
+        let content = if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            serde_json::to_string_pretty(&changed_files)
+                .map_err(|e| GeoffreyError::ReportError(path.to_path_buf(), e.to_string()))?
+                + "\n"
+        } else {
+            changed_files
+                .iter()
+                .map(|file| format!("{}\n", file.display()))
+                .collect()
+        };
+        fs::write(path, content)?;
+
+        Ok(())
+    }
+
+    pub fn write_json(&self, path: &Path) -> Result<(), GeoffreyError> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| GeoffreyError::ReportError(path.to_path_buf(), e.to_string()))?;
+        fs::write(path, json + "\n")?;
+
+        Ok(())
+    }
+
+    pub fn write_html(&self, path: &Path) -> Result<(), GeoffreyError> {
+        fs::write(path, html::render(self))?;
+
+        Ok(())
+    }
+
+    /// Writes the report as JUnit XML for CI systems, with a test case per markdown file
+    pub fn write_junit(&self, path: &Path) -> Result<(), GeoffreyError> {
+        fs::write(path, junit::render(self))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,84 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::marker::LineRange;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+use std::path::Path;
+
+/// A custom strategy to select the lines of a content file which are embedded into a code block.
+///
+/// Selectors are registered with a name at `Documents::register_selector` and referenced by
+/// '[name:argument]' instead of a snippet name in geoffrey tags. The selected lines are rendered like
+/// a snippet, i.e. snippet markers are removed, the lines are dedented and nested snippets can be elided
+/// with '[[name:argument] [not elided snippet]]'.
+pub trait SnippetSelector: Send + Sync {
+    /// The 0-based range of the lines of `content` selected by `argument` or `None` if nothing matches;
+    /// the lines include their line endings
+    fn select(&self, path: &Path, content: &[String], argument: &str) -> Option<Range<usize>>;
+}
+
+/// Selects lines by their 1-based line numbers, e.g. '[lines:40-78]'
+#[derive(Debug, Default)]
+pub struct LinesSelector;
+
+impl SnippetSelector for LinesSelector {
+    fn select(&self, _path: &Path, _content: &[String], argument: &str) -> Option<Range<usize>> {
+        argument
+            .parse::<LineRange>()
+            .ok()
+            .map(|range| range.first - 1..range.last)
+    }
+}
+
+/// The registered selectors by name
+#[derive(Default)]
+pub(crate) struct Selectors(HashMap<String, Box<dyn SnippetSelector>>);
+
+impl Selectors {
+    pub(crate) fn with_builtins() -> Self {
+        let mut selectors = Self::default();
+        selectors.register("lines", Box::new(LinesSelector));
+        selectors
+    }
+
+    pub(crate) fn register(&mut self, name: &str, selector: Box<dyn SnippetSelector>) {
+        self.0.insert(name.to_owned(), selector);
+    }
+
+    /// The selector and its argument if `tag` references a registered selector like 'name:argument'
+    pub(crate) fn get<'a>(&self, tag: &'a str) -> Option<(&dyn SnippetSelector, &'a str)> {
+        let (name, argument) = tag.split_once(':')?;
+        self.0
+            .get(name)
+            .map(|selector| (selector.as_ref(), argument))
+    }
+}
+
+impl fmt::Debug for Selectors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names = self.0.keys().collect::<Vec<&String>>();
+        names.sort();
+        f.debug_tuple("Selectors").field(&names).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn lines_selector_selects_one_based_line_range() {
+        let selectors = Selectors::with_builtins();
+        let (selector, argument) = selectors.get("lines:2-3").expect("builtin");
+
+        assert_eq!(
+            selector.select(Path::new("a.cpp"), &[], argument),
+            Some(1..3)
+        );
+        assert_eq!(selector.select(Path::new("a.cpp"), &[], "0-3"), None);
+        assert!(selectors.get("unknown:2-3").is_none());
+        assert!(selectors.get("lines").is_none());
+    }
+}
@@ -0,0 +1,357 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::socket::Root;
+
+use geoffrey::documents::{Documents, SyncOptions};
+use geoffrey::error::{FileError, GeoffreyError};
+use geoffrey::lock::RunLock;
+use geoffrey::report::Diagnostic;
+
+use anyhow::Result;
+use serde_json::{json, Value};
+
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// The JSON-RPC error codes, see https://www.jsonrpc.org/specification#error_object
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// The code of errors of geoffrey, whose diagnostic is the data of the error
+const GEOFFREY_ERROR: i64 = -32000;
+
+/// Serves JSON-RPC requests, one per line, on `reader` and answers each with a line on `writer` until the end
+/// of the input
+pub fn serve<R, W, F>(reader: R, mut writer: W, server: &mut Server<'_, F>) -> Result<()>
+where
+    R: BufRead,
+    W: Write,
+    F: Fn(&Path) -> Result<Documents, GeoffreyError>,
+{
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = server.handle(&line) {
+            writeln!(writer, "{}", response)?;
+            writer.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Serves the connections to the unix socket at `path` one after the other, see `serve` and `socket::serve`
+#[cfg(unix)]
+pub fn serve_socket<F>(path: &Path, server: &mut Server<'_, F>) -> Result<()>
+where
+    F: Fn(&Path) -> Result<Documents, GeoffreyError>,
+{
+    crate::socket::serve(path, |reader, writer| serve(reader, writer, server))
+}
+
+/// The parsed documents of a path and the modification times of the files they were parsed from
+struct Warm {
+    documents: Documents,
+    fingerprint: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+fn fingerprint(files: Vec<PathBuf>) -> Vec<(PathBuf, Option<SystemTime>)> {
+    files
+        .into_iter()
+        .map(|file| {
+            let modified = std::fs::metadata(&file)
+                .and_then(|metadata| metadata.modified())
+                .ok();
+            (file, modified)
+        })
+        .collect()
+}
+
+/// Keeps the parsed documents of the requested paths in memory until one of their files changes, so that
+/// repeated requests of editors and dev-servers are answered without parsing again
+pub struct Server<'a, F> {
+    open: F,
+    options: &'a SyncOptions,
+    /// requests for paths outside of it are refused
+    root: Root,
+    /// wait for other runs writing the same documentation instead of failing, see `RunLock`
+    wait: bool,
+    warm: HashMap<PathBuf, Warm>,
+}
+
+impl<'a, F> Server<'a, F>
+where
+    F: Fn(&Path) -> Result<Documents, GeoffreyError>,
+{
+    /// `open` creates the documents for a markdown file or folder inside of `root`, which the server parses
+    pub fn new(open: F, options: &'a SyncOptions, root: Root, wait: bool) -> Self {
+        Self {
+            open,
+            options,
+            root,
+            wait,
+            warm: HashMap::new(),
+        }
+    }
+
+    /// The response to a request; `None` for a notification, i.e. a request without id
+    fn handle(&mut self, request: &str) -> Option<Value> {
+        let request = match serde_json::from_str::<Value>(request) {
+            Ok(request) => request,
+            Err(e) => return Some(error(Value::Null, PARSE_ERROR, e.to_string(), None)),
+        };
+        let id = request.get("id").cloned();
+        let Some(method) = request["method"].as_str() else {
+            return Some(error(
+                id.unwrap_or_default(),
+                INVALID_REQUEST,
+                "request without method".to_owned(),
+                None,
+            ));
+        };
+        log::info!("{} {}", method, request["params"]);
+
+        let result = match request["params"]["path"].as_str() {
+            Some(path) => match self.root.check(Path::new(path)) {
+                Ok(()) => self.call(method, Path::new(path)),
+                Err(reason) => Err(error(Value::Null, INVALID_PARAMS, reason, None)),
+            },
+            None => Err(error(
+                Value::Null,
+                INVALID_PARAMS,
+                "expected the params '{\"path\": \"<markdown file or folder>\"}'".to_owned(),
+                None,
+            )),
+        };
+
+        let id = id?;
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(mut response) => {
+                response["id"] = id;
+                response
+            }
+        })
+    }
+
+    /// The result of the method for the documentation in `path`, or the error response without id
+    fn call(&mut self, method: &str, path: &Path) -> Result<Value, Value> {
+        let options = self.options;
+        let geoffrey_error = |e: GeoffreyError| {
+            error(
+                Value::Null,
+                GEOFFREY_ERROR,
+                e.to_string(),
+                Some(json!(Diagnostic::from(&e))),
+            )
+        };
+        let to_value = |value: serde_json::Result<Value>| {
+            value.map_err(|e| error(Value::Null, GEOFFREY_ERROR, e.to_string(), None))
+        };
+
+        match method {
+            "sync" => {
+                let _lock = RunLock::acquire(path, self.wait).map_err(geoffrey_error)?;
+                let documents = self.take(path).map_err(geoffrey_error)?;
+                let report = documents.sync(self.options).map_err(geoffrey_error)?;
+                to_value(serde_json::to_value(&report))
+            }
+            "check" => {
+                let documents = self.documents(path).map_err(geoffrey_error)?;
+                let mut problems = documents
+                    .failures()
+                    .iter()
+                    .map(Diagnostic::from)
+                    .collect::<Vec<Diagnostic>>();
+                problems.extend(documents.tag_problems().into_iter().map(|problem| {
+                    Diagnostic::from(&FileError::new(problem.md_file, problem.error))
+                }));
+                let stale = documents.stale_blocks(options).diffs;
+                to_value(Ok(json!({ "diagnostics": problems, "stale": stale })))
+            }
+            "listTags" => {
+                let documents = self.documents(path).map_err(geoffrey_error)?;
+                to_value(serde_json::to_value(documents.tags()))
+            }
+            "diff" => {
+                let documents = self.documents(path).map_err(geoffrey_error)?;
+                let report = documents.stale_blocks(options);
+                let diagnostics = documents
+                    .failures()
+                    .iter()
+                    .chain(&report.failures)
+                    .map(Diagnostic::from)
+                    .collect::<Vec<Diagnostic>>();
+                to_value(Ok(json!({
+                    "diffs": report.diffs,
+                    "diagnostics": diagnostics,
+                    "warnings": report.warnings,
+                })))
+            }
+            _ => Err(error(
+                Value::Null,
+                METHOD_NOT_FOUND,
+                format!(
+                    "unknown method '{}'; expected 'sync', 'check', 'listTags' or 'diff'",
+                    method
+                ),
+                None,
+            )),
+        }
+    }
+
+    /// The parsed documents of `path`, which are parsed again if any of their files changed
+    fn documents(&mut self, path: &Path) -> Result<&Documents, GeoffreyError> {
+        let documents = self.take(path)?;
+        let fingerprint = fingerprint(documents.source_files());
+        let warm = Warm {
+            documents,
+            fingerprint,
+        };
+
+        Ok(&self
+            .warm
+            .entry(path.to_path_buf())
+            .insert_entry(warm)
+            .into_mut()
+            .documents)
+    }
+
+    /// Takes the parsed documents of `path` out of the cache for methods which consume them; they are parsed
+    /// if they are not cached or outdated
+    fn take(&mut self, path: &Path) -> Result<Documents, GeoffreyError> {
+        if let Some(warm) = self.warm.remove(path) {
+            if fingerprint(
+                warm.fingerprint
+                    .iter()
+                    .map(|(file, _)| file.clone())
+                    .collect(),
+            ) == warm.fingerprint
+            {
+                log::debug!("reusing the parsed documents of {:?}", path);
+                return Ok(warm.documents);
+            }
+        }
+        let mut documents = (self.open)(path)?;
+        documents.parse()?;
+
+        Ok(documents)
+    }
+}
+
+/// An error response, whose id is set by the caller
+fn error(id: Value, code: i64, message: String, data: Option<Value>) -> Value {
+    let mut error = json!({ "code": code, "message": message });
+    if let Some(data) = data {
+        error["data"] = data;
+    }
+
+    json!({ "jsonrpc": "2.0", "id": id, "error": error })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use geoffrey::documents::Symlinks;
+
+    use std::fs;
+    use tempfile::Builder;
+
+    #[test]
+    fn requests_are_answered_from_the_parsed_documents_until_a_file_changes() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let content_path = tmp_dir.path().join("content.cpp");
+        fs::write(&content_path, "//! [answer]\n42\n//! [answer]\n")?;
+        let md_path = tmp_dir.path().join("doc.md");
+        let md = format!(
+            "<!-- [geoffrey] [{}] [answer] -->\n```cpp\nstale\n```\n",
+            content_path.display()
+        );
+        fs::write(&md_path, &md)?;
+
+        let options = SyncOptions::default();
+        let mut server = Server::new(
+            |path: &Path| Documents::new(path.to_path_buf(), Symlinks::Follow),
+            &options,
+            Root::new(tmp_dir.path())?,
+            false,
+        );
+        let path = tmp_dir.path().display().to_string();
+        let mut request = |id: u64, method: &str| {
+            let request =
+                json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": { "path": path } });
+            server.handle(&request.to_string())
+        };
+
+        let response = request(1, "listTags").unwrap_or_default();
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["result"][0]["snippet"], "answer");
+        assert_eq!(response["result"][0]["line"], 1);
+
+        let response = request(2, "check").unwrap_or_default();
+        assert_eq!(response["result"]["diagnostics"], json!([]));
+        assert_eq!(response["result"]["stale"][0]["current"], "stale\n");
+
+        let response = request(3, "diff").unwrap_or_default();
+        assert_eq!(response["result"]["diffs"][0]["current"], "stale\n");
+        assert_eq!(response["result"]["diffs"][0]["synced"], "42\n");
+
+        let response = request(4, "sync").unwrap_or_default();
+        assert_eq!(response["result"]["summary"]["snippets_updated"], 1);
+        assert_eq!(fs::read_to_string(&md_path)?, md.replace("stale", "42"));
+
+        // the changed markdown file is parsed again
+        fs::write(&md_path, md.replace("[answer]", "[answr]"))?;
+        let response = request(5, "check").unwrap_or_default();
+        assert_eq!(response["result"]["diagnostics"][0]["code"], "G009");
+
+        let response = request(6, "hover").unwrap_or_default();
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+
+        let response = server.handle("{\"id\": 7").unwrap_or_default();
+        assert_eq!(response["error"]["code"], PARSE_ERROR);
+
+        let response = server
+            .handle(r#"{"jsonrpc": "2.0", "id": 8, "method": "sync"}"#)
+            .unwrap_or_default();
+        assert_eq!(response["id"], 8);
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+
+        let missing = tmp_dir.path().join("missing");
+        let response = server
+            .handle(
+                &json!({ "id": 9, "method": "check", "params": { "path": missing } }).to_string(),
+            )
+            .unwrap_or_default();
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+        assert!(response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("is not readable")));
+
+        // the documents are kept after a diff
+        server.handle(
+            &json!({ "id": 10, "method": "diff", "params": { "path": tmp_dir.path() } })
+                .to_string(),
+        );
+        assert!(server.warm.contains_key(tmp_dir.path()));
+
+        let outside = tmp_dir.path().join("..");
+        let response = server
+            .handle(
+                &json!({ "id": 11, "method": "check", "params": { "path": outside } }).to_string(),
+            )
+            .unwrap_or_default();
+        assert!(response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("is outside of")));
+
+        Ok(())
+    }
+}
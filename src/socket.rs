@@ -0,0 +1,57 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::{Context, Result};
+
+use std::path::{Path, PathBuf};
+
+/// The folder with the documentation of a server; it is canonical, so that paths which leave it, e.g. with '..'
+/// or symbolic links, are refused
+#[derive(Debug)]
+pub struct Root(PathBuf);
+
+impl Root {
+    pub fn new(root: &Path) -> Result<Self> {
+        root.canonicalize()
+            .map(Self)
+            .with_context(|| format!("failed to read the root {:?}", root))
+    }
+
+    /// Fails with the reason if `path` is not readable or outside of the root
+    pub fn check(&self, path: &Path) -> Result<(), String> {
+        match path.canonicalize() {
+            Ok(canonical) if canonical.starts_with(&self.0) => Ok(()),
+            Ok(_) => Err(format!("the path {:?} is outside of {:?}", path, self.0)),
+            Err(e) => Err(format!("the path {:?} is not readable: {}", path, e)),
+        }
+    }
+}
+
+/// Serves the connections to the unix socket at `path` one after the other with `handle`, which gets a reader and
+/// a writer of the connection. Only users with write permission on the socket can connect; a socket left over by
+/// a previous server is replaced.
+#[cfg(unix)]
+pub fn serve<F>(path: &Path, mut handle: F) -> Result<()>
+where
+    F: FnMut(
+        std::io::BufReader<std::os::unix::net::UnixStream>,
+        std::os::unix::net::UnixStream,
+    ) -> Result<()>,
+{
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::net::UnixListener;
+
+    if std::fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+        std::fs::remove_file(path)?;
+    }
+    let listener =
+        UnixListener::bind(path).with_context(|| format!("failed to listen on {:?}", path))?;
+    log::info!("listening on {:?}", path);
+    for stream in listener.incoming() {
+        let stream = stream?;
+        if let Err(e) = handle(std::io::BufReader::new(stream.try_clone()?), stream) {
+            log::warn!("connection failed: {}", e);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,410 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::config::{Config, GeneratedConfig, GeneratedOutput};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::process::Command;
+use std::sync::OnceLock;
+
+/// A custom origin of the content referenced by geoffrey tags, e.g. a file at a git revision or a web page.
+///
+/// Sources are registered with a name at `Documents::register_source` and referenced by content paths like
+/// '[name:argument]' in geoffrey tags. The content is parsed for snippets like a content file, with the comment
+/// style of the extension of the argument. A configured repository with the same name takes precedence.
+pub trait SnippetSource: Send + Sync {
+    /// The content referenced by `argument`, the part of the path after 'name:'; relative paths are resolved
+    /// against `dir`, the directory of the config file. The error is the reason reported to the user.
+    fn read(&self, dir: &Path, argument: &str) -> Result<String, String>;
+}
+
+/// Reads a file, e.g. '[file:docs/snippets.cpp]', without the content roots and overrides of plain paths; plain
+/// paths like '[src/main.cpp]' are read with it after they are resolved to an absolute path, see `Sources::files`
+#[derive(Debug, Default)]
+pub struct FileSource;
+
+impl SnippetSource for FileSource {
+    fn read(&self, dir: &Path, argument: &str) -> Result<String, String> {
+        std::fs::read_to_string(dir.join(argument)).map_err(|e| e.to_string())
+    }
+}
+
+/// Reads a file at a git revision with 'git show', e.g. '[git:v1.0:src/main.rs]'; a revision starting with '-' is
+/// rejected, so that it cannot be passed as an option to git
+#[derive(Debug, Default)]
+pub struct GitSource;
+
+impl SnippetSource for GitSource {
+    fn read(&self, dir: &Path, argument: &str) -> Result<String, String> {
+        let (rev, path) = argument.split_once(':').ok_or_else(|| {
+            format!(
+                "expected 'git:<revision>:<path>' instead of 'git:{}'",
+                argument
+            )
+        })?;
+        if rev.starts_with('-') {
+            return Err(format!("the revision '{}' must not start with '-'", rev));
+        }
+        let mut git_show = Command::new("git");
+        git_show
+            .args(["show", "--end-of-options"])
+            .arg(format!("{}:./{}", rev, path))
+            .current_dir(dir);
+
+        output(git_show)
+    }
+}
+
+/// The content generated during the run, e.g. '[gen:cli-help]', as configured in the 'generated' table of the
+/// config file; the build command is run once before the first output is read
+#[derive(Debug, Default)]
+pub struct GeneratedSource {
+    config: GeneratedConfig,
+    build: OnceLock<Result<(), String>>,
+}
+
+impl GeneratedSource {
+    pub fn new(config: GeneratedConfig) -> Self {
+        Self {
+            config,
+            build: OnceLock::new(),
+        }
+    }
+}
+
+impl SnippetSource for GeneratedSource {
+    fn read(&self, dir: &Path, argument: &str) -> Result<String, String> {
+        self.build
+            .get_or_init(|| match &self.config.build {
+                Some(command) => run_command(dir, command).map(|_| ()),
+                None => Ok(()),
+            })
+            .clone()?;
+
+        match self.config.outputs.get(argument) {
+            Some(GeneratedOutput::Command(command)) => run_command(dir, command),
+            Some(GeneratedOutput::File(file)) => {
+                std::fs::read_to_string(dir.join(file)).map_err(|e| e.to_string())
+            }
+            None => Err(format!("'{}' is not configured", argument)),
+        }
+    }
+}
+
+/// Downloads a file with 'curl', e.g. '[https://example.com/hello.cpp]'; registered for the schemes 'http' and
+/// 'https', whose argument is the rest of the url. A download taking longer than `HttpSource::MAX_TIME` fails.
+#[derive(Debug)]
+pub struct HttpSource {
+    scheme: &'static str,
+}
+
+impl HttpSource {
+    /// The seconds a download may take
+    pub const MAX_TIME: u32 = 60;
+
+    pub fn new(scheme: &'static str) -> Self {
+        Self { scheme }
+    }
+}
+
+impl SnippetSource for HttpSource {
+    fn read(&self, _dir: &Path, argument: &str) -> Result<String, String> {
+        let url = format!("{}:{}", self.scheme, argument);
+        log::info!("downloading '{}'", url);
+        let mut curl = Command::new("curl");
+        curl.args(["--fail", "--silent", "--show-error", "--location"])
+            .arg("--max-time")
+            .arg(Self::MAX_TIME.to_string())
+            .arg(&url);
+
+        output(curl)
+    }
+}
+
+/// The standard output of a shell command run in the directory of the config file; the argument of the path is
+/// passed in the environment variable 'GEOFFREY_ARGUMENT', e.g. for '[version:--short]' registered as
+/// `CommandSource::new("tool version $GEOFFREY_ARGUMENT")`
+#[derive(Debug)]
+pub struct CommandSource {
+    command: String,
+}
+
+impl CommandSource {
+    pub fn new(command: impl Into<String>) -> Self {
+        Self {
+            command: command.into(),
+        }
+    }
+}
+
+impl SnippetSource for CommandSource {
+    fn read(&self, dir: &Path, argument: &str) -> Result<String, String> {
+        log::info!("running '{}'", self.command);
+        let mut sh = Command::new("sh");
+        sh.arg("-c")
+            .arg(&self.command)
+            .env("GEOFFREY_ARGUMENT", argument)
+            .current_dir(dir);
+
+        output(sh)
+    }
+}
+
+/// The standard output of a command in a tag, e.g. '[cmd:cargo run --example foo -- --help]', which is run in the
+/// directory of the config file if it is allowed; the command is split into words at whitespace and run without a
+/// shell, so that the words of an allowed command cannot be followed by a second one like '; rm -rf .'
+#[derive(Debug, Default)]
+pub struct AllowedCommandSource {
+    allowed: Vec<Vec<String>>,
+}
+
+impl AllowedCommandSource {
+    /// A command is allowed if its words start with the words of one of the `allowed` commands
+    pub fn new(allowed: &[String]) -> Self {
+        Self {
+            allowed: allowed.iter().map(|command| words(command)).collect(),
+        }
+    }
+
+    fn is_allowed(&self, words: &[String]) -> bool {
+        self.allowed
+            .iter()
+            .any(|allowed| !allowed.is_empty() && words.starts_with(allowed))
+    }
+}
+
+impl SnippetSource for AllowedCommandSource {
+    fn read(&self, dir: &Path, argument: &str) -> Result<String, String> {
+        let words = words(argument);
+        if !self.is_allowed(&words) {
+            return Err(format!(
+                "the command is not allowed by 'allowed_commands' of '{}'",
+                Config::FILE_NAME
+            ));
+        }
+        log::info!("running '{}'", argument);
+        let mut command = Command::new(&words[0]);
+        command.args(&words[1..]).current_dir(dir);
+
+        output(command)
+    }
+}
+
+fn words(command: &str) -> Vec<String> {
+    command.split_whitespace().map(str::to_owned).collect()
+}
+
+/// Runs a shell command in `dir` and returns its standard output
+pub(crate) fn run_command(dir: &Path, command: &str) -> Result<String, String> {
+    log::info!("running '{}'", command);
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .current_dir(dir)
+        .output()
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(format!(
+            "'{}' failed with {}: {}",
+            command,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    String::from_utf8(output.stdout).map_err(|e| e.to_string())
+}
+
+/// The standard output of `command` or its standard error if it fails
+fn output(mut command: Command) -> Result<String, String> {
+    let output = command.output().map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_owned());
+    }
+
+    String::from_utf8(output.stdout).map_err(|e| e.to_string())
+}
+
+/// The registered sources by name
+#[derive(Default)]
+pub(crate) struct Sources(HashMap<String, Box<dyn SnippetSource>>);
+
+impl Sources {
+    /// The name of the source of files, which also reads the plain content paths, see `files`
+    const FILES: &'static str = "file";
+
+    /// The builtin sources with the content generated as configured in `generated`
+    pub(crate) fn with_builtins(generated: GeneratedConfig) -> Self {
+        let mut sources = Self::default();
+        sources.register(Self::FILES, Box::new(FileSource));
+        sources.register(
+            Config::GENERATED_PREFIX,
+            Box::new(GeneratedSource::new(generated)),
+        );
+        sources.register("git", Box::new(GitSource));
+        sources.register("http", Box::new(HttpSource::new("http")));
+        sources.register("https", Box::new(HttpSource::new("https")));
+        sources
+    }
+
+    /// The source reading the plain content paths, once they are resolved against the content roots, repositories
+    /// and overrides to an absolute path, which is its argument
+    pub(crate) fn files(&self) -> &dyn SnippetSource {
+        self.0
+            .get(Self::FILES)
+            .map_or(&FileSource, |source| source.as_ref())
+    }
+
+    pub(crate) fn register(&mut self, name: &str, source: Box<dyn SnippetSource>) {
+        self.0.insert(name.to_owned(), source);
+    }
+
+    /// The source and its argument if `path` references a registered source like 'name:argument'
+    pub(crate) fn get<'a>(&self, path: &'a str) -> Option<(&dyn SnippetSource, &'a str)> {
+        let (name, argument) = path.split_once(':')?;
+        self.0.get(name).map(|source| (source.as_ref(), argument))
+    }
+}
+
+impl fmt::Debug for Sources {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names = self.0.keys().collect::<Vec<&String>>();
+        names.sort();
+        f.debug_tuple("Sources").field(&names).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use anyhow::Result;
+    use tempfile::Builder;
+
+    #[test]
+    fn builtin_and_custom_sources_are_found_by_the_prefix_of_the_path() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+        std::fs::write(tmp_dir.path().join("a.cpp"), "42\n")?;
+
+        let mut sources = Sources::with_builtins(GeneratedConfig::default());
+        sources.register(
+            "echo",
+            Box::new(CommandSource::new("echo $GEOFFREY_ARGUMENT")),
+        );
+
+        let (source, argument) = sources.get("file:a.cpp").expect("builtin");
+        assert_eq!(source.read(tmp_dir.path(), argument), Ok("42\n".to_owned()));
+        let (source, argument) = sources.get("echo:hello").expect("registered");
+        assert_eq!(
+            source.read(tmp_dir.path(), argument),
+            Ok("hello\n".to_owned())
+        );
+        let (source, argument) = sources.get("git:a.cpp").expect("builtin");
+        assert!(source.read(tmp_dir.path(), argument).is_err());
+
+        assert!(sources.get("https://example.com/a.cpp").is_some());
+        assert!(sources.get("cmd:echo hello").is_none());
+        assert!(sources.get("unknown:a.cpp").is_none());
+        assert!(sources.get("a.cpp").is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn generated_content_and_plain_paths_are_read_by_builtin_sources() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+        let content_file = tmp_dir.path().join("a.cpp");
+        std::fs::write(&content_file, "42\n")?;
+
+        let generated = GeneratedConfig {
+            build: Some("echo built >> build.log".to_owned()),
+            outputs: [
+                (
+                    "greeting".to_owned(),
+                    GeneratedOutput::Command("echo hello".to_owned()),
+                ),
+                ("log".to_owned(), GeneratedOutput::File("build.log".into())),
+            ]
+            .into(),
+        };
+        let sources = Sources::with_builtins(generated);
+
+        let (source, argument) = sources.get("gen:greeting").expect("builtin");
+        assert_eq!(
+            source.read(tmp_dir.path(), argument),
+            Ok("hello\n".to_owned())
+        );
+        // the build command is run only once
+        let (source, argument) = sources.get("gen:log").expect("builtin");
+        assert_eq!(
+            source.read(tmp_dir.path(), argument),
+            Ok("built\n".to_owned())
+        );
+        assert!(source.read(tmp_dir.path(), "missing").is_err());
+
+        let path = content_file.to_str().expect("UTF-8 path");
+        assert_eq!(
+            sources.files().read(Path::new(""), path),
+            Ok("42\n".to_owned())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn git_revisions_cannot_be_options() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+        let injected = tmp_dir.path().join("injected");
+
+        let reason = GitSource
+            .read(
+                tmp_dir.path(),
+                &format!("--output={}:a.cpp", injected.display()),
+            )
+            .expect_err("option as revision");
+        assert!(reason.contains("must not start with '-'"));
+        assert!(!injected.exists());
+
+        Command::new("git")
+            .arg("init")
+            .current_dir(tmp_dir.path())
+            .output()?;
+        std::fs::write(tmp_dir.path().join("a.cpp"), "42\n")?;
+        Command::new("git")
+            .args(["add", "a.cpp"])
+            .current_dir(tmp_dir.path())
+            .output()?;
+        assert_eq!(
+            GitSource.read(tmp_dir.path(), ":a.cpp"),
+            Ok("42\n".to_owned())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn only_allowed_commands_are_run() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let source = AllowedCommandSource::new(&["echo  hello".to_owned(), String::new()]);
+        assert_eq!(
+            source.read(tmp_dir.path(), "echo hello  world"),
+            Ok("hello world\n".to_owned())
+        );
+        // without a shell, the separator is an argument of the allowed command
+        assert_eq!(
+            source.read(tmp_dir.path(), "echo hello ; touch evil"),
+            Ok("hello ; touch evil\n".to_owned())
+        );
+        assert!(!tmp_dir.path().join("evil").exists());
+
+        assert!(source
+            .read(tmp_dir.path(), "echo hell")
+            .is_err_and(|reason| reason.contains("allowed_commands")));
+        assert!(source.read(tmp_dir.path(), "").is_err());
+        assert!(source.read(tmp_dir.path(), "touch evil").is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,107 @@
+// SPDX-License-Identifier: Apache-2.0
+
+/// The maximum number of suggestions for a misspelled name
+const MAX_SUGGESTIONS: usize = 3;
+
+/// The Levenshtein distance between `a` and `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<char>>();
+    let mut previous_row = (0..=b.len()).collect::<Vec<usize>>();
+    let mut current_row = vec![0; b.len() + 1];
+
+    for (i, a_char) in a.chars().enumerate() {
+        current_row[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous_row[j] + usize::from(a_char != *b_char);
+            current_row[j + 1] = substitution
+                .min(previous_row[j + 1] + 1)
+                .min(current_row[j] + 1);
+        }
+        std::mem::swap(&mut previous_row, &mut current_row);
+    }
+
+    previous_row[b.len()]
+}
+
+/// Returns up to three of the `candidates` closest to `name`, ordered by their edit distance
+pub fn closest<'a, I>(name: &str, candidates: I) -> Vec<String>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let threshold = (name.chars().count() / 2).max(2);
+
+    let mut ranked = candidates
+        .into_iter()
+        .filter(|candidate| !candidate.is_empty() && *candidate != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= threshold)
+        .collect::<Vec<(usize, &str)>>();
+    ranked.sort();
+
+    ranked
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| candidate.to_owned())
+        .collect()
+}
+
+/// Formats the suggestions as ' (did you mean 'a', 'b'?)' or an empty string without suggestions
+pub fn did_you_mean(suggestions: &[String]) -> String {
+    if suggestions.is_empty() {
+        return String::new();
+    }
+
+    format!(
+        " (did you mean {}?)",
+        suggestions
+            .iter()
+            .map(|suggestion| format!("'{}'", suggestion))
+            .collect::<Vec<String>>()
+            .join(", ")
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn edit_distance_counts_insertions_deletions_and_substitutions() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("answer", "answer"), 0);
+        assert_eq!(edit_distance("answr", "answer"), 1);
+        assert_eq!(edit_distance("answer", "anwser"), 2);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn closest_returns_at_most_three_ordered_suggestions() {
+        let candidates = [
+            "the answer",
+            "the answers",
+            "answer",
+            "the question",
+            "response",
+        ];
+
+        assert_eq!(
+            closest("the answr", candidates),
+            vec!["the answer", "the answers"]
+        );
+        assert_eq!(
+            closest("foo", ["fo", "foo1", "foo2", "fooo", "bar"]),
+            vec!["fo", "foo1", "foo2"]
+        );
+        assert!(closest("hypnotoad", candidates).is_empty());
+    }
+
+    #[test]
+    fn did_you_mean_formats_suggestions() {
+        assert_eq!(did_you_mean(&[]), "");
+        assert_eq!(
+            did_you_mean(&["foo".to_owned(), "bar".to_owned()]),
+            " (did you mean 'foo', 'bar'?)"
+        );
+    }
+}
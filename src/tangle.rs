@@ -0,0 +1,225 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::documents::{escape_tag_field, unescape_tag_field};
+use crate::error::GeoffreyError;
+use crate::fence::{CodeFence, FenceTracker};
+
+use regex::{Captures, Regex};
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// A code block of the markdown documentation which is part of a tangled source file
+#[derive(Debug)]
+struct TangleBlock {
+    order: u32,
+    md_file: PathBuf,
+    line: usize,
+    code: String,
+}
+
+/// A source file assembled from the code blocks following '<!-- [tangle] [path] -->' tags
+#[derive(Debug, PartialEq)]
+pub struct TangledFile {
+    /// the path of the source file relative to the output directory
+    pub path: PathBuf,
+    pub content: String,
+}
+
+/// Matches a tangle tag; the path in group 1 may contain any characters, with '[', ']' and '\' escaped by a
+/// backslash like in geoffrey tags
+pub(crate) fn tangle_regex() -> Result<Regex, GeoffreyError> {
+    Regex::new(r"^<!-- *\[tangle\] *\[((?:[^\]\\]|\\.)*)\] *(\[ *(\d+) *\])? *-->")
+        .map_err(|_| GeoffreyError::RegexError)
+}
+
+/// Assembles the source files from the code blocks of the markdown files; the code blocks of a source file
+/// are ordered by the optional order of their tags, e.g. '<!-- [tangle] [hello.cpp] [2] -->', then by the
+/// markdown file and their position therein
+pub fn tangle(md_files: &[PathBuf]) -> Result<Vec<TangledFile>, GeoffreyError> {
+    let re_tangle = tangle_regex()?;
+
+    let mut files = BTreeMap::<PathBuf, Vec<TangleBlock>>::new();
+    for md_file in md_files {
+        for (path, block) in tangle_blocks(md_file, &re_tangle)? {
+            files.entry(path).or_default().push(block);
+        }
+    }
+
+    Ok(files
+        .into_iter()
+        .map(|(path, mut blocks)| {
+            blocks
+                .sort_by(|a, b| (a.order, &a.md_file, a.line).cmp(&(b.order, &b.md_file, b.line)));
+            TangledFile {
+                path,
+                content: blocks.into_iter().map(|block| block.code).collect(),
+            }
+        })
+        .collect())
+}
+
+fn tangle_blocks(
+    md_file: &Path,
+    re_tangle: &Regex,
+) -> Result<Vec<(PathBuf, TangleBlock)>, GeoffreyError> {
+    let content = fs::read_to_string(md_file)?;
+    let mut lines = content.split_inclusive('\n').enumerate();
+
+    // tags in code blocks which are not tangled, like examples of tangle tags, are ignored
+    let mut untangled = FenceTracker::default();
+    let mut blocks = Vec::new();
+    while let Some((index, line)) = lines.next() {
+        if untangled.in_code_block(line) {
+            continue;
+        }
+        let caps = match re_tangle.captures(line) {
+            Some(caps) => caps,
+            None => continue,
+        };
+        let (path, order) = parse_tag(&caps)?;
+
+        // next line must be the begin of a code block
+        let fence = match lines.next().and_then(|(_, line)| CodeFence::open(line)) {
+            Some(fence) => fence,
+            None => {
+                return Err(GeoffreyError::CodeBlockMustFollowTag(
+                    md_file.to_path_buf(),
+                    path.display().to_string(),
+                ))
+            }
+        };
+
+        let mut code = String::new();
+        let mut end_of_block_found = false;
+        for (_, line) in lines.by_ref() {
+            if fence.closes(line) {
+                end_of_block_found = true;
+                break;
+            }
+            code.push_str(line);
+        }
+        if !end_of_block_found {
+            return Err(GeoffreyError::CodeBlockEndMissing(
+                md_file.to_path_buf(),
+                path.display().to_string(),
+            ));
+        }
+
+        blocks.push((
+            path,
+            TangleBlock {
+                order,
+                md_file: md_file.to_path_buf(),
+                line: index + 1,
+                code,
+            },
+        ));
+    }
+
+    Ok(blocks)
+}
+
+/// The path and the order of a tangle tag
+fn parse_tag(caps: &Captures) -> Result<(PathBuf, u32), GeoffreyError> {
+    let path = caps.get(1).map_or("", |matcher| matcher.as_str().trim());
+    let path = tangle_path(&unescape_tag_field(path))?;
+    let order = caps
+        .get(3)
+        .map_or(Ok(0), |matcher| matcher.as_str().parse())
+        .map_err(|_| GeoffreyError::InvalidTanglePath(path.display().to_string()))?;
+
+    Ok((path, order))
+}
+
+/// Rewrites the tangle tag at the begin of `line` into its canonical form, e.g. '<!-- [tangle] [hello.cpp] [2] -->'
+/// without the default order 0, and keeps the rest of the line; `None` if the line has no tangle tag
+pub(crate) fn format_tag(re_tangle: &Regex, line: &str) -> Result<Option<String>, GeoffreyError> {
+    let caps = match re_tangle.captures(line) {
+        Some(caps) => caps,
+        None => return Ok(None),
+    };
+    let (path, order) = parse_tag(&caps)?;
+    let end = caps.get(0).ok_or(GeoffreyError::RegexError)?.end();
+
+    let path = escape_tag_field(&path.display().to_string());
+    let tag = match order {
+        0 => format!("<!-- [tangle] [{}] -->", path),
+        order => format!("<!-- [tangle] [{}] [{}] -->", path, order),
+    };
+    Ok(Some(tag + &line[end..]))
+}
+
+/// The path of a tangled file, which must stay within the output directory
+fn tangle_path(path: &str) -> Result<PathBuf, GeoffreyError> {
+    let path = PathBuf::from(path);
+    if path.as_os_str().is_empty()
+        || !path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(GeoffreyError::InvalidTanglePath(path.display().to_string()));
+    }
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use anyhow::Result;
+    use tempfile::Builder;
+
+    #[test]
+    fn tangle_assembles_code_blocks_in_order() -> Result<()> {
+        let tmp_dir = Builder::new().prefix("geoffrey").tempdir()?;
+
+        let first = tmp_dir.path().join("01-intro.md");
+        fs::write(
+            &first,
+            "# Intro\n<!-- [tangle] [hello.cpp] [1] -->\n```cpp\nint main() {\n```\n<!-- [tangle] [hello.cpp] -->\n```cpp\n#include <iostream>\n```\n",
+        )?;
+        let second = tmp_dir.path().join("02-output.md");
+        fs::write(
+            &second,
+            "<!-- [tangle] [hello.cpp] [1] -->\n```cpp\n    std::cout << \"hello\";\n}\n```\n<!-- [tangle] [src/other.cpp] -->\n~~~cpp\nint other;\n~~~\n<!-- [tangle] [c++ examples/señal[1\\].cpp] -->\n```cpp\nint signal;\n```\n",
+        )?;
+
+        assert_eq!(
+            tangle(&[second.clone(), first.clone()])?,
+            [
+                TangledFile {
+                    path: PathBuf::from("c++ examples/señal[1].cpp"),
+                    content: "int signal;\n".to_owned()
+                },
+                TangledFile {
+                    path: PathBuf::from("hello.cpp"),
+                    content: "#include <iostream>\nint main() {\n    std::cout << \"hello\";\n}\n"
+                        .to_owned()
+                },
+                TangledFile {
+                    path: PathBuf::from("src/other.cpp"),
+                    content: "int other;\n".to_owned()
+                }
+            ]
+        );
+
+        assert_eq!(
+            format_tag(
+                &tangle_regex()?,
+                "<!--[tangle] [ c++ examples/señal[1\\].cpp ] [ 02 ]-->\n"
+            )?,
+            Some("<!-- [tangle] [c++ examples/señal\\[1\\].cpp] [2] -->\n".to_owned())
+        );
+
+        fs::write(&second, "<!-- [tangle] [../escape.cpp] -->\n```cpp\n```\n")?;
+        assert!(matches!(
+            tangle(&[second]),
+            Err(GeoffreyError::InvalidTanglePath(_))
+        ));
+
+        Ok(())
+    }
+}